@@ -0,0 +1,756 @@
+//! # OAuth 2.1 authorization for remote MCP servers
+//!
+//! Implements the authorization flow of the MCP specification
+//! (<https://modelcontextprotocol.io/specification/2025-06-18/basic/authorization>):
+//!   1. Find the authorization server of the MCP server in its protected resource metadata (RFC
+//!      9728), and the endpoints of the authorization server in its own metadata (RFC 8414).
+//!      - Code: [discover]
+//!   2. Register a client dynamically (RFC 7591).
+//!   3. Open [McpAuthorization::url] in the browser, and approve the request. The browser is
+//!      redirected to a [LocalRedirect] with the authorization code.
+//!   4. Exchange the code for access and refresh tokens, bound to the MCP server with the
+//!      `resource` parameter (RFC 8707).
+//!      - Code: [McpAuthorization::finish]
+//!
+//! Tokens are stored per server, and [McpTokenProvider] refreshes them when they expire.
+
+use std::time::Duration;
+
+use percent_encoding::{
+    NON_ALPHANUMERIC,
+    utf8_percent_encode,
+};
+use reqwest::header::{
+    ACCEPT,
+    WWW_AUTHENTICATE,
+};
+use reqwest::{
+    StatusCode,
+    Url,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use time::OffsetDateTime;
+use tokio::sync::{
+    Mutex,
+    OnceCell,
+};
+use tracing::{
+    debug,
+    error,
+    warn,
+};
+
+use crate::auth::AuthError;
+use crate::auth::consts::CLIENT_NAME;
+use crate::auth::pkce::LocalRedirect;
+use crate::database::Database;
+
+/// Tokens expiring in less than this are refreshed before being used
+const EXPIRY_MARGIN: time::Duration = time::Duration::seconds(60);
+
+/// The parts of the metadata of an authorization server (RFC 8414) used by the flow
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AuthorizationServerMetadata {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub registration_endpoint: Option<String>,
+}
+
+/// The parts of the metadata of a protected resource (RFC 9728) used by the flow
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ProtectedResourceMetadata {
+    #[serde(default)]
+    authorization_servers: Vec<String>,
+    #[serde(default)]
+    scopes_supported: Vec<String>,
+}
+
+/// Where and how to authorize access to an MCP server
+#[derive(Debug, Clone)]
+pub struct Discovery {
+    pub metadata: AuthorizationServerMetadata,
+    /// The scopes the server says it supports, all of which are requested
+    pub scopes: Vec<String>,
+}
+
+/// Finds the authorization server of the MCP server at `server_url`.
+///
+/// Servers that do not publish protected resource metadata are assumed to be their own
+/// authorization server, and authorization servers that do not publish metadata are assumed to
+/// use the default endpoints, as in the 2025-03-26 version of the specification.
+pub async fn discover(client: &reqwest::Client, server_url: &Url) -> Result<Discovery, AuthError> {
+    let resource_metadata = protected_resource_metadata(client, server_url).await;
+    let issuer = match resource_metadata.authorization_servers.first() {
+        Some(issuer) => Url::parse(issuer)
+            .map_err(|e| AuthError::OAuthCustomError(format!("Invalid authorization server {issuer}: {e}")))?,
+        None => origin(server_url),
+    };
+
+    for url in well_known_urls(&issuer, &["oauth-authorization-server", "openid-configuration"]) {
+        match get_json::<AuthorizationServerMetadata>(client, url.clone()).await {
+            Ok(Some(metadata)) => {
+                return Ok(Discovery {
+                    metadata,
+                    scopes: resource_metadata.scopes_supported,
+                });
+            },
+            Ok(None) => {},
+            Err(e) => debug!("Failed to read authorization server metadata at {url}: {e}"),
+        }
+    }
+
+    let endpoint = |path: &str| origin(&issuer).join(path).map(String::from).unwrap_or_default();
+    Ok(Discovery {
+        metadata: AuthorizationServerMetadata {
+            authorization_endpoint: endpoint("/authorize"),
+            token_endpoint: endpoint("/token"),
+            registration_endpoint: Some(endpoint("/register")),
+        },
+        scopes: resource_metadata.scopes_supported,
+    })
+}
+
+/// Reads the protected resource metadata of the server, from the url given by the server when it
+/// refuses an unauthorized request or else from the well-known location.
+async fn protected_resource_metadata(client: &reqwest::Client, server_url: &Url) -> ProtectedResourceMetadata {
+    let mut urls = Vec::new();
+    if let Ok(response) = client
+        .get(server_url.clone())
+        .header(ACCEPT, "text/event-stream")
+        .send()
+        .await
+    {
+        if response.status() == StatusCode::UNAUTHORIZED {
+            if let Some(url) = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(resource_metadata_url)
+                .and_then(|url| Url::parse(&url).ok())
+            {
+                urls.push(url);
+            }
+        }
+    }
+    urls.extend(well_known_urls(server_url, &["oauth-protected-resource"]));
+
+    for url in urls {
+        match get_json::<ProtectedResourceMetadata>(client, url.clone()).await {
+            Ok(Some(metadata)) => return metadata,
+            Ok(None) => {},
+            Err(e) => debug!("Failed to read protected resource metadata at {url}: {e}"),
+        }
+    }
+    ProtectedResourceMetadata::default()
+}
+
+/// Extracts the `resource_metadata` parameter of a `WWW-Authenticate` header
+fn resource_metadata_url(header: &str) -> Option<String> {
+    header
+        .split([',', ' '])
+        .filter_map(|param| param.trim().split_once('='))
+        .find(|(name, _)| *name == "resource_metadata")
+        .map(|(_, value)| value.trim_matches('"').to_string())
+}
+
+/// The well-known urls of `url`, with the path of `url` appended after the well-known part first
+/// and then without it
+fn well_known_urls(url: &Url, suffixes: &[&str]) -> Vec<Url> {
+    let origin = origin(url);
+    let path = url.path().trim_end_matches('/');
+    let mut urls = Vec::new();
+    for suffix in suffixes {
+        if !path.is_empty() {
+            urls.extend(origin.join(&format!("/.well-known/{suffix}{path}")).ok());
+        }
+        urls.extend(origin.join(&format!("/.well-known/{suffix}")).ok());
+    }
+    urls
+}
+
+fn origin(url: &Url) -> Url {
+    let mut origin = url.clone();
+    origin.set_path("/");
+    origin.set_query(None);
+    origin.set_fragment(None);
+    origin
+}
+
+/// Fetches a JSON document, returning [None] if there is none at the url
+async fn get_json<T: for<'de> Deserialize<'de>>(client: &reqwest::Client, url: Url) -> Result<Option<T>, AuthError> {
+    let response = client.get(url).header(ACCEPT, "application/json").send().await?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+    Ok(Some(response.json().await?))
+}
+
+#[derive(Debug, Serialize)]
+struct RegistrationRequest<'a> {
+    client_name: &'a str,
+    redirect_uris: Vec<&'a str>,
+    grant_types: Vec<&'a str>,
+    response_types: Vec<&'a str>,
+    token_endpoint_auth_method: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistrationResponse {
+    client_id: String,
+    client_secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// Reads the body of a response of the authorization server, turning OAuth error responses into
+/// errors
+async fn read_response<T: for<'de> Deserialize<'de>>(response: reqwest::Response) -> Result<T, AuthError> {
+    let status = response.status();
+    let body = response.bytes().await?;
+    if status.is_success() {
+        return Ok(serde_json::from_slice(&body)?);
+    }
+    Err(AuthError::OAuthCustomError(
+        match serde_json::from_slice::<ErrorResponse>(&body) {
+            Ok(ErrorResponse {
+                error,
+                error_description: Some(description),
+            }) => format!("{error}: {description}"),
+            Ok(ErrorResponse { error, .. }) => error,
+            Err(_) => format!("{status}: {}", String::from_utf8_lossy(&body)),
+        },
+    ))
+}
+
+/// The tokens of a server, along with what is needed to refresh them
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpToken {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    pub token_endpoint: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    /// The url of the MCP server the tokens are bound to
+    pub resource: String,
+}
+
+impl std::fmt::Debug for McpToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpToken")
+            .field("access_token", &"<redacted>")
+            .field("refresh_token", &self.refresh_token.as_ref().map(|_| "<redacted>"))
+            .field("expires_at", &self.expires_at)
+            .field("token_endpoint", &self.token_endpoint)
+            .field("client_id", &self.client_id)
+            .field("resource", &self.resource)
+            .finish()
+    }
+}
+
+impl McpToken {
+    /// Tokens are stored per server and url, so that a server configured under the same name with
+    /// a different url never receives them
+    fn secret_key(server_name: &str, resource: &str) -> String {
+        format!("mcp-oauth:{server_name}:{resource}")
+    }
+
+    /// Loads the tokens of `server_name` issued for `resource`, the url of the server.
+    pub async fn load(database: &Database, server_name: &str, resource: &str) -> Result<Option<Self>, AuthError> {
+        match database.get_secret(&Self::secret_key(server_name, resource)).await? {
+            Some(secret) => Ok(Some(serde_json::from_str(&secret.0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores the tokens under the server name and the url they were issued for.
+    pub async fn save(&self, database: &Database, server_name: &str) -> Result<(), AuthError> {
+        database
+            .set_secret(
+                &Self::secret_key(server_name, &self.resource),
+                &serde_json::to_string(self)?,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the tokens of `server_name`, for every url. Returns whether there were any.
+    pub async fn delete(database: &Database, server_name: &str) -> Result<bool, AuthError> {
+        let prefix = Self::secret_key(server_name, "");
+        let keys = database
+            .get_secret_keys(&prefix)
+            .await?
+            .into_iter()
+            .filter(|key| {
+                key.strip_prefix(&prefix)
+                    .is_some_and(|url| url.starts_with("http://") || url.starts_with("https://"))
+            })
+            .collect::<Vec<_>>();
+        for key in &keys {
+            database.delete_secret(key).await?;
+        }
+        Ok(!keys.is_empty())
+    }
+
+    /// Whether the access token has expired, or is about to
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at - EXPIRY_MARGIN <= OffsetDateTime::now_utc())
+    }
+
+    /// Trades the refresh token for new tokens. Servers may keep the refresh token unchanged.
+    pub async fn refresh(&self, client: &reqwest::Client) -> Result<Self, AuthError> {
+        let refresh_token = self.refresh_token.as_deref().ok_or(AuthError::NoToken)?;
+        let mut form = vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", self.client_id.as_str()),
+            ("resource", self.resource.as_str()),
+        ];
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+        let response = client.post(&self.token_endpoint).form(&form).send().await?;
+        let response = read_response::<TokenResponse>(response).await?;
+        Ok(Self {
+            refresh_token: response.refresh_token.or_else(|| self.refresh_token.clone()),
+            ..self.with_response(response.access_token, response.expires_in)
+        })
+    }
+
+    fn with_response(&self, access_token: String, expires_in: Option<i64>) -> Self {
+        Self {
+            access_token,
+            expires_at: expires_in.map(|secs| OffsetDateTime::now_utc() + time::Duration::seconds(secs)),
+            ..self.clone()
+        }
+    }
+}
+
+/// An authorization request for an MCP server that is waiting for the user to approve it in the
+/// browser. To execute the flow:
+/// 1. Call [`McpAuthorization::start`] to register a client and receive the URL to be opened by the
+///    browser.
+/// 2. Call [`McpAuthorization::finish`] to wait for the redirect and trade the authorization code
+///    for tokens.
+#[derive(Debug)]
+pub struct McpAuthorization {
+    /// URL to be opened by the user's browser.
+    pub url: String,
+    client: reqwest::Client,
+    resource: String,
+    token_endpoint: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect: LocalRedirect,
+}
+
+impl McpAuthorization {
+    pub async fn start(client: reqwest::Client, server_url: &str) -> Result<Self, AuthError> {
+        let url = Url::parse(server_url)
+            .map_err(|e| AuthError::OAuthCustomError(format!("Invalid url {server_url}: {e}")))?;
+        let Discovery { metadata, scopes } = discover(&client, &url).await?;
+        let redirect = LocalRedirect::bind().await?;
+
+        let registration_endpoint = metadata.registration_endpoint.as_deref().ok_or_else(|| {
+            AuthError::OAuthCustomError("The authorization server does not support client registration".into())
+        })?;
+        let response = client
+            .post(registration_endpoint)
+            .json(&RegistrationRequest {
+                client_name: CLIENT_NAME,
+                redirect_uris: vec![&redirect.redirect_uri],
+                grant_types: vec!["authorization_code", "refresh_token"],
+                response_types: vec!["code"],
+                token_endpoint_auth_method: "none",
+            })
+            .send()
+            .await?;
+        let registration = read_response::<RegistrationResponse>(response).await?;
+
+        let mut query = vec![
+            ("response_type", "code".to_string()),
+            ("client_id", registration.client_id.clone()),
+            ("redirect_uri", redirect.redirect_uri.clone()),
+            ("state", redirect.state.clone()),
+            ("code_challenge", redirect.code_challenge.clone()),
+            ("code_challenge_method", "S256".to_string()),
+            ("resource", server_url.to_string()),
+        ];
+        if !scopes.is_empty() {
+            query.push(("scope", scopes.join(" ")));
+        }
+        let query = query
+            .into_iter()
+            .map(|(name, value)| format!("{name}={}", utf8_percent_encode(&value, NON_ALPHANUMERIC)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let separator = if metadata.authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+
+        Ok(Self {
+            url: format!("{}{separator}{query}", metadata.authorization_endpoint),
+            client,
+            resource: server_url.to_string(),
+            token_endpoint: metadata.token_endpoint,
+            client_id: registration.client_id,
+            client_secret: registration.client_secret,
+            redirect,
+        })
+    }
+
+    /// Hosts a local HTTP server to listen for the browser redirect, for at most `timeout`, and
+    /// trades the authorization code for tokens.
+    pub async fn finish(self, timeout: Duration) -> Result<McpToken, AuthError> {
+        let redirect_uri = self.redirect.redirect_uri.clone();
+        let code_verifier = self.redirect.code_verifier.clone();
+        let code = self.redirect.recv_code(timeout).await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", code_verifier.as_str()),
+            ("resource", self.resource.as_str()),
+        ];
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+        let response = self.client.post(&self.token_endpoint).form(&form).send().await?;
+        let response = read_response::<TokenResponse>(response).await?;
+
+        Ok(McpToken {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: response
+                .expires_in
+                .map(|secs| OffsetDateTime::now_utc() + time::Duration::seconds(secs)),
+            token_endpoint: self.token_endpoint,
+            client_id: self.client_id,
+            client_secret: self.client_secret,
+            resource: self.resource,
+        })
+    }
+}
+
+/// Supplies the access token of a server to its transport, refreshing it when it expires.
+#[derive(Debug)]
+pub struct McpTokenProvider {
+    server_name: String,
+    /// The url of the server. Only tokens issued for it are sent.
+    resource: String,
+    database: Database,
+    client: reqwest::Client,
+    /// Loaded from the database on first use
+    token: OnceCell<Mutex<Option<McpToken>>>,
+}
+
+impl McpTokenProvider {
+    pub fn new(server_name: String, resource: String, database: Database, client: reqwest::Client) -> Self {
+        Self {
+            server_name,
+            resource,
+            database,
+            client,
+            token: OnceCell::new(),
+        }
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    async fn token(&self) -> &Mutex<Option<McpToken>> {
+        self.token.get_or_init(|| async { Mutex::new(self.load().await) }).await
+    }
+
+    async fn load(&self) -> Option<McpToken> {
+        McpToken::load(&self.database, &self.server_name, &self.resource)
+            .await
+            .inspect_err(|err| error!(?err, "Failed to load the token of {}", self.server_name))
+            .ok()
+            .flatten()
+    }
+
+    /// The access token to send, if the user has logged in to the server
+    pub async fn access_token(&self) -> Option<String> {
+        let mut token = self.token().await.lock().await;
+        if token.as_ref().is_some_and(McpToken::is_expired) {
+            self.refresh_locked(&mut token).await;
+        }
+        token.as_ref().map(|token| token.access_token.clone())
+    }
+
+    /// Gets a new access token after the server refused the current one. Returns whether there is
+    /// a new token to retry with.
+    pub async fn refresh(&self) -> bool {
+        let mut token = self.token().await.lock().await;
+        // The user may have logged in again since the token was loaded
+        let stored = self.load().await;
+        if stored.is_some() && stored != *token {
+            *token = stored;
+            return true;
+        }
+        self.refresh_locked(&mut token).await
+    }
+
+    async fn refresh_locked(&self, token: &mut Option<McpToken>) -> bool {
+        let Some(current) = token.as_ref() else {
+            return false;
+        };
+        match current.refresh(&self.client).await {
+            Ok(refreshed) => {
+                if let Err(err) = refreshed.save(&self.database, &self.server_name).await {
+                    error!(?err, "Failed to store the token of {}", self.server_name);
+                }
+                *token = Some(refreshed);
+                true
+            },
+            Err(err) => {
+                warn!(?err, "Failed to refresh the token of {}", self.server_name);
+                false
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::{
+        Arc,
+        Mutex as SyncMutex,
+    };
+
+    use bytes::Bytes;
+    use http_body_util::{
+        BodyExt,
+        Full,
+    };
+    use hyper::body::Incoming;
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper_util::rt::TokioIo;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// The forms received by the token endpoint of the mock authorization server
+    type Received = Arc<SyncMutex<Vec<HashMap<String, String>>>>;
+
+    fn json_response(status: u16, body: serde_json::Value) -> hyper::Response<Full<Bytes>> {
+        hyper::Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    async fn handle(
+        base: String,
+        received: Received,
+        req: hyper::Request<Incoming>,
+    ) -> Result<hyper::Response<Full<Bytes>>, Infallible> {
+        let path = req.uri().path().to_string();
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        Ok(match path.as_str() {
+            "/mcp" => hyper::Response::builder()
+                .status(401)
+                .header(
+                    "www-authenticate",
+                    format!(r#"Bearer resource_metadata="{base}/meta/resource""#),
+                )
+                .body(Full::new(Bytes::new()))
+                .unwrap(),
+            "/meta/resource" => json_response(
+                200,
+                json!({ "resource": format!("{base}/mcp"), "authorization_servers": [format!("{base}/issuer")], "scopes_supported": ["mcp:tools"] }),
+            ),
+            "/.well-known/oauth-authorization-server/issuer" => json_response(
+                200,
+                json!({
+                    "issuer": format!("{base}/issuer"),
+                    "authorization_endpoint": format!("{base}/issuer/authorize"),
+                    "token_endpoint": format!("{base}/issuer/token"),
+                    "registration_endpoint": format!("{base}/issuer/register"),
+                }),
+            ),
+            "/issuer/register" => {
+                let request = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+                assert_eq!(request["token_endpoint_auth_method"], "none");
+                json_response(201, json!({ "client_id": "test-client" }))
+            },
+            "/issuer/token" => {
+                let form = url::form_urlencoded::parse(&body)
+                    .into_owned()
+                    .collect::<HashMap<_, _>>();
+                let response = match form["grant_type"].as_str() {
+                    "authorization_code" if form["code"] == "test-code" => json_response(
+                        200,
+                        json!({ "access_token": "access-1", "refresh_token": "refresh-1", "expires_in": 3600, "token_type": "Bearer" }),
+                    ),
+                    "refresh_token" if form["refresh_token"] == "refresh-1" => json_response(
+                        200,
+                        json!({ "access_token": "access-2", "expires_in": 3600, "token_type": "Bearer" }),
+                    ),
+                    _ => json_response(
+                        400,
+                        json!({ "error": "invalid_grant", "error_description": "bad grant" }),
+                    ),
+                };
+                received.lock().unwrap().push(form);
+                response
+            },
+            _ => json_response(404, json!({})),
+        })
+    }
+
+    async fn serve(received: Received) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let base_clone = base.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (base, received) = (base_clone.clone(), received.clone());
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(base.clone(), received.clone(), req));
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn test_resource_metadata_url() {
+        assert_eq!(
+            resource_metadata_url(r#"Bearer realm="mcp", resource_metadata="https://a.com/meta""#),
+            Some("https://a.com/meta".to_string())
+        );
+        assert_eq!(resource_metadata_url(r#"Bearer realm="mcp""#), None);
+    }
+
+    #[test]
+    fn test_well_known_urls() {
+        let urls = well_known_urls(&Url::parse("https://a.com/tenant/mcp").unwrap(), &[
+            "oauth-protected-resource",
+        ]);
+        assert_eq!(urls.iter().map(Url::as_str).collect::<Vec<_>>(), vec![
+            "https://a.com/.well-known/oauth-protected-resource/tenant/mcp",
+            "https://a.com/.well-known/oauth-protected-resource",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_authorization_flow() {
+        let received = Received::default();
+        let base = serve(received.clone()).await;
+        let server_url = format!("{base}/mcp");
+        let client = reqwest::Client::new();
+
+        let authorization = McpAuthorization::start(client.clone(), &server_url).await.unwrap();
+        let url = Url::parse(&authorization.url).unwrap();
+        assert_eq!(url.path(), "/issuer/authorize");
+        let query = url.query_pairs().into_owned().collect::<HashMap<_, _>>();
+        assert_eq!(query["client_id"], "test-client");
+        assert_eq!(query["code_challenge_method"], "S256");
+        assert_eq!(query["resource"], server_url);
+        assert_eq!(query["scope"], "mcp:tools");
+
+        // What the browser does once the user approves the request
+        let (redirect_uri, state) = (query["redirect_uri"].clone(), query["state"].clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            reqwest::get(format!("{redirect_uri}?code=test-code&state={state}"))
+                .await
+                .unwrap();
+        });
+
+        let token = authorization.finish(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(token.access_token, "access-1");
+        assert_eq!(token.resource, server_url);
+        assert!(!token.is_expired());
+        let form = received.lock().unwrap()[0].clone();
+        assert_eq!(form["resource"], server_url);
+        assert!(form.contains_key("code_verifier"));
+
+        let refreshed = token.refresh(&client).await.unwrap();
+        assert_eq!(refreshed.access_token, "access-2");
+        // The refresh token is kept when the server does not issue a new one
+        assert_eq!(refreshed.refresh_token.as_deref(), Some("refresh-1"));
+
+        let revoked = McpToken {
+            refresh_token: Some("revoked".into()),
+            ..refreshed
+        };
+        let err = revoked.refresh(&client).await.map(|_| ()).unwrap_err();
+        assert!(err.to_string().contains("invalid_grant"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_token_bound_to_url() {
+        let database = Database::new().await.unwrap();
+        let token = McpToken {
+            access_token: "a".into(),
+            refresh_token: None,
+            expires_at: None,
+            token_endpoint: "https://a.com/token".into(),
+            client_id: "c".into(),
+            client_secret: None,
+            resource: "https://a.com/mcp".into(),
+        };
+        token.save(&database, "tickets").await.unwrap();
+
+        let provider =
+            |url: &str| McpTokenProvider::new("tickets".into(), url.into(), database.clone(), reqwest::Client::new());
+        assert_eq!(provider("https://a.com/mcp").access_token().await.as_deref(), Some("a"));
+        assert_eq!(provider("https://evil.com/mcp").access_token().await, None);
+
+        assert!(McpToken::delete(&database, "tickets").await.unwrap());
+        assert!(!McpToken::delete(&database, "tickets").await.unwrap());
+    }
+
+    #[test]
+    fn test_token_expiry() {
+        let token = McpToken {
+            access_token: "a".into(),
+            refresh_token: None,
+            expires_at: Some(OffsetDateTime::now_utc() + time::Duration::seconds(30)),
+            token_endpoint: "https://a.com/token".into(),
+            client_id: "c".into(),
+            client_secret: None,
+            resource: "https://a.com/mcp".into(),
+        };
+        assert!(token.is_expired());
+        assert!(
+            !McpToken {
+                expires_at: None,
+                ..token
+            }
+            .is_expired()
+        );
+    }
+}
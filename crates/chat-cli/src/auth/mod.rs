@@ -1,5 +1,6 @@
 pub mod builder_id;
 mod consts;
+pub mod mcp_oauth;
 pub mod pkce;
 mod scope;
 
@@ -46,6 +47,8 @@ pub enum AuthError {
     OAuthCustomError(String),
     #[error(transparent)]
     DatabaseError(#[from] crate::database::DatabaseError),
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
 }
 
 impl From<aws_sdk_ssooidc::Error> for AuthError {
@@ -58,7 +58,7 @@ use crate::auth::{
 };
 use crate::database::Database;
 
-const DEFAULT_AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(60 * 3);
+pub const DEFAULT_AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(60 * 3);
 
 /// Starts the PKCE authorization flow, using [`START_URL`] and [`OIDC_BUILDER_ID_REGION`] as the
 /// default issuer URL and region. Returns the [`PkceClient`] to use to finish the flow.
@@ -170,13 +170,8 @@ pub struct PkceRegistration {
     registered_client: RegisterClientResponse,
     /// Configured URI that the authorization server will redirect the client to.
     pub redirect_uri: String,
-    code_verifier: String,
-    /// Random value generated for every authentication attempt.
-    ///
-    /// <https://stackoverflow.com/questions/26132066/what-is-the-purpose-of-the-state-parameter-in-oauth-authorization-request>
-    pub state: String,
-    /// Listener for hosting the local HTTP server.
-    listener: TcpListener,
+    /// The local HTTP server the browser is redirected to.
+    redirect: LocalRedirect,
     region: Region,
     /// Interchangeable with the "start URL" concept in the device code flow.
     issuer_url: String,
@@ -191,25 +186,19 @@ impl PkceRegistration {
         issuer_url: String,
         timeout: Option<Duration>,
     ) -> Result<Self, AuthError> {
-        let listener = TcpListener::bind("127.0.0.1:0").await?;
-        let redirect_uri = format!("http://{}/oauth/callback", listener.local_addr()?);
-        let code_verifier = generate_code_verifier();
-        let code_challenge = generate_code_challenge(&code_verifier);
-        let state = rand::rng()
-            .sample_iter(rand::distr::Alphanumeric)
-            .take(10)
-            .collect::<Vec<_>>();
-        let state = String::from_utf8(state).unwrap_or("state".to_string());
+        let redirect = LocalRedirect::bind().await?;
 
-        let response = client.register_client(redirect_uri.clone(), issuer_url.clone()).await?;
+        let response = client
+            .register_client(redirect.redirect_uri.clone(), issuer_url.clone())
+            .await?;
 
         let query = PkceQueryParams {
             client_id: response.client_id().to_string(),
-            redirect_uri: redirect_uri.clone(),
+            redirect_uri: redirect.redirect_uri.clone(),
             // Scopes must be space delimited.
             scopes: SCOPES.join(" "),
-            state: state.clone(),
-            code_challenge: code_challenge.clone(),
+            state: redirect.state.clone(),
+            code_challenge: redirect.code_challenge.clone(),
             code_challenge_method: "S256".to_string(),
         };
         let url = format!("{}/authorize?{}", oidc_url(&region), query.as_query_params());
@@ -217,10 +206,8 @@ impl PkceRegistration {
         Ok(Self {
             url,
             registered_client: response,
-            code_verifier,
-            state,
-            listener,
-            redirect_uri,
+            redirect_uri: redirect.redirect_uri.clone(),
+            redirect,
             region,
             issuer_url,
             timeout: timeout.unwrap_or(DEFAULT_AUTHORIZATION_TIMEOUT),
@@ -232,21 +219,15 @@ impl PkceRegistration {
     ///
     /// Only the first connection will be served.
     pub async fn finish<C: PkceClient>(self, client: &C, database: Option<&mut Database>) -> Result<(), AuthError> {
-        let code = tokio::select! {
-            code = Self::recv_code(self.listener, self.state) => {
-                code?
-            },
-            _ = tokio::time::sleep(self.timeout) => {
-                return Err(AuthError::OAuthTimeout);
-            }
-        };
+        let code_verifier = self.redirect.code_verifier.clone();
+        let code = self.redirect.recv_code(self.timeout).await?;
 
         let response = client
             .create_token(CreateTokenArgs {
                 client_id: self.registered_client.client_id().to_string(),
                 client_secret: self.registered_client.client_secret().to_string(),
                 redirect_uri: self.redirect_uri,
-                code_verifier: self.code_verifier,
+                code_verifier,
                 code,
             })
             .await?;
@@ -281,8 +262,55 @@ impl PkceRegistration {
 
         Ok(())
     }
+}
+
+/// A local HTTP server that the browser is redirected to once the user approves an authorization
+/// request, along with the PKCE parameters of that request.
+#[derive(Debug)]
+pub struct LocalRedirect {
+    /// URI to register with the authorization server and pass as `redirect_uri`.
+    pub redirect_uri: String,
+    /// Random value generated for every authentication attempt.
+    ///
+    /// <https://stackoverflow.com/questions/26132066/what-is-the-purpose-of-the-state-parameter-in-oauth-authorization-request>
+    pub state: String,
+    pub code_verifier: String,
+    /// The S256 challenge of [`Self::code_verifier`].
+    pub code_challenge: String,
+    listener: TcpListener,
+}
+
+impl LocalRedirect {
+    pub async fn bind() -> Result<Self, AuthError> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let redirect_uri = format!("http://{}/oauth/callback", listener.local_addr()?);
+        let code_verifier = generate_code_verifier();
+        let code_challenge = generate_code_challenge(&code_verifier);
+        let state = rand::rng()
+            .sample_iter(rand::distr::Alphanumeric)
+            .take(10)
+            .collect::<Vec<_>>();
+        let state = String::from_utf8(state).unwrap_or("state".to_string());
+        Ok(Self {
+            redirect_uri,
+            state,
+            code_verifier,
+            code_challenge,
+            listener,
+        })
+    }
+
+    /// Waits for the browser to be redirected with the authorization code, for at most `timeout`.
+    ///
+    /// Only the first connection will be served.
+    pub async fn recv_code(self, timeout: Duration) -> Result<String, AuthError> {
+        tokio::select! {
+            code = Self::serve(self.listener, self.state) => code,
+            _ = tokio::time::sleep(timeout) => Err(AuthError::OAuthTimeout),
+        }
+    }
 
-    async fn recv_code(listener: TcpListener, expected_state: String) -> Result<String, AuthError> {
+    async fn serve(listener: TcpListener, expected_state: String) -> Result<String, AuthError> {
         let (code_tx, mut code_rx) = tokio::sync::mpsc::channel::<Result<(String, String), AuthError>>(1);
         let (stream, _) = listener.accept().await?;
         let stream = TokioIo::new(stream); // Wrapper to implement Hyper IO traits for Tokio types.
@@ -538,7 +566,7 @@ mod tests {
             .unwrap();
 
         let redirect_uri = registration.redirect_uri.clone();
-        let state = registration.state.clone();
+        let state = registration.redirect.state.clone();
         tokio::spawn(async move {
             // Let registration.finish be called to handle the request.
             tokio::time::sleep(std::time::Duration::from_secs(1)).await;
@@ -32,7 +32,8 @@ impl McpServerConfig {
     }
 
     pub async fn save_to_file(&self, os: &Os, path: impl AsRef<Path>) -> eyre::Result<()> {
-        let json = serde_json::to_string_pretty(self)?;
+        // Mirrors [Self::load_from_file], which expects the servers under `mcpServers`
+        let json = serde_json::to_string_pretty(&serde_json::json!({ "mcpServers": self }))?;
         os.fs.write(path.as_ref(), json).await?;
         Ok(())
     }
@@ -45,10 +45,12 @@ pub use wrapper_types::{
     tool_settings_schema,
 };
 
+use super::chat::tools::script_tool::ScriptToolConfig;
 use super::chat::tools::{
     DEFAULT_APPROVE,
     NATIVE_TOOLS,
     ToolOrigin,
+    use_aws,
 };
 use crate::database::settings::Setting;
 use crate::os::Os;
@@ -109,6 +111,14 @@ pub struct Agent {
     #[serde(default)]
     #[schemars(schema_with = "tool_settings_schema")]
     pub tools_settings: HashMap<ToolSettingTarget, serde_json::Value>,
+    /// Tools that run a command template. Like other tools, they need to be listed in `tools` to
+    /// be made available
+    #[serde(default)]
+    pub script_tools: Vec<ScriptToolConfig>,
+    /// Directories, besides the current working directory, that mcp servers are told they may
+    /// operate on. Relative paths are resolved against the current working directory
+    #[serde(default)]
+    pub mcp_roots: Vec<String>,
     #[serde(skip)]
     pub path: Option<PathBuf>,
 }
@@ -135,6 +145,8 @@ impl Default for Agent {
             create_hooks: Default::default(),
             prompt_hooks: Default::default(),
             tools_settings: Default::default(),
+            script_tools: Default::default(),
+            mcp_roots: Default::default(),
             path: None,
         }
     }
@@ -162,7 +174,9 @@ impl Agent {
         match config_path {
             Ok(config_path) => {
                 let content = os.fs.read(&config_path).await?;
-                Ok((serde_json::from_slice::<Agent>(&content)?, config_path))
+                let agent = serde_json::from_slice::<Agent>(&content)?;
+                agent.validate_tools_settings()?;
+                Ok((agent, config_path))
             },
             Err(global_config_dir) if agent_name == "default" => {
                 os.fs
@@ -183,6 +197,14 @@ impl Agent {
             _ => bail!("Agent {agent_name} does not exist"),
         }
     }
+
+    /// Validates the settings of native tools that can not be checked by deserialization alone.
+    pub fn validate_tools_settings(&self) -> eyre::Result<()> {
+        if let Some(settings) = self.tools_settings.get("use_aws") {
+            use_aws::validate_settings(settings)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -570,6 +592,11 @@ async fn load_agents_from_entries(mut files: ReadDir) -> Vec<Agent> {
                     continue;
                 },
             };
+            if let Err(e) = agent.validate_tools_settings() {
+                let file_path = file_path.to_string_lossy();
+                tracing::error!("Error validating agent file {file_path}: {:?}", e);
+                continue;
+            }
             if let Some(name) = Path::new(&file.file_name()).file_stem() {
                 agent.name = name.to_string_lossy().to_string();
                 res.push(agent);
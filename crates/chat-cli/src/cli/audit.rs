@@ -0,0 +1,171 @@
+use std::process::ExitCode;
+
+use anstream::println;
+use clap::Args;
+use crossterm::style::Stylize;
+use eyre::{
+    Result,
+    bail,
+};
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use super::OutputFormat;
+use crate::os::Os;
+use crate::util::audit_log::{
+    AuditEntry,
+    AuditLog,
+    AuditQuery,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Args)]
+pub struct AuditArgs {
+    /// Only show entries at or after this time. Accepts an RFC 3339 timestamp or a relative
+    /// duration such as 30m, 12h or 7d
+    #[arg(long, value_parser = parse_time)]
+    pub since: Option<OffsetDateTime>,
+    /// Only show entries at or before this time. Accepts the same values as --since
+    #[arg(long, value_parser = parse_time)]
+    pub until: Option<OffsetDateTime>,
+    /// Only show invocations of this tool
+    #[arg(long)]
+    pub tool: Option<String>,
+    /// Only show entries from this conversation. A prefix of the id is enough
+    #[arg(long)]
+    pub conversation: Option<String>,
+    /// Only show the most recent N entries
+    #[arg(long, short = 'n')]
+    pub limit: Option<usize>,
+    /// Format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl AuditArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let query = AuditQuery {
+            since: self.since,
+            until: self.until,
+            tool_name: self.tool,
+            conversation_id: self.conversation,
+        };
+
+        let mut entries = AuditLog::read(os, &query).await?;
+        if let Some(limit) = self.limit {
+            let skip = entries.len().saturating_sub(limit);
+            entries.drain(..skip);
+        }
+
+        match self.format {
+            OutputFormat::Plain => {
+                if entries.is_empty() {
+                    println!("No audit log entries found.");
+                }
+                for entry in &entries {
+                    println!("{}", format_entry(entry));
+                }
+            },
+            OutputFormat::Json => println!("{}", serde_json::to_string(&entries)?),
+            OutputFormat::JsonPretty => println!("{}", serde_json::to_string_pretty(&entries)?),
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn format_entry(entry: &AuditEntry) -> String {
+    let timestamp = entry.timestamp.format(&Rfc3339).unwrap_or_default();
+    let decision = match (entry.decision, entry.decided_by) {
+        (Some(decision), Some(by)) => format!("{} by {}", to_label(&decision), to_label(&by)),
+        _ => "undecided".to_string(),
+    };
+    let duration = entry.duration_ms.map(|ms| format!(" in {ms}ms")).unwrap_or_default();
+
+    format!(
+        "{} {} {} ({}) [{}] {}{}\n    conversation: {}\n    arguments: {}",
+        timestamp.dark_grey(),
+        entry.tool_name.as_str().green(),
+        entry.tool_use_id,
+        entry.origin,
+        decision,
+        to_label(&entry.exit_status),
+        duration,
+        entry.conversation_id,
+        entry.arguments,
+    )
+}
+
+/// Renders a snake_case serde enum the same way it is written to the log.
+fn to_label(value: &impl serde::Serialize) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+pub fn parse_time(arg: &str) -> Result<OffsetDateTime> {
+    if let Ok(time) = OffsetDateTime::parse(arg, &Rfc3339) {
+        return Ok(time);
+    }
+
+    let split = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    let (amount, unit) = arg.split_at(split);
+    let Ok(amount) = amount.parse::<i64>() else {
+        bail!("Invalid time '{arg}'. Expected an RFC 3339 timestamp or a duration like 30m, 12h or 7d");
+    };
+    let duration = match unit {
+        "s" => time::Duration::seconds(amount),
+        "m" => time::Duration::minutes(amount),
+        "h" => time::Duration::hours(amount),
+        "d" => time::Duration::days(amount),
+        "w" => time::Duration::weeks(amount),
+        _ => bail!("Invalid duration unit '{unit}'. Expected one of s, m, h, d or w"),
+    };
+
+    Ok(OffsetDateTime::now_utc() - duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::RootSubcommand;
+    use crate::util::test::assert_parse;
+
+    #[test]
+    fn test_parse_time() {
+        let time = parse_time("2025-01-01T00:00:00Z").unwrap();
+        assert_eq!(time.year(), 2025);
+
+        let before = OffsetDateTime::now_utc() - time::Duration::hours(2);
+        let parsed = parse_time("2h").unwrap();
+        assert!((parsed - before).abs() < time::Duration::seconds(5));
+
+        assert!(parse_time("yesterday").is_err());
+        assert!(parse_time("5y").is_err());
+    }
+
+    #[test]
+    fn test_audit_args() {
+        assert_parse!(
+            [
+                "audit",
+                "--tool",
+                "execute_bash",
+                "--conversation",
+                "abc",
+                "-n",
+                "5",
+                "-f",
+                "json"
+            ],
+            RootSubcommand::Audit(AuditArgs {
+                since: None,
+                until: None,
+                tool: Some("execute_bash".to_string()),
+                conversation: Some("abc".to_string()),
+                limit: Some(5),
+                format: OutputFormat::Json,
+            })
+        );
+    }
+}
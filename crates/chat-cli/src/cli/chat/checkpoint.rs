@@ -0,0 +1,96 @@
+use std::io;
+use std::path::PathBuf;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::warn;
+
+use crate::os::Os;
+
+/// The contents of a file before it was first modified by a tool during an exchange, so that
+/// `/rewind` can undo the change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileCheckpoint {
+    /// Index in the conversation history of the exchange that modified the file.
+    pub exchange: usize,
+    pub path: PathBuf,
+    /// [None] if the file did not exist yet.
+    pub contents: Option<String>,
+}
+
+impl FileCheckpoint {
+    /// Snapshots the file at `path`. Returns [None] if the file exists but can't be read as text,
+    /// since restoring it would not be possible.
+    pub async fn capture(os: &Os, exchange: usize, path: PathBuf) -> Option<Self> {
+        let contents = match os.fs.exists(&path) {
+            true => match os.fs.read_to_string(&path).await {
+                Ok(contents) => Some(contents),
+                Err(err) => {
+                    warn!(?path, ?err, "unable to checkpoint file");
+                    return None;
+                },
+            },
+            false => None,
+        };
+
+        Some(Self {
+            exchange,
+            path,
+            contents,
+        })
+    }
+
+    /// Writes the snapshot back, deleting the file if it did not exist when it was captured.
+    pub async fn restore(&self, os: &Os) -> io::Result<()> {
+        match &self.contents {
+            Some(contents) => {
+                if let Some(parent) = self.path.parent() {
+                    os.fs.create_dir_all(parent).await?;
+                }
+                os.fs.write(&self.path, contents).await
+            },
+            None if os.fs.exists(&self.path) => os.fs.remove_file(&self.path).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Restores `checkpoints`, given in the order they were captured, so that every file ends up as
+/// it was at its earliest checkpoint. Returns the files that could not be restored.
+pub async fn restore_checkpoints(os: &Os, checkpoints: &[FileCheckpoint]) -> Vec<(PathBuf, io::Error)> {
+    let mut failed = Vec::new();
+    for checkpoint in checkpoints.iter().rev() {
+        if let Err(err) = checkpoint.restore(os).await {
+            failed.push((checkpoint.path.clone(), err));
+        }
+    }
+
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_restore_checkpoints() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/edited.txt", "original").await.unwrap();
+
+        let mut checkpoints = vec![
+            FileCheckpoint::capture(&os, 0, "/edited.txt".into()).await.unwrap(),
+            FileCheckpoint::capture(&os, 0, "/created.txt".into()).await.unwrap(),
+        ];
+        os.fs.write("/edited.txt", "first edit").await.unwrap();
+        os.fs.write("/created.txt", "new file").await.unwrap();
+
+        checkpoints.push(FileCheckpoint::capture(&os, 1, "/edited.txt".into()).await.unwrap());
+        os.fs.write("/edited.txt", "second edit").await.unwrap();
+
+        assert!(restore_checkpoints(&os, &checkpoints).await.is_empty());
+        assert_eq!(os.fs.read_to_string("/edited.txt").await.unwrap(), "original");
+        assert!(!os.fs.exists("/created.txt"));
+    }
+}
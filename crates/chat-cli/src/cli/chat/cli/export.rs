@@ -0,0 +1,76 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+
+use crate::cli::chat::export::{
+    ExportFormat,
+    export_conversation,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct ExportArgs {
+    /// File to write the export to
+    pub path: String,
+    /// Format of the export. Guessed from the extension of the path if omitted, otherwise
+    /// Markdown
+    #[arg(long, value_enum)]
+    pub format: Option<ExportFormat>,
+    /// Overwrite the file if it already exists
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+impl ExportArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if os.fs.exists(&self.path) && !self.force {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!(
+                    "\nFile at {} already exists. To overwrite, use -f or --force\n\n",
+                    &self.path
+                )),
+                style::SetAttribute(Attribute::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let format = self.format.unwrap_or_else(|| ExportFormat::from_path(&self.path));
+        let contents = export_conversation(os, &session.conversation, format, true).await;
+        if let Err(err) = os.fs.write(&self.path, contents).await {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("\nFailed to export to {}: {}\n\n", &self.path, &err)),
+                style::SetAttribute(Attribute::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("\n✔ Exported conversation to {}\n\n", &self.path)),
+            style::SetAttribute(Attribute::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
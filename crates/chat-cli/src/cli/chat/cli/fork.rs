@@ -0,0 +1,60 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct ForkArgs {
+    /// Title of the new conversation. Defaults to the title of the current one
+    pub name: Option<String>,
+}
+
+impl ForkArgs {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if session.conversation.history().is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nThere is nothing to fork yet.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        // Make sure the original is saved as it is right now before branching off of it.
+        session.conversation.save(os);
+        let original_id = session.conversation.fork(self.name);
+        session.conversation.save(os);
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!(
+                "\n✔ Forked into {} ({}).\n",
+                session.conversation.title().unwrap_or_default(),
+                session.conversation.conversation_id()
+            )),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "The original conversation can be picked up again with /resume {original_id}\n\n"
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
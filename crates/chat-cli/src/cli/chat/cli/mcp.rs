@@ -1,24 +1,51 @@
 use std::io::Write;
+use std::time::Duration;
 
-use clap::Args;
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::style::{
+    Attribute,
+    Color,
+};
 use crossterm::{
     queue,
     style,
 };
 
+use crate::cli::chat::server_logs::LogEntry;
+use crate::cli::chat::server_supervisor::{
+    ServerHealth,
+    ServerStatus,
+};
 use crate::cli::chat::tool_manager::LoadingRecord;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
 };
+use crate::mcp_client::LoggingLevel;
+use crate::os::Os;
 
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
-pub struct McpArgs;
+#[command(
+    after_long_help = "Resources of a server can be mentioned anywhere in a message as @<server>:<uri>.
+Their contents are read and attached to the message. Pinned resources are attached to every message
+and are read again whenever the server notifies that they changed."
+)]
+pub struct McpArgs {
+    #[command(subcommand)]
+    subcommand: Option<McpSubcommand>,
+}
 
 impl McpArgs {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if let Some(subcommand) = self.subcommand {
+            return subcommand.execute(os, session).await;
+        }
+
         let terminal_width = session.terminal_width();
         let still_loading = session
             .conversation
@@ -46,9 +73,18 @@ impl McpArgs {
                 style::Print(server_name),
                 style::Print("\n"),
                 style::Print(format!("{}\n", "▔".repeat(terminal_width))),
-                style::Print(msg),
-                style::Print("\n")
             )?;
+            if let Some(health) = session.conversation.tool_manager.server_health(server_name) {
+                let (status, color) = status_line(&health);
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(color),
+                    style::Print(status),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print("\n"),
+                )?;
+            }
+            queue!(session.stderr, style::Print(msg), style::Print("\n"))?;
         }
 
         if !still_loading.is_empty() {
@@ -68,3 +104,439 @@ impl McpArgs {
         })
     }
 }
+
+impl McpArgs {
+    pub fn subcommand_name(&self) -> Option<&'static str> {
+        self.subcommand.as_ref().map(|s| s.name())
+    }
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum McpSubcommand {
+    /// List the resources and resource templates of the servers
+    Resources {
+        /// Only list the resources of this server
+        server: Option<String>,
+    },
+    /// Attach a resource to every message, as @<server>:<uri>
+    Pin { resource: String },
+    /// Stop attaching a pinned resource
+    Unpin { resource: String },
+    /// List the directories that servers may operate on, or change them
+    Roots {
+        #[command(subcommand)]
+        action: Option<RootsAction>,
+    },
+    /// Stop a server and start it again
+    Restart { name: String },
+    /// Show what a server printed to stderr and the log messages it sent
+    Logs {
+        name: String,
+        /// Keep showing new output until ctrl+c is pressed
+        #[arg(long, short)]
+        follow: bool,
+        /// Only show log messages of at least this level, and ask the server to send them
+        #[arg(long, short, value_enum)]
+        level: Option<LoggingLevel>,
+    },
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum RootsAction {
+    /// Allow servers to operate on a directory
+    Add { path: String },
+    /// No longer allow servers to operate on a directory
+    Remove { path: String },
+}
+
+impl McpSubcommand {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::Resources { server } => {
+                let tool_manager = &session.conversation.tool_manager;
+                let mut server_names = tool_manager
+                    .clients
+                    .keys()
+                    .filter(|name| server.as_ref().is_none_or(|server| server == *name))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                server_names.sort();
+                if server_names.is_empty() {
+                    let msg = match server {
+                        Some(server) => format!("\nNo mcp server named {server}\n\n"),
+                        None => "\nNo mcp servers loaded\n\n".to_string(),
+                    };
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(msg),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+
+                let pinned = tool_manager
+                    .pinned_resources
+                    .lock()
+                    .await
+                    .iter()
+                    .map(|p| (p.server_name.clone(), p.uri.clone()))
+                    .collect::<Vec<_>>();
+                for server_name in server_names {
+                    let client = &tool_manager.clients[&server_name];
+                    let resources = client.list_resources();
+                    let templates = client.list_resource_templates();
+                    queue!(
+                        session.stderr,
+                        style::Print("\n"),
+                        style::SetAttribute(Attribute::Bold),
+                        style::Print(&server_name),
+                        style::SetAttribute(Attribute::Reset),
+                        style::Print("\n"),
+                    )?;
+                    if resources.is_empty() && templates.is_empty() {
+                        queue!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print("  No resources\n"),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                    }
+                    for resource in resources {
+                        let is_pinned = pinned.contains(&(server_name.clone(), resource.uri.clone()));
+                        queue!(
+                            session.stderr,
+                            style::Print("- "),
+                            style::SetForegroundColor(Color::Green),
+                            style::Print(format!("@{server_name}:{}", resource.uri)),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print(if is_pinned { " (pinned)" } else { "" }),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!(
+                                "  {}",
+                                resource.description.as_deref().unwrap_or(&resource.name)
+                            )),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print("\n"),
+                        )?;
+                    }
+                    for template in templates {
+                        queue!(
+                            session.stderr,
+                            style::Print("- "),
+                            style::SetForegroundColor(Color::Cyan),
+                            style::Print(format!("@{server_name}:{}", template.uri_template)),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!(
+                                "  (template) {}",
+                                template.description.as_deref().unwrap_or(&template.name)
+                            )),
+                            style::SetForegroundColor(Color::Reset),
+                            style::Print("\n"),
+                        )?;
+                    }
+                }
+                queue!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Pin { resource } => {
+                let (server_name, uri) = parse_resource(&resource)?;
+                match session.conversation.tool_manager.pin_resource(server_name, uri).await {
+                    Ok(()) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("\nPinned {resource}, it will be attached to every message\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                    Err(e) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nFailed to pin {resource}: {e}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                }
+            },
+            Self::Unpin { resource } => {
+                let (server_name, uri) = parse_resource(&resource)?;
+                match session.conversation.tool_manager.unpin_resource(server_name, uri).await {
+                    Ok(()) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("\nUnpinned {resource}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                    Err(e) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\nFailed to unpin {resource}: {e}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                }
+            },
+            Self::Roots { action: None } => {
+                let roots = session.conversation.tool_manager.roots();
+                queue!(session.stderr, style::Print("\n"))?;
+                if roots.is_empty() {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("No roots\n"),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+                for root in roots {
+                    queue!(
+                        session.stderr,
+                        style::Print("- "),
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(root.uri),
+                        style::SetForegroundColor(Color::Reset),
+                        style::Print("\n"),
+                    )?;
+                }
+                queue!(session.stderr, style::Print("\n"))?;
+            },
+            Self::Roots { action: Some(action) } => {
+                let tool_manager = &session.conversation.tool_manager;
+                let (result, done) = match &action {
+                    RootsAction::Add { path } => (tool_manager.add_root(os, path).await, "Added"),
+                    RootsAction::Remove { path } => (tool_manager.remove_root(os, path).await, "Removed"),
+                };
+                match result {
+                    Ok(root) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("\n{done} root {}\n\n", root.uri)),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                    Err(e) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("\n{e}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                }
+            },
+            Self::Restart { name } => {
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("\nRestarting {name}...\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                session.stderr.flush()?;
+                match session.conversation.tool_manager.restart_server(&name).await {
+                    Ok(()) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("Restarted {name}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                    Err(e) => queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("Failed to restart {name}: {e}\n\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?,
+                }
+            },
+            Self::Logs { name, follow, level } => {
+                let tool_manager = &session.conversation.tool_manager;
+                if let Some(level) = level {
+                    let note = match tool_manager.set_log_level(&name, level).await {
+                        Ok(true) => None,
+                        Ok(false) => Some(format!(
+                            "{name} does not support changing its log level, only the messages it sends are filtered\n"
+                        )),
+                        Err(e) => Some(format!("{e}\n")),
+                    };
+                    if let Some(note) = note {
+                        queue!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!("\n{note}")),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                    }
+                }
+
+                let (entries, mut seen) = tool_manager.server_logs(&name, 0);
+                if seen == 0 {
+                    let msg = if tool_manager.clients.contains_key(&name) {
+                        format!("\nNo output from {name} yet\n")
+                    } else {
+                        format!("\nNo mcp server named {name}\n")
+                    };
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(msg),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+                queue!(session.stderr, style::Print("\n"))?;
+                queue_log_entries(&mut session.stderr, &entries, level)?;
+
+                if follow {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!("Following the output of {name}, press ctrl+c to stop\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    session.stderr.flush()?;
+                    let ctrl_c = tokio::signal::ctrl_c();
+                    tokio::pin!(ctrl_c);
+                    loop {
+                        tokio::select! {
+                            _ = &mut ctrl_c => break,
+                            _ = tokio::time::sleep(Duration::from_millis(250)) => {
+                                let (entries, total) = session.conversation.tool_manager.server_logs(&name, seen);
+                                seen = total;
+                                queue_log_entries(&mut session.stderr, &entries, level)?;
+                                session.stderr.flush()?;
+                            },
+                        }
+                    }
+                }
+                queue!(session.stderr, style::Print("\n"))?;
+            },
+        }
+        session.stderr.flush()?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Resources { .. } => "resources",
+            Self::Pin { .. } => "pin",
+            Self::Unpin { .. } => "unpin",
+            Self::Roots { .. } => "roots",
+            Self::Restart { .. } => "restart",
+            Self::Logs { .. } => "logs",
+        }
+    }
+}
+
+/// Prints the entries of at least `level`, colored by level
+fn queue_log_entries(
+    output: &mut impl Write,
+    entries: &[LogEntry],
+    level: Option<LoggingLevel>,
+) -> Result<(), ChatError> {
+    for entry in entries.iter().filter(|entry| entry.is_at_least(level)) {
+        let color = match entry.level {
+            None | Some(LoggingLevel::Debug) => Color::DarkGrey,
+            Some(LoggingLevel::Info | LoggingLevel::Notice) => Color::Reset,
+            Some(LoggingLevel::Warning) => Color::Yellow,
+            Some(_) => Color::Red,
+        };
+        queue!(
+            output,
+            style::SetForegroundColor(color),
+            style::Print(log_line(entry)),
+            style::SetForegroundColor(Color::Reset),
+            style::Print("\n"),
+        )?;
+    }
+    Ok(())
+}
+
+/// Formats an entry as `12:30:05 warning [db] connection lost`, or `12:30:05 stderr  <line>` for
+/// stderr output
+fn log_line(entry: &LogEntry) -> String {
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        entry.time.hour(),
+        entry.time.minute(),
+        entry.time.second()
+    );
+    let level = entry.level.map_or("stderr".to_string(), |level| level.to_string());
+    match &entry.logger {
+        Some(logger) => format!("{time} {level:<9} [{logger}] {}", entry.message),
+        None => format!("{time} {level:<9} {}", entry.message),
+    }
+}
+
+/// Describes the status of a server in one line, such as `running for 5m (restarted 1 time)`
+fn status_line(health: &ServerHealth) -> (String, Color) {
+    match &health.status {
+        ServerStatus::NotStarted => (
+            "not started, starts when one of its tools is used".to_string(),
+            Color::DarkGrey,
+        ),
+        ServerStatus::Running => {
+            let mut status = format!("running for {}", format_uptime(health.started_at.elapsed()));
+            match health.restart_count {
+                0 => {},
+                1 => status.push_str(" (restarted 1 time)"),
+                n => status.push_str(&format!(" (restarted {n} times)")),
+            }
+            (status, Color::Green)
+        },
+        ServerStatus::Restarting { attempt, max_retries } => {
+            let mut status = format!("restarting (attempt {attempt}/{max_retries})");
+            if let Some(error) = &health.last_error {
+                status.push_str(&format!(": {error}"));
+            }
+            (status, Color::Yellow)
+        },
+        ServerStatus::Failed => (
+            format!("failed: {}", health.last_error.as_deref().unwrap_or("unknown error")),
+            Color::Red,
+        ),
+    }
+}
+
+fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// Splits a resource written as `@server:uri` (or without the `@`) into server name and uri
+fn parse_resource(resource: &str) -> Result<(&str, &str), ChatError> {
+    resource
+        .strip_prefix('@')
+        .unwrap_or(resource)
+        .split_once(':')
+        .filter(|(server_name, uri)| !server_name.is_empty() && !uri.is_empty())
+        .ok_or_else(|| ChatError::Custom(format!("Expected a resource as @<server>:<uri>, got {resource}").into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!(format_uptime(Duration::from_secs(42)), "42s");
+        assert_eq!(format_uptime(Duration::from_secs(125)), "2m 5s");
+        assert_eq!(format_uptime(Duration::from_secs(7500)), "2h 5m");
+    }
+
+    #[test]
+    fn test_log_line() {
+        let time = time::macros::datetime!(2025-06-01 12:30:05 UTC);
+        let entry = LogEntry {
+            time,
+            level: Some(LoggingLevel::Warning),
+            logger: Some("db".to_string()),
+            message: "connection lost".to_string(),
+        };
+        assert_eq!(log_line(&entry), "12:30:05 warning   [db] connection lost");
+        let entry = LogEntry {
+            time,
+            level: None,
+            logger: None,
+            message: "listening on stdio".to_string(),
+        };
+        assert_eq!(log_line(&entry), "12:30:05 stderr    listening on stdio");
+    }
+}
@@ -2,6 +2,8 @@ pub mod clear;
 pub mod compact;
 pub mod context;
 pub mod editor;
+pub mod export;
+pub mod fork;
 pub mod hooks;
 pub mod knowledge;
 pub mod mcp;
@@ -9,6 +11,8 @@ pub mod model;
 pub mod persist;
 pub mod profile;
 pub mod prompts;
+pub mod resume;
+pub mod rewind;
 pub mod subscribe;
 pub mod tools;
 pub mod usage;
@@ -18,6 +22,8 @@ use clear::ClearArgs;
 use compact::CompactArgs;
 use context::ContextSubcommand;
 use editor::EditorArgs;
+use export::ExportArgs;
+use fork::ForkArgs;
 use hooks::HooksArgs;
 use knowledge::KnowledgeSubcommand;
 use mcp::McpArgs;
@@ -25,6 +31,8 @@ use model::ModelArgs;
 use persist::PersistSubcommand;
 use profile::AgentSubcommand;
 use prompts::PromptsArgs;
+use resume::ResumeArgs;
+use rewind::RewindArgs;
 use tools::ToolsArgs;
 
 use crate::cli::chat::cli::subscribe::SubscribeArgs;
@@ -72,7 +80,7 @@ pub enum SlashCommand {
     Hooks(HooksArgs),
     /// Show current session's context window usage
     Usage(UsageArgs),
-    /// See mcp server loaded
+    /// See mcp server loaded and browse their resources
     Mcp(McpArgs),
     /// Select a model for the current conversation session
     Model(ModelArgs),
@@ -80,6 +88,14 @@ pub enum SlashCommand {
     Subscribe(SubscribeArgs),
     #[command(flatten)]
     Persist(PersistSubcommand),
+    /// Resume a previous conversation from any directory
+    Resume(ResumeArgs),
+    /// Drop the most recent exchanges from the conversation
+    Rewind(RewindArgs),
+    /// Branch the conversation into a new saved conversation
+    Fork(ForkArgs),
+    /// Export the conversation to Markdown, HTML or JSONL for sharing
+    Export(ExportArgs),
     // #[command(flatten)]
     // Root(RootSubcommand),
 }
@@ -107,10 +123,14 @@ impl SlashCommand {
             Self::Prompts(args) => args.execute(session).await,
             Self::Hooks(args) => args.execute(os, session).await,
             Self::Usage(args) => args.execute(os, session).await,
-            Self::Mcp(args) => args.execute(session).await,
+            Self::Mcp(args) => args.execute(os, session).await,
             Self::Model(args) => args.execute(session).await,
             Self::Subscribe(args) => args.execute(os, session).await,
             Self::Persist(subcommand) => subcommand.execute(os, session).await,
+            Self::Resume(args) => args.execute(os, session).await,
+            Self::Rewind(args) => args.execute(os, session).await,
+            Self::Fork(args) => args.execute(os, session).await,
+            Self::Export(args) => args.execute(os, session).await,
             // Self::Root(subcommand) => {
             //     if let Err(err) = subcommand.execute(os, database, telemetry).await {
             //         return Err(ChatError::Custom(err.to_string().into()));
@@ -144,6 +164,10 @@ impl SlashCommand {
                 PersistSubcommand::Save { .. } => "save",
                 PersistSubcommand::Load { .. } => "load",
             },
+            Self::Resume(_) => "resume",
+            Self::Rewind(_) => "rewind",
+            Self::Fork(_) => "fork",
+            Self::Export(_) => "export",
         }
     }
 
@@ -155,6 +179,7 @@ impl SlashCommand {
             SlashCommand::Tools(arg) => arg.subcommand_name(),
             SlashCommand::Prompts(arg) => arg.subcommand_name(),
             SlashCommand::Hooks(arg) => arg.subcommand_name(),
+            SlashCommand::Mcp(arg) => arg.subcommand_name(),
             _ => None,
         }
     }
@@ -0,0 +1,133 @@
+use clap::Args;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+
+#[cfg(unix)]
+use crate::cli::chat::skim_integration::select_conversation_with_skim;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::database::ConversationMetadata;
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct ResumeArgs {
+    /// Id of the conversation to resume. A prefix of the id is enough. Opens a picker over all
+    /// saved conversations if omitted
+    pub id: Option<String>,
+}
+
+impl ResumeArgs {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let id = match self.id {
+            Some(id) => Some(id),
+            None => {
+                let conversations = os
+                    .database
+                    .list_conversations(None)
+                    .map_err(|err| ChatError::Custom(err.to_string().into()))?;
+                if conversations.is_empty() {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("\nNo saved conversations found.\n\n"),
+                        style::SetAttribute(Attribute::Reset)
+                    )?;
+                }
+
+                select_conversation(&conversations).map_err(|err| ChatError::Custom(err.to_string().into()))?
+            },
+        };
+
+        let Some(id) = id else {
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        let mut new_state = match os.database.get_conversation(&id) {
+            Ok(Some(state)) => state,
+            Ok(None) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nNo saved conversation matches {id}\n\n")),
+                    style::SetAttribute(Attribute::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+            Err(err) => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to resume {id}: {err}\n\n")),
+                    style::SetAttribute(Attribute::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            },
+        };
+
+        std::mem::swap(&mut new_state.tool_manager, &mut session.conversation.tool_manager);
+        std::mem::swap(
+            &mut new_state.context_manager,
+            &mut session.conversation.context_manager,
+        );
+        std::mem::swap(&mut new_state.agents, &mut session.conversation.agents);
+        new_state.load_file_checkpoints(os);
+        new_state.update_state(true).await;
+        new_state.enforce_tool_use_history_invariants();
+        session.conversation = new_state;
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!(
+                "\n✔ Resumed conversation {}\n\n",
+                session.conversation.title().unwrap_or(id)
+            )),
+            style::SetAttribute(Attribute::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Lets the user pick one of `conversations`, returning the id of the selected one.
+#[cfg(unix)]
+pub fn select_conversation(conversations: &[ConversationMetadata]) -> eyre::Result<Option<String>> {
+    select_conversation_with_skim(conversations)
+}
+
+/// Lets the user pick one of `conversations`, returning the id of the selected one.
+#[cfg(not(unix))]
+pub fn select_conversation(conversations: &[ConversationMetadata]) -> eyre::Result<Option<String>> {
+    if conversations.is_empty() {
+        return Ok(None);
+    }
+
+    let items: Vec<String> = conversations
+        .iter()
+        .map(|conversation| {
+            format!(
+                "{}  ({})  {}",
+                conversation.title.as_deref().unwrap_or("(untitled)"),
+                conversation.cwd,
+                conversation.id
+            )
+        })
+        .collect();
+    Ok(crate::util::choose("Select conversation", &items)?.map(|index| conversations[index].id.clone()))
+}
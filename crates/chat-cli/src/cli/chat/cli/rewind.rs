@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::io::Write;
+
+use clap::Args;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use crossterm::{
+    cursor,
+    execute,
+};
+
+use crate::cli::chat::checkpoint::restore_checkpoints;
+use crate::cli::chat::message::{
+    AssistantMessage,
+    UserMessage,
+};
+#[cfg(unix)]
+use crate::cli::chat::skim_integration::select_exchange_with_skim;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Maximum number of characters of the prompt and response shown for each exchange in the picker.
+const SUMMARY_PART_LEN: usize = 60;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct RewindArgs {
+    /// Number of exchanges to drop from the end of the conversation. Opens a picker previewing
+    /// each exchange if omitted (macOS and Linux only)
+    pub count: Option<usize>,
+    /// Restore the files changed by fs_write during the dropped exchanges without asking
+    #[arg(long, short)]
+    pub restore_files: bool,
+}
+
+impl RewindArgs {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let history = session.conversation.history();
+        if history.is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nThere is nothing to rewind.\n\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let count = match self.count {
+            Some(count) => count.min(history.len()),
+            #[cfg(unix)]
+            None => {
+                let exchanges: Vec<_> = history
+                    .iter()
+                    .map(|(user, assistant)| preview_exchange(user, assistant))
+                    .collect();
+                match select_exchange_with_skim(&exchanges).map_err(|err| ChatError::Custom(err.to_string().into()))? {
+                    Some(index) => history.len() - index,
+                    None => 0,
+                }
+            },
+            #[cfg(not(unix))]
+            None => {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print("\nSpecify the number of exchanges to drop, e.g. /rewind 1\n\n"),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                0
+            },
+        };
+
+        if count == 0 {
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        // Anything queued up for the dropped exchanges no longer applies.
+        session.tool_uses.clear();
+        session.pending_tool_index = None;
+
+        let checkpoints = session.conversation.rewind(count);
+        session.conversation.save(os);
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!(
+                "\n✔ Dropped the last {count} exchange{}.\n",
+                if count == 1 { "" } else { "s" }
+            )),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        let files = checkpoints.iter().map(|c| &c.path).collect::<HashSet<_>>().len();
+        if files == 0 {
+            writeln!(session.stderr)?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let restore = self.restore_files || {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(
+                    "\nRestore the {files} file{} changed during these exchanges? ",
+                    if files == 1 { "" } else { "s" }
+                )),
+                style::Print("["),
+                style::SetForegroundColor(Color::Green),
+                style::Print("y"),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("/"),
+                style::SetForegroundColor(Color::Green),
+                style::Print("n"),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("]:\n\n"),
+                style::SetForegroundColor(Color::Reset),
+                cursor::Show,
+            )?;
+
+            // Setting `exit_on_single_ctrl_c` for better ux: exit the confirmation dialog rather than the CLI
+            let user_input = session
+                .read_user_input("> ".yellow().to_string().as_str(), true)
+                .unwrap_or_default();
+            ["y", "Y"].contains(&user_input.as_str())
+        };
+
+        if restore {
+            let failed = restore_checkpoints(os, &checkpoints).await;
+            for (path, err) in &failed {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\nFailed to restore {}: {err}", path.display())),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            }
+            if failed.is_empty() {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!(
+                        "\n✔ Restored {files} file{}.",
+                        if files == 1 { "" } else { "s" }
+                    )),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            }
+            writeln!(session.stderr)?;
+        }
+
+        writeln!(session.stderr)?;
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Returns a single line summary of the exchange and the full text to preview it with.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn preview_exchange(user: &UserMessage, assistant: &AssistantMessage) -> (String, String) {
+    let prompt = match (user.prompt(), user.tool_use_results()) {
+        (Some(prompt), _) => prompt.to_string(),
+        (None, Some(results)) => format!("[{} tool result(s)]", results.len()),
+        (None, None) => String::new(),
+    };
+
+    let mut response = assistant.content().to_string();
+    let tool_uses: Vec<_> = assistant
+        .tool_uses()
+        .unwrap_or_default()
+        .iter()
+        .map(|tool_use| tool_use.name.as_str())
+        .collect();
+    if !tool_uses.is_empty() {
+        response.push_str(&format!("\n[Tool uses: {}]", tool_uses.join(", ")));
+    }
+
+    let summary = format!(
+        "{} → {}",
+        first_line(&prompt, SUMMARY_PART_LEN),
+        first_line(&response, SUMMARY_PART_LEN)
+    );
+    (summary, format!("> {prompt}\n\n{response}\n"))
+}
+
+/// The first non-empty line of `text`, truncated to `max_len` characters.
+fn first_line(text: &str, max_len: usize) -> String {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    match line.char_indices().nth(max_len) {
+        Some((idx, _)) => format!("{}…", &line[..idx]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_exchange() {
+        let user = UserMessage::new_prompt("\nRename the config module\nand update imports".to_string());
+        let assistant = AssistantMessage::new_response(None, "Sure, renaming it now.".to_string());
+
+        let (summary, preview) = preview_exchange(&user, &assistant);
+        assert_eq!(summary, "Rename the config module → Sure, renaming it now.");
+        assert!(preview.starts_with("> \nRename the config module\nand update imports"));
+        assert!(preview.contains("Sure, renaming it now."));
+        assert!(!summary.contains('\n'));
+    }
+
+    #[test]
+    fn test_first_line() {
+        assert_eq!(first_line("  \n hello \nworld", 10), "hello");
+        assert_eq!(first_line("abcdef", 3), "abc…");
+        assert_eq!(first_line("", 3), "");
+    }
+}
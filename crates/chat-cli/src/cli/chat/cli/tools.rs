@@ -86,10 +86,12 @@ impl ToolsArgs {
 
         let mut origin_tools: Vec<_> = session.conversation.tools.iter().collect();
 
-        // Built in tools always appear first.
+        // Built in tools always appear first, followed by the script tools of the agent.
         origin_tools.sort_by(|(origin_a, _), (origin_b, _)| match (origin_a, origin_b) {
             (ToolOrigin::Native, _) => std::cmp::Ordering::Less,
             (_, ToolOrigin::Native) => std::cmp::Ordering::Greater,
+            (ToolOrigin::Script, _) => std::cmp::Ordering::Less,
+            (_, ToolOrigin::Script) => std::cmp::Ordering::Greater,
             (ToolOrigin::McpServer(name_a), ToolOrigin::McpServer(name_b)) => name_a.cmp(name_b),
         });
 
@@ -4,6 +4,7 @@ use std::collections::{
     VecDeque,
 };
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 
 use crossterm::style::Color;
@@ -20,6 +21,7 @@ use tracing::{
     warn,
 };
 
+use super::checkpoint::FileCheckpoint;
 use super::cli::compact::CompactStrategy;
 use super::consts::{
     DUMMY_TOOL_NAME,
@@ -62,8 +64,11 @@ use crate::cli::chat::cli::hooks::{
 use crate::mcp_client::Prompt;
 use crate::os::Os;
 
+const MAX_TITLE_LEN: usize = 80;
 const CONTEXT_ENTRY_START_HEADER: &str = "--- CONTEXT ENTRY BEGIN ---\n";
 const CONTEXT_ENTRY_END_HEADER: &str = "--- CONTEXT ENTRY END ---\n\n";
+/// Starts the last line of every assistant entry in the transcript.
+const TRANSCRIPT_TOOL_USES_PREFIX: &str = "[Tool uses: ";
 
 /// Tracks state related to an ongoing conversation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,6 +101,16 @@ pub struct ConversationState {
     /// Model explicitly selected by the user in this conversation state via `/model`.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    /// Title given to the conversation via `/fork`, used instead of the first prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// Snapshots of the files modified by tools, restorable with `/rewind`. They are saved to
+    /// the database separately from the rest of the conversation.
+    #[serde(skip)]
+    file_checkpoints: Vec<FileCheckpoint>,
+    /// Whether [Self::file_checkpoints] changed since the conversation was last saved.
+    #[serde(skip)]
+    file_checkpoints_changed: bool,
 }
 
 impl ConversationState {
@@ -137,6 +152,9 @@ impl ConversationState {
             latest_summary: None,
             agents,
             model: current_model_id,
+            title: None,
+            file_checkpoints: Vec::new(),
+            file_checkpoints_changed: false,
         }
     }
 
@@ -152,6 +170,8 @@ impl ConversationState {
     pub fn clear(&mut self, preserve_summary: bool) {
         self.next_message = None;
         self.history.clear();
+        self.file_checkpoints_changed |= !self.file_checkpoints.is_empty();
+        self.file_checkpoints.clear();
         if !preserve_summary {
             self.latest_summary = None;
         }
@@ -218,17 +238,143 @@ impl ConversationState {
 
         self.append_assistant_transcript(&message);
         self.history.push_back((next_user_message, message));
+        self.save(os);
+    }
 
+    /// Saves the conversation as the latest one for the current directory and to the conversation
+    /// history.
+    pub fn save(&mut self, os: &mut Os) {
         if let Ok(cwd) = std::env::current_dir() {
-            os.database.set_conversation_by_path(cwd, self).ok();
+            os.database.set_conversation_by_path(&cwd, self).ok();
+            if let Err(err) = os.database.set_conversation(&cwd, self) {
+                warn!(?err, "failed to save the conversation to the conversation history");
+            }
+        }
+        if std::mem::take(&mut self.file_checkpoints_changed) {
+            if let Err(err) = os
+                .database
+                .set_file_checkpoints(&self.conversation_id, &self.file_checkpoints)
+            {
+                warn!(?err, "failed to save the file checkpoints of the conversation");
+            }
         }
     }
 
+    /// Loads the file checkpoints of a conversation restored from the database.
+    pub fn load_file_checkpoints(&mut self, os: &Os) {
+        match os.database.get_file_checkpoints(&self.conversation_id) {
+            Ok(checkpoints) => self.file_checkpoints = checkpoints,
+            Err(err) => warn!(?err, "failed to load the file checkpoints of the conversation"),
+        }
+    }
+
+    /// A short title for the conversation, taken from the first line of the first prompt unless
+    /// one was given with [Self::fork].
+    pub fn title(&self) -> Option<String> {
+        if let Some(title) = &self.title {
+            return Some(title.clone());
+        }
+
+        let prompt = self.history.iter().find_map(|(user, _)| user.prompt())?;
+        let line = prompt.lines().map(str::trim).find(|line| !line.is_empty())?;
+        Some(match line.char_indices().nth(MAX_TITLE_LEN) {
+            Some((idx, _)) => format!("{}…", &line[..idx]),
+            None => line.to_string(),
+        })
+    }
+
     /// Returns the conversation id.
     pub fn conversation_id(&self) -> &str {
         self.conversation_id.as_ref()
     }
 
+    /// Branches the conversation off under a new conversation id, so that the original and the
+    /// fork are saved and resumable separately. Returns the id of the original conversation.
+    pub fn fork(&mut self, title: Option<String>) -> String {
+        self.title = title.or_else(|| self.title().map(|title| format!("{title} (fork)")));
+        self.file_checkpoints_changed |= !self.file_checkpoints.is_empty();
+        std::mem::replace(&mut self.conversation_id, uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Drops the last `count` exchanges from the history and transcript. Returns the file
+    /// checkpoints taken during the dropped exchanges, in the order they were taken.
+    pub fn rewind(&mut self, count: usize) -> Vec<FileCheckpoint> {
+        let len = self.history.len().saturating_sub(count);
+        self.rewind_transcript(self.history.len() - len);
+        let dropped = self.drop_newest_exchanges(len);
+        self.next_message = None;
+        self.enforce_conversation_invariants();
+        dropped
+    }
+
+    /// Removes the transcript entries of the last `count` exchanges, i.e. everything after the
+    /// assistant entry of the last exchange that is kept.
+    fn rewind_transcript(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        let mut remaining = count + 1;
+        let len = self
+            .transcript
+            .iter()
+            .rposition(|entry| {
+                let is_assistant = entry
+                    .lines()
+                    .last()
+                    .is_some_and(|line| line.starts_with(TRANSCRIPT_TOOL_USES_PREFIX) && line.ends_with(']'));
+                remaining -= usize::from(is_assistant);
+                remaining == 0
+            })
+            .map_or(0, |idx| idx + 1);
+        self.transcript.truncate(len);
+    }
+
+    /// Snapshots `path` before a tool modifies it during the latest exchange. Only the first
+    /// modification of a file in each exchange is recorded.
+    pub async fn checkpoint_file(&mut self, os: &Os, path: PathBuf) {
+        let exchange = self.history.len().saturating_sub(1);
+        if self
+            .file_checkpoints
+            .iter()
+            .any(|checkpoint| checkpoint.exchange == exchange && checkpoint.path == path)
+        {
+            return;
+        }
+
+        if let Some(checkpoint) = FileCheckpoint::capture(os, exchange, path).await {
+            self.file_checkpoints.push(checkpoint);
+            self.file_checkpoints_changed = true;
+        }
+    }
+
+    /// Truncates the history to its first `len` exchanges. Returns the file checkpoints taken
+    /// during the removed exchanges, in the order they were taken.
+    fn drop_newest_exchanges(&mut self, len: usize) -> Vec<FileCheckpoint> {
+        self.history.truncate(len);
+        let (dropped, kept) = std::mem::take(&mut self.file_checkpoints)
+            .into_iter()
+            .partition(|checkpoint| checkpoint.exchange >= len);
+        self.file_checkpoints = kept;
+        self.file_checkpoints_changed |= !dropped.is_empty();
+        dropped
+    }
+
+    /// Removes `count` exchanges from the front of the history, keeping the exchange index of
+    /// file checkpoints in sync.
+    fn drop_oldest_exchanges(&mut self, count: usize) {
+        self.history.drain(..count);
+        self.file_checkpoints_changed |= !self.file_checkpoints.is_empty();
+        self.file_checkpoints
+            .retain_mut(|checkpoint| match checkpoint.exchange.checked_sub(count) {
+                Some(exchange) => {
+                    checkpoint.exchange = exchange;
+                    true
+                },
+                None => false,
+            });
+    }
+
     /// Returns the message id associated with the last assistant message, if present.
     ///
     /// This is equivalent to `utterance_id` in the Q API.
@@ -294,8 +440,8 @@ impl ConversationState {
     ) -> Result<FigConversationState, ChatError> {
         debug_assert!(self.next_message.is_some());
         self.enforce_conversation_invariants();
-        self.history.drain(self.valid_history_range.1..);
-        self.history.drain(..self.valid_history_range.0);
+        self.drop_newest_exchanges(self.valid_history_range.1);
+        self.drop_oldest_exchanges(self.valid_history_range.0);
 
         let context = self.backend_conversation_state(os, run_hooks, stderr).await?;
         if !context.dropped_context_files.is_empty() {
@@ -472,7 +618,7 @@ impl ConversationState {
                 });
                 true
             },
-            ToolOrigin::McpServer(_) => false,
+            ToolOrigin::Script | ToolOrigin::McpServer(_) => false,
         });
 
         enforce_conversation_invariants(&mut history, &mut summary_message, &tools);
@@ -489,8 +635,7 @@ impl ConversationState {
     /// `strategy` - The [CompactStrategy] used for the corresponding
     /// [ConversationState::create_summary_request].
     pub fn replace_history_with_summary(&mut self, summary: String, strategy: CompactStrategy) {
-        self.history
-            .drain(..(self.history.len().saturating_sub(strategy.messages_to_exclude)));
+        self.drop_oldest_exchanges(self.history.len().saturating_sub(strategy.messages_to_exclude));
         self.latest_summary = Some(summary);
     }
 
@@ -548,6 +693,11 @@ impl ConversationState {
             }
         }
 
+        let pinned_resources = self.tool_manager.pinned_resources().await;
+        if !pinned_resources.is_empty() {
+            context_content.push_str(&format_resource_context(&pinned_resources));
+        }
+
         if let Some(context) = conversation_start_context {
             context_content.push_str(&context);
         }
@@ -594,7 +744,10 @@ impl ConversationState {
         let tool_uses = message.tool_uses().map_or("none".to_string(), |tools| {
             tools.iter().map(|tool| tool.name.clone()).collect::<Vec<_>>().join(",")
         });
-        self.append_transcript(format!("{}\n[Tool uses: {tool_uses}]", message.content()));
+        self.append_transcript(format!(
+            "{}\n{TRANSCRIPT_TOOL_USES_PREFIX}{tool_uses}]",
+            message.content()
+        ));
     }
 
     pub fn append_transcript(&mut self, message: String) {
@@ -737,6 +890,18 @@ fn format_hook_context<'a>(hook_results: impl IntoIterator<Item = &'a (Hook, Str
     context_content
 }
 
+/// Formats the `(@server:uri, contents)` of mcp resources as a context entry
+pub fn format_resource_context(resources: &[(String, String)]) -> String {
+    let mut context_content = String::new();
+    context_content.push_str(CONTEXT_ENTRY_START_HEADER);
+    context_content.push_str("This section contains the contents of MCP resources I referenced.\n\n");
+    for (resource, contents) in resources {
+        context_content.push_str(&format!("[{resource}]\n{contents}\n"));
+    }
+    context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+    context_content
+}
+
 fn enforce_conversation_invariants(
     history: &mut VecDeque<(UserMessage, AssistantMessage)>,
     next_message: &mut Option<UserMessage>,
@@ -1223,4 +1388,159 @@ mod tests {
             conversation.set_next_user_message(i.to_string()).await;
         }
     }
+
+    #[tokio::test]
+    async fn test_conversation_history_saved_and_searchable() {
+        let mut os = Os::new().await.unwrap();
+        let mut conversation = ConversationState::new(
+            "history_conv_id",
+            Agents::default(),
+            HashMap::new(),
+            ToolManager::default(),
+            None,
+        )
+        .await;
+        conversation
+            .set_next_user_message("How do I configure the flux capacitor?\nIt keeps failing".to_string())
+            .await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, "Set the temporal displacement to 88mph.".to_string()),
+        );
+
+        let conversations = os.database.list_conversations(None).unwrap();
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(conversations[0].id, "history_conv_id");
+        assert_eq!(
+            conversations[0].title.as_deref(),
+            Some("How do I configure the flux capacitor?")
+        );
+
+        let results = os.database.search_conversations("temporal displacement", None).unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].snippet.contains("**temporal**"));
+        assert!(os.database.search_conversations("plutonium", None).unwrap().is_empty());
+
+        assert!(os.database.get_conversation("history").unwrap().is_some());
+        assert_eq!(
+            os.database.delete_conversation("history").unwrap().as_deref(),
+            Some("history_conv_id")
+        );
+        assert!(os.database.list_conversations(None).unwrap().is_empty());
+        assert!(os.database.search_conversations("temporal", None).unwrap().is_empty());
+
+        conversation.set_next_user_message("Another question".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "Answer".to_string()));
+        let pruned = os
+            .database
+            .prune_conversations(time::OffsetDateTime::now_utc() + time::Duration::minutes(1))
+            .unwrap();
+        assert_eq!(pruned, 1);
+        assert!(os.database.list_conversations(None).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conversation_checkpoints_follow_history_truncation() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let tool_config = tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap();
+        let mut conversation =
+            ConversationState::new("fake_conv_id", Agents::default(), tool_config, tool_manager, None).await;
+        conversation.set_next_user_message("start".to_string()).await;
+
+        // A history made only of tool results overflows into being cleared from the back.
+        let mut cleared = false;
+        for i in 0..=(MAX_CONVERSATION_STATE_HISTORY_LEN + 100) {
+            let had_checkpoints = !conversation.file_checkpoints.is_empty();
+            conversation
+                .as_sendable_conversation_state(&os, &mut vec![], true)
+                .await
+                .unwrap();
+            cleared |= had_checkpoints && conversation.history().is_empty();
+            assert!(
+                conversation
+                    .file_checkpoints
+                    .iter()
+                    .all(|checkpoint| checkpoint.exchange < conversation.history().len())
+            );
+
+            conversation.push_assistant_message(
+                &mut os,
+                AssistantMessage::new_tool_use(None, i.to_string(), vec![AssistantToolUse {
+                    id: "tool_id".to_string(),
+                    name: "tool name".to_string(),
+                    args: serde_json::Value::Null,
+                    ..Default::default()
+                }]),
+            );
+            conversation
+                .checkpoint_file(&os, PathBuf::from(format!("/file_{i}.txt")))
+                .await;
+            conversation.add_tool_results(vec![ToolUseResult {
+                tool_use_id: "tool_id".to_string(),
+                content: vec![],
+                status: ToolResultStatus::Success,
+            }]);
+        }
+        assert!(cleared);
+    }
+
+    #[tokio::test]
+    async fn test_conversation_rewind_and_fork() {
+        let mut os = Os::new().await.unwrap();
+        let mut conversation = ConversationState::new(
+            "rewind_conv_id",
+            Agents::default(),
+            HashMap::new(),
+            ToolManager::default(),
+            None,
+        )
+        .await;
+        for i in 0..3 {
+            conversation.set_next_user_message(format!("prompt {i}")).await;
+            conversation.append_user_transcript(&format!("prompt {i}"));
+            conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, format!("response {i}")));
+            conversation
+                .checkpoint_file(&os, PathBuf::from(format!("/file_{i}.txt")))
+                .await;
+        }
+
+        let checkpoints = conversation.rewind(2);
+        assert_eq!(conversation.history().len(), 1);
+        assert_eq!(checkpoints.iter().map(|c| c.path.clone()).collect::<Vec<_>>(), vec![
+            PathBuf::from("/file_1.txt"),
+            PathBuf::from("/file_2.txt")
+        ]);
+        assert!(conversation.rewind(0).is_empty());
+        assert_eq!(conversation.transcript, ["> prompt 0", "response 0\n[Tool uses: none]"]);
+
+        // Checkpoints are saved apart from the conversation
+        conversation.save(&mut os);
+        let mut restored = os.database.get_conversation("rewind_conv_id").unwrap().unwrap();
+        assert!(restored.file_checkpoints.is_empty());
+        restored.load_file_checkpoints(&os);
+        assert_eq!(restored.file_checkpoints, conversation.file_checkpoints);
+
+        let original_id = conversation.fork(Some("experiment".to_string()));
+        assert_eq!(original_id, "rewind_conv_id");
+        assert_ne!(conversation.conversation_id(), original_id);
+        assert_eq!(conversation.title().as_deref(), Some("experiment"));
+        conversation.save(&mut os);
+        assert_eq!(
+            os.database
+                .get_file_checkpoints(conversation.conversation_id())
+                .unwrap(),
+            conversation.file_checkpoints
+        );
+
+        conversation.clear(false);
+        conversation.save(&mut os);
+        assert!(
+            os.database
+                .get_file_checkpoints(conversation.conversation_id())
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(os.database.get_file_checkpoints(&original_id).unwrap().len(), 1);
+    }
 }
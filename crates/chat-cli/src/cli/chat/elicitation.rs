@@ -0,0 +1,373 @@
+//! Renders the JSON schemas of `elicitation/create` requests as interactive forms.
+
+use std::io::{
+    ErrorKind,
+    Write,
+};
+
+use crossterm::style::Color;
+use crossterm::{
+    execute,
+    style,
+};
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{
+    Confirm,
+    Input,
+    Select,
+};
+use serde_json::{
+    Map,
+    Value,
+};
+
+use crate::cli::chat::ChatError;
+use crate::mcp_client::{
+    ElicitAction,
+    ElicitResult,
+};
+use crate::util::dialoguer_theme;
+
+/// A property of the requested schema, which is restricted to primitive types by the protocol
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    name: String,
+    title: Option<String>,
+    description: Option<String>,
+    kind: FieldKind,
+    required: bool,
+    default: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldKind {
+    String {
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+    },
+    Number {
+        integer: bool,
+        minimum: Option<f64>,
+        maximum: Option<f64>,
+    },
+    Boolean,
+    Enum {
+        values: Vec<String>,
+        /// Display names of the values
+        names: Vec<String>,
+    },
+}
+
+impl Field {
+    fn label(&self) -> String {
+        let mut label = self.title.clone().unwrap_or_else(|| self.name.clone());
+        if let Some(description) = &self.description {
+            label.push_str(&format!(" ({description})"));
+        }
+        if !self.required {
+            label.push_str(" [optional]");
+        }
+        label
+    }
+
+    /// Converts what the user typed into the value of the field. Empty input leaves optional
+    /// fields out.
+    fn parse(&self, input: &str) -> Result<Option<Value>, String> {
+        let input = input.trim();
+        if input.is_empty() {
+            return if self.required {
+                Err("This field is required".to_string())
+            } else {
+                Ok(None)
+            };
+        }
+        match &self.kind {
+            FieldKind::String { min_length, max_length } => {
+                let len = input.chars().count();
+                if min_length.is_some_and(|min| len < min) || max_length.is_some_and(|max| len > max) {
+                    return Err(format!(
+                        "Must be between {} and {} characters long",
+                        min_length.unwrap_or(0),
+                        max_length.map_or("any number of".to_string(), |max| max.to_string())
+                    ));
+                }
+                Ok(Some(Value::String(input.to_string())))
+            },
+            FieldKind::Number {
+                integer,
+                minimum,
+                maximum,
+            } => {
+                let number = input.parse::<f64>().map_err(|_err| "Must be a number".to_string())?;
+                if *integer && number.fract() != 0.0 {
+                    return Err("Must be an integer".to_string());
+                }
+                if minimum.is_some_and(|min| number < min) || maximum.is_some_and(|max| number > max) {
+                    return Err(format!(
+                        "Must be between {} and {}",
+                        minimum.map_or("-inf".to_string(), |min| min.to_string()),
+                        maximum.map_or("inf".to_string(), |max| max.to_string())
+                    ));
+                }
+                Ok(Some(if *integer {
+                    Value::from(number as i64)
+                } else {
+                    Value::from(number)
+                }))
+            },
+            FieldKind::Boolean => match input.to_lowercase().as_str() {
+                "y" | "yes" | "true" => Ok(Some(Value::Bool(true))),
+                "n" | "no" | "false" => Ok(Some(Value::Bool(false))),
+                _ => Err("Must be yes or no".to_string()),
+            },
+            FieldKind::Enum { values, names } => values
+                .iter()
+                .zip(names)
+                .find(|(value, name)| *value == input || *name == input)
+                .map(|(value, _)| Some(Value::String(value.clone())))
+                .ok_or_else(|| format!("Must be one of {}", names.join(", "))),
+        }
+    }
+}
+
+/// The form described by the requested schema of an elicitation request
+#[derive(Debug, Clone)]
+pub struct Form {
+    fields: Vec<Field>,
+}
+
+impl Form {
+    pub fn from_schema(schema: &Value) -> Result<Self, String> {
+        Ok(Self {
+            fields: fields(schema)?,
+        })
+    }
+
+    /// Asks the user to fill the form. Dismissing any of the prompts cancels the whole request.
+    pub fn fill(&self, server_name: &str, message: &str, stderr: &mut impl Write) -> Result<ElicitResult, ChatError> {
+        let cancel = ElicitResult {
+            action: ElicitAction::Cancel,
+            content: None,
+        };
+
+        execute!(
+            stderr,
+            style::Print("\n"),
+            style::SetForegroundColor(Color::Green),
+            style::Print(server_name),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!(" asks: {message}\n\n")),
+        )?;
+        let theme = dialoguer_theme();
+        let choice = Select::with_theme(&theme)
+            .items(&["Respond", "Decline"])
+            .default(0)
+            .interact_opt()
+            .map_err(dialoguer_error)?;
+        match choice {
+            Some(0) => {},
+            Some(_) => {
+                return Ok(ElicitResult {
+                    action: ElicitAction::Decline,
+                    content: None,
+                });
+            },
+            None => return Ok(cancel),
+        }
+
+        let mut content = Map::new();
+        for field in &self.fields {
+            match field.ask(&theme)? {
+                Answer::Value(value) => {
+                    content.insert(field.name.clone(), value);
+                },
+                Answer::Skipped => {},
+                Answer::Dismissed => return Ok(cancel),
+            }
+        }
+
+        Ok(ElicitResult {
+            action: ElicitAction::Accept,
+            content: Some(content),
+        })
+    }
+}
+
+/// Reads the fields of a requested schema, in the order they are declared
+fn fields(schema: &Value) -> Result<Vec<Field>, String> {
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Err("The requested schema has no properties".to_string());
+    };
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let string = |property: &Value, key: &str| property.get(key).and_then(Value::as_str).map(str::to_string);
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let kind = match (
+                property.get("type").and_then(Value::as_str),
+                property.get("enum").and_then(Value::as_array),
+            ) {
+                (_, Some(values)) => {
+                    let values = values
+                        .iter()
+                        .map(|v| v.as_str().map(str::to_string))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| format!("The values of {name} must be strings"))?;
+                    let names = property
+                        .get("enumNames")
+                        .and_then(Value::as_array)
+                        .map(|names| {
+                            names
+                                .iter()
+                                .filter_map(Value::as_str)
+                                .map(str::to_string)
+                                .collect::<Vec<_>>()
+                        })
+                        .filter(|names| names.len() == values.len())
+                        .unwrap_or_else(|| values.clone());
+                    FieldKind::Enum { values, names }
+                },
+                (Some("string"), None) => FieldKind::String {
+                    min_length: property.get("minLength").and_then(Value::as_u64).map(|n| n as usize),
+                    max_length: property.get("maxLength").and_then(Value::as_u64).map(|n| n as usize),
+                },
+                (Some(ty @ ("number" | "integer")), None) => FieldKind::Number {
+                    integer: ty == "integer",
+                    minimum: property.get("minimum").and_then(Value::as_f64),
+                    maximum: property.get("maximum").and_then(Value::as_f64),
+                },
+                (Some("boolean"), None) => FieldKind::Boolean,
+                (ty, None) => return Err(format!("Unsupported type {} of {name}", ty.unwrap_or("(none)"))),
+            };
+            Ok(Field {
+                name: name.clone(),
+                title: string(property, "title"),
+                description: string(property, "description"),
+                kind,
+                required: required.contains(&name.as_str()),
+                default: property.get("default").cloned(),
+            })
+        })
+        .collect()
+}
+
+/// What the user answered to the prompt of a field
+enum Answer {
+    Value(Value),
+    /// An optional field was left empty
+    Skipped,
+    /// The prompt was dismissed, which cancels the whole form
+    Dismissed,
+}
+
+impl From<Option<Value>> for Answer {
+    fn from(value: Option<Value>) -> Self {
+        value.map_or(Answer::Skipped, Answer::Value)
+    }
+}
+
+impl Field {
+    fn ask(&self, theme: &ColorfulTheme) -> Result<Answer, ChatError> {
+        Ok(match &self.kind {
+            FieldKind::Boolean => Confirm::with_theme(theme)
+                .with_prompt(self.label())
+                .default(self.default.as_ref().and_then(Value::as_bool).unwrap_or(false))
+                .interact_opt()
+                .map_err(dialoguer_error)?
+                .map_or(Answer::Dismissed, |answer| Answer::Value(Value::Bool(answer))),
+            FieldKind::Enum { values, names } => {
+                let mut items = names.clone();
+                if !self.required {
+                    items.push("(skip)".to_string());
+                }
+                let default = self
+                    .default
+                    .as_ref()
+                    .and_then(Value::as_str)
+                    .and_then(|default| values.iter().position(|v| v == default))
+                    .unwrap_or(0);
+                Select::with_theme(theme)
+                    .with_prompt(self.label())
+                    .items(&items)
+                    .default(default)
+                    .interact_opt()
+                    .map_err(dialoguer_error)?
+                    .map_or(Answer::Dismissed, |index| {
+                        values.get(index).map(|value| Value::String(value.clone())).into()
+                    })
+            },
+            FieldKind::String { .. } | FieldKind::Number { .. } => {
+                let mut input = Input::<String>::with_theme(theme)
+                    .with_prompt(self.label())
+                    .allow_empty(true)
+                    .validate_with(|input: &String| self.parse(input).map(|_| ()));
+                match &self.default {
+                    Some(Value::String(default)) => input = input.default(default.clone()),
+                    Some(Value::Number(default)) => input = input.default(default.to_string()),
+                    _ => {},
+                }
+                match input.interact_text() {
+                    // Already validated
+                    Ok(input) => self.parse(&input).unwrap_or_default().into(),
+                    Err(dialoguer::Error::IO(e)) if e.kind() == ErrorKind::Interrupted => Answer::Dismissed,
+                    Err(e) => return Err(dialoguer_error(e)),
+                }
+            },
+        })
+    }
+}
+
+fn dialoguer_error(e: dialoguer::Error) -> ChatError {
+    match e {
+        dialoguer::Error::IO(e) => ChatError::Std(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "title": "Name", "maxLength": 5 },
+                "age": { "type": "integer", "minimum": 0 },
+                "subscribe": { "type": "boolean", "default": true },
+                "color": { "type": "string", "enum": ["r", "g"], "enumNames": ["Red", "Green"] }
+            },
+            "required": ["name", "color"]
+        });
+        let fields = Form::from_schema(&schema).unwrap().fields;
+        let field = |name: &str| fields.iter().find(|f| f.name == name).unwrap();
+
+        let name = field("name");
+        assert!(name.required);
+        assert_eq!(name.label(), "Name");
+        assert_eq!(name.parse("Ann").unwrap(), Some(json!("Ann")));
+        assert!(name.parse("Annabelle").is_err());
+        assert!(name.parse("").is_err());
+
+        let age = field("age");
+        assert_eq!(age.label(), "age [optional]");
+        assert_eq!(age.parse("").unwrap(), None);
+        assert_eq!(age.parse("42").unwrap(), Some(json!(42)));
+        assert!(age.parse("4.2").is_err());
+        assert!(age.parse("-1").is_err());
+
+        assert_eq!(field("subscribe").kind, FieldKind::Boolean);
+        assert_eq!(field("subscribe").default, Some(json!(true)));
+        assert_eq!(field("color").parse("Green").unwrap(), Some(json!("g")));
+        assert!(field("color").parse("Blue").is_err());
+
+        assert!(Form::from_schema(&json!({ "properties": { "a": { "type": "object" } } })).is_err());
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+
+use anstream::eprintln;
+use clap::{
+    Args,
+    ValueEnum,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde::Serialize;
+
+use super::conversation::ConversationState;
+use super::message::{
+    ToolUseResult,
+    ToolUseResultBlock,
+};
+use crate::api_client::model::ToolResultStatus;
+use crate::os::Os;
+
+/// Maximum number of characters of each tool result included in an export.
+const MAX_TOOL_RESULT_LEN: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Markdown, with tool uses and context files in collapsible sections
+    Md,
+    /// A standalone HTML page, with tool uses and context files in collapsible sections
+    Html,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl ExportFormat {
+    /// Guesses the format from the extension of `path`, defaulting to Markdown.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("html" | "htm") => Self::Html,
+            Some("jsonl" | "ndjson") => Self::Jsonl,
+            _ => Self::Md,
+        }
+    }
+}
+
+/// Arguments for `q chat export`.
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ExportConversationArgs {
+    /// Id of the conversation to export. A prefix of the id is enough. See `q history list`
+    pub id: String,
+    /// File to write the export to. Prints to stdout if omitted
+    #[arg(long, short)]
+    pub output: Option<PathBuf>,
+    /// Format of the export. Guessed from the extension of --output if omitted, otherwise
+    /// Markdown
+    #[arg(long, short, value_enum)]
+    pub format: Option<ExportFormat>,
+}
+
+impl ExportConversationArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let (Some(metadata), Some(conversation)) = (
+            os.database.get_conversation_metadata(&self.id)?,
+            os.database.get_conversation(&self.id)?,
+        ) else {
+            bail!("No saved conversation matches {}", self.id);
+        };
+
+        // Context files are resolved against the current directory, so they are only those of the
+        // conversation when exporting from the directory it was held in.
+        let in_conversation_dir = os
+            .env
+            .current_dir()
+            .is_ok_and(|cwd| cwd.to_str() == Some(metadata.cwd.as_str()));
+        if !in_conversation_dir {
+            eprintln!("Leaving out context files. Run from {} to include them", metadata.cwd);
+        }
+
+        let format = match (self.format, &self.output) {
+            (Some(format), _) => format,
+            (None, Some(output)) => ExportFormat::from_path(output),
+            (None, None) => ExportFormat::Md,
+        };
+        let contents = export_conversation(os, &conversation, format, in_conversation_dir).await;
+
+        match self.output {
+            Some(output) => {
+                os.fs.write(&output, contents).await?;
+                eprintln!(
+                    "Exported conversation {} to {}",
+                    conversation.conversation_id(),
+                    output.display()
+                );
+            },
+            None => std::io::stdout().write_all(contents.as_bytes())?,
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Renders `conversation` into a shareable document, leaving out internal state. Context files
+/// are read from the current directory, and only included if `include_context_files` is set.
+pub async fn export_conversation(
+    os: &Os,
+    conversation: &ConversationState,
+    format: ExportFormat,
+    include_context_files: bool,
+) -> String {
+    let entries = collect_entries(os, conversation, include_context_files).await;
+    match format {
+        ExportFormat::Md => render_markdown(&entries),
+        ExportFormat::Html => render_html(&entries),
+        ExportFormat::Jsonl => render_jsonl(&entries),
+    }
+}
+
+/// A single part of an exported conversation. Also the schema of each line of a JSONL export.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ExportEntry {
+    Conversation {
+        id: String,
+        title: Option<String>,
+        agent: Option<String>,
+        model: Option<String>,
+    },
+    ContextFile {
+        path: String,
+        content: String,
+    },
+    Summary {
+        content: String,
+    },
+    User {
+        prompt: String,
+    },
+    Assistant {
+        content: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        args: serde_json::Value,
+        status: Option<&'static str>,
+        result: Option<String>,
+    },
+}
+
+async fn collect_entries(os: &Os, conversation: &ConversationState, include_context_files: bool) -> Vec<ExportEntry> {
+    let mut entries = vec![ExportEntry::Conversation {
+        id: conversation.conversation_id().to_string(),
+        title: conversation.title(),
+        agent: conversation.current_profile().map(str::to_string),
+        model: conversation.model.clone(),
+    }];
+
+    if let Some(context_manager) = conversation.context_manager.as_ref().filter(|_| include_context_files) {
+        if let Ok(files) = context_manager.get_context_files(os).await {
+            entries.extend(
+                files
+                    .into_iter()
+                    .map(|(path, content)| ExportEntry::ContextFile { path, content }),
+            );
+        }
+    }
+
+    if let Some(summary) = conversation.latest_summary() {
+        entries.push(ExportEntry::Summary {
+            content: summary.to_string(),
+        });
+    }
+
+    // Tool results are sent with the user message following the tool uses.
+    let results: HashMap<&str, &ToolUseResult> = conversation
+        .history()
+        .iter()
+        .filter_map(|(user, _)| user.tool_use_results())
+        .flatten()
+        .map(|result| (result.tool_use_id.as_str(), result))
+        .collect();
+
+    for (user, assistant) in conversation.history() {
+        if let Some(prompt) = user.prompt() {
+            entries.push(ExportEntry::User {
+                prompt: prompt.to_string(),
+            });
+        }
+
+        if !assistant.content().trim().is_empty() {
+            entries.push(ExportEntry::Assistant {
+                content: assistant.content().to_string(),
+            });
+        }
+
+        for tool_use in assistant.tool_uses().unwrap_or_default() {
+            let result = results.get(tool_use.id.as_str());
+            entries.push(ExportEntry::ToolUse {
+                id: tool_use.id.clone(),
+                name: tool_use.name.clone(),
+                args: tool_use.args.clone(),
+                status: result.map(|result| match result.status {
+                    ToolResultStatus::Success => "success",
+                    ToolResultStatus::Error => "error",
+                }),
+                result: result.map(|result| truncate(&tool_result_text(result), MAX_TOOL_RESULT_LEN)),
+            });
+        }
+    }
+
+    entries
+}
+
+fn tool_result_text(result: &ToolUseResult) -> String {
+    result
+        .content
+        .iter()
+        .map(|block| match block {
+            ToolUseResultBlock::Text(text) => text.clone(),
+            ToolUseResultBlock::Json(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn truncate(text: &str, max_len: usize) -> String {
+    match text.char_indices().nth(max_len) {
+        Some((idx, _)) => format!("{}\n… ({} more characters)", &text[..idx], text[idx..].chars().count()),
+        None => text.to_string(),
+    }
+}
+
+fn render_markdown(entries: &[ExportEntry]) -> String {
+    let mut out = String::new();
+    for entry in entries {
+        match entry {
+            ExportEntry::Conversation {
+                id,
+                title,
+                agent,
+                model,
+            } => {
+                let _ = writeln!(out, "# {}\n", title.as_deref().unwrap_or("Conversation"));
+                let _ = writeln!(out, "- Conversation: `{id}`");
+                if let Some(agent) = agent {
+                    let _ = writeln!(out, "- Agent: `{agent}`");
+                }
+                if let Some(model) = model {
+                    let _ = writeln!(out, "- Model: `{model}`");
+                }
+                out.push('\n');
+            },
+            ExportEntry::ContextFile { path, content } => {
+                let _ = writeln!(
+                    out,
+                    "<details>\n<summary>Context file: <code>{path}</code></summary>\n\n{}\n</details>\n",
+                    code_block(content, "")
+                );
+            },
+            ExportEntry::Summary { content } => {
+                let _ = writeln!(out, "## Summary of earlier conversation\n\n{content}\n");
+            },
+            ExportEntry::User { prompt } => {
+                let _ = writeln!(out, "## User\n\n{prompt}\n");
+            },
+            ExportEntry::Assistant { content } => {
+                let _ = writeln!(out, "## Assistant\n\n{content}\n");
+            },
+            ExportEntry::ToolUse {
+                name,
+                args,
+                status,
+                result,
+                ..
+            } => {
+                let args = serde_json::to_string_pretty(args).unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "<details>\n<summary>Tool use: <code>{name}</code> ({})</summary>\n\n{}",
+                    status.unwrap_or("no result"),
+                    code_block(&args, "json")
+                );
+                if let Some(result) = result {
+                    let _ = writeln!(out, "Result:\n\n{}", code_block(result, ""));
+                }
+                out.push_str("</details>\n\n");
+            },
+        }
+    }
+
+    out
+}
+
+/// Wraps `content` in a fence longer than any run of backticks it contains.
+fn code_block(content: &str, language: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or_default();
+    let fence = "`".repeat((longest_run + 1).max(3));
+    format!("{fence}{language}\n{}\n{fence}\n", content.trim_end_matches('\n'))
+}
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; }
+.message { white-space: pre-wrap; border-radius: 6px; padding: 0.75rem 1rem; margin: 1rem 0; }
+.user { background: #e8f0fe; }
+.assistant { background: #f6f8fa; }
+.summary { background: #fff8e1; }
+details { border: 1px solid #d0d7de; border-radius: 6px; padding: 0.5rem 1rem; margin: 0.5rem 0; }
+pre { white-space: pre-wrap; background: #f6f8fa; padding: 0.5rem; overflow-x: auto; }";
+
+fn render_html(entries: &[ExportEntry]) -> String {
+    let title = entries
+        .iter()
+        .find_map(|entry| match entry {
+            ExportEntry::Conversation { title, .. } => title.as_deref(),
+            _ => None,
+        })
+        .unwrap_or("Conversation");
+
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{HTML_STYLE}\n</style>\n</head>\n<body>\n",
+        escape_html(title)
+    );
+    for entry in entries {
+        match entry {
+            ExportEntry::Conversation {
+                id,
+                title,
+                agent,
+                model,
+            } => {
+                let _ = writeln!(
+                    out,
+                    "<h1>{}</h1>\n<ul>\n<li>Conversation: <code>{}</code></li>",
+                    escape_html(title.as_deref().unwrap_or("Conversation")),
+                    escape_html(id)
+                );
+                if let Some(agent) = agent {
+                    let _ = writeln!(out, "<li>Agent: <code>{}</code></li>", escape_html(agent));
+                }
+                if let Some(model) = model {
+                    let _ = writeln!(out, "<li>Model: <code>{}</code></li>", escape_html(model));
+                }
+                out.push_str("</ul>\n");
+            },
+            ExportEntry::ContextFile { path, content } => {
+                let _ = writeln!(
+                    out,
+                    "<details>\n<summary>Context file: <code>{}</code></summary>\n<pre>{}</pre>\n</details>",
+                    escape_html(path),
+                    escape_html(content)
+                );
+            },
+            ExportEntry::Summary { content } => {
+                let _ = writeln!(
+                    out,
+                    "<h2>Summary of earlier conversation</h2>\n<div class=\"message summary\">{}</div>",
+                    escape_html(content)
+                );
+            },
+            ExportEntry::User { prompt } => {
+                let _ = writeln!(
+                    out,
+                    "<h2>User</h2>\n<div class=\"message user\">{}</div>",
+                    escape_html(prompt)
+                );
+            },
+            ExportEntry::Assistant { content } => {
+                let _ = writeln!(
+                    out,
+                    "<h2>Assistant</h2>\n<div class=\"message assistant\">{}</div>",
+                    escape_html(content)
+                );
+            },
+            ExportEntry::ToolUse {
+                name,
+                args,
+                status,
+                result,
+                ..
+            } => {
+                let args = serde_json::to_string_pretty(args).unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "<details>\n<summary>Tool use: <code>{}</code> ({})</summary>\n<pre>{}</pre>",
+                    escape_html(name),
+                    status.unwrap_or("no result"),
+                    escape_html(&args)
+                );
+                if let Some(result) = result {
+                    let _ = writeln!(out, "<p>Result:</p>\n<pre>{}</pre>", escape_html(result));
+                }
+                out.push_str("</details>\n");
+            },
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn render_jsonl(entries: &[ExportEntry]) -> String {
+    entries
+        .iter()
+        .filter_map(|entry| serde_json::to_string(entry).ok())
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<ExportEntry> {
+        vec![
+            ExportEntry::Conversation {
+                id: "conv-1".to_string(),
+                title: Some("Fix <the> build".to_string()),
+                agent: Some("default".to_string()),
+                model: None,
+            },
+            ExportEntry::User {
+                prompt: "Why does the build fail?".to_string(),
+            },
+            ExportEntry::Assistant {
+                content: "Let me check.".to_string(),
+            },
+            ExportEntry::ToolUse {
+                id: "tool-1".to_string(),
+                name: "execute_bash".to_string(),
+                args: serde_json::json!({ "command": "cargo build" }),
+                status: Some("error"),
+                result: Some("error: ```missing``` crate".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_export_format_from_path() {
+        assert_eq!(ExportFormat::from_path("out.HTML"), ExportFormat::Html);
+        assert_eq!(ExportFormat::from_path("out.jsonl"), ExportFormat::Jsonl);
+        assert_eq!(ExportFormat::from_path("out.md"), ExportFormat::Md);
+        assert_eq!(ExportFormat::from_path("out"), ExportFormat::Md);
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let markdown = render_markdown(&entries());
+        assert!(markdown.starts_with("# Fix <the> build\n"));
+        assert!(markdown.contains("## User\n\nWhy does the build fail?"));
+        assert!(markdown.contains("<summary>Tool use: <code>execute_bash</code> (error)</summary>"));
+        // The result contains a fence of three backticks, so it must be wrapped in a longer one
+        assert!(markdown.contains("````\nerror: ```missing``` crate\n````"));
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html(&entries());
+        assert!(html.contains("<title>Fix &lt;the&gt; build</title>"));
+        assert!(html.contains("<div class=\"message user\">Why does the build fail?</div>"));
+        assert!(html.contains("&quot;command&quot;: &quot;cargo build&quot;"));
+    }
+
+    #[test]
+    fn test_render_jsonl() {
+        let jsonl = render_jsonl(&entries());
+        let lines: Vec<serde_json::Value> = jsonl.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["type"], "conversation");
+        assert_eq!(lines[3]["type"], "tool_use");
+        assert_eq!(lines[3]["args"]["command"], "cargo build");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("abcdef", 4), "abcd\n… (2 more characters)");
+    }
+}
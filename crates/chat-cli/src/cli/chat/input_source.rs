@@ -1,7 +1,10 @@
 use eyre::Result;
 use rustyline::error::ReadlineError;
 
-use super::prompt::rl;
+use super::prompt::{
+    CompletionRequest,
+    rl,
+};
 #[cfg(unix)]
 use super::skim_integration::SkimCommandSelector;
 use crate::os::Os;
@@ -30,7 +33,7 @@ mod inner {
 impl InputSource {
     pub fn new(
         os: &Os,
-        sender: std::sync::mpsc::Sender<Option<String>>,
+        sender: std::sync::mpsc::Sender<CompletionRequest>,
         receiver: std::sync::mpsc::Receiver<Vec<String>>,
     ) -> Result<Self> {
         Ok(Self(inner::Inner::Readline(rl(os, sender, receiver)?)))
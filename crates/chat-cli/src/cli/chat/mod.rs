@@ -1,8 +1,11 @@
+mod checkpoint;
 pub mod cli;
 mod consts;
 pub mod context;
 mod conversation;
+mod elicitation;
 mod error_formatter;
+mod export;
 mod input_source;
 mod message;
 mod parse;
@@ -10,7 +13,12 @@ use std::path::MAIN_SEPARATOR;
 mod parser;
 mod prompt;
 mod prompt_parser;
+pub mod server_config_vars;
+mod server_logs;
 mod server_messenger;
+mod server_requests;
+mod server_supervisor;
+mod server_tool_cache;
 #[cfg(unix)]
 mod skim_integration;
 mod token_counter;
@@ -32,14 +40,19 @@ use std::process::ExitCode;
 use std::time::Duration;
 
 use amzn_codewhisperer_client::types::SubscriptionStatus;
+pub use checkpoint::FileCheckpoint;
 use clap::{
     Args,
     CommandFactory,
     Parser,
+    Subcommand,
 };
 use cli::compact::CompactStrategy;
 pub use conversation::ConversationState;
-use conversation::TokenWarningLevel;
+use conversation::{
+    TokenWarningLevel,
+    format_resource_context,
+};
 use crossterm::style::{
     Attribute,
     Color,
@@ -52,6 +65,7 @@ use crossterm::{
     style,
     terminal,
 };
+pub use export::ExportConversationArgs;
 use eyre::{
     Report,
     Result,
@@ -73,7 +87,9 @@ use parser::{
     RecvErrorKind,
     ResponseParser,
 };
+use prompt::CompletionRequest;
 use regex::Regex;
+use server_requests::UnattendedRequests;
 use spinners::{
     Spinner,
     Spinners,
@@ -92,6 +108,7 @@ use tools::{
     QueuedTool,
     Tool,
     ToolSpec,
+    sanitize_path_tool_arg,
 };
 use tracing::{
     debug,
@@ -135,6 +152,13 @@ use crate::telemetry::{
     get_error_reason,
 };
 use crate::util::MCP_SERVER_TOOL_DELIMITER;
+use crate::util::audit_log::{
+    AuditEntry,
+    AuditLog,
+    DecisionSource,
+    ExitStatus,
+    PermissionDecision,
+};
 
 const LIMIT_REACHED_TEXT: &str = color_print::cstr! { "You've used all your free requests for this month. You have two options:
 1. Upgrade to a paid subscription for increased limits. See our Pricing page for what's included> <blue!>https://aws.amazon.com/q/developer/pricing/</blue!>
@@ -160,6 +184,9 @@ pub struct ChatArgs {
     /// Resumes the previous conversation from this directory.
     #[arg(short, long)]
     pub resume: bool,
+    /// Pick a previous conversation from any directory to resume.
+    #[arg(long, conflicts_with_all = ["resume", "no_interactive"])]
+    pub resume_picker: bool,
     /// Context profile to use
     #[arg(long = "agent", alias = "profile")]
     pub agent: Option<String>,
@@ -181,10 +208,22 @@ pub struct ChatArgs {
     /// Run migration of legacy profiles to agents if applicable
     #[arg(long)]
     pub migrate: bool,
+    #[command(subcommand)]
+    pub subcommand: Option<ChatSubcommand>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum ChatSubcommand {
+    /// Export a saved conversation to Markdown, HTML or JSONL
+    Export(ExportConversationArgs),
 }
 
 impl ChatArgs {
     pub async fn execute(mut self, os: &mut Os) -> Result<ExitCode> {
+        if let Some(ChatSubcommand::Export(args)) = self.subcommand.take() {
+            return args.execute(os).await;
+        }
+
         let mut input = self.input;
 
         if self.no_interactive && input.is_none() {
@@ -207,6 +246,21 @@ impl ChatArgs {
             }
         }
 
+        let previous_conversation = if self.resume_picker {
+            let conversations = os.database.list_conversations(None)?;
+            match cli::resume::select_conversation(&conversations)? {
+                Some(id) => os.database.get_conversation(&id)?,
+                None => None,
+            }
+        } else if self.resume {
+            std::env::current_dir()
+                .ok()
+                .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
+                .flatten()
+        } else {
+            None
+        };
+
         let stdout = std::io::stdout();
         let mut stderr = std::io::stderr();
 
@@ -277,7 +331,7 @@ impl ChatArgs {
 
         let conversation_id = uuid::Uuid::new_v4().to_string();
         info!(?conversation_id, "Generated new conversation id");
-        let (prompt_request_sender, prompt_request_receiver) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, prompt_request_receiver) = std::sync::mpsc::channel::<CompletionRequest>();
         let (prompt_response_sender, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let mut tool_manager = ToolManagerBuilder::default()
             .prompt_list_sender(prompt_response_sender)
@@ -296,7 +350,7 @@ impl ChatArgs {
             agents,
             input,
             InputSource::new(os, prompt_request_sender, prompt_response_receiver)?,
-            self.resume,
+            previous_conversation,
             || terminal::window_size().map(|s| s.columns.into()).ok(),
             tool_manager,
             model_id,
@@ -477,6 +531,8 @@ pub struct ChatSession {
     pending_tool_index: Option<usize>,
     /// Telemetry events to be sent as part of the conversation.
     tool_use_telemetry_events: HashMap<String, ToolUseEventBuilder>,
+    /// Audit log entries for tool uses that have not finished yet, keyed by tool use id.
+    tool_use_audit_entries: HashMap<String, AuditEntry>,
     /// State used to keep track of tool use relation
     tool_use_status: ToolUseStatus,
     /// Any failed requests that could be useful for error report/debugging
@@ -497,7 +553,7 @@ impl ChatSession {
         mut agents: Agents,
         mut input: Option<String>,
         input_source: InputSource,
-        resume_conversation: bool,
+        previous_conversation: Option<ConversationState>,
         terminal_width_provider: fn() -> Option<usize>,
         tool_manager: ToolManager,
         model_id: Option<String>,
@@ -527,17 +583,12 @@ impl ChatSession {
 
         // Reload prior conversation
         let mut existing_conversation = false;
-        let previous_conversation = std::env::current_dir()
-            .ok()
-            .and_then(|cwd| os.database.get_conversation_by_path(cwd).ok())
-            .flatten();
 
         // Only restore conversations where there were actual messages.
         // Prevents edge case where user clears conversation then exits without chatting.
-        let conversation = match resume_conversation
-            && previous_conversation
-                .as_ref()
-                .is_some_and(|cs| !cs.history().is_empty())
+        let conversation = match previous_conversation
+            .as_ref()
+            .is_some_and(|cs| !cs.history().is_empty())
         {
             true => {
                 let mut cs = previous_conversation.unwrap();
@@ -559,6 +610,7 @@ impl ChatSession {
                     }
                 }
                 cs.agents = agents;
+                cs.load_file_checkpoints(os);
                 cs.update_state(true).await;
                 cs.enforce_tool_use_history_invariants();
                 cs
@@ -580,6 +632,7 @@ impl ChatSession {
             tool_uses: vec![],
             pending_tool_index: None,
             tool_use_telemetry_events: HashMap::new(),
+            tool_use_audit_entries: HashMap::new(),
             tool_use_status: ToolUseStatus::Idle,
             failed_request_ids: Vec::new(),
             pending_prompts: VecDeque::new(),
@@ -593,7 +646,17 @@ impl ChatSession {
         self.conversation.update_state(false).await;
 
         let ctrl_c_stream = ctrl_c();
-        let result = match self.inner.take().expect("state must always be Some") {
+        let state = self.inner.take().expect("state must always be Some");
+        // Tools are run and the user is prompted in the foreground, where server requests can be
+        // brought to the user. Anywhere else, they are answered in the background.
+        let unattended = match state {
+            ChatState::HandleInput { .. }
+            | ChatState::CompactHistory { .. }
+            | ChatState::ValidateTools(_)
+            | ChatState::HandleResponseStream(_) => Some(self.answer_server_requests_in_background(os)),
+            ChatState::PromptUser { .. } | ChatState::ExecuteTools | ChatState::Exit => None,
+        };
+        let result = match state {
             ChatState::PromptUser { skip_printing_tools } => {
                 match (self.interactive, self.tool_uses.is_empty()) {
                     (false, true) => {
@@ -646,6 +709,9 @@ impl ChatSession {
             },
             ChatState::Exit => return Ok(()),
         };
+        if let Some(unattended) = unattended {
+            unattended.stop().await;
+        }
 
         let err = match result {
             Ok(state) => {
@@ -657,6 +723,12 @@ impl ChatSession {
 
         // We encountered an error. Handle it.
         error!(?err, "An error occurred processing the current state");
+        // Tool uses still pending or running at this point are abandoned.
+        let exit_status = match err {
+            ChatError::Interrupted { .. } => ExitStatus::Interrupted,
+            _ => ExitStatus::Error,
+        };
+        self.record_abandoned_tool_uses(os, exit_status).await;
         let (reason, reason_desc) = get_error_reason(&err);
         self.send_error_telemetry(os, reason, Some(reason_desc), err.status_code())
             .await;
@@ -1045,11 +1117,16 @@ impl ChatSession {
             self.inner = Some(ChatState::HandleInput { input: user_input });
         }
 
-        while !matches!(self.inner, Some(ChatState::Exit)) {
-            self.next(os).await?;
+        let result = async {
+            while !matches!(self.inner, Some(ChatState::Exit)) {
+                self.next(os).await?;
+            }
+            Ok(())
         }
+        .await;
 
-        Ok(())
+        self.conversation.tool_manager.language_servers.shutdown_all().await;
+        result
     }
 
     /// Compacts the conversation history using the strategy specified by [CompactStrategy],
@@ -1307,6 +1384,19 @@ impl ChatSession {
     async fn prompt_user(&mut self, os: &Os, skip_printing_tools: bool) -> Result<ChatState, ChatError> {
         execute!(self.stderr, cursor::Show)?;
 
+        while let Some(request) = self.conversation.tool_manager.try_next_server_request() {
+            server_requests::handle_server_request(
+                os,
+                request,
+                &self.conversation.agents,
+                self.conversation.model.as_deref(),
+                &mut self.input_source,
+                &mut self.stderr,
+                self.interactive,
+            )
+            .await;
+        }
+
         // Check token usage and display warnings if needed
         if self.pending_tool_index.is_none() {
             // Only display warnings when not waiting for tool approval
@@ -1368,7 +1458,10 @@ impl ChatSession {
             style::SetAttribute(Attribute::Reset)
         )?;
         let prompt = self.generate_tool_trust_prompt();
-        let user_input = match self.read_user_input(&prompt, false) {
+        let unattended = self.answer_server_requests_in_background(os);
+        let user_input = self.read_user_input(&prompt, false);
+        unattended.stop().await;
+        let user_input = match user_input {
             Some(input) => input,
             None => return Ok(ChatState::Exit),
         };
@@ -1386,6 +1479,11 @@ impl ChatSession {
         if let Some(chat_state) = does_input_reference_file(input) {
             return Ok(chat_state);
         }
+        // A message starting with @server:uri mentions a resource rather than invoking a prompt
+        let starts_with_resource = input
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| !self.conversation.tool_manager.resource_mentions(word).is_empty());
         if let Some(mut args) = input.strip_prefix("/").and_then(shlex::split) {
             // Required for printing errors correctly.
             let orig_args = args.clone();
@@ -1477,7 +1575,7 @@ impl ChatSession {
             Ok(ChatState::PromptUser {
                 skip_printing_tools: false,
             })
-        } else if let Some(command) = input.strip_prefix("@") {
+        } else if let Some(command) = input.strip_prefix("@").filter(|_| !starts_with_resource) {
             let input_parts =
                 shlex::split(command).ok_or(ChatError::Custom("Error splitting prompt command".into()))?;
 
@@ -1551,6 +1649,9 @@ impl ChatSession {
                         self.conversation.agents.trust_tools(vec![formatted_tool_name]);
                     }
                     tool_use.accepted = true;
+                    if let Some(entry) = self.tool_use_audit_entries.get_mut(&tool_use.id) {
+                        entry.decide(PermissionDecision::Allowed, DecisionSource::User);
+                    }
 
                     return Ok(ChatState::ExecuteTools);
                 }
@@ -1576,8 +1677,10 @@ impl ChatSession {
                 } else {
                     user_input
                 };
+                self.record_rejected_tool_uses(os).await;
                 self.conversation.abandon_tool_use(&self.tool_uses, user_input);
             } else {
+                let user_input = self.attach_mentioned_resources(user_input).await?;
                 self.conversation.set_next_user_message(user_input).await;
             }
 
@@ -1601,6 +1704,30 @@ impl ChatSession {
         }
     }
 
+    /// Reads the resources mentioned as `@server:uri` in the input and appends their contents
+    async fn attach_mentioned_resources(&mut self, user_input: String) -> Result<String, ChatError> {
+        let mut resources = Vec::new();
+        for (server_name, uri) in self.conversation.tool_manager.resource_mentions(&user_input) {
+            let contents = match self.conversation.tool_manager.start_server(&server_name).await {
+                Some(Err(e)) => Err(e.wrap_err(format!("Failed to start {server_name}"))),
+                _ => self.conversation.tool_manager.read_resource(&server_name, &uri).await,
+            };
+            match contents {
+                Ok(contents) => resources.push((format!("@{server_name}:{uri}"), contents)),
+                Err(e) => queue!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!("Failed to read @{server_name}:{uri}: {e}\n")),
+                    style::SetForegroundColor(Color::Reset)
+                )?,
+            }
+        }
+        if resources.is_empty() {
+            return Ok(user_input);
+        }
+        Ok(format!("{user_input}\n\n{}", format_resource_context(&resources)))
+    }
+
     async fn tool_use_execute(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
         // Verify tools have permissions.
         for i in 0..self.tool_uses.len() {
@@ -1612,7 +1739,7 @@ impl ChatSession {
             }
 
             let mut denied = false;
-            let allowed =
+            let allowed_by_agent =
                 self.conversation
                     .agents
                     .get_active()
@@ -1623,8 +1750,24 @@ impl ChatSession {
                             denied = true;
                             false
                         },
-                    })
-                    || self.conversation.agents.trust_all_tools;
+                    });
+            let allowed = allowed_by_agent || self.conversation.agents.trust_all_tools;
+
+            if let Some(mut entry) = self.tool_use_audit_entries.remove(&tool.id) {
+                if denied {
+                    entry.decide(PermissionDecision::Denied, DecisionSource::Auto);
+                    AuditLog::append(os, &entry).await;
+                } else {
+                    if allowed {
+                        let source = match allowed_by_agent {
+                            true => DecisionSource::Auto,
+                            false => DecisionSource::TrustAll,
+                        };
+                        entry.decide(PermissionDecision::Allowed, source);
+                    }
+                    self.tool_use_audit_entries.insert(tool.id.clone(), entry);
+                }
+            }
 
             if denied {
                 return Ok(ChatState::HandleInput {
@@ -1661,16 +1804,87 @@ impl ChatSession {
             });
         }
 
+        // Lazy servers are only started once the use of one of their tools has been approved
+        let mut start_failures = HashMap::new();
+        for tool in &mut self.tool_uses {
+            let Tool::Custom(custom_tool) = &mut tool.tool else {
+                continue;
+            };
+            if custom_tool.client.is_some() {
+                continue;
+            }
+            let server_name = custom_tool.server_name.clone();
+            match self.conversation.tool_manager.start_server(&server_name).await {
+                Some(Ok(())) => queue!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("Started {server_name}\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?,
+                Some(Err(err)) => {
+                    start_failures.insert(
+                        tool.id.clone(),
+                        eyre::eyre!("Failed to start the mcp server {server_name}: {err}"),
+                    );
+                },
+                None => {},
+            }
+            custom_tool.client = self.conversation.tool_manager.clients.get(&server_name).cloned();
+        }
+
         // Execute the requested tools.
         let mut tool_results = vec![];
         let mut image_blocks: Vec<RichImageBlock> = Vec::new();
 
+        let language_servers = self.conversation.tool_manager.language_servers.clone();
         for tool in &self.tool_uses {
+            if let Tool::FsWrite(fs_write) = &tool.tool {
+                let path = sanitize_path_tool_arg(os, fs_write.path());
+                self.conversation.checkpoint_file(os, path).await;
+            }
+
             let mut tool_telemetry = self.tool_use_telemetry_events.entry(tool.id.clone());
             tool_telemetry = tool_telemetry.and_modify(|ev| ev.is_accepted = true);
 
             let tool_start = std::time::Instant::now();
-            let invoke_result = tool.tool.invoke(os, &mut self.stdout).await;
+            let invoke_result = match start_failures.remove(&tool.id) {
+                Some(err) => Err(err),
+                None => {
+                    let invoke = tool.tool.invoke(os, &mut self.stdout, &language_servers);
+                    tokio::pin!(invoke);
+                    loop {
+                        tokio::select! {
+                            result = &mut invoke => break result,
+                            // Servers commonly ask to sample from the model while one of their tools runs
+                            Some(request) = self.conversation.tool_manager.next_server_request() => {
+                                // The user may be asked to approve the request
+                                let had_spinner = self.spinner.take().is_some();
+                                if had_spinner {
+                                    queue!(
+                                        self.stderr,
+                                        terminal::Clear(terminal::ClearType::CurrentLine),
+                                        cursor::MoveToColumn(0),
+                                        cursor::Show
+                                    )?;
+                                }
+                                server_requests::handle_server_request(
+                                    os,
+                                    request,
+                                    &self.conversation.agents,
+                                    self.conversation.model.as_deref(),
+                                    &mut self.input_source,
+                                    &mut self.stderr,
+                                    self.interactive,
+                                )
+                                .await;
+                                if had_spinner {
+                                    self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_owned()));
+                                }
+                            },
+                        }
+                    }
+                },
+            };
 
             if self.spinner.is_some() {
                 queue!(
@@ -1683,6 +1897,13 @@ impl ChatSession {
             execute!(self.stdout, style::Print("\n"))?;
 
             let tool_time = std::time::Instant::now().duration_since(tool_start);
+            if let Some(mut entry) = self.tool_use_audit_entries.remove(&tool.id) {
+                match &invoke_result {
+                    Ok(result) => entry.complete(ExitStatus::Success, tool_time, result.as_str()),
+                    Err(err) => entry.complete(ExitStatus::Error, tool_time, &err.to_string()),
+                }
+                AuditLog::append(os, &entry).await;
+            }
             if let Tool::Custom(ct) = &tool.tool {
                 tool_telemetry = tool_telemetry.and_modify(|ev| {
                     ev.custom_tool_call_latency = Some(tool_time.as_secs() as usize);
@@ -2053,11 +2274,27 @@ impl ChatSession {
         for tool_use in tool_uses {
             let tool_use_id = tool_use.id.clone();
             let tool_use_name = tool_use.name.clone();
+            let tool_use_args = tool_use.args.clone();
             let mut tool_telemetry =
                 ToolUseEventBuilder::new(conv_id.clone(), tool_use.id.clone(), self.conversation.model.clone())
                     .set_tool_use_id(tool_use_id.clone())
                     .set_tool_name(tool_use.name.clone())
                     .utterance_id(self.conversation.message_id().map(|s| s.to_string()));
+            let origin = match self.conversation.tool_manager.tn_map.get(&tool_use_name) {
+                Some(info) => info.server_name.clone(),
+                None if self.conversation.tool_manager.script_tools.contains_key(&tool_use_name) => {
+                    "script".to_string()
+                },
+                None => "native".to_string(),
+            };
+            let mut audit_entry = AuditEntry::new(
+                conv_id.clone(),
+                self.conversation.agents.get_active().map(|a| a.name.clone()),
+                tool_use_id.clone(),
+                tool_use_name.clone(),
+                origin,
+                tool_use_args,
+            );
             match self.conversation.tool_manager.get_tool_from_tool_use(tool_use) {
                 Ok(mut tool) => {
                     // Apply non-Q-generated context to tools
@@ -2066,6 +2303,7 @@ impl ChatSession {
                     match tool.validate(os).await {
                         Ok(()) => {
                             tool_telemetry.is_valid = Some(true);
+                            self.tool_use_audit_entries.insert(tool_use_id.clone(), audit_entry);
                             queued_tools.push(QueuedTool {
                                 id: tool_use_id.clone(),
                                 name: tool_use_name,
@@ -2082,12 +2320,16 @@ impl ChatSession {
                                 ))],
                                 status: ToolResultStatus::Error,
                             });
+                            audit_entry.exit_status = ExitStatus::Invalid;
+                            AuditLog::append(os, &audit_entry).await;
                         },
                     };
                 },
                 Err(err) => {
                     tool_telemetry.is_valid = Some(false);
                     tool_results.push(err.into());
+                    audit_entry.exit_status = ExitStatus::Invalid;
+                    AuditLog::append(os, &audit_entry).await;
                 },
             }
             self.tool_use_telemetry_events.insert(tool_use_id, tool_telemetry);
@@ -2096,6 +2338,12 @@ impl ChatSession {
         // If we have any validation errors, then return them immediately to the model.
         if !tool_results.is_empty() {
             debug!(?tool_results, "Error found in the model tools");
+            // The valid tool uses of the batch are dropped along with the invalid ones.
+            for tool in &queued_tools {
+                if let Some(entry) = self.tool_use_audit_entries.remove(&tool.id) {
+                    AuditLog::append(os, &entry).await;
+                }
+            }
             queue!(
                 self.stderr,
                 style::SetAttribute(Attribute::Bold),
@@ -2191,7 +2439,7 @@ impl ChatSession {
                 style::SetForegroundColor(Color::Reset),
                 style::Print(" from mcp server "),
                 style::SetForegroundColor(Color::Magenta),
-                style::Print(tool.client.get_server_name()),
+                style::Print(&tool.server_name),
                 style::SetForegroundColor(Color::Reset),
             )?;
         }
@@ -2244,6 +2492,39 @@ impl ChatSession {
         }
     }
 
+    /// Answers the requests of servers in the background until stopped, for while the session
+    /// can't bring them to the user.
+    fn answer_server_requests_in_background(&self, os: &Os) -> UnattendedRequests {
+        UnattendedRequests::spawn(
+            os.clone(),
+            self.conversation.tool_manager.server_request_receiver(),
+            &self.conversation.agents,
+            self.conversation.model.clone(),
+        )
+    }
+
+    /// Writes audit entries for every tool use that was still pending or running when the session
+    /// was interrupted or failed. Decisions already made are kept.
+    async fn record_abandoned_tool_uses(&mut self, os: &Os, exit_status: ExitStatus) {
+        for (_, mut entry) in self.tool_use_audit_entries.drain() {
+            entry.exit_status = exit_status;
+            AuditLog::append(os, &entry).await;
+        }
+    }
+
+    /// Writes audit entries for tool uses that were abandoned because the user declined to run
+    /// them. Tool uses that were already allowed keep their decision and are recorded as not run.
+    async fn record_rejected_tool_uses(&mut self, os: &Os) {
+        for tool_use in &self.tool_uses {
+            if let Some(mut entry) = self.tool_use_audit_entries.remove(&tool_use.id) {
+                if entry.decision.is_none() {
+                    entry.decide(PermissionDecision::Rejected, DecisionSource::User);
+                }
+                AuditLog::append(os, &entry).await;
+            }
+        }
+    }
+
     /// Helper function to generate a prompt based on the current context
     fn generate_tool_trust_prompt(&mut self) -> String {
         let profile = self.conversation.current_profile().map(|s| s.to_string());
@@ -2536,7 +2817,7 @@ mod tests {
                 "y".to_string(),
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2677,7 +2958,7 @@ mod tests {
                 "n".to_string(),             // cancel
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2773,7 +3054,7 @@ mod tests {
                 "y".to_string(),
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2847,7 +3128,7 @@ mod tests {
                 "create a new file".to_string(),
                 "exit".to_string(),
             ]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -2897,7 +3178,7 @@ mod tests {
             agents,
             None,
             InputSource::new_mock(vec!["/subscribe".to_string(), "y".to_string(), "/quit".to_string()]),
-            false,
+            None,
             || Some(80),
             tool_manager,
             None,
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::HashMap;
 
 use eyre::Result;
 use rustyline::completion::{
@@ -11,7 +12,10 @@ use rustyline::highlight::{
     CmdKind,
     Highlighter,
 };
-use rustyline::hint::Hinter as RustylineHinter;
+use rustyline::hint::{
+    Hint,
+    Hinter as RustylineHinter,
+};
 use rustyline::history::DefaultHistory;
 use rustyline::validate::{
     ValidationContext,
@@ -28,7 +32,6 @@ use rustyline::{
     Editor,
     EventHandler,
     Helper,
-    Hinter,
     KeyCode,
     KeyEvent,
     Modifiers,
@@ -37,7 +40,9 @@ use winnow::stream::AsChar;
 
 pub use super::prompt_parser::generate_prompt;
 use super::prompt_parser::parse_prompt_components;
+use super::tool_manager::assign_prompt_arguments;
 use crate::database::settings::Setting;
+use crate::mcp_client::PromptGetArg;
 use crate::os::Os;
 
 pub const COMMANDS: &[&str] = &[
@@ -52,6 +57,14 @@ pub const COMMANDS: &[&str] = &[
     "/tools trust-all",
     "/tools reset",
     "/mcp",
+    "/mcp resources",
+    "/mcp pin",
+    "/mcp unpin",
+    "/mcp roots",
+    "/mcp roots add",
+    "/mcp roots remove",
+    "/mcp restart",
+    "/mcp logs",
     "/model",
     "/agent",
     "/agent help",
@@ -82,6 +95,10 @@ pub const COMMANDS: &[&str] = &[
     "/usage",
     "/save",
     "/load",
+    "/resume",
+    "/rewind",
+    "/fork",
+    "/export",
     "/subscribe",
 ];
 
@@ -133,40 +150,298 @@ impl PathCompleter {
     }
 }
 
+/// A request for completions sent to the tool manager, which knows about the prompts and resources
+/// of the mcp servers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionRequest {
+    /// Prompt names containing the search word
+    Prompts { search_word: Option<String> },
+    /// `server:uri` of the resources and resource templates starting with the search word
+    Resources { search_word: String },
+    /// The arguments of a prompt, each sent back serialized as a [PromptGetArg]
+    PromptArguments { name: String },
+    /// Values the server suggests for an argument of a prompt or a variable of a resource template
+    ArgumentValues {
+        target: CompletionTarget,
+        argument: String,
+        /// What has been typed of the value so far
+        value: String,
+        /// The arguments that have been given already
+        context: HashMap<String, String>,
+    },
+}
+
+/// What an argument being completed belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionTarget {
+    /// A prompt, whose name may be qualified with its server as in `server/name`
+    Prompt {
+        name: String,
+    },
+    ResourceTemplate {
+        server_name: String,
+        uri_template: String,
+    },
+}
+
 pub struct PromptCompleter {
-    sender: std::sync::mpsc::Sender<Option<String>>,
+    sender: std::sync::mpsc::Sender<CompletionRequest>,
     receiver: std::sync::mpsc::Receiver<Vec<String>>,
 }
 
 impl PromptCompleter {
-    fn new(sender: std::sync::mpsc::Sender<Option<String>>, receiver: std::sync::mpsc::Receiver<Vec<String>>) -> Self {
+    fn new(
+        sender: std::sync::mpsc::Sender<CompletionRequest>,
+        receiver: std::sync::mpsc::Receiver<Vec<String>>,
+    ) -> Self {
         PromptCompleter { sender, receiver }
     }
 
     fn complete_prompt(&self, word: &str) -> Result<Vec<String>, ReadlineError> {
-        let sender = &self.sender;
-        let receiver = &self.receiver;
-        sender
-            .send(if !word.is_empty() { Some(word.to_string()) } else { None })
+        let prompts = self.request(CompletionRequest::Prompts {
+            search_word: if !word.is_empty() { Some(word.to_string()) } else { None },
+        })?;
+        Ok(prompts.iter().map(|n| format!("@{n}")).collect())
+    }
+
+    fn complete_resource(&self, word: &str) -> Result<Vec<String>, ReadlineError> {
+        let resources = self.request(CompletionRequest::Resources {
+            search_word: word.to_string(),
+        })?;
+        Ok(resources.iter().map(|n| format!("@{n}")).collect())
+    }
+
+    /// Completes the variable being typed in a `server:uri` that follows one of the resource
+    /// templates of the server
+    fn complete_resource_template(&self, word: &str) -> Result<Vec<String>, ReadlineError> {
+        let Some((server_name, typed)) = word.split_once(':') else {
+            return Ok(Vec::new());
+        };
+        let templates = self.request(CompletionRequest::Resources {
+            search_word: format!("{server_name}:"),
+        })?;
+        for uri_template in templates
+            .iter()
+            .filter_map(|t| t.strip_prefix(&format!("{server_name}:")))
+        {
+            let Some(variable) = match_template_variable(uri_template, typed) else {
+                continue;
+            };
+            let values = self.request(CompletionRequest::ArgumentValues {
+                target: CompletionTarget::ResourceTemplate {
+                    server_name: server_name.to_string(),
+                    uri_template: uri_template.to_string(),
+                },
+                argument: variable.name,
+                value: typed[variable.value_start..].to_string(),
+                context: variable.context,
+            })?;
+            if !values.is_empty() {
+                let prefix = &typed[..variable.value_start];
+                return Ok(values.iter().map(|v| format!("@{server_name}:{prefix}{v}")).collect());
+            }
+        }
+        Ok(Vec::new())
+    }
+
+    /// Completes the argument being typed after a prompt name, either as `name=value` or as a
+    /// positional value. `word` is the part of the argument before the cursor.
+    fn complete_prompt_argument(
+        &self,
+        invocation: &PromptInvocation<'_>,
+        word: &str,
+    ) -> Result<Vec<String>, ReadlineError> {
+        let schema = self.prompt_arguments(invocation.name)?;
+        let given = invocation.args_before(word);
+        let assigned = assign_prompt_arguments(&schema, &given);
+
+        let (argument, value, named) = match word.split_once('=') {
+            Some((name, value)) if schema.iter().any(|arg| arg.name == name) => (name.to_string(), value, true),
+            _ => {
+                let names = schema
+                    .iter()
+                    .filter(|arg| !assigned.contains_key(&arg.name) && arg.name.starts_with(word))
+                    .map(|arg| format!("{}=", arg.name))
+                    .collect::<Vec<_>>();
+                if !names.is_empty() {
+                    return Ok(names);
+                }
+                match schema.iter().find(|arg| !assigned.contains_key(&arg.name)) {
+                    Some(arg) => (arg.name.clone(), word, false),
+                    None => return Ok(Vec::new()),
+                }
+            },
+        };
+        let values = self.request(CompletionRequest::ArgumentValues {
+            target: CompletionTarget::Prompt {
+                name: invocation.name.to_string(),
+            },
+            argument: argument.clone(),
+            value: value.to_string(),
+            context: assigned,
+        })?;
+        Ok(values
+            .iter()
+            .filter_map(|v| shlex::try_quote(v).ok())
+            .map(|v| {
+                if named {
+                    format!("{argument}={v}")
+                } else {
+                    v.to_string()
+                }
+            })
+            .collect())
+    }
+
+    /// Shows what is expected next while a prompt is being invoked: the rest of the name of a
+    /// matching argument, or the description of the arguments that are still missing.
+    fn argument_hint(&self, line: &str) -> Option<ChatHint> {
+        let invocation = PromptInvocation::parse(line)?;
+        let schema = self.prompt_arguments(invocation.name).ok()?;
+        let word = line.rsplit(char::is_whitespace).next().unwrap_or_default();
+        if let Some((name, _)) = word.split_once('=') {
+            let arg = schema.iter().find(|arg| arg.name == name)?;
+            return arg.description.as_ref().map(|d| ChatHint::Info(format!("  {d}")));
+        }
+
+        let given = invocation.args_before(word);
+        let assigned = assign_prompt_arguments(&schema, &given);
+        let mut missing = schema.iter().filter(|arg| !assigned.contains_key(&arg.name));
+        if !word.is_empty() {
+            return missing
+                .find(|arg| arg.name.starts_with(word))
+                .map(|arg| ChatHint::Completion(format!("{}=", &arg.name[word.len()..])));
+        }
+        let next = missing.next()?;
+        let mut hint = format!("{}{}", next.name, if next.required == Some(true) { "*" } else { "" });
+        if let Some(description) = &next.description {
+            hint.push_str(&format!(": {description}"));
+        }
+        let rest = missing.map(|arg| arg.name.as_str()).collect::<Vec<_>>();
+        if !rest.is_empty() {
+            hint.push_str(&format!(" (then {})", rest.join(", ")));
+        }
+        Some(ChatHint::Info(hint))
+    }
+
+    fn prompt_arguments(&self, name: &str) -> Result<Vec<PromptGetArg>, ReadlineError> {
+        let arguments = self.request(CompletionRequest::PromptArguments { name: name.to_string() })?;
+        Ok(arguments
+            .iter()
+            .filter_map(|arg| serde_json::from_str::<PromptGetArg>(arg).ok())
+            .collect())
+    }
+
+    fn request(&self, request: CompletionRequest) -> Result<Vec<String>, ReadlineError> {
+        self.sender
+            .send(request)
             .map_err(|e| ReadlineError::Io(std::io::Error::other(e.to_string())))?;
-        let prompt_info = receiver
+        self.receiver
             .recv()
-            .map_err(|e| ReadlineError::Io(std::io::Error::other(e.to_string())))?
-            .iter()
-            .map(|n| format!("@{n}"))
-            .collect::<Vec<_>>();
+            .map_err(|e| ReadlineError::Io(std::io::Error::other(e.to_string())))
+    }
+}
+
+/// A line invoking a prompt, either as `@name args` or as `/prompts get name args`, whose
+/// arguments are being typed
+struct PromptInvocation<'a> {
+    name: &'a str,
+    /// Everything after the prompt name
+    args: &'a str,
+}
 
-        Ok(prompt_info)
+impl<'a> PromptInvocation<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let rest = line.strip_prefix('@').or_else(|| line.strip_prefix("/prompts get "))?;
+        let (name, args) = rest.split_once(char::is_whitespace)?;
+        // `@server:uri` mentions a resource
+        if name.is_empty() || name.contains(':') {
+            return None;
+        }
+        Some(Self { name, args })
+    }
+
+    /// The arguments given before the one being typed
+    fn args_before(&self, word: &str) -> Vec<String> {
+        let given = self.args.strip_suffix(word).unwrap_or(self.args);
+        shlex::split(given).unwrap_or_else(|| given.split_whitespace().map(str::to_string).collect())
     }
 }
 
+/// The variable of a resource template that is being typed
+#[derive(Debug, PartialEq, Eq)]
+struct TemplateVariable {
+    name: String,
+    /// Where the value of the variable starts in the typed uri
+    value_start: usize,
+    /// The values of the variables before it
+    context: HashMap<String, String>,
+}
+
+/// Matches a uri being typed against an RFC 6570 uri template, returning the variable the end of
+/// the uri falls into. Returns [None] if the uri does not follow the template or is still in one of
+/// its literal parts.
+fn match_template_variable(template: &str, typed: &str) -> Option<TemplateVariable> {
+    let mut parts = Vec::<(bool, &str)>::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let close = open + rest[open..].find('}')?;
+        parts.push((false, &rest[..open]));
+        parts.push((true, &rest[open + 1..close]));
+        rest = &rest[close + 1..];
+    }
+    parts.push((false, rest));
+
+    let mut context = HashMap::new();
+    let mut offset = 0;
+    for (i, (is_variable, part)) in parts.iter().enumerate() {
+        let remaining = &typed[offset..];
+        if !is_variable {
+            if !remaining.starts_with(part) {
+                return None;
+            }
+            offset += part.len();
+            continue;
+        }
+        // Operators and modifiers are not part of the name, and only the first of a list of
+        // variables is completed
+        let name = part
+            .trim_start_matches(['+', '#', '.', '/', ';', '?', '&'])
+            .split([',', ':', '*'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let end = match parts.get(i + 1) {
+            Some((false, next)) if !next.is_empty() => remaining.find(next),
+            _ => None,
+        };
+        match end {
+            Some(end) => {
+                context.insert(name, remaining[..end].to_string());
+                offset += end;
+            },
+            None => {
+                return Some(TemplateVariable {
+                    name,
+                    value_start: offset,
+                    context,
+                });
+            },
+        }
+    }
+    None
+}
+
 pub struct ChatCompleter {
     path_completer: PathCompleter,
     prompt_completer: PromptCompleter,
 }
 
 impl ChatCompleter {
-    fn new(sender: std::sync::mpsc::Sender<Option<String>>, receiver: std::sync::mpsc::Receiver<Vec<String>>) -> Self {
+    fn new(
+        sender: std::sync::mpsc::Sender<CompletionRequest>,
+        receiver: std::sync::mpsc::Receiver<Vec<String>>,
+    ) -> Self {
         Self {
             path_completer: PathCompleter::new(),
             prompt_completer: PromptCompleter::new(sender, receiver),
@@ -190,6 +465,27 @@ impl Completer for ChatCompleter {
             return Ok(complete_command(word, start));
         }
 
+        // Prompts are only invoked at the start of the line, while resources can be mentioned
+        // anywhere as @server:uri
+        if let Some(search_word) = word.strip_prefix('@') {
+            if start > 0 || search_word.contains(':') {
+                let completions = self.prompt_completer.complete_resource(search_word)?;
+                if !completions.is_empty() {
+                    return Ok((start, completions));
+                }
+                return Ok((start, self.prompt_completer.complete_resource_template(search_word)?));
+            }
+        }
+
+        // Arguments of a prompt being invoked
+        if let Some(invocation) = PromptInvocation::parse(&line[..pos]) {
+            if let Ok(completions) = self.prompt_completer.complete_prompt_argument(&invocation, word) {
+                if !completions.is_empty() {
+                    return Ok((start, completions));
+                }
+            }
+        }
+
         if line.starts_with('@') {
             let search_word = line.strip_prefix('@').unwrap_or("");
             if let Ok(completions) = self.prompt_completer.complete_prompt(search_word) {
@@ -197,6 +493,11 @@ impl Completer for ChatCompleter {
                     return Ok((0, completions));
                 }
             }
+            if let Ok(completions) = self.prompt_completer.complete_resource(search_word) {
+                if !completions.is_empty() {
+                    return Ok((0, completions));
+                }
+            }
         }
 
         // Handle file path completion as fallback
@@ -264,6 +565,29 @@ impl ChatHinter {
     }
 }
 
+/// A hint shown after the cursor. Only completion hints can be accepted.
+pub enum ChatHint {
+    /// Text that can be inserted with Ctrl+F
+    Completion(String),
+    /// Text that is only shown, such as the description of an argument
+    Info(String),
+}
+
+impl Hint for ChatHint {
+    fn display(&self) -> &str {
+        match self {
+            ChatHint::Completion(hint) | ChatHint::Info(hint) => hint,
+        }
+    }
+
+    fn completion(&self) -> Option<&str> {
+        match self {
+            ChatHint::Completion(hint) => Some(hint),
+            ChatHint::Info(_) => None,
+        }
+    }
+}
+
 impl RustylineHinter for ChatHinter {
     type Hint = String;
 
@@ -298,11 +622,10 @@ impl Validator for MultiLineValidator {
     }
 }
 
-#[derive(Helper, Completer, Hinter)]
+#[derive(Helper, Completer)]
 pub struct ChatHelper {
     #[rustyline(Completer)]
     completer: ChatCompleter,
-    #[rustyline(Hinter)]
     hinter: ChatHinter,
     validator: MultiLineValidator,
 }
@@ -314,6 +637,22 @@ impl ChatHelper {
     }
 }
 
+impl RustylineHinter for ChatHelper {
+    type Hint = ChatHint;
+
+    fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<Self::Hint> {
+        // Only provide hints when cursor is at the end of the line
+        if pos < line.len() {
+            return None;
+        }
+
+        if let Some(hint) = self.completer.prompt_completer.argument_hint(line) {
+            return Some(hint);
+        }
+        self.hinter.hint(line, pos, ctx).map(ChatHint::Completion)
+    }
+}
+
 impl Validator for ChatHelper {
     fn validate(&self, os: &mut ValidationContext<'_>) -> rustyline::Result<ValidationResult> {
         self.validator.validate(os)
@@ -364,7 +703,7 @@ impl Highlighter for ChatHelper {
 
 pub fn rl(
     os: &Os,
-    sender: std::sync::mpsc::Sender<Option<String>>,
+    sender: std::sync::mpsc::Sender<CompletionRequest>,
     receiver: std::sync::mpsc::Receiver<Vec<String>>,
 ) -> Result<Editor<ChatHelper, DefaultHistory>> {
     let edit_mode = match os.database.settings.get_string(Setting::ChatEditMode).as_deref() {
@@ -422,7 +761,7 @@ mod tests {
 
     #[test]
     fn test_chat_completer_command_completion() {
-        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<CompletionRequest>();
         let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let completer = ChatCompleter::new(prompt_request_sender, prompt_response_receiver);
         let line = "/h";
@@ -442,9 +781,153 @@ mod tests {
         assert!(completions.contains(&"/help".to_string()));
     }
 
+    #[test]
+    fn test_chat_completer_resource_completion() {
+        let (request_sender, request_receiver) = std::sync::mpsc::channel::<CompletionRequest>();
+        let (response_sender, response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
+        let completer = ChatCompleter::new(request_sender, response_receiver);
+        std::thread::spawn(move || {
+            while let Ok(request) = request_receiver.recv() {
+                let completions = match request {
+                    CompletionRequest::Resources { search_word } => ["docs:file:///README.md", "db:schema://users"]
+                        .into_iter()
+                        .filter(|r| r.starts_with(&search_word))
+                        .map(str::to_string)
+                        .collect(),
+                    _ => vec![],
+                };
+                response_sender.send(completions).unwrap();
+            }
+        });
+        let empty_history = DefaultHistory::new();
+        let ctx = Context::new(&empty_history);
+
+        let line = "explain @do";
+        let (start, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 8);
+        assert_eq!(completions, vec!["@docs:file:///README.md".to_string()]);
+
+        // Falls back to resources when no prompt matches at the start of the line
+        let line = "@db";
+        let (start, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 0);
+        assert_eq!(completions, vec!["@db:schema://users".to_string()]);
+    }
+
+    /// A completer backed by a tool manager that knows of a `review` prompt and a resource
+    /// template of the `db` server
+    fn completer_with_arguments() -> ChatCompleter {
+        let (request_sender, request_receiver) = std::sync::mpsc::channel::<CompletionRequest>();
+        let (response_sender, response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
+        std::thread::spawn(move || {
+            while let Ok(request) = request_receiver.recv() {
+                let completions = match request {
+                    CompletionRequest::Resources { search_word } => ["db:db://{schema}/tables/{table}"]
+                        .into_iter()
+                        .filter(|r| r.starts_with(&search_word))
+                        .map(str::to_string)
+                        .collect(),
+                    CompletionRequest::PromptArguments { name } if name == "review" => [
+                        serde_json::json!({ "name": "file", "description": "File to review", "required": true }),
+                        serde_json::json!({ "name": "focus" }),
+                    ]
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect(),
+                    CompletionRequest::ArgumentValues {
+                        argument,
+                        value,
+                        context,
+                        ..
+                    } => match argument.as_str() {
+                        "file" => vec!["main.rs".to_string(), "my file.rs".to_string()],
+                        "focus" => vec![format!("{value}formance")],
+                        "table" => vec![format!("{}_users", context["schema"])],
+                        _ => vec![],
+                    },
+                    _ => vec![],
+                };
+                response_sender.send(completions).unwrap();
+            }
+        });
+        ChatCompleter::new(request_sender, response_receiver)
+    }
+
+    #[test]
+    fn test_chat_completer_prompt_argument_completion() {
+        let completer = completer_with_arguments();
+        let empty_history = DefaultHistory::new();
+        let ctx = Context::new(&empty_history);
+
+        let line = "@review fo";
+        let (start, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 8);
+        assert_eq!(completions, vec!["focus=".to_string()]);
+
+        let line = "@review focus=per";
+        let (start, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 8);
+        assert_eq!(completions, vec!["focus=performance".to_string()]);
+
+        // Positional values complete the first argument that has not been given
+        let line = "/prompts get review focus=perf m";
+        let (_, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(completions, vec!["main.rs".to_string(), "'my file.rs'".to_string()]);
+    }
+
+    #[test]
+    fn test_chat_completer_resource_template_completion() {
+        let completer = completer_with_arguments();
+        let empty_history = DefaultHistory::new();
+        let ctx = Context::new(&empty_history);
+
+        let line = "show @db:db://main/tables/u";
+        let (start, completions) = completer.complete(line, line.len(), &ctx).unwrap();
+        assert_eq!(start, 5);
+        assert_eq!(completions, vec!["@db:db://main/tables/main_users".to_string()]);
+    }
+
+    #[test]
+    fn test_argument_hint() {
+        let completer = completer_with_arguments();
+        let hint = |line: &str| {
+            completer
+                .prompt_completer
+                .argument_hint(line)
+                .map(|h| (h.display().to_string(), h.completion().is_some()))
+        };
+
+        assert_eq!(
+            hint("@review "),
+            Some(("file*: File to review (then focus)".to_string(), false))
+        );
+        assert_eq!(hint("@review main.rs "), Some(("focus".to_string(), false)));
+        assert_eq!(hint("@review fi"), Some(("le=".to_string(), true)));
+        assert_eq!(hint("@review file="), Some(("  File to review".to_string(), false)));
+        assert_eq!(hint("@review main.rs focus=x "), None);
+        assert_eq!(hint("@unknown "), None);
+        assert_eq!(hint("@review"), None);
+    }
+
+    #[test]
+    fn test_match_template_variable() {
+        let variable = match_template_variable("db://{schema}/tables/{table}", "db://main/tables/us").unwrap();
+        assert_eq!(variable.name, "table");
+        assert_eq!(variable.value_start, 17);
+        assert_eq!(variable.context.get("schema").map(String::as_str), Some("main"));
+
+        let variable = match_template_variable("file:///{+path}", "file:///src/ma").unwrap();
+        assert_eq!(variable.name, "path");
+        assert!(variable.context.is_empty());
+
+        assert_eq!(match_template_variable("db://{schema}", "db:/"), None);
+        assert_eq!(match_template_variable("db://{schema}", "file://"), None);
+        assert_eq!(match_template_variable("db://schema", "db://schema"), None);
+    }
+
     #[test]
     fn test_chat_completer_no_completion() {
-        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<CompletionRequest>();
         let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let completer = ChatCompleter::new(prompt_request_sender, prompt_response_receiver);
         let line = "Hello, how are you?";
@@ -463,7 +946,7 @@ mod tests {
 
     #[test]
     fn test_highlight_prompt_basic() {
-        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<CompletionRequest>();
         let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let helper = ChatHelper {
             completer: ChatCompleter::new(prompt_request_sender, prompt_response_receiver),
@@ -479,7 +962,7 @@ mod tests {
 
     #[test]
     fn test_highlight_prompt_with_warning() {
-        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<CompletionRequest>();
         let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let helper = ChatHelper {
             completer: ChatCompleter::new(prompt_request_sender, prompt_response_receiver),
@@ -495,7 +978,7 @@ mod tests {
 
     #[test]
     fn test_highlight_prompt_with_profile() {
-        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<CompletionRequest>();
         let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let helper = ChatHelper {
             completer: ChatCompleter::new(prompt_request_sender, prompt_response_receiver),
@@ -511,7 +994,7 @@ mod tests {
 
     #[test]
     fn test_highlight_prompt_with_profile_and_warning() {
-        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<CompletionRequest>();
         let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let helper = ChatHelper {
             completer: ChatCompleter::new(prompt_request_sender, prompt_response_receiver),
@@ -530,7 +1013,7 @@ mod tests {
 
     #[test]
     fn test_highlight_prompt_invalid_format() {
-        let (prompt_request_sender, _) = std::sync::mpsc::channel::<Option<String>>();
+        let (prompt_request_sender, _) = std::sync::mpsc::channel::<CompletionRequest>();
         let (_, prompt_response_receiver) = std::sync::mpsc::channel::<Vec<String>>();
         let helper = ChatHelper {
             completer: ChatCompleter::new(prompt_request_sender, prompt_response_receiver),
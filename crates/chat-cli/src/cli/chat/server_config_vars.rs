@@ -0,0 +1,229 @@
+//! Resolves the `${...}` references in the configs of mcp servers, so that agent files can be
+//! shared without the tokens that servers need. The command, args, env, url and headers of a
+//! server may reference `${env:VAR}`, `${workspaceFolder}`, `${home}` and `${secret:name}`, the
+//! latter being stored with `q mcp secret set`. Any other `${...}` is left as it is.
+
+use std::collections::HashMap;
+
+use super::tools::custom_tool::CustomToolConfig;
+use crate::database::DatabaseError;
+use crate::os::Os;
+use crate::util::directories;
+
+/// What replaces the value of a secret in output
+pub const SECRET_MASK: &str = "****";
+
+const SECRET_KEY_PREFIX: &str = "mcp-secret:";
+
+#[derive(Debug, thiserror::Error)]
+pub enum VarError {
+    #[error("The environment variable {0} is not set")]
+    MissingEnv(String),
+    #[error("The secret {0} is not set. Set it with `q mcp secret set {0}`")]
+    MissingSecret(String),
+    #[error("Failed to resolve ${{{0}}}: {1}")]
+    Unavailable(&'static str, String),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
+}
+
+/// A config whose references have been replaced, along with the values of the secrets it
+/// references so that they can be masked
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub config: CustomToolConfig,
+    pub secrets: Vec<String>,
+}
+
+/// The key under which a secret is kept in the database
+pub fn secret_key(name: &str) -> String {
+    format!("{SECRET_KEY_PREFIX}{name}")
+}
+
+/// The names of the stored secrets
+pub async fn secret_names(os: &Os) -> Result<Vec<String>, VarError> {
+    let mut names = os
+        .database
+        .get_secret_keys(SECRET_KEY_PREFIX)
+        .await?
+        .into_iter()
+        .filter_map(|key| key.strip_prefix(SECRET_KEY_PREFIX).map(str::to_string))
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
+
+/// Whether `name` can be referenced as `${secret:name}`
+pub fn is_valid_secret_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+pub async fn resolve(os: &Os, config: &CustomToolConfig) -> Result<ResolvedConfig, VarError> {
+    let mut config = config.clone();
+
+    // Secrets are read up front, the rest is looked up while replacing
+    let mut secrets = HashMap::<String, String>::new();
+    for value in fields(&mut config) {
+        for name in variables(value).filter_map(|var| var.strip_prefix("secret:")) {
+            if secrets.contains_key(name) {
+                continue;
+            }
+            let secret = os
+                .database
+                .get_secret(&secret_key(name))
+                .await?
+                .ok_or_else(|| VarError::MissingSecret(name.to_string()))?;
+            secrets.insert(name.to_string(), secret.0);
+        }
+    }
+
+    let lookup = |var: &str| -> Result<Option<String>, VarError> {
+        if let Some(name) = var.strip_prefix("env:") {
+            return os
+                .env
+                .get(name)
+                .map(Some)
+                .map_err(|_err| VarError::MissingEnv(name.to_string()));
+        }
+        if let Some(name) = var.strip_prefix("secret:") {
+            return Ok(secrets.get(name).cloned());
+        }
+        match var {
+            "workspaceFolder" => os
+                .env
+                .current_dir()
+                .map(|dir| Some(dir.to_string_lossy().to_string()))
+                .map_err(|e| VarError::Unavailable("workspaceFolder", e.to_string())),
+            "home" => directories::home_dir(os)
+                .map(|dir| Some(dir.to_string_lossy().to_string()))
+                .map_err(|e| VarError::Unavailable("home", e.to_string())),
+            _ => Ok(None),
+        }
+    };
+    for value in fields(&mut config) {
+        *value = interpolate(value, lookup)?;
+    }
+
+    Ok(ResolvedConfig {
+        config,
+        secrets: secrets.into_values().filter(|secret| !secret.is_empty()).collect(),
+    })
+}
+
+/// Replaces every secret in the text with [SECRET_MASK]
+pub fn mask(text: &str, secrets: &[String]) -> String {
+    // Longer secrets first, in case one contains another
+    let mut secrets = secrets.iter().filter(|secret| !secret.is_empty()).collect::<Vec<_>>();
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    secrets.into_iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret.as_str(), SECRET_MASK)
+    })
+}
+
+/// The values of a config that may contain references
+fn fields(config: &mut CustomToolConfig) -> Vec<&mut String> {
+    let mut fields = vec![&mut config.command];
+    fields.extend(config.url.as_mut());
+    fields.extend(config.args.iter_mut());
+    fields.extend(config.env.iter_mut().flat_map(|env| env.values_mut()));
+    fields.extend(config.headers.iter_mut().flat_map(|headers| headers.values_mut()));
+    fields
+}
+
+/// The names of the references in a value, such as `env:TOKEN` for `${env:TOKEN}`
+fn variables(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split("${")
+        .skip(1)
+        .filter_map(|part| part.split_once('}').map(|(var, _)| var))
+}
+
+/// Replaces every `${var}` in the value with what `lookup` returns for `var`, leaving the ones it
+/// returns [None] for and an unterminated `${` as they are
+pub(crate) fn interpolate<E>(
+    value: &str,
+    mut lookup: impl FnMut(&str) -> Result<Option<String>, E>,
+) -> Result<String, E> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let var = &rest[start + 2..start + len];
+        out.push_str(&rest[..start]);
+        match lookup(var)? {
+            Some(replacement) => out.push_str(&replacement),
+            None => out.push_str(&rest[start..=start + len]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::os::Env;
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let mut os = Os::new().await.unwrap();
+        os.env = Env::from_slice(&[("HOME", "/home/testuser"), ("GIT_HOST", "example.com")]);
+        os.database.set_secret(&secret_key("gh-token"), "s3cr3t").await.unwrap();
+
+        let config: CustomToolConfig = serde_json::from_value(json!({
+            "command": "${home}/bin/git-mcp",
+            "args": ["--host", "${env:GIT_HOST}", "--root=${workspaceFolder}", "${unknown}"],
+            "env": { "TOKEN": "${secret:gh-token}", "SHELL_VAR": "$HOME" },
+        }))
+        .unwrap();
+        let resolved = resolve(&os, &config).await.unwrap();
+        let home = directories::home_dir(&os).unwrap();
+        assert_eq!(resolved.config.command, format!("{}/bin/git-mcp", home.display()));
+        assert_eq!(resolved.config.args, [
+            "--host",
+            "example.com",
+            "--root=/",
+            "${unknown}"
+        ]);
+        let env = resolved.config.env.unwrap();
+        assert_eq!(env["TOKEN"], "s3cr3t");
+        assert_eq!(env["SHELL_VAR"], "$HOME");
+        assert_eq!(resolved.secrets, ["s3cr3t"]);
+
+        let missing_env: CustomToolConfig =
+            serde_json::from_value(json!({ "command": "git-mcp", "args": ["${env:NOPE}"] })).unwrap();
+        let err = resolve(&os, &missing_env).await.unwrap_err();
+        assert!(err.to_string().contains("NOPE"), "{err}");
+
+        let missing_secret: CustomToolConfig = serde_json::from_value(json!({
+            "url": "https://example.com/mcp",
+            "headers": { "Authorization": "Bearer ${secret:nope}" },
+        }))
+        .unwrap();
+        let err = resolve(&os, &missing_secret).await.unwrap_err();
+        assert!(matches!(&err, VarError::MissingSecret(name) if name == "nope"), "{err}");
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let lookup = |var: &str| Ok::<_, VarError>((var == "a").then(|| "1".to_string()));
+        assert_eq!(interpolate("${a}${a}-${b}", lookup).unwrap(), "11-${b}");
+        assert_eq!(interpolate("x${a", lookup).unwrap(), "x${a");
+        assert_eq!(interpolate("", lookup).unwrap(), "");
+    }
+
+    #[test]
+    fn test_mask() {
+        let secrets = vec!["abc".to_string(), "abcdef".to_string(), String::new()];
+        assert_eq!(mask("token abcdef and abc", &secrets), "token **** and ****");
+        assert_eq!(mask("nothing here", &secrets), "nothing here");
+    }
+}
@@ -0,0 +1,216 @@
+//! Keeps the most recent stderr output and log messages of each mcp server, for `/mcp logs`.
+
+use std::collections::{
+    HashMap,
+    VecDeque,
+};
+use std::sync::{
+    Arc,
+    RwLock,
+};
+
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use super::server_config_vars;
+use crate::mcp_client::{
+    LoggingLevel,
+    ServerLogMessage,
+};
+
+/// How many entries are kept per server before the oldest ones are dropped
+pub const LOG_CAPACITY: usize = 500;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEntry {
+    pub time: OffsetDateTime,
+    /// The level of a log message, or `None` for a line of stderr output
+    pub level: Option<LoggingLevel>,
+    pub logger: Option<String>,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn new(message: ServerLogMessage) -> Self {
+        let time = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc());
+        match message {
+            ServerLogMessage::Stderr(line) => Self {
+                time,
+                level: None,
+                logger: None,
+                message: line,
+            },
+            ServerLogMessage::Notification(params) => Self {
+                time,
+                level: Some(params.level),
+                logger: params.logger,
+                message: match params.data {
+                    Value::String(s) => s,
+                    data => data.to_string(),
+                },
+            },
+        }
+    }
+
+    /// Whether the entry is shown when only messages of at least `level` are asked for. Stderr
+    /// output has no level and is always shown.
+    pub fn is_at_least(&self, level: Option<LoggingLevel>) -> bool {
+        match (self.level, level) {
+            (Some(own), Some(min)) => own >= min,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ServerLog {
+    entries: VecDeque<LogEntry>,
+    /// How many entries have been logged in total, including the dropped ones
+    total: usize,
+    /// The secrets referenced in the config of the server, which are masked in its entries
+    secrets: Vec<String>,
+}
+
+/// A bounded ring buffer of log entries per server, shared by the messengers of all servers
+#[derive(Clone, Debug, Default)]
+pub struct ServerLogs {
+    logs: Arc<RwLock<HashMap<String, ServerLog>>>,
+}
+
+impl ServerLogs {
+    pub fn push(&self, server_name: &str, mut entry: LogEntry) {
+        let mut logs = match self.logs.write() {
+            Ok(logs) => logs,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let log = logs.entry(server_name.to_string()).or_default();
+        if !log.secrets.is_empty() {
+            entry.message = server_config_vars::mask(&entry.message, &log.secrets);
+        }
+        if log.entries.len() == LOG_CAPACITY {
+            log.entries.pop_front();
+        }
+        log.entries.push_back(entry);
+        log.total += 1;
+    }
+
+    /// Sets the secrets to mask in the entries of the server from now on
+    pub fn set_secrets(&self, server_name: &str, secrets: Vec<String>) {
+        let mut logs = match self.logs.write() {
+            Ok(logs) => logs,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        logs.entry(server_name.to_string()).or_default().secrets = secrets;
+    }
+
+    /// Masks the secrets of the server in text that did not go through the log, such as errors
+    pub fn mask(&self, server_name: &str, text: &str) -> String {
+        let Ok(logs) = self.logs.read() else {
+            return text.to_string();
+        };
+        match logs.get(server_name) {
+            Some(log) => server_config_vars::mask(text, &log.secrets),
+            None => text.to_string(),
+        }
+    }
+
+    /// Returns the entries still kept that were logged after the first `seen` entries of the
+    /// server, along with the total number of entries logged so far, to be passed as `seen` next
+    /// time
+    pub fn since(&self, server_name: &str, seen: usize) -> (Vec<LogEntry>, usize) {
+        let Ok(logs) = self.logs.read() else {
+            return (Vec::new(), seen);
+        };
+        let Some(log) = logs.get(server_name) else {
+            return (Vec::new(), 0);
+        };
+        let dropped = log.total - log.entries.len();
+        let entries = log.entries.iter().skip(seen.saturating_sub(dropped)).cloned().collect();
+        (entries, log.total)
+    }
+
+    /// The last `count` lines that the server printed to stderr
+    pub fn recent_stderr(&self, server_name: &str, count: usize) -> Vec<String> {
+        let Ok(logs) = self.logs.read() else {
+            return Vec::new();
+        };
+        let Some(log) = logs.get(server_name) else {
+            return Vec::new();
+        };
+        let mut lines = log
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.level.is_none())
+            .take(count)
+            .map(|entry| entry.message.clone())
+            .collect::<Vec<_>>();
+        lines.reverse();
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mcp_client::LoggingMessageParams;
+
+    fn stderr(line: &str) -> LogEntry {
+        LogEntry::new(ServerLogMessage::Stderr(line.to_string()))
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let logs = ServerLogs::default();
+        for i in 0..LOG_CAPACITY + 10 {
+            logs.push("git", stderr(&i.to_string()));
+        }
+        let (entries, total) = logs.since("git", 0);
+        assert_eq!(entries.len(), LOG_CAPACITY);
+        assert_eq!(entries[0].message, "10");
+        assert_eq!(total, LOG_CAPACITY + 10);
+
+        logs.push("git", stderr("new"));
+        let (entries, total) = logs.since("git", total);
+        assert_eq!(entries.iter().map(|e| e.message.as_str()).collect::<Vec<_>>(), ["new"]);
+        assert_eq!(logs.since("git", total).0.len(), 0);
+        assert_eq!(logs.since("other", 0), (vec![], 0));
+    }
+
+    #[test]
+    fn test_levels() {
+        let logs = ServerLogs::default();
+        logs.push("git", stderr("starting"));
+        logs.push(
+            "git",
+            LogEntry::new(ServerLogMessage::Notification(LoggingMessageParams {
+                level: LoggingLevel::Info,
+                logger: Some("db".to_string()),
+                data: json!({ "connected": true }),
+            })),
+        );
+        logs.push("git", stderr("stopping"));
+
+        let (entries, _) = logs.since("git", 0);
+        assert_eq!(entries[1].message, r#"{"connected":true}"#);
+        assert!(entries[1].is_at_least(Some(LoggingLevel::Debug)));
+        assert!(!entries[1].is_at_least(Some(LoggingLevel::Warning)));
+        assert!(entries[0].is_at_least(Some(LoggingLevel::Emergency)));
+        assert_eq!(logs.recent_stderr("git", 1), ["stopping"]);
+        assert_eq!(logs.recent_stderr("git", 5), ["starting", "stopping"]);
+    }
+
+    #[test]
+    fn test_secrets() {
+        let logs = ServerLogs::default();
+        logs.set_secrets("git", vec!["s3cr3t".to_string()]);
+        logs.push("git", stderr("using token s3cr3t"));
+        logs.push("other", stderr("s3cr3t"));
+        assert_eq!(logs.recent_stderr("git", 1), ["using token ****"]);
+        assert_eq!(logs.recent_stderr("other", 1), ["s3cr3t"]);
+        assert_eq!(logs.mask("git", "bad token s3cr3t"), "bad token ****");
+        assert_eq!(logs.mask("unknown", "s3cr3t"), "s3cr3t");
+    }
+}
@@ -1,15 +1,32 @@
+use std::sync::{
+    Arc,
+    RwLock,
+};
+
 use tokio::sync::mpsc::{
     Receiver,
     Sender,
     channel,
 };
+use tokio::sync::oneshot;
 
+use super::server_logs::{
+    LogEntry,
+    ServerLogs,
+};
 use crate::mcp_client::{
+    CreateMessageParams,
+    CreateMessageResult,
+    ElicitParams,
+    ElicitResult,
+    JsonRpcError,
     Messenger,
     MessengerError,
     PromptsListResult,
     ResourceTemplatesListResult,
     ResourcesListResult,
+    Root,
+    ServerLogMessage,
     ToolsListResult,
 };
 
@@ -32,29 +49,65 @@ pub enum UpdateEventMessage {
         server_name: String,
         result: eyre::Result<ResourceTemplatesListResult>,
     },
+    ResourceUpdated {
+        server_name: String,
+        uri: String,
+    },
     InitStart {
         server_name: String,
     },
 }
 
+/// A request made by a server that has to be answered by the chat session, since it may involve
+/// the user or the model
+#[derive(Debug)]
+pub enum ServerRequest {
+    Sampling {
+        server_name: String,
+        params: CreateMessageParams,
+        responder: oneshot::Sender<Result<CreateMessageResult, JsonRpcError>>,
+    },
+    Elicitation {
+        server_name: String,
+        params: ElicitParams,
+        responder: oneshot::Sender<Result<ElicitResult, JsonRpcError>>,
+    },
+}
+
 #[derive(Clone, Debug)]
 pub struct ServerMessengerBuilder {
     pub update_event_sender: Sender<UpdateEventMessage>,
+    pub server_request_sender: Sender<ServerRequest>,
+    /// The roots shared by all servers, which the tool manager keeps up to date
+    pub roots: Arc<RwLock<Vec<Root>>>,
+    /// The stderr output and log messages of all servers
+    pub logs: ServerLogs,
 }
 
 impl ServerMessengerBuilder {
-    pub fn new(capacity: usize) -> (Receiver<UpdateEventMessage>, Self) {
+    pub fn new(
+        capacity: usize,
+        roots: Arc<RwLock<Vec<Root>>>,
+        logs: ServerLogs,
+    ) -> (Receiver<UpdateEventMessage>, Receiver<ServerRequest>, Self) {
         let (tx, rx) = channel::<UpdateEventMessage>(capacity);
+        let (request_tx, request_rx) = channel::<ServerRequest>(capacity);
         let this = Self {
             update_event_sender: tx,
+            server_request_sender: request_tx,
+            roots,
+            logs,
         };
-        (rx, this)
+        (rx, request_rx, this)
     }
 
     pub fn build_with_name(&self, server_name: String) -> ServerMessenger {
         ServerMessenger {
             server_name,
             update_event_sender: self.update_event_sender.clone(),
+            server_request_sender: self.server_request_sender.clone(),
+            roots: self.roots.clone(),
+            logs: self.logs.clone(),
         }
     }
 }
@@ -63,6 +116,9 @@ impl ServerMessengerBuilder {
 pub struct ServerMessenger {
     pub server_name: String,
     pub update_event_sender: Sender<UpdateEventMessage>,
+    pub server_request_sender: Sender<ServerRequest>,
+    pub roots: Arc<RwLock<Vec<Root>>>,
+    pub logs: ServerLogs,
 }
 
 #[async_trait::async_trait]
@@ -117,6 +173,52 @@ impl Messenger for ServerMessenger {
             .map_err(|e| MessengerError::Custom(e.to_string()))?)
     }
 
+    async fn send_resource_updated(&self, uri: String) -> Result<(), MessengerError> {
+        Ok(self
+            .update_event_sender
+            .send(UpdateEventMessage::ResourceUpdated {
+                server_name: self.server_name.clone(),
+                uri,
+            })
+            .await
+            .map_err(|e| MessengerError::Custom(e.to_string()))?)
+    }
+
+    async fn send_sampling_request(&self, params: CreateMessageParams) -> Result<CreateMessageResult, JsonRpcError> {
+        let internal_error = |e: String| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e);
+        let (responder, response) = oneshot::channel();
+        self.server_request_sender
+            .send(ServerRequest::Sampling {
+                server_name: self.server_name.clone(),
+                params,
+                responder,
+            })
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+        response.await.map_err(|e| internal_error(e.to_string()))?
+    }
+
+    async fn send_elicitation_request(&self, params: ElicitParams) -> Result<ElicitResult, JsonRpcError> {
+        let internal_error = |e: String| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e);
+        let (responder, response) = oneshot::channel();
+        self.server_request_sender
+            .send(ServerRequest::Elicitation {
+                server_name: self.server_name.clone(),
+                params,
+                responder,
+            })
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+        response.await.map_err(|e| internal_error(e.to_string()))?
+    }
+
+    async fn list_roots(&self) -> Result<Vec<Root>, JsonRpcError> {
+        self.roots
+            .read()
+            .map(|roots| roots.clone())
+            .map_err(|e| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e.to_string()))
+    }
+
     async fn send_init_msg(&self) -> Result<(), MessengerError> {
         Ok(self
             .update_event_sender
@@ -127,6 +229,10 @@ impl Messenger for ServerMessenger {
             .map_err(|e| MessengerError::Custom(e.to_string()))?)
     }
 
+    fn log(&self, message: ServerLogMessage) {
+        self.logs.push(&self.server_name, LogEntry::new(message));
+    }
+
     fn duplicate(&self) -> Box<dyn Messenger> {
         Box::new(self.clone())
     }
@@ -0,0 +1,518 @@
+//! Answers the requests that mcp servers make to the chat session: `sampling/createMessage` to
+//! get completions from the model, and `elicitation/create` to get input from the user.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Write;
+
+use crossterm::style::{
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+    style,
+};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::error;
+
+use super::elicitation::Form;
+use super::input_source::InputSource;
+use super::server_messenger::ServerRequest;
+use super::token_counter::TokenCounter;
+use super::tool_manager::{
+    ServerRequestReceiver,
+    recv_server_request,
+};
+use super::tools::custom_tool::SamplingConfig;
+use super::util::truncate_safe;
+use crate::api_client::model::{
+    AssistantResponseMessage,
+    ChatMessage,
+    ChatResponseStream,
+    ConversationState,
+    UserInputMessage,
+};
+use crate::cli::agent::Agents;
+use crate::cli::chat::ChatError;
+use crate::mcp_client::{
+    CreateMessageParams,
+    CreateMessageResult,
+    ElicitAction,
+    ElicitResult,
+    JsonRpcError,
+    MessageContent,
+    ResourceContents,
+    Role,
+};
+use crate::os::Os;
+
+/// Error code sent back when the user declines a request
+const USER_REJECTED: i32 = -1;
+
+/// Error code sent back when a request needs the user while they can't be asked
+const USER_UNAVAILABLE: i32 = -2;
+
+/// How many characters of each message are shown when asking the user for approval
+const PREVIEW_LEN: usize = 500;
+
+/// Answers a request made by a server. Sampling requests need the approval of the user unless the
+/// server is configured to be auto approved. Failures are answered with an error to the server.
+pub async fn handle_server_request(
+    os: &Os,
+    request: ServerRequest,
+    agents: &Agents,
+    model_id: Option<&str>,
+    input_source: &mut InputSource,
+    stderr: &mut impl Write,
+    interactive: bool,
+) {
+    match request {
+        ServerRequest::Sampling {
+            server_name,
+            params,
+            responder,
+        } => {
+            let config = sampling_configs(agents).remove(&server_name).unwrap_or_default();
+            let approved = match (config.auto_approve, interactive) {
+                (true, _) => Ok(true),
+                (false, true) => ask_approval(&server_name, &params, input_source, stderr),
+                (false, false) => Ok(false),
+            };
+            let result = match approved {
+                Ok(true) => sample(os, &params, config.max_tokens, model_id).await,
+                Ok(false) => Err(JsonRpcError::new(USER_REJECTED, "User rejected sampling request")),
+                Err(err) => Err(internal_error(&server_name, err)),
+            };
+            // The server may have given up on the request in the meantime
+            let _ = responder.send(result);
+        },
+        ServerRequest::Elicitation {
+            server_name,
+            params,
+            responder,
+        } => {
+            let result = match Form::from_schema(&params.requested_schema) {
+                Ok(form) if interactive => form
+                    .fill(&server_name, &params.message, stderr)
+                    .map_err(|err| internal_error(&server_name, err)),
+                Ok(_) => Ok(ElicitResult {
+                    action: ElicitAction::Decline,
+                    content: None,
+                }),
+                Err(e) => Err(JsonRpcError::new(JsonRpcError::INVALID_PARAMS, e)),
+            };
+            let _ = responder.send(result);
+        },
+    }
+}
+
+/// Answers a request made by a server while the user can't be asked anything, e.g. while they are
+/// typing a prompt. Sampling requests of auto approved servers are carried out, everything else is
+/// refused.
+async fn handle_unattended(
+    os: &Os,
+    request: ServerRequest,
+    configs: &HashMap<String, SamplingConfig>,
+    model_id: Option<&str>,
+) {
+    match request {
+        ServerRequest::Sampling {
+            server_name,
+            params,
+            responder,
+        } => {
+            let config = configs.get(&server_name).cloned().unwrap_or_default();
+            let result = if config.auto_approve {
+                sample(os, &params, config.max_tokens, model_id).await
+            } else {
+                Err(JsonRpcError::new(
+                    USER_UNAVAILABLE,
+                    "The user is not available to approve the sampling request",
+                ))
+            };
+            let _ = responder.send(result);
+        },
+        ServerRequest::Elicitation { responder, .. } => {
+            let _ = responder.send(Ok(ElicitResult {
+                action: ElicitAction::Cancel,
+                content: None,
+            }));
+        },
+    }
+}
+
+/// Answers the requests of servers in the background while the chat session is busy with
+/// something other than running tools, so that servers are not left waiting for a reply.
+pub struct UnattendedRequests {
+    stop: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl UnattendedRequests {
+    pub fn spawn(os: Os, receiver: ServerRequestReceiver, agents: &Agents, model_id: Option<String>) -> Self {
+        let configs = sampling_configs(agents);
+        let stop = CancellationToken::new();
+        let task = tokio::spawn({
+            let stop = stop.clone();
+            async move {
+                loop {
+                    let request = tokio::select! {
+                        biased;
+                        _ = stop.cancelled() => break,
+                        Some(request) = recv_server_request(&receiver) => request,
+                    };
+                    handle_unattended(&os, request, &configs, model_id.as_deref()).await;
+                }
+            }
+        });
+
+        Self { stop, task }
+    }
+
+    /// Stops answering requests, once the request being answered, if any, has been answered.
+    pub async fn stop(self) {
+        self.stop.cancel();
+        let _ = self.task.await;
+    }
+}
+
+/// The sampling config of every server of the active agent
+fn sampling_configs(agents: &Agents) -> HashMap<String, SamplingConfig> {
+    agents
+        .get_active()
+        .map(|agent| {
+            agent
+                .mcp_servers
+                .mcp_servers
+                .iter()
+                .filter_map(|(name, config)| Some((name.clone(), config.sampling.clone()?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn internal_error(server_name: &str, err: impl Display) -> JsonRpcError {
+    error!(%err, "Failed to answer the request of {server_name}");
+    JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, err.to_string())
+}
+
+fn ask_approval(
+    server_name: &str,
+    params: &CreateMessageParams,
+    input_source: &mut InputSource,
+    stderr: &mut impl Write,
+) -> Result<bool, ChatError> {
+    queue!(
+        stderr,
+        style::Print("\n"),
+        style::SetForegroundColor(Color::Green),
+        style::Print(server_name),
+        style::SetForegroundColor(Color::Reset),
+        style::Print(format!(
+            " requests a completion from the model (up to {} tokens):\n",
+            params.max_tokens
+        )),
+    )?;
+    let system_prompt = params.system_prompt.iter().map(|prompt| ("system", prompt.clone()));
+    let messages = params.messages.iter().map(|message| {
+        let role = match message.role {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        };
+        (role, content_text(&message.content))
+    });
+    for (role, text) in system_prompt.chain(messages) {
+        let preview = truncate_safe(&text, PREVIEW_LEN);
+        queue!(
+            stderr,
+            style::SetAttribute(Attribute::Bold),
+            style::Print(format!("[{role}] ")),
+            style::SetAttribute(Attribute::Reset),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(preview),
+            style::Print(if preview.len() < text.len() { "...\n" } else { "\n" }),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    }
+    execute!(
+        stderr,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("\nAllow this request? ["),
+        style::SetForegroundColor(Color::Green),
+        style::Print("y"),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("/"),
+        style::SetForegroundColor(Color::Green),
+        style::Print("n"),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("]:\n\n"),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+
+    loop {
+        match input_source.read_line(Some("> "))? {
+            Some(line) if ["y", "Y"].contains(&line.trim()) => return Ok(true),
+            Some(line) if ["n", "N"].contains(&line.trim()) => return Ok(false),
+            Some(_) => {},
+            // Ctrl+C or Ctrl+D
+            None => return Ok(false),
+        }
+    }
+}
+
+/// Sends the messages of the request to the model and returns its response
+async fn sample(
+    os: &Os,
+    params: &CreateMessageParams,
+    max_tokens: Option<u32>,
+    model_id: Option<&str>,
+) -> Result<CreateMessageResult, JsonRpcError> {
+    let internal_error =
+        |e: crate::api_client::ApiClientError| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e.to_string());
+    let max_tokens = max_tokens.map_or(params.max_tokens, |limit| limit.min(params.max_tokens));
+    let conversation = conversation_state(params, max_tokens, model_id)?;
+    let mut output = os.client.send_message(conversation).await.map_err(internal_error)?;
+
+    // The backend has no token limit, so stop receiving the response once it is reached, which
+    // ends the generation.
+    let max_len = TokenCounter::token_to_chars(max_tokens as usize);
+    let mut text = String::new();
+    while text.len() <= max_len {
+        match output.recv().await.map_err(internal_error)? {
+            Some(ChatResponseStream::AssistantResponseEvent { content }) => text.push_str(&content),
+            Some(_) => {},
+            None => break,
+        }
+    }
+
+    let stop_sequences = params.stop_sequences.as_deref().unwrap_or_default();
+    let (text, stop_reason) = truncate_completion(text, stop_sequences, max_tokens);
+    Ok(CreateMessageResult {
+        role: Role::Assistant,
+        content: MessageContent::Text { text },
+        model: model_id.unwrap_or("default").to_string(),
+        stop_reason: Some(stop_reason.to_string()),
+    })
+}
+
+/// Turns the messages of the request into a conversation, since the backend only takes a user
+/// message along with the history of alternating user and assistant messages before it. The
+/// backend takes no token limit either, so `max_tokens` is given to the model in the prompt.
+fn conversation_state(
+    params: &CreateMessageParams,
+    max_tokens: u32,
+    model_id: Option<&str>,
+) -> Result<ConversationState, JsonRpcError> {
+    let mut messages = Vec::<(Role, String)>::new();
+    if let Some(system_prompt) = &params.system_prompt {
+        messages.push((Role::User, system_prompt.clone()));
+    }
+    for message in &params.messages {
+        let text = content_text(&message.content);
+        match messages.last_mut() {
+            Some((role, content)) if *role == message.role => {
+                content.push_str("\n\n");
+                content.push_str(&text);
+            },
+            _ => messages.push((message.role.clone(), text)),
+        }
+    }
+    let Some((Role::User, mut prompt)) = messages.pop() else {
+        return Err(JsonRpcError::new(
+            JsonRpcError::INVALID_PARAMS,
+            "The last message must be from the user",
+        ));
+    };
+    prompt.push_str(&format!("\n\nRespond in at most {max_tokens} tokens."));
+
+    let user_message = |content: String| UserInputMessage {
+        content,
+        user_input_message_context: None,
+        user_intent: None,
+        images: None,
+        model_id: model_id.map(str::to_string),
+    };
+    let mut history = Vec::new();
+    for (role, content) in messages {
+        match role {
+            Role::User => history.push(ChatMessage::UserInputMessage(user_message(content))),
+            Role::Assistant => {
+                if history.is_empty() {
+                    history.push(ChatMessage::UserInputMessage(user_message(
+                        "Continue the conversation.".to_string(),
+                    )));
+                }
+                history.push(ChatMessage::AssistantResponseMessage(AssistantResponseMessage {
+                    message_id: None,
+                    content,
+                    tool_uses: None,
+                }));
+            },
+        }
+    }
+
+    Ok(ConversationState {
+        conversation_id: None,
+        user_input_message: user_message(prompt),
+        history: (!history.is_empty()).then_some(history),
+    })
+}
+
+fn content_text(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text { text } => text.clone(),
+        MessageContent::Image { mime_type, .. } => format!("[{mime_type} image]"),
+        MessageContent::Resource { resource } => match &resource.contents {
+            ResourceContents::Text { text } => format!("[{}]\n{text}", resource.uri),
+            ResourceContents::Blob { .. } => format!("[binary resource {}]", resource.uri),
+        },
+    }
+}
+
+/// Cuts the completion at the first stop sequence, or to the most tokens allowed since the backend
+/// may send a little more than that. Returns the completion along with the reason it stopped.
+fn truncate_completion(mut text: String, stop_sequences: &[String], max_tokens: u32) -> (String, &'static str) {
+    let stop = stop_sequences
+        .iter()
+        .filter(|sequence| !sequence.is_empty())
+        .filter_map(|sequence| text.find(sequence.as_str()))
+        .min();
+    let mut stop_reason = "endTurn";
+    if let Some(stop) = stop {
+        text.truncate(stop);
+        stop_reason = "stopSequence";
+    }
+    let max_len = TokenCounter::token_to_chars(max_tokens as usize);
+    if text.len() > max_len {
+        let len = truncate_safe(&text, max_len).len();
+        text.truncate(len);
+        stop_reason = "maxTokens";
+    }
+    (text, stop_reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mcp_client::ElicitParams;
+
+    fn params(value: serde_json::Value) -> CreateMessageParams {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_conversation_state() {
+        let params = params(json!({
+            "systemPrompt": "You are a summarizer",
+            "messages": [
+                { "role": "user", "content": { "type": "text", "text": "Summarize this" } },
+                { "role": "assistant", "content": { "type": "text", "text": "Sure, what?" } },
+                { "role": "user", "content": { "type": "text", "text": "The text" } },
+                { "role": "user", "content": { "type": "image", "data": "", "mimeType": "image/png" } }
+            ],
+            "maxTokens": 100
+        }));
+        let state = conversation_state(&params, 100, Some("model")).unwrap();
+        assert_eq!(
+            state.user_input_message.content,
+            "The text\n\n[image/png image]\n\nRespond in at most 100 tokens."
+        );
+        assert_eq!(state.user_input_message.model_id.as_deref(), Some("model"));
+        let history = state.history.unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(matches!(
+            &history[0],
+            ChatMessage::UserInputMessage(msg) if msg.content == "You are a summarizer\n\nSummarize this"
+        ));
+        assert!(matches!(
+            &history[1],
+            ChatMessage::AssistantResponseMessage(msg) if msg.content == "Sure, what?"
+        ));
+
+        let params = self::params(json!({
+            "messages": [{ "role": "assistant", "content": { "type": "text", "text": "Hi" } }],
+            "maxTokens": 100
+        }));
+        assert_eq!(
+            conversation_state(&params, 100, None).unwrap_err().code,
+            JsonRpcError::INVALID_PARAMS
+        );
+    }
+
+    #[test]
+    fn test_truncate_completion() {
+        let text = "first line\nSTOP\nsecond line".to_string();
+        assert_eq!(truncate_completion(text.clone(), &[], 100), (text.clone(), "endTurn"));
+        assert_eq!(
+            truncate_completion(text.clone(), &["\nSTOP".to_string()], 100),
+            ("first line".to_string(), "stopSequence")
+        );
+        assert_eq!(truncate_completion(text, &[], 1), ("firs".to_string(), "maxTokens"));
+    }
+
+    #[tokio::test]
+    async fn test_sample() {
+        let mut os = Os::new().await.unwrap();
+        os.client.set_mock_output(json!([["Hello", " world"]]));
+        let params = params(json!({
+            "messages": [{ "role": "user", "content": { "type": "text", "text": "Greet me" } }],
+            "maxTokens": 2
+        }));
+
+        let result = sample(&os, &params, None, None).await.unwrap();
+        assert_eq!(result.role, Role::Assistant);
+        assert!(matches!(result.content, MessageContent::Text { ref text } if text == "Hello wo"));
+        assert_eq!(result.stop_reason.as_deref(), Some("maxTokens"));
+    }
+
+    #[tokio::test]
+    async fn test_handle_unattended() {
+        let mut os = Os::new().await.unwrap();
+        os.client.set_mock_output(json!([["Hello"]]));
+        let params = || {
+            params(json!({
+                "messages": [{ "role": "user", "content": { "type": "text", "text": "Greet me" } }],
+                "maxTokens": 10
+            }))
+        };
+        let configs = HashMap::from([("trusted".to_string(), SamplingConfig {
+            auto_approve: true,
+            max_tokens: None,
+        })]);
+
+        let (responder, rx) = tokio::sync::oneshot::channel();
+        let request = ServerRequest::Sampling {
+            server_name: "trusted".to_string(),
+            params: params(),
+            responder,
+        };
+        handle_unattended(&os, request, &configs, None).await;
+        assert!(rx.await.unwrap().is_ok());
+
+        let (responder, rx) = tokio::sync::oneshot::channel();
+        let request = ServerRequest::Sampling {
+            server_name: "other".to_string(),
+            params: params(),
+            responder,
+        };
+        handle_unattended(&os, request, &configs, None).await;
+        assert_eq!(rx.await.unwrap().unwrap_err().code, USER_UNAVAILABLE);
+
+        let (responder, rx) = tokio::sync::oneshot::channel();
+        let request = ServerRequest::Elicitation {
+            server_name: "other".to_string(),
+            params: ElicitParams {
+                message: "Name?".to_string(),
+                requested_schema: json!({ "type": "object", "properties": {} }),
+            },
+            responder,
+        };
+        handle_unattended(&os, request, &configs, None).await;
+        assert_eq!(rx.await.unwrap().unwrap().action, ElicitAction::Cancel);
+    }
+}
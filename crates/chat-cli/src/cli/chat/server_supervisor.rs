@@ -0,0 +1,482 @@
+//! Keeps track of the health of mcp servers and restarts the ones that stopped.
+
+use std::collections::HashMap;
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::sync::{
+    Arc,
+    RwLock,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use tracing::warn;
+
+use super::server_messenger::ServerMessengerBuilder;
+use super::tools::custom_tool::{
+    CustomToolClient,
+    CustomToolConfig,
+    TransportType,
+};
+use crate::database::Database;
+use crate::mcp_client::Messenger;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerStatus {
+    /// A lazy server whose tools have not been used yet
+    NotStarted,
+    Running,
+    /// Waiting for or making a restart attempt
+    Restarting {
+        attempt: u32,
+        max_retries: u32,
+    },
+    /// Stopped and no longer restarted
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct ServerHealth {
+    pub status: ServerStatus,
+    pub last_error: Option<String>,
+    /// How many times the server has been restarted successfully
+    pub restart_count: u32,
+    /// When the current server process was started
+    pub started_at: Instant,
+    /// Changes whenever the client of the server is replaced, so that whoever was watching the old
+    /// client stands down
+    generation: u64,
+}
+
+impl ServerHealth {
+    fn new(status: ServerStatus) -> Self {
+        Self {
+            status,
+            last_error: None,
+            restart_count: 0,
+            started_at: Instant::now(),
+            generation: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ServerSupervisor {
+    configs: HashMap<String, CustomToolConfig>,
+    messenger_builder: ServerMessengerBuilder,
+    database: Database,
+    clients: RwLock<HashMap<String, Arc<CustomToolClient>>>,
+    health: RwLock<HashMap<String, ServerHealth>>,
+    /// Whether a client has been replaced since the tool manager last took the clients
+    has_restarted: AtomicBool,
+}
+
+impl ServerSupervisor {
+    pub fn new(
+        configs: HashMap<String, CustomToolConfig>,
+        messenger_builder: ServerMessengerBuilder,
+        database: Database,
+        clients: &HashMap<String, Arc<CustomToolClient>>,
+    ) -> Arc<Self> {
+        // Servers without a client are the lazy ones that have not been started
+        let health = configs
+            .keys()
+            .chain(clients.keys())
+            .map(|name| {
+                let status = match clients.contains_key(name) {
+                    true => ServerStatus::Running,
+                    false => ServerStatus::NotStarted,
+                };
+                (name.clone(), ServerHealth::new(status))
+            })
+            .collect();
+        let this = Arc::new(Self {
+            configs,
+            messenger_builder,
+            database,
+            clients: RwLock::new(clients.clone()),
+            health: RwLock::new(health),
+            has_restarted: AtomicBool::new(false),
+        });
+        for (name, client) in clients {
+            this.watch(name.clone(), client, 0);
+        }
+        this
+    }
+
+    pub fn clients(&self) -> HashMap<String, Arc<CustomToolClient>> {
+        self.clients.read().map(|clients| clients.clone()).unwrap_or_default()
+    }
+
+    /// Whether any server is supervised, including the ones that have not been started
+    pub fn has_servers(&self) -> bool {
+        !self.configs.is_empty()
+    }
+
+    /// Returns the clients if any of them has been replaced since the last call
+    pub fn take_restarted_clients(&self) -> Option<HashMap<String, Arc<CustomToolClient>>> {
+        self.has_restarted.swap(false, Ordering::AcqRel).then(|| self.clients())
+    }
+
+    pub fn health(&self, server_name: &str) -> Option<ServerHealth> {
+        self.health.read().ok()?.get(server_name).cloned()
+    }
+
+    /// Starts a lazy server for the first time
+    pub async fn start_lazy(self: &Arc<Self>, server_name: &str) -> eyre::Result<Arc<CustomToolClient>> {
+        match self.start(server_name).await {
+            Ok(client) => {
+                self.install(server_name, client.clone(), 0);
+                Ok(client)
+            },
+            Err(e) => {
+                self.update_health(server_name, |health| {
+                    health.status = ServerStatus::Failed;
+                    health.last_error = Some(e.to_string());
+                });
+                Err(e)
+            },
+        }
+    }
+
+    /// Stops the server and starts it again, whatever its status
+    pub async fn restart(self: &Arc<Self>, server_name: &str) -> eyre::Result<Arc<CustomToolClient>> {
+        if !self.configs.contains_key(server_name) {
+            eyre::bail!("No mcp server named {server_name}");
+        }
+        // The old client is expected to close from now on
+        let restart_count = self
+            .update_health(server_name, |health| {
+                health.generation += 1;
+                health.status = ServerStatus::Restarting {
+                    attempt: 1,
+                    max_retries: 1,
+                };
+            })
+            .restart_count;
+        let old_client = self.clients.read().ok().and_then(|c| c.get(server_name).cloned());
+        if let Some(old_client) = old_client {
+            old_client.terminate();
+        }
+        match self.start(server_name).await {
+            Ok(client) => {
+                self.install(server_name, client.clone(), restart_count + 1);
+                Ok(client)
+            },
+            Err(e) => {
+                self.update_health(server_name, |health| {
+                    health.status = ServerStatus::Failed;
+                    health.last_error = Some(e.to_string());
+                });
+                Err(e)
+            },
+        }
+    }
+
+    /// Launches a new client of the server and initializes it, which also refreshes its tools
+    async fn start(&self, server_name: &str) -> eyre::Result<Arc<CustomToolClient>> {
+        let config = self
+            .configs
+            .get(server_name)
+            .cloned()
+            .ok_or(eyre::eyre!("No mcp server named {server_name}"))?;
+        let messenger = self.messenger_builder.build_with_name(server_name.to_string());
+        let result = async {
+            let mut client = CustomToolClient::from_config(server_name.to_string(), config, &self.database)?;
+            client.assign_messenger(Box::new(messenger.clone()));
+            let client = Arc::new(client);
+            client.init().await?;
+            Ok(client)
+        }
+        .await;
+        if let Err(e) = &result {
+            // Lets the tool manager know that the server is no longer loading
+            let _ = messenger.send_tools_list_result(Err(eyre::eyre!("{e}"))).await;
+        }
+        result
+    }
+
+    fn install(self: &Arc<Self>, server_name: &str, client: Arc<CustomToolClient>, restart_count: u32) {
+        let generation = self
+            .update_health(server_name, |health| {
+                health.generation += 1;
+                health.status = ServerStatus::Running;
+                health.restart_count = restart_count;
+                health.started_at = Instant::now();
+            })
+            .generation;
+        if let Ok(mut clients) = self.clients.write() {
+            clients.insert(server_name.to_string(), client.clone());
+        }
+        self.has_restarted.store(true, Ordering::Release);
+        self.watch(server_name.to_string(), &client, generation);
+    }
+
+    /// Restarts the server when its client closes, unless the client has been replaced by then
+    fn watch(self: &Arc<Self>, server_name: String, client: &CustomToolClient, generation: u64) {
+        let closed = client.closed();
+        let this = self.clone();
+        tokio::spawn(async move {
+            let reason = closed.await;
+            this.on_closed(&server_name, generation, reason).await;
+        });
+    }
+
+    async fn on_closed(self: &Arc<Self>, server_name: &str, generation: u64, reason: String) {
+        let is_current = self.update_current_health(server_name, generation, |health| {
+            health.status = ServerStatus::Failed;
+            health.last_error = Some(reason.clone());
+        });
+        if !is_current {
+            return;
+        }
+        warn!("mcp server {server_name} stopped: {reason}");
+        let Some(config) = self.configs.get(server_name) else {
+            return;
+        };
+        // Remote servers reconnect on their own
+        if config.transport_type() != TransportType::Stdio {
+            return;
+        }
+        let policy = config.restart.unwrap_or_default();
+        for attempt in 1..=policy.max_retries {
+            // The server may have been restarted by hand in the meantime
+            let is_current = self.update_current_health(server_name, generation, |health| {
+                health.status = ServerStatus::Restarting {
+                    attempt,
+                    max_retries: policy.max_retries,
+                };
+            });
+            if !is_current {
+                return;
+            }
+            tokio::time::sleep(backoff(policy.backoff, attempt)).await;
+            if self.generation(server_name) != Some(generation) {
+                return;
+            }
+            match self.start(server_name).await {
+                Ok(client) => {
+                    let restart_count = self.health(server_name).map_or(0, |health| health.restart_count);
+                    self.install(server_name, client, restart_count + 1);
+                    return;
+                },
+                Err(e) => {
+                    warn!("Failed to restart mcp server {server_name}: {e}");
+                    self.update_current_health(server_name, generation, |health| {
+                        health.last_error = Some(e.to_string());
+                    });
+                },
+            }
+        }
+        self.update_current_health(server_name, generation, |health| health.status = ServerStatus::Failed);
+    }
+
+    fn generation(&self, server_name: &str) -> Option<u64> {
+        self.health(server_name).map(|health| health.generation)
+    }
+
+    /// Applies `f` to the health of the server unless its client has been replaced since
+    /// `generation`, and returns whether it did
+    fn update_current_health(&self, server_name: &str, generation: u64, f: impl FnOnce(&mut ServerHealth)) -> bool {
+        let mut health = match self.health.write() {
+            Ok(health) => health,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match health.get_mut(server_name) {
+            Some(health) if health.generation == generation => {
+                f(health);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Applies `f` to the health of the server and returns the updated health
+    fn update_health(&self, server_name: &str, f: impl FnOnce(&mut ServerHealth)) -> ServerHealth {
+        let mut health = match self.health.write() {
+            Ok(health) => health,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let health = health
+            .entry(server_name.to_string())
+            .or_insert_with(|| ServerHealth::new(ServerStatus::Running));
+        f(health);
+        health.clone()
+    }
+}
+
+/// The delay before a restart attempt, which doubles with every attempt
+fn backoff(initial: u64, attempt: u32) -> Duration {
+    Duration::from_millis(initial.saturating_mul(1 << attempt.saturating_sub(1).min(16)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{
+        Path,
+        PathBuf,
+    };
+    use std::sync::RwLock as SyncRwLock;
+
+    use tokio::sync::mpsc::Receiver;
+
+    use super::*;
+    use crate::cli::chat::server_logs::ServerLogs;
+    use crate::cli::chat::server_messenger::UpdateEventMessage;
+
+    const TEST_SERVER_NAME: &str = "test_mcp_server";
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Builds the test server and returns the path to its binary, which sits next to the
+    /// directory of the test binary
+    fn test_server_bin() -> PathBuf {
+        let mut cargo = std::process::Command::new(std::env::var("CARGO").unwrap_or("cargo".to_string()));
+        cargo.args(["build", "--bin", TEST_SERVER_NAME]);
+        // Cargo describes the package under test to the tests, and some build scripts rebuild their
+        // crate whenever these variables change
+        for (key, _) in std::env::vars() {
+            if key == "OUT_DIR"
+                || [
+                    "CARGO_PKG_",
+                    "CARGO_MANIFEST_",
+                    "CARGO_CRATE_",
+                    "CARGO_BIN_",
+                    "CARGO_PRIMARY_",
+                    "CARGO_TARGET_TMPDIR",
+                    "CARGO_RUSTC_",
+                ]
+                .iter()
+                .any(|prefix| key.starts_with(prefix))
+            {
+                cargo.env_remove(key);
+            }
+        }
+        let status = cargo.status().expect("Failed to build the test server");
+        assert!(status.success());
+        let deps_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+        deps_dir
+            .parent()
+            .unwrap()
+            .join(format!("{TEST_SERVER_NAME}{}", std::env::consts::EXE_SUFFIX))
+    }
+
+    async fn supervise(bin: &Path) -> (Arc<ServerSupervisor>, Receiver<UpdateEventMessage>) {
+        let config = serde_json::from_value::<CustomToolConfig>(serde_json::json!({
+            "command": bin,
+            "restart": { "maxRetries": 2, "backoff": 100 }
+        }))
+        .unwrap();
+        let (rx, _, messenger_builder) =
+            ServerMessengerBuilder::new(50, Arc::new(SyncRwLock::new(Vec::new())), ServerLogs::default());
+        let supervisor = ServerSupervisor::new(
+            HashMap::from([("test".to_string(), config)]),
+            messenger_builder,
+            Database::new().await.unwrap(),
+            &HashMap::new(),
+        );
+        (supervisor, rx)
+    }
+
+    /// Waits for the next tools list of the server and returns the names of its tools
+    async fn next_tools(rx: &mut Receiver<UpdateEventMessage>) -> eyre::Result<Vec<String>> {
+        tokio::time::timeout(TIMEOUT, async {
+            loop {
+                if let UpdateEventMessage::ToolsListResult { result, .. } = rx.recv().await.unwrap() {
+                    break result.map(|result| {
+                        result
+                            .tools
+                            .iter()
+                            .filter_map(|tool| tool.get("name")?.as_str().map(str::to_string))
+                            .collect()
+                    });
+                }
+            }
+        })
+        .await
+        .expect("Timed out waiting for the tools of the server")
+    }
+
+    async fn wait_for_status(supervisor: &ServerSupervisor, status: ServerStatus) -> ServerHealth {
+        tokio::time::timeout(TIMEOUT, async {
+            loop {
+                match supervisor.health("test") {
+                    Some(health) if health.status == status => break health,
+                    _ => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        })
+        .await
+        .expect("Timed out waiting for the status of the server")
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_restart_after_exit() {
+        let bin = test_server_bin();
+        let (supervisor, mut rx) = supervise(&bin).await;
+        assert_eq!(supervisor.health("test").unwrap().status, ServerStatus::NotStarted);
+
+        let client = supervisor.start_lazy("test").await.unwrap();
+        assert_eq!(next_tools(&mut rx).await.unwrap(), vec!["ping"]);
+        assert!(supervisor.take_restarted_clients().is_some());
+
+        // The server stopping on its own is detected, and it comes back with its tools
+        client.terminate();
+        assert_eq!(next_tools(&mut rx).await.unwrap(), vec!["ping"]);
+        let health = wait_for_status(&supervisor, ServerStatus::Running).await;
+        assert_eq!(health.restart_count, 1);
+        assert!(health.last_error.is_some());
+        let clients = supervisor.take_restarted_clients().unwrap();
+        let restarted = clients.get("test").unwrap().clone();
+        assert!(!Arc::ptr_eq(&client, &restarted));
+
+        // A restart by hand replaces the client, and the automatic restart stands down
+        restarted.terminate();
+        let client = supervisor.restart("test").await.unwrap();
+        assert_eq!(next_tools(&mut rx).await.unwrap(), vec!["ping"]);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let health = supervisor.health("test").unwrap();
+        assert_eq!((health.status, health.restart_count), (ServerStatus::Running, 2));
+        assert!(Arc::ptr_eq(&client, supervisor.clients().get("test").unwrap()));
+
+        client.terminate();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failed_after_max_retries() {
+        // The server can not be started again once its binary is gone
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir
+            .path()
+            .join(format!("{TEST_SERVER_NAME}{}", std::env::consts::EXE_SUFFIX));
+        std::fs::copy(test_server_bin(), &bin).unwrap();
+        let (supervisor, mut rx) = supervise(&bin).await;
+
+        let client = supervisor.start_lazy("test").await.unwrap();
+        assert!(next_tools(&mut rx).await.is_ok());
+        std::fs::remove_file(&bin).unwrap();
+        client.terminate();
+
+        // Every failed attempt is reported to the tool manager
+        assert!(next_tools(&mut rx).await.is_err());
+        assert!(next_tools(&mut rx).await.is_err());
+        let health = wait_for_status(&supervisor, ServerStatus::Failed).await;
+        assert_eq!(health.restart_count, 0);
+        assert!(health.last_error.is_some());
+        assert!(
+            supervisor
+                .take_restarted_clients()
+                .is_some_and(|clients| { clients.get("test").is_some_and(|stale| Arc::ptr_eq(stale, &client)) })
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1000, 1), Duration::from_secs(1));
+        assert_eq!(backoff(1000, 2), Duration::from_secs(2));
+        assert_eq!(backoff(1000, 4), Duration::from_secs(8));
+    }
+}
@@ -0,0 +1,113 @@
+//! Caches the tools of lazily started mcp servers, so that they can be advertised to the model
+//! without starting the server. Entries are keyed by a hash of what is used to launch the server,
+//! so changing the command, args, env or url of a server invalidates its entry.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tracing::warn;
+
+use super::tools::custom_tool::CustomToolConfig;
+use crate::mcp_client::ToolsListResult;
+use crate::os::Os;
+use crate::util::directories;
+
+/// The path of the cache entry of a server
+pub fn cache_path(os: &Os, config: &CustomToolConfig) -> eyre::Result<PathBuf> {
+    Ok(directories::mcp_tool_cache_dir(os)?.join(format!("{}.json", config_hash(config))))
+}
+
+/// Reads the tools cached for a server, if any
+pub async fn read(os: &Os, config: &CustomToolConfig) -> Option<ToolsListResult> {
+    let path = cache_path(os, config).ok()?;
+    let content = os.fs.read_to_string(&path).await.ok()?;
+    match serde_json::from_str(&content) {
+        Ok(result) => Some(result),
+        Err(e) => {
+            warn!("Ignoring the invalid mcp tool cache at {}: {e}", path.display());
+            None
+        },
+    }
+}
+
+/// Caches the tools of a server, unless the cache already has them
+pub async fn write(os: &Os, config: &CustomToolConfig, result: &ToolsListResult) -> eyre::Result<()> {
+    let path = cache_path(os, config)?;
+    let content = serde_json::to_string(result)?;
+    if os.fs.read_to_string(&path).await.is_ok_and(|cached| cached == content) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        os.fs.create_dir_all(parent).await?;
+    }
+    os.fs.write(&path, content).await?;
+    Ok(())
+}
+
+fn config_hash(config: &CustomToolConfig) -> String {
+    // The env is sorted so that the hash does not depend on the order of a hash map
+    let key = serde_json::json!({
+        "command": config.command,
+        "args": config.args,
+        "env": config.env.as_ref().map(|env| env.iter().collect::<BTreeMap<_, _>>()),
+        "url": config.url,
+    });
+    hex::encode(Sha256::digest(key.to_string().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn config(value: serde_json::Value) -> CustomToolConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_config_hash() {
+        let base = config(json!({ "command": "git-mcp", "args": ["--repo", "."], "env": { "A": "1", "B": "2" } }));
+        let mut same = base.clone();
+        same.env = Some(HashMap::from([
+            ("B".to_string(), "2".to_string()),
+            ("A".to_string(), "1".to_string()),
+        ]));
+        same.timeout = 1;
+        assert_eq!(config_hash(&base), config_hash(&same));
+
+        let mut args = base.clone();
+        args.args.push("--verbose".to_string());
+        let mut env = base.clone();
+        env.env = Some(HashMap::from([("A".to_string(), "3".to_string())]));
+        let mut command = base.clone();
+        command.command = "other-mcp".to_string();
+        for changed in [args, env, command] {
+            assert_ne!(config_hash(&base), config_hash(&changed));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_write() {
+        let os = Os::new().await.unwrap();
+        let config = config(json!({ "command": "git-mcp", "startup": "lazy" }));
+        assert!(read(&os, &config).await.is_none());
+
+        let result = ToolsListResult {
+            tools: vec![json!({ "name": "status", "inputSchema": { "type": "object" } })],
+            next_cursor: None,
+        };
+        write(&os, &config, &result).await.unwrap();
+        assert_eq!(read(&os, &config).await.unwrap().tools, result.tools);
+
+        let mut changed = config.clone();
+        changed.args.push("--verbose".to_string());
+        assert!(read(&os, &changed).await.is_none());
+    }
+}
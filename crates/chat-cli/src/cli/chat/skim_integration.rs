@@ -24,6 +24,7 @@ use skim::prelude::*;
 use tempfile::NamedTempFile;
 
 use super::context::ContextManager;
+use crate::database::ConversationMetadata;
 use crate::os::Os;
 
 pub struct SkimCommandSelector {
@@ -207,6 +208,77 @@ pub fn select_context_paths_with_skim(context_manager: &ContextManager) -> Resul
     }
 }
 
+/// Select a conversation from the conversation history using skim, returning its id
+pub fn select_conversation_with_skim(conversations: &[ConversationMetadata]) -> Result<Option<String>> {
+    if conversations.is_empty() {
+        return Ok(None);
+    }
+
+    let items: Vec<String> = conversations.iter().map(format_conversation_item).collect();
+    match launch_skim_selector(&items, "Select conversation: ", false)? {
+        Some(selections) if !selections.is_empty() => {
+            // The conversation id is always the last column
+            Ok(selections[0].split_whitespace().last().map(str::to_string))
+        },
+        _ => Ok(None), // User cancelled selection
+    }
+}
+
+/// Select an exchange of the conversation using skim, previewing its full prompt and response.
+/// `exchanges` holds a single line summary and the preview text of every exchange, oldest first.
+/// Returns the index of the selected exchange.
+pub fn select_exchange_with_skim(exchanges: &[(String, String)]) -> Result<Option<usize>> {
+    if exchanges.is_empty() {
+        return Ok(None);
+    }
+
+    // skim renders the preview of the highlighted item by running a command, so write each
+    // preview to a file named after the index of the exchange.
+    let preview_dir = tempfile::tempdir()?;
+    let mut items = Vec::with_capacity(exchanges.len());
+    for (index, (summary, preview)) in exchanges.iter().enumerate().rev() {
+        std::fs::write(preview_dir.path().join(index.to_string()), preview)?;
+        items.push(format!("{index} {summary}"));
+    }
+
+    let options = SkimOptionsBuilder::default()
+        .height("100%".to_string())
+        .prompt("Rewind to before: ".to_string())
+        .reverse(true)
+        .preview(Some(format!("cat '{}'/{{1}}", preview_dir.path().display())))
+        .build()
+        .map_err(|e| eyre!("Failed to build skim options: {}", e))?;
+    let item_reader = SkimItemReader::default();
+    let items = item_reader.of_bufread(Cursor::new(items.join("\n")));
+
+    match run_skim_with_options(&options, items)? {
+        Some(items) if !items.is_empty() => Ok(items[0]
+            .output()
+            .split_whitespace()
+            .next()
+            .and_then(|index| index.parse().ok())),
+        _ => Ok(None), // User cancelled selection
+    }
+}
+
+fn format_conversation_item(conversation: &ConversationMetadata) -> String {
+    let updated_at = conversation
+        .updated_at
+        .to_offset(time::UtcOffset::current_local_offset().unwrap_or(time::UtcOffset::UTC))
+        .format(time::macros::format_description!(
+            "[year]-[month]-[day] [hour]:[minute]"
+        ))
+        .unwrap_or_default();
+
+    format!(
+        "{}  {}  ({})  {}",
+        updated_at,
+        conversation.title.as_deref().unwrap_or("(untitled)"),
+        conversation.cwd,
+        conversation.id
+    )
+}
+
 /// Launch the command selector and handle the selected command
 pub fn select_command(_os: &Os, context_manager: &ContextManager, tools: &[String]) -> Result<Option<String>> {
     let commands = get_available_commands();
@@ -323,6 +395,24 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_format_conversation_item() {
+        let conversation = ConversationMetadata {
+            id: "0b6e2c1a-2f6e-4a55-9d43-5d3c8b1f2a77".to_string(),
+            cwd: "/home/user/project".to_string(),
+            agent: None,
+            model: None,
+            title: Some("fix the flaky test".to_string()),
+            created_at: time::OffsetDateTime::UNIX_EPOCH,
+            updated_at: time::OffsetDateTime::UNIX_EPOCH,
+        };
+
+        let item = format_conversation_item(&conversation);
+        assert!(item.contains("fix the flaky test"));
+        assert!(item.contains("(/home/user/project)"));
+        assert_eq!(item.split_whitespace().last(), Some(conversation.id.as_str()));
+    }
+
     /// Test to verify that all hardcoded command strings in select_command
     /// are present in the COMMANDS array from prompt.rs
     #[test]
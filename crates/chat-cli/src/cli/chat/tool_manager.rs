@@ -64,31 +64,58 @@ use crate::cli::agent::{
 };
 use crate::cli::chat::cli::prompts::GetPromptError;
 use crate::cli::chat::message::AssistantToolUse;
+use crate::cli::chat::prompt::{
+    CompletionRequest,
+    CompletionTarget,
+};
+use crate::cli::chat::server_logs::{
+    LogEntry,
+    ServerLogs,
+};
 use crate::cli::chat::server_messenger::{
     ServerMessengerBuilder,
+    ServerRequest,
     UpdateEventMessage,
 };
+use crate::cli::chat::server_supervisor::{
+    ServerHealth,
+    ServerSupervisor,
+};
+use crate::cli::chat::tools::code_intel::LanguageServers;
 use crate::cli::chat::tools::custom_tool::{
     CustomTool,
     CustomToolClient,
+    CustomToolConfig,
+    StartupMode,
+    ToolOverride,
+};
+use crate::cli::chat::tools::script_tool::{
+    ScriptTool,
+    ScriptToolConfig,
 };
-use crate::cli::chat::tools::execute::ExecuteCommand;
-use crate::cli::chat::tools::fs_read::FsRead;
-use crate::cli::chat::tools::fs_write::FsWrite;
-use crate::cli::chat::tools::gh_issue::GhIssue;
-use crate::cli::chat::tools::knowledge::Knowledge;
-use crate::cli::chat::tools::thinking::Thinking;
-use crate::cli::chat::tools::use_aws::UseAws;
 use crate::cli::chat::tools::{
     Tool,
     ToolOrigin,
     ToolSpec,
 };
+use crate::cli::chat::{
+    server_config_vars,
+    server_tool_cache,
+};
 use crate::database::settings::Setting;
 use crate::mcp_client::{
+    CompleteParams,
+    CompletionArgument,
+    CompletionContext,
+    CompletionReference,
     JsonRpcResponse,
+    LoggingLevel,
     Messenger,
     PromptGet,
+    PromptGetArg,
+    ResourcesReadResult,
+    Root,
+    ToolsListResult,
 };
 use crate::os::Os;
 use crate::telemetry::TelemetryThread;
@@ -100,6 +127,10 @@ const NAMESPACE_DELIMITER: &str = "___";
 // model is just {server_name}{NAMESPACE_DELIMITER}{tool_name}
 const VALID_TOOL_NAME: &str = "^[a-zA-Z][a-zA-Z0-9_]*$";
 const SPINNER_CHARS: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+/// How many of the last stderr lines of a server are shown when it fails to load
+const FAILURE_STDERR_LINES: usize = 5;
+/// How long tab completion waits for a server to suggest argument values
+const ARGUMENT_COMPLETION_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub fn workspace_mcp_config_path(os: &Os) -> eyre::Result<PathBuf> {
     Ok(os.env.current_dir()?.join(".amazonq").join("mcp.json"))
@@ -151,7 +182,7 @@ pub enum LoadingRecord {
 pub struct ToolManagerBuilder {
     mcp_server_config: Option<McpServerConfig>,
     prompt_list_sender: Option<std::sync::mpsc::Sender<Vec<String>>>,
-    prompt_list_receiver: Option<std::sync::mpsc::Receiver<Option<String>>>,
+    prompt_list_receiver: Option<std::sync::mpsc::Receiver<CompletionRequest>>,
     conversation_id: Option<String>,
     agent: Option<Agent>,
 }
@@ -162,7 +193,7 @@ impl ToolManagerBuilder {
         self
     }
 
-    pub fn prompt_list_receiver(mut self, receiver: std::sync::mpsc::Receiver<Option<String>>) -> Self {
+    pub fn prompt_list_receiver(mut self, receiver: std::sync::mpsc::Receiver<CompletionRequest>) -> Self {
         self.prompt_list_receiver.replace(receiver);
         self
     }
@@ -199,6 +230,45 @@ impl ToolManagerBuilder {
             .map(|(server_name, _)| server_name.clone())
             .collect();
 
+        // References to env vars and secrets are resolved once, restarts reuse the resolved configs
+        let logs = ServerLogs::default();
+        let mut resolution_errors = HashMap::<String, eyre::Report>::new();
+        let mut resolved_servers = Vec::with_capacity(enabled_servers.len());
+        for (server_name, server_config) in enabled_servers {
+            match server_config_vars::resolve(os, &server_config).await {
+                Ok(resolved) => {
+                    logs.set_secrets(&server_name, resolved.secrets);
+                    resolved_servers.push((server_name, resolved.config));
+                },
+                Err(e) => {
+                    resolution_errors.insert(server_name.clone(), e.into());
+                    resolved_servers.push((server_name, server_config));
+                },
+            }
+        }
+        let enabled_servers = resolved_servers;
+
+        // Lazy servers whose tools are known are only started once one of their tools is used
+        let lazy_configs = enabled_servers
+            .iter()
+            .filter(|(server_name, server_config)| {
+                server_config.startup == StartupMode::Lazy && !resolution_errors.contains_key(server_name)
+            })
+            .map(|(server_name, server_config)| (server_name.clone(), server_config.clone()))
+            .collect::<HashMap<_, _>>();
+        // Tool prefixes and overrides are applied whenever a server lists its tools
+        let tool_configs = enabled_servers
+            .iter()
+            .map(|(server_name, server_config)| (server_name.clone(), server_config.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut cached_tools = HashMap::<String, ToolsListResult>::new();
+        for (server_name, server_config) in &lazy_configs {
+            if let Some(tools) = server_tool_cache::read(os, server_config).await {
+                cached_tools.insert(server_name.clone(), tools);
+            }
+        }
+        let mut deferred_servers = Vec::<(String, CustomToolConfig)>::new();
+
         let pre_initialized = enabled_servers
             .into_iter()
             .filter_map(|(server_name, server_config)| {
@@ -210,15 +280,24 @@ impl ToolManagerBuilder {
                         ))
                     );
                     None
+                } else if let Some(e) = resolution_errors.remove(&server_name) {
+                    Some((server_name, server_config, Err(e)))
+                } else if cached_tools.contains_key(&server_name) {
+                    deferred_servers.push((server_name, server_config));
+                    None
                 } else {
-                    let custom_tool_client = CustomToolClient::from_config(server_name.clone(), server_config);
-                    Some((server_name, custom_tool_client))
+                    let custom_tool_client = CustomToolClient::from_config(server_name.clone(), server_config.clone(), &os.database);
+                    Some((server_name, server_config, custom_tool_client))
                 }
             })
-            .collect::<Vec<(String, _)>>();
+            .collect::<Vec<(String, _, _)>>();
 
         let mut loading_servers = HashMap::<String, Instant>::new();
-        for (server_name, _) in &pre_initialized {
+        let server_names = pre_initialized
+            .iter()
+            .map(|(server_name, _, _)| server_name)
+            .chain(deferred_servers.iter().map(|(server_name, _)| server_name));
+        for server_name in server_names {
             let init_time = std::time::Instant::now();
             loading_servers.insert(server_name.clone(), init_time);
         }
@@ -232,6 +311,7 @@ impl ToolManagerBuilder {
         {
             let (tx, mut rx) = tokio::sync::mpsc::channel::<LoadingMsg>(50);
             let disabled_servers_display_clone = disabled_servers_display.clone();
+            let logs_clone = logs.clone();
             (
                 Some(tokio::task::spawn(async move {
                     let mut spinner_logo_idx: usize = 0;
@@ -269,7 +349,8 @@ impl ToolManagerBuilder {
                                         cursor::MoveUp(1),
                                         terminal::Clear(terminal::ClearType::CurrentLine),
                                     )?;
-                                    queue_failure_message(&name, &msg, time.as_str(), &mut output)?;
+                                    let stderr = logs_clone.recent_stderr(&name, FAILURE_STDERR_LINES);
+                                    queue_failure_message(&name, &msg, time.as_str(), &stderr, &mut output)?;
                                     queue_init_message(spinner_logo_idx, complete, failed, total, &mut output)?;
                                 },
                                 LoadingMsg::Warn { name, msg, time } => {
@@ -344,14 +425,22 @@ impl ToolManagerBuilder {
         let has_new_stuff_clone = has_new_stuff.clone();
         let pending = Arc::new(RwLock::new(HashSet::<String>::new()));
         let pending_clone = pending.clone();
-        let (mut msg_rx, messenger_builder) = ServerMessengerBuilder::new(20);
+        let roots = Arc::new(SyncRwLock::new(initial_roots(os, self.agent.as_ref())));
+        let (mut msg_rx, server_request_rx, messenger_builder) =
+            ServerMessengerBuilder::new(20, roots.clone(), logs.clone());
         let telemetry_clone = os.telemetry.clone();
         let notify = Arc::new(Notify::new());
         let notify_weak = Arc::downgrade(&notify);
         let load_record = Arc::new(Mutex::new(HashMap::<String, Vec<LoadingRecord>>::new()));
         let load_record_clone = load_record.clone();
-        let agent = Arc::new(Mutex::new(self.agent.unwrap_or_default()));
+        let logs_clone = logs.clone();
+        let os_clone = os.clone();
+        let agent = self.agent.unwrap_or_default();
+        let language_servers = LanguageServers::new(&agent);
+        let agent = Arc::new(Mutex::new(agent));
         let agent_clone = agent.clone();
+        let pinned_resources = Arc::new(Mutex::new(Vec::<PinnedResource>::new()));
+        let pinned_resources_clone = pinned_resources.clone();
 
         tokio::spawn(async move {
             let mut record_temp_buf = Vec::<u8>::new();
@@ -436,6 +525,11 @@ impl ToolManagerBuilder {
 
                         match result {
                             Ok(result) => {
+                                if let Some(config) = lazy_configs.get(&server_name) {
+                                    if let Err(e) = server_tool_cache::write(&os_clone, config, &result).await {
+                                        warn!("Failed to cache the tools of {server_name}: {e}");
+                                    }
+                                }
                                 let mut specs = result
                                     .tools
                                     .into_iter()
@@ -449,6 +543,7 @@ impl ToolManagerBuilder {
                                     &mut specs,
                                     &mut sanitized_mapping,
                                     &alias_list,
+                                    tool_configs.get(&server_name),
                                     &regex,
                                     &telemetry_clone,
                                 );
@@ -513,11 +608,19 @@ impl ToolManagerBuilder {
                                     .or_insert(vec![record]);
                             },
                             Err(e) => {
+                                let e = eyre::eyre!(logs_clone.mask(&server_name, &format!("{e:#}")));
                                 // Log error to chat Log
                                 error!("Error loading server {server_name}: {:?}", e);
                                 // Maintain a record of the server load:
                                 let mut buf_writer = BufWriter::new(&mut record_temp_buf);
-                                let _ = queue_failure_message(server_name.as_str(), &e, &time_taken, &mut buf_writer);
+                                let stderr = logs_clone.recent_stderr(&server_name, FAILURE_STDERR_LINES);
+                                let _ = queue_failure_message(
+                                    server_name.as_str(),
+                                    &e,
+                                    &time_taken,
+                                    &stderr,
+                                    &mut buf_writer,
+                                );
                                 let _ = buf_writer.flush();
                                 drop(buf_writer);
                                 let record = String::from_utf8_lossy(&record_temp_buf).to_string();
@@ -567,6 +670,14 @@ impl ToolManagerBuilder {
                         server_name: _,
                         result: _,
                     } => {},
+                    UpdateEventMessage::ResourceUpdated { server_name, uri } => {
+                        // Pinned resources are read again the next time they are needed
+                        for pinned in pinned_resources_clone.lock().await.iter_mut() {
+                            if pinned.server_name == server_name && pinned.uri == uri {
+                                pinned.contents = None;
+                            }
+                        }
+                    },
                     UpdateEventMessage::InitStart { server_name } => {
                         pending_clone.write().await.insert(server_name.clone());
                         loading_servers.insert(server_name, std::time::Instant::now());
@@ -575,20 +686,30 @@ impl ToolManagerBuilder {
             }
         });
 
-        for (mut name, init_res) in pre_initialized {
+        let mut configs = HashMap::<String, CustomToolConfig>::new();
+        for (mut name, config, init_res) in pre_initialized {
             let messenger = messenger_builder.build_with_name(name.clone());
             match init_res {
                 Ok(mut client) => {
                     client.assign_messenger(Box::new(messenger));
                     let mut client = Arc::new(client);
+                    // The configs follow the clients around so that they can be restarted
+                    let mut config = Some(config);
                     while let Some(collided_client) = clients.insert(name.clone(), client) {
                         // to avoid server name collision we are going to circumvent this by
                         // appending the name with 1
+                        if let Some(c) = config.take() {
+                            config = configs.insert(name.clone(), c);
+                        }
                         name.push('1');
                         client = collided_client;
                     }
+                    if let Some(config) = config {
+                        configs.insert(name, config);
+                    }
                 },
                 Err(e) => {
+                    let e = eyre::eyre!(logs.mask(&name, &format!("{e:#}")));
                     error!("Error initializing mcp client for server {}: {:?}", name, &e);
                     os.telemetry
                         .send_mcp_server_init(conversation_id.clone(), Some(e.to_string()), 0)
@@ -598,60 +719,124 @@ impl ToolManagerBuilder {
             }
         }
 
+        for (name, config) in deferred_servers {
+            let messenger = messenger_builder.build_with_name(name.clone());
+            if let Some(tools) = cached_tools.remove(&name) {
+                let _ = messenger.send_tools_list_result(Ok(tools)).await;
+            }
+            configs.insert(name, config);
+        }
+
+        let supervisor = ServerSupervisor::new(configs, messenger_builder, os.database.clone(), &clients);
+
         // Set up task to handle prompt requests
         let sender = self.prompt_list_sender.take();
         let receiver = self.prompt_list_receiver.take();
         let prompts = Arc::new(SyncRwLock::new(HashMap::default()));
-        // TODO: accommodate hot reload of mcp servers
         if let (Some(sender), Some(receiver)) = (sender, receiver) {
-            let clients = clients.iter().fold(HashMap::new(), |mut acc, (n, c)| {
-                acc.insert(n.clone(), Arc::downgrade(c));
-                acc
-            });
+            // Clients are looked up on every request since servers may have been restarted
+            let supervisor = Arc::downgrade(&supervisor);
             let prompts_clone = prompts.clone();
+            // Argument completions are asked of the servers from the blocking thread below
+            let runtime = tokio::runtime::Handle::current();
             tokio::task::spawn_blocking(move || {
                 let receiver = Arc::new(std::sync::Mutex::new(receiver));
                 loop {
-                    let search_word = receiver.lock().map_err(|e| eyre::eyre!("{:?}", e))?.recv()?;
-                    if clients
-                        .values()
-                        .any(|client| client.upgrade().is_some_and(|c| c.is_prompts_out_of_date()))
-                    {
-                        let mut prompts_wl = prompts_clone.write().map_err(|e| {
-                            eyre::eyre!(
-                                "Error retrieving write lock on prompts for tab complete {}",
-                                e.to_string()
-                            )
-                        })?;
-                        *prompts_wl = clients.iter().fold(
-                            HashMap::<String, Vec<PromptBundle>>::new(),
-                            |mut acc, (server_name, client)| {
-                                let Some(client) = client.upgrade() else {
-                                    return acc;
-                                };
-                                let prompt_gets = client.list_prompt_gets();
-                                let Ok(prompt_gets) = prompt_gets.read() else {
-                                    tracing::error!("Error retrieving read lock for prompt gets for tab complete");
-                                    return acc;
-                                };
-                                for (prompt_name, prompt_get) in prompt_gets.iter() {
-                                    acc.entry(prompt_name.clone())
-                                        .and_modify(|bundles| {
-                                            bundles.push(PromptBundle {
-                                                server_name: server_name.to_owned(),
-                                                prompt_get: prompt_get.clone(),
-                                            });
+                    let request = receiver.lock().map_err(|e| eyre::eyre!("{:?}", e))?.recv()?;
+                    let clients = supervisor.upgrade().map(|s| s.clients()).unwrap_or_default();
+                    let search_word = match request {
+                        CompletionRequest::Prompts { search_word } => search_word,
+                        CompletionRequest::Resources { search_word } => {
+                            let resources = clients
+                                .iter()
+                                .flat_map(|(server_name, client)| {
+                                    let uris = client
+                                        .list_resources()
+                                        .into_iter()
+                                        .map(|resource| resource.uri)
+                                        .chain(
+                                            client
+                                                .list_resource_templates()
+                                                .into_iter()
+                                                .map(|template| template.uri_template),
+                                        )
+                                        .collect::<Vec<_>>();
+                                    uris.into_iter().map(move |uri| format!("{server_name}:{uri}"))
+                                })
+                                .filter(|resource| resource.starts_with(&search_word))
+                                .collect::<Vec<_>>();
+                            if let Err(e) = sender.send(resources) {
+                                error!("Error sending resources to chat helper: {:?}", e);
+                            }
+                            continue;
+                        },
+                        CompletionRequest::PromptArguments { name } => {
+                            refresh_completion_prompts(&prompts_clone, &clients)?;
+                            let arguments = find_prompt_bundle(&prompts_clone, &name)
+                                .and_then(|bundle| bundle.prompt_get.arguments)
+                                .unwrap_or_default()
+                                .iter()
+                                .filter_map(|arg| serde_json::to_string(arg).ok())
+                                .collect::<Vec<_>>();
+                            if let Err(e) = sender.send(arguments) {
+                                error!("Error sending prompt arguments to chat helper: {:?}", e);
+                            }
+                            continue;
+                        },
+                        CompletionRequest::ArgumentValues {
+                            target,
+                            argument,
+                            value,
+                            context,
+                        } => {
+                            let target = match target {
+                                CompletionTarget::Prompt { name } => {
+                                    refresh_completion_prompts(&prompts_clone, &clients)?;
+                                    find_prompt_bundle(&prompts_clone, &name).map(|bundle| {
+                                        (bundle.server_name, CompletionReference::Prompt {
+                                            name: bundle.prompt_get.name,
                                         })
-                                        .or_insert(vec![PromptBundle {
-                                            server_name: server_name.to_owned(),
-                                            prompt_get: prompt_get.clone(),
-                                        }]);
-                                }
-                                client.prompts_updated();
-                                acc
-                            },
-                        );
-                    }
+                                    })
+                                },
+                                CompletionTarget::ResourceTemplate {
+                                    server_name,
+                                    uri_template,
+                                } => Some((server_name, CompletionReference::Resource { uri: uri_template })),
+                            };
+                            let values = match target.and_then(|(server_name, reference)| {
+                                Some((clients.get(&server_name)?.clone(), reference))
+                            }) {
+                                Some((client, reference)) => {
+                                    let params = CompleteParams {
+                                        reference,
+                                        argument: CompletionArgument { name: argument, value },
+                                        context: (!context.is_empty())
+                                            .then_some(CompletionContext { arguments: context }),
+                                    };
+                                    match runtime.block_on(tokio::time::timeout(
+                                        ARGUMENT_COMPLETION_TIMEOUT,
+                                        client.complete(params),
+                                    )) {
+                                        Ok(Ok(values)) => values,
+                                        Ok(Err(e)) => {
+                                            warn!("Argument completion failed: {:?}", e);
+                                            Vec::new()
+                                        },
+                                        Err(_) => {
+                                            warn!("Argument completion timed out");
+                                            Vec::new()
+                                        },
+                                    }
+                                },
+                                None => Vec::new(),
+                            };
+                            if let Err(e) = sender.send(values) {
+                                error!("Error sending argument completions to chat helper: {:?}", e);
+                            }
+                            continue;
+                        },
+                    };
+                    refresh_completion_prompts(&prompts_clone, &clients)?;
                     let prompts_rl = prompts_clone.read().map_err(|e| {
                         eyre::eyre!(
                             "Error retrieving read lock on prompts for tab complete {}",
@@ -701,11 +886,76 @@ impl ToolManagerBuilder {
             mcp_load_record: load_record,
             agent,
             disabled_servers: disabled_servers_display,
+            pinned_resources,
+            server_requests: Arc::new(Mutex::new(Some(server_request_rx))),
+            roots,
+            logs,
+            supervisor: Some(supervisor),
+            language_servers,
             ..Default::default()
         })
     }
 }
 
+/// The roots servers start with: the current working directory and the `mcpRoots` of the agent
+fn initial_roots(os: &Os, agent: Option<&Agent>) -> Vec<Root> {
+    let mut roots = Vec::<Root>::new();
+    let paths = std::iter::once(".").chain(agent.into_iter().flat_map(|a| a.mcp_roots.iter().map(String::as_str)));
+    for path in paths {
+        match resolve_root(os, path) {
+            Ok(root) if !roots.contains(&root) => roots.push(root),
+            Ok(_) => {},
+            Err(e) => warn!("Ignoring mcp root {path}: {e}"),
+        }
+    }
+    roots
+}
+
+/// Turns a path, which may be relative to the current working directory or start with `~`, into
+/// a root
+fn resolve_root(os: &Os, path: &str) -> eyre::Result<Root> {
+    let path = PathBuf::from(path);
+    let path = match path.strip_prefix("~") {
+        Ok(rest) => os
+            .env
+            .home()
+            .ok_or(eyre::eyre!("Could not find the home directory"))?
+            .join(rest),
+        Err(_) => os.env.current_dir()?.join(path),
+    };
+    // Normalize away `.` and `..` without touching the file system
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {},
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    let uri = url::Url::from_file_path(&normalized).map_err(|()| eyre::eyre!("Invalid path {}", path.display()))?;
+    Ok(Root {
+        uri: uri.to_string(),
+        name: normalized.file_name().map(|name| name.to_string_lossy().to_string()),
+    })
+}
+
+fn path_of_root(root: &Root) -> eyre::Result<PathBuf> {
+    url::Url::parse(&root.uri)?
+        .to_file_path()
+        .map_err(|()| eyre::eyre!("{} is not a file uri", root.uri))
+}
+
+/// A resource whose contents are attached as context to every message
+#[derive(Clone, Debug)]
+pub struct PinnedResource {
+    pub server_name: String,
+    pub uri: String,
+    /// The contents last read, [None] once the server notified us that the resource changed
+    pub contents: Option<String>,
+}
+
 #[derive(Clone, Debug)]
 /// A collection of information that is used for the following purposes:
 /// - Checking if prompt info cached is out of date
@@ -717,6 +967,90 @@ pub struct PromptBundle {
     pub prompt_get: PromptGet,
 }
 
+/// Matches the arguments given to a prompt with the ones it declares. Arguments may be given by
+/// name as `name=value`, the others are assigned in the order in which the prompt declares its
+/// arguments.
+pub fn assign_prompt_arguments(schema: &[PromptGetArg], values: &[String]) -> HashMap<String, String> {
+    let mut assigned = HashMap::<String, String>::new();
+    let mut positional = Vec::<&String>::new();
+    for value in values {
+        match value.split_once('=') {
+            Some((name, value)) if schema.iter().any(|arg| arg.name == name) => {
+                assigned.insert(name.to_string(), value.to_string());
+            },
+            _ => positional.push(value),
+        }
+    }
+    let unassigned = schema
+        .iter()
+        .filter(|arg| !assigned.contains_key(&arg.name))
+        .collect::<Vec<_>>();
+    for (arg, value) in unassigned.into_iter().zip(positional) {
+        assigned.insert(arg.name.clone(), value.clone());
+    }
+    assigned
+}
+
+type PromptsByName = Arc<SyncRwLock<HashMap<String, Vec<PromptBundle>>>>;
+
+/// Rebuilds the prompts used for tab completion if any server changed its prompts
+fn refresh_completion_prompts(
+    prompts: &PromptsByName,
+    clients: &HashMap<String, Arc<CustomToolClient>>,
+) -> eyre::Result<()> {
+    if clients.values().any(|client| client.is_prompts_out_of_date()) {
+        let mut prompts_wl = prompts.write().map_err(|e| {
+            eyre::eyre!(
+                "Error retrieving write lock on prompts for tab complete {}",
+                e.to_string()
+            )
+        })?;
+        *prompts_wl = clients.iter().fold(
+            HashMap::<String, Vec<PromptBundle>>::new(),
+            |mut acc, (server_name, client)| {
+                let prompt_gets = client.list_prompt_gets();
+                let Ok(prompt_gets) = prompt_gets.read() else {
+                    tracing::error!("Error retrieving read lock for prompt gets for tab complete");
+                    return acc;
+                };
+                for (prompt_name, prompt_get) in prompt_gets.iter() {
+                    acc.entry(prompt_name.clone())
+                        .and_modify(|bundles| {
+                            bundles.push(PromptBundle {
+                                server_name: server_name.to_owned(),
+                                prompt_get: prompt_get.clone(),
+                            });
+                        })
+                        .or_insert(vec![PromptBundle {
+                            server_name: server_name.to_owned(),
+                            prompt_get: prompt_get.clone(),
+                        }]);
+                }
+                client.prompts_updated();
+                acc
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Finds the prompt with the given name, which may be qualified with its server as in
+/// `server/name`. Unqualified names must be unambiguous.
+fn find_prompt_bundle(prompts: &PromptsByName, name: &str) -> Option<PromptBundle> {
+    let prompts = prompts.read().ok()?;
+    match name.split_once('/') {
+        Some((server_name, prompt_name)) => prompts
+            .get(prompt_name)?
+            .iter()
+            .find(|bundle| bundle.server_name == server_name)
+            .cloned(),
+        None => match prompts.get(name)?.as_slice() {
+            [bundle] => Some(bundle.clone()),
+            _ => None,
+        },
+    }
+}
+
 /// Categorizes different types of tool name validation failures:
 /// - `TooLong`: The tool name exceeds the maximum allowed length
 /// - `IllegalChar`: The tool name contains characters that are not allowed
@@ -732,6 +1066,8 @@ enum OutOfSpecName {
 pub struct ToolInfo {
     pub server_name: String,
     pub host_tool_name: HostToolName,
+    /// Arguments added to every call of the tool, which the model does not see
+    pub fixed_args: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Borrow<HostToolName> for ToolInfo {
@@ -835,6 +1171,27 @@ pub struct ToolManager {
     /// A collection of preferences that pertains to the conversation.
     /// As far as tool manager goes, this is relevant for tool and server filters
     pub agent: Arc<Mutex<Agent>>,
+
+    /// Tools declared in the `scriptTools` section of the agent, keyed by name
+    pub script_tools: HashMap<String, Arc<ScriptToolConfig>>,
+
+    /// Resources pinned with `/mcp pin`, in the order they were pinned
+    pub pinned_resources: Arc<Mutex<Vec<PinnedResource>>>,
+
+    /// Requests made by servers that are waiting to be answered by the chat session
+    server_requests: ServerRequestReceiver,
+
+    /// Directories that servers are allowed to operate on, as answered to `roots/list`
+    roots: Arc<SyncRwLock<Vec<Root>>>,
+
+    /// The recent stderr output and log messages of each server
+    logs: ServerLogs,
+
+    /// Restarts servers that stopped and keeps track of their health
+    supervisor: Option<Arc<ServerSupervisor>>,
+
+    /// The language servers used by `code_intel`, configured by the agent
+    pub language_servers: LanguageServers,
 }
 
 impl Clone for ToolManager {
@@ -850,6 +1207,13 @@ impl Clone for ToolManager {
             is_interactive: self.is_interactive,
             mcp_load_record: self.mcp_load_record.clone(),
             disabled_servers: self.disabled_servers.clone(),
+            script_tools: self.script_tools.clone(),
+            pinned_resources: self.pinned_resources.clone(),
+            server_requests: self.server_requests.clone(),
+            roots: self.roots.clone(),
+            logs: self.logs.clone(),
+            supervisor: self.supervisor.clone(),
+            language_servers: self.language_servers.clone(),
             ..Default::default()
         }
     }
@@ -864,14 +1228,27 @@ impl ToolManager {
         let tx = self.loading_status_sender.take();
         let notify = self.notify.take();
         self.schema = {
-            let tool_list = &self.agent.lock().await.tools;
+            let agent = self.agent.lock().await;
+            let tool_list = &agent.tools;
+            let is_included = |name: &str| {
+                tool_list.len() == 1 && tool_list.first().is_some_and(|n| n == "*")
+                    || tool_list.iter().any(|n| n == name)
+            };
             let mut tool_specs =
                 serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))?
                     .into_iter()
-                    .filter(|(name, _)| {
-                        tool_list.len() == 1 && tool_list.first().is_some_and(|n| n == "*") || tool_list.contains(name)
-                    })
+                    .filter(|(name, _)| is_included(name))
                     .collect::<HashMap<_, _>>();
+
+            self.script_tools.clear();
+            for config in agent.script_tools.iter().filter(|config| is_included(&config.name)) {
+                if let Err(e) = config.validate() {
+                    queue!(stderr, style::Print(format!("{e}\n")))?;
+                    continue;
+                }
+                tool_specs.insert(config.name.clone(), config.tool_spec());
+                self.script_tools.insert(config.name.clone(), Arc::new(config.clone()));
+            }
             if !crate::cli::chat::tools::thinking::Thinking::is_enabled(os) {
                 tool_specs.remove("thinking");
             }
@@ -903,6 +1280,8 @@ impl ToolManager {
                     }
                     },
                         "required": ["command"]})),
+                    output_schema: None,
+                    annotations: None,
                     tool_origin: ToolOrigin::Native,
                 });
             }
@@ -925,7 +1304,8 @@ impl ToolManager {
         });
         // We need to cast it to erase the type otherwise the compiler will default to static
         // dispatch, which would result in an error of inconsistent match arm return type.
-        let timeout_fut: Pin<Box<dyn Future<Output = ()>>> = if self.clients.is_empty() {
+        let has_servers = !self.clients.is_empty() || self.supervisor.as_ref().is_some_and(|s| s.has_servers());
+        let timeout_fut: Pin<Box<dyn Future<Output = ()>>> = if !has_servers {
             // If there is no server loaded, we want to resolve immediately
             Box::pin(future::ready(()))
         } else if self.is_interactive {
@@ -962,7 +1342,7 @@ impl ToolManager {
                         ).await;
                     }
                 }
-                if !self.clients.is_empty() && !self.is_interactive {
+                if has_servers && !self.is_interactive {
                     let _ = queue!(
                         stderr,
                         style::Print(
@@ -1019,21 +1399,14 @@ impl ToolManager {
             status: ToolResultStatus::Error,
         };
 
+        if let Some(tool) = Tool::native(&value.name, &value.args) {
+            return tool.map_err(map_err);
+        }
         Ok(match value.name.as_str() {
-            "fs_read" => Tool::FsRead(serde_json::from_value::<FsRead>(value.args).map_err(map_err)?),
-            "fs_write" => Tool::FsWrite(serde_json::from_value::<FsWrite>(value.args).map_err(map_err)?),
-            #[cfg(windows)]
-            "execute_cmd" => {
-                Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(value.args).map_err(map_err)?)
-            },
-            #[cfg(not(windows))]
-            "execute_bash" => {
-                Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(value.args).map_err(map_err)?)
-            },
-            "use_aws" => Tool::UseAws(serde_json::from_value::<UseAws>(value.args).map_err(map_err)?),
-            "report_issue" => Tool::GhIssue(serde_json::from_value::<GhIssue>(value.args).map_err(map_err)?),
-            "thinking" => Tool::Thinking(serde_json::from_value::<Thinking>(value.args).map_err(map_err)?),
-            "knowledge" => Tool::Knowledge(serde_json::from_value::<Knowledge>(value.args).map_err(map_err)?),
+            name if self.script_tools.contains_key(name) => Tool::Script(ScriptTool {
+                config: self.script_tools[name].clone(),
+                args: value.args,
+            }),
             // Note that this name is namespaced with server_name{DELIMITER}tool_name
             name => {
                 // Note: tn_map also has tools that underwent no transformation. In otherwords, if
@@ -1041,6 +1414,7 @@ impl ToolManager {
                 let ToolInfo {
                     server_name,
                     host_tool_name: tool_name,
+                    fixed_args,
                 } = match self.tn_map.get(name) {
                     Some(tool_info) => Ok::<&ToolInfo, ToolResult>(tool_info),
                     None => {
@@ -1054,7 +1428,10 @@ impl ToolManager {
                         })
                     },
                 }?;
-                let Some(client) = self.clients.get(server_name) else {
+                // Servers that are known but not running, such as lazy ones, are started once the
+                // tool use has been approved
+                let client = self.clients.get(server_name).cloned();
+                if client.is_none() && !self.has_server(server_name) {
                     return Err(ToolResult {
                         tool_use_id: value.id,
                         content: vec![ToolResultContentBlock::Text(format!(
@@ -1062,21 +1439,34 @@ impl ToolManager {
                         ))],
                         status: ToolResultStatus::Error,
                     });
-                };
+                }
                 // The tool input schema has the shape of { type, properties }.
                 // The field "params" expected by MCP is { name, arguments }, where name is the
                 // name of the tool being invoked,
                 // https://spec.modelcontextprotocol.io/specification/2024-11-05/server/tools/#calling-tools.
                 // The field "arguments" is where ToolUse::args belong.
+                let mut args = value.args;
+                if !fixed_args.is_empty() {
+                    if args.is_null() {
+                        args = serde_json::json!({});
+                    }
+                    if let Some(args) = args.as_object_mut() {
+                        args.extend(fixed_args.clone());
+                    }
+                }
                 let mut params = serde_json::Map::<String, serde_json::Value>::new();
                 params.insert("name".to_owned(), serde_json::Value::String(tool_name.to_owned()));
-                params.insert("arguments".to_owned(), value.args);
+                params.insert("arguments".to_owned(), args);
                 let params = serde_json::Value::Object(params);
+                let spec = self.schema.get(name);
                 let custom_tool = CustomTool {
                     name: tool_name.to_owned(),
-                    client: client.clone(),
+                    server_name: server_name.clone(),
+                    client,
                     method: "tools/call".to_owned(),
                     params: Some(params),
+                    output_schema: spec.and_then(|spec| spec.output_schema.clone()),
+                    annotations: spec.and_then(|spec| spec.annotations.clone()),
                 };
                 Tool::Custom(custom_tool)
             },
@@ -1085,6 +1475,9 @@ impl ToolManager {
 
     /// Updates tool managers various states with new information
     pub async fn update(&mut self) {
+        if let Some(clients) = self.supervisor.as_ref().and_then(|s| s.take_restarted_clients()) {
+            self.clients = clients;
+        }
         // A hashmap of <tool name, tool spec>
         let mut tool_specs = HashMap::<String, ToolSpec>::new();
         let new_tools = {
@@ -1248,18 +1641,9 @@ impl ToolManager {
                         .and_then(|bundles| bundles.iter().find(|b| b.server_name == server_name))
                         .ok_or(GetPromptError::MissingPromptInfo)?;
 
-                    // Here we need to convert the positional arguments into key value pair
-                    // The assignment order is assumed to be the order of args as they are
-                    // presented in PromptGet::arguments
+                    // Here we need to convert the arguments into key value pairs
                     let args = if let (Some(schema), Some(value)) = (&prompt_get.arguments, &arguments) {
-                        let params = schema.iter().zip(value.iter()).fold(
-                            HashMap::<String, String>::new(),
-                            |mut acc, (prompt_get_arg, value)| {
-                                acc.insert(prompt_get_arg.name.clone(), value.clone());
-                                acc
-                            },
-                        );
-                        Some(serde_json::json!(params))
+                        Some(serde_json::json!(assign_prompt_arguments(schema, value)))
                     } else {
                         None
                     };
@@ -1321,15 +1705,282 @@ impl ToolManager {
     pub async fn pending_clients(&self) -> Vec<String> {
         self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>()
     }
+
+    /// Waits for the next request made by a server. This never resolves if there are no servers.
+    pub async fn next_server_request(&self) -> Option<ServerRequest> {
+        recv_server_request(&self.server_requests).await
+    }
+
+    /// Where requests made by servers are received, to answer them from a background task
+    pub fn server_request_receiver(&self) -> ServerRequestReceiver {
+        self.server_requests.clone()
+    }
+
+    /// Returns a request made by a server if one is waiting to be answered
+    pub fn try_next_server_request(&self) -> Option<ServerRequest> {
+        self.server_requests.try_lock().ok()?.as_mut()?.try_recv().ok()
+    }
+
+    pub fn roots(&self) -> Vec<Root> {
+        self.roots.read().map(|roots| roots.clone()).unwrap_or_default()
+    }
+
+    /// Allows servers to operate on the directory at `path`
+    pub async fn add_root(&self, os: &Os, path: &str) -> eyre::Result<Root> {
+        let root = resolve_root(os, path)?;
+        if !os.fs.exists(path_of_root(&root)?) {
+            eyre::bail!("{path} does not exist");
+        }
+        {
+            let mut roots = self.roots.write().map_err(|e| eyre::eyre!("{e}"))?;
+            if roots.contains(&root) {
+                eyre::bail!("{} is already a root", root.uri);
+            }
+            roots.push(root.clone());
+        }
+        self.notify_roots_changed().await;
+        Ok(root)
+    }
+
+    /// No longer allows servers to operate on the directory at `path`
+    pub async fn remove_root(&self, os: &Os, path: &str) -> eyre::Result<Root> {
+        let root = resolve_root(os, path)?;
+        {
+            let mut roots = self.roots.write().map_err(|e| eyre::eyre!("{e}"))?;
+            let Some(index) = roots.iter().position(|r| r.uri == root.uri) else {
+                eyre::bail!("{} is not a root", root.uri);
+            };
+            roots.remove(index);
+        }
+        self.notify_roots_changed().await;
+        Ok(root)
+    }
+
+    async fn notify_roots_changed(&self) {
+        for (server_name, client) in &self.clients {
+            if let Err(e) = client.notify("roots/list_changed", None).await {
+                warn!("Failed to notify {server_name} of the change of roots: {e}");
+            }
+        }
+    }
+
+    pub fn server_health(&self, server_name: &str) -> Option<ServerHealth> {
+        let mut health = self.supervisor.as_ref()?.health(server_name)?;
+        health.last_error = health.last_error.map(|error| self.logs.mask(server_name, &error));
+        Some(health)
+    }
+
+    /// Stops the server and starts it again, which also reloads its tools
+    pub async fn restart_server(&mut self, server_name: &str) -> eyre::Result<()> {
+        let Some(supervisor) = self.supervisor.clone().filter(|s| s.health(server_name).is_some()) else {
+            eyre::bail!("No mcp server named {server_name}");
+        };
+        self.pending_clients.write().await.insert(server_name.to_string());
+        let result = supervisor.restart(server_name).await;
+        if let Some(clients) = supervisor.take_restarted_clients() {
+            self.clients = clients;
+        }
+        result
+            .map(|_| ())
+            .map_err(|e| eyre::eyre!(self.logs.mask(server_name, &format!("{e:#}"))))
+    }
+
+    /// Whether the server is configured, whether or not it is running
+    fn has_server(&self, server_name: &str) -> bool {
+        self.clients.contains_key(server_name)
+            || self
+                .supervisor
+                .as_ref()
+                .is_some_and(|supervisor| supervisor.health(server_name).is_some())
+    }
+
+    /// Starts the server if it is a lazy server that has not been started yet. Returns [None] if
+    /// the server is already running or unknown.
+    pub async fn start_server(&mut self, server_name: &str) -> Option<eyre::Result<()>> {
+        if self.clients.contains_key(server_name) {
+            return None;
+        }
+        let supervisor = self.supervisor.clone().filter(|s| s.health(server_name).is_some())?;
+        self.pending_clients.write().await.insert(server_name.to_string());
+        let result = supervisor.start_lazy(server_name).await;
+        if let Some(clients) = supervisor.take_restarted_clients() {
+            self.clients = clients;
+        }
+        Some(
+            result
+                .map(|_| ())
+                .map_err(|e| eyre::eyre!(self.logs.mask(server_name, &format!("{e:#}")))),
+        )
+    }
+
+    /// Returns the log entries of the server kept after the first `seen` ones, and the number of
+    /// entries logged so far. See [ServerLogs::since].
+    pub fn server_logs(&self, server_name: &str, seen: usize) -> (Vec<LogEntry>, usize) {
+        self.logs.since(server_name, seen)
+    }
+
+    /// Asks the server to send log messages of at least `level` with `logging/setLevel`. Returns
+    /// whether the server supports logging.
+    pub async fn set_log_level(&self, server_name: &str, level: LoggingLevel) -> eyre::Result<bool> {
+        let client = self
+            .clients
+            .get(server_name)
+            .ok_or_else(|| eyre::eyre!("No mcp server named {server_name}"))?;
+        if client.capabilities().await.is_none_or(|cap| cap.logging.is_none()) {
+            return Ok(false);
+        }
+        let resp = client
+            .request("logging/setLevel", Some(serde_json::json!({ "level": level })))
+            .await?;
+        if let Some(error) = resp.error {
+            eyre::bail!("Failed to set the log level of {server_name}: {}", error.message);
+        }
+        Ok(true)
+    }
+
+    /// Returns the `(server name, uri)` of the resources referenced as `@server:uri` in the input,
+    /// ignoring mentions of unknown servers. Servers that are not running yet are included.
+    pub fn resource_mentions(&self, input: &str) -> Vec<(String, String)> {
+        parse_resource_mentions(input)
+            .into_iter()
+            .filter(|(server_name, _)| self.has_server(server_name))
+            .map(|(server_name, uri)| (server_name.to_string(), uri.to_string()))
+            .collect()
+    }
+
+    /// Reads a resource with `resources/read` and returns its contents as text
+    pub async fn read_resource(&self, server_name: &str, uri: &str) -> eyre::Result<String> {
+        let client = self
+            .clients
+            .get(server_name)
+            .ok_or_else(|| eyre::eyre!("No mcp server named {server_name}"))?;
+        let resp = client
+            .request("resources/read", Some(serde_json::json!({ "uri": uri })))
+            .await?;
+        if let Some(error) = resp.error {
+            eyre::bail!("Failed to read {uri}: {}", error.message);
+        }
+        let result = resp.result.ok_or_else(|| eyre::eyre!("Missing result reading {uri}"))?;
+        let result = serde_json::from_value::<ResourcesReadResult>(result)?;
+        Ok(format_resource_contents(&result))
+    }
+
+    /// Reads a resource and keeps it as context, subscribing to its updates if the server supports
+    /// it.
+    pub async fn pin_resource(&self, server_name: &str, uri: &str) -> eyre::Result<()> {
+        let contents = self.read_resource(server_name, uri).await?;
+        let mut pinned_resources = self.pinned_resources.lock().await;
+        if let Some(pinned) = pinned_resources
+            .iter_mut()
+            .find(|p| p.server_name == server_name && p.uri == uri)
+        {
+            pinned.contents = Some(contents);
+            return Ok(());
+        }
+        pinned_resources.push(PinnedResource {
+            server_name: server_name.to_string(),
+            uri: uri.to_string(),
+            contents: Some(contents),
+        });
+        drop(pinned_resources);
+
+        if let Some(client) = self.clients.get(server_name) {
+            if client.supports_resource_subscriptions().await {
+                client
+                    .request("resources/subscribe", Some(serde_json::json!({ "uri": uri })))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn unpin_resource(&self, server_name: &str, uri: &str) -> eyre::Result<()> {
+        let mut pinned_resources = self.pinned_resources.lock().await;
+        let len = pinned_resources.len();
+        pinned_resources.retain(|p| !(p.server_name == server_name && p.uri == uri));
+        if pinned_resources.len() == len {
+            eyre::bail!("@{server_name}:{uri} is not pinned");
+        }
+        drop(pinned_resources);
+
+        if let Some(client) = self.clients.get(server_name) {
+            if client.supports_resource_subscriptions().await {
+                client
+                    .request("resources/unsubscribe", Some(serde_json::json!({ "uri": uri })))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the `(@server:uri, contents)` of the pinned resources, reading again the ones that
+    /// changed since they were last read.
+    pub async fn pinned_resources(&self) -> Vec<(String, String)> {
+        let mut pinned_resources = self.pinned_resources.lock().await;
+        let mut resources = Vec::new();
+        for pinned in pinned_resources.iter_mut() {
+            if pinned.contents.is_none() {
+                match self.read_resource(&pinned.server_name, &pinned.uri).await {
+                    Ok(contents) => pinned.contents = Some(contents),
+                    Err(e) => warn!("Failed to refresh pinned resource {}: {:?}", pinned.uri, e),
+                }
+            }
+            if let Some(contents) = &pinned.contents {
+                resources.push((format!("@{}:{}", pinned.server_name, pinned.uri), contents.clone()));
+            }
+        }
+        resources
+    }
+}
+
+/// Finds the `@server:uri` tokens of the input and splits them into server name and uri
+fn parse_resource_mentions(input: &str) -> Vec<(&str, &str)> {
+    input
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('@')?.split_once(':'))
+        .filter(|(server_name, uri)| !server_name.is_empty() && !uri.is_empty())
+        .collect()
+}
+
+/// Joins the contents of a resource, describing binary contents instead of including them
+fn format_resource_contents(result: &ResourcesReadResult) -> String {
+    result
+        .contents
+        .iter()
+        .map(|contents| match (&contents.text, &contents.blob) {
+            (Some(text), _) => text.clone(),
+            (None, Some(blob)) => format!(
+                "[binary contents of {} ({}), {} bytes base64 encoded]",
+                contents.uri,
+                contents.mime_type.as_deref().unwrap_or("unknown type"),
+                blob.len()
+            ),
+            (None, None) => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The requests made by servers that are waiting to be answered by the chat session
+pub type ServerRequestReceiver = Arc<Mutex<Option<tokio::sync::mpsc::Receiver<ServerRequest>>>>;
+
+/// Waits for the next request made by a server. This never resolves if there are no servers.
+pub async fn recv_server_request(receiver: &ServerRequestReceiver) -> Option<ServerRequest> {
+    match receiver.lock().await.as_mut() {
+        Some(receiver) => receiver.recv().await,
+        None => future::pending().await,
+    }
 }
 
 #[inline]
+#[allow(clippy::too_many_arguments)]
 fn process_tool_specs(
     conversation_id: &str,
     server_name: &str,
     specs: &mut Vec<ToolSpec>,
     tn_map: &mut HashMap<ModelToolName, ToolInfo>,
     alias_list: &HashMap<HostToolName, ModelToolName>,
+    config: Option<&CustomToolConfig>,
     regex: &Regex,
     telemetry: &TelemetryThread,
 ) -> eyre::Result<()> {
@@ -1346,16 +1997,27 @@ fn process_tool_specs(
 
     for spec in specs.iter_mut() {
         let model_tool_name = alias_list.get(&spec.name).cloned().unwrap_or({
-            if !regex.is_match(&spec.name) {
-                let mut sn = sanitize_name(spec.name.clone(), regex, &mut hasher);
+            let prefixed_name = match config.and_then(|c| c.tool_prefix.as_ref()) {
+                Some(prefix) => format!("{prefix}{}", spec.name),
+                None => spec.name.clone(),
+            };
+            if !regex.is_match(&prefixed_name) {
+                let mut sn = sanitize_name(prefixed_name, regex, &mut hasher);
                 while tn_map.contains_key(&sn) {
                     sn.push('1');
                 }
                 sn
             } else {
-                spec.name.clone()
+                prefixed_name
             }
         });
+        let tool_override = config.and_then(|c| c.tool_overrides.get(&spec.name));
+        if let Some(tool_override) = tool_override {
+            apply_tool_override(spec, tool_override);
+        }
+        if let Some(max_len) = config.and_then(|c| c.max_description_length) {
+            truncate_description(&mut spec.description, max_len);
+        }
         if model_tool_name.len() > 64 {
             out_of_spec_tool_names.push(OutOfSpecName::TooLong(spec.name.clone()));
             continue;
@@ -1366,6 +2028,7 @@ fn process_tool_specs(
         tn_map.insert(model_tool_name.clone(), ToolInfo {
             server_name: server_name.to_string(),
             host_tool_name: spec.name.clone(),
+            fixed_args: tool_override.map(|o| o.fixed_args.clone()).unwrap_or_default(),
         });
         spec.name = model_tool_name;
         spec.tool_origin = ToolOrigin::McpServer(server_name.to_string());
@@ -1409,6 +2072,43 @@ fn process_tool_specs(
     }
 }
 
+/// Changes the description of a tool as configured, and hides its fixed arguments from the model
+fn apply_tool_override(spec: &mut ToolSpec, tool_override: &ToolOverride) {
+    if let Some(description) = &tool_override.description {
+        spec.description = description.clone();
+    }
+    if let Some(append) = &tool_override.append_description {
+        if !spec.description.is_empty() {
+            spec.description.push('\n');
+        }
+        spec.description.push_str(append);
+    }
+    if tool_override.fixed_args.is_empty() {
+        return;
+    }
+    let schema = &mut spec.input_schema.0;
+    if let Some(properties) = schema.get_mut("properties").and_then(|p| p.as_object_mut()) {
+        properties.retain(|name, _| !tool_override.fixed_args.contains_key(name));
+    }
+    if let Some(required) = schema.get_mut("required").and_then(|r| r.as_array_mut()) {
+        required.retain(|name| {
+            !name
+                .as_str()
+                .is_some_and(|name| tool_override.fixed_args.contains_key(name))
+        });
+    }
+}
+
+/// Cuts a description down to at most `max_len` characters, marking that it was truncated
+fn truncate_description(description: &mut String, max_len: usize) {
+    if description.chars().count() <= max_len {
+        return;
+    }
+    let mut truncated = description.chars().take(max_len.saturating_sub(1)).collect::<String>();
+    truncated.push('…');
+    *description = truncated;
+}
+
 fn sanitize_name(orig: String, regex: &regex::Regex, hasher: &mut impl Hasher) -> String {
     if regex.is_match(&orig) && !orig.contains(NAMESPACE_DELIMITER) {
         return orig;
@@ -1501,10 +2201,11 @@ fn queue_failure_message(
     name: &str,
     fail_load_msg: &eyre::Report,
     time: &str,
+    stderr: &[String],
     output: &mut impl Write,
 ) -> eyre::Result<()> {
     use crate::util::CHAT_BINARY_NAME;
-    Ok(queue!(
+    queue!(
         output,
         style::SetForegroundColor(style::Color::Red),
         style::Print("✗ "),
@@ -1518,6 +2219,20 @@ fn queue_failure_message(
         style::Print("\n - "),
         style::Print(fail_load_msg),
         style::Print("\n"),
+    )?;
+    if !stderr.is_empty() {
+        queue!(output, style::Print(" - last output on stderr:\n"))?;
+        for line in stderr {
+            queue!(
+                output,
+                style::SetForegroundColor(style::Color::DarkGrey),
+                style::Print(format!("   {line}\n")),
+                style::ResetColor,
+            )?;
+        }
+    }
+    Ok(queue!(
+        output,
         style::Print(format!(
             " - run with Q_LOG_LEVEL=trace and see $TMPDIR/{CHAT_BINARY_NAME} for detail\n"
         )),
@@ -1606,4 +2321,166 @@ mod tests {
         let sanitized = sanitize_name(with_delim, &regex, &mut hasher);
         assert_eq!(sanitized, "abc");
     }
+
+    #[tokio::test]
+    async fn test_lazy_server_not_started() {
+        let database = crate::database::Database::new().await.unwrap();
+        let config = serde_json::from_value::<CustomToolConfig>(
+            serde_json::json!({ "command": "docs-server", "startup": "lazy" }),
+        )
+        .unwrap();
+        let (_, _, messenger_builder) =
+            ServerMessengerBuilder::new(20, Arc::new(SyncRwLock::new(Vec::new())), ServerLogs::default());
+        let supervisor = ServerSupervisor::new(
+            HashMap::from([("docs".to_string(), config)]),
+            messenger_builder,
+            database,
+            &HashMap::new(),
+        );
+        let tool_manager = ToolManager {
+            tn_map: HashMap::from([("docs___search".to_string(), ToolInfo {
+                server_name: "docs".to_string(),
+                host_tool_name: "search".to_string(),
+                fixed_args: Default::default(),
+            })]),
+            supervisor: Some(supervisor),
+            ..Default::default()
+        };
+
+        // The server is only started once the tool use is approved
+        let tool = tool_manager
+            .get_tool_from_tool_use(AssistantToolUse {
+                id: "1".to_string(),
+                name: "docs___search".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        let Tool::Custom(tool) = tool else {
+            panic!("expected an mcp tool, got {tool:?}");
+        };
+        assert_eq!((tool.server_name.as_str(), tool.name.as_str()), ("docs", "search"));
+        assert!(tool.client.is_none());
+
+        assert_eq!(
+            tool_manager.resource_mentions("@docs:file:///a.md @other:file:///b.md"),
+            vec![("docs".to_string(), "file:///a.md".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_initial_roots() {
+        let os = Os::new().await.unwrap();
+        let cwd = os.env.current_dir().unwrap();
+        let uri = |path: PathBuf| url::Url::from_file_path(path).unwrap().to_string();
+
+        let root = resolve_root(&os, "./a/../b").unwrap();
+        assert_eq!(root.uri, uri(cwd.join("b")));
+        assert_eq!(root.name.as_deref(), Some("b"));
+        let root = resolve_root(&os, "~/projects").unwrap();
+        assert_eq!(root.uri, uri(os.env.home().unwrap().join("projects")));
+
+        let agent = Agent {
+            mcp_roots: vec![".".to_string(), "./other".to_string()],
+            ..Default::default()
+        };
+        let roots = initial_roots(&os, Some(&agent));
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0].uri, uri(cwd.clone()));
+        assert_eq!(roots[1].uri, uri(cwd.join("other")));
+    }
+
+    #[test]
+    fn test_parse_resource_mentions() {
+        assert_eq!(
+            parse_resource_mentions("summarize @docs:file:///README.md and @db:schema://users please"),
+            vec![("docs", "file:///README.md"), ("db", "schema://users")]
+        );
+        assert!(parse_resource_mentions("@prompt-name with args").is_empty());
+        assert!(parse_resource_mentions("email me at someone@example.com: @:uri @server:").is_empty());
+    }
+
+    #[test]
+    fn test_format_resource_contents() {
+        let result = serde_json::from_value::<ResourcesReadResult>(serde_json::json!({
+            "contents": [
+                { "uri": "file:///a.txt", "mimeType": "text/plain", "text": "hello" },
+                { "uri": "file:///b.png", "mimeType": "image/png", "blob": "aGVsbG8=" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            format_resource_contents(&result),
+            "hello\n[binary contents of file:///b.png (image/png), 8 bytes base64 encoded]"
+        );
+    }
+
+    #[test]
+    fn test_assign_prompt_arguments() {
+        let arg = |name: &str| PromptGetArg {
+            name: name.to_string(),
+            description: None,
+            required: None,
+        };
+        let schema = [arg("file"), arg("focus"), arg("style")];
+        let values = ["focus=perf".to_string(), "main.rs".to_string(), "terse".to_string()];
+        let assigned = assign_prompt_arguments(&schema, &values);
+        assert_eq!(assigned.get("file").map(String::as_str), Some("main.rs"));
+        assert_eq!(assigned.get("focus").map(String::as_str), Some("perf"));
+        assert_eq!(assigned.get("style").map(String::as_str), Some("terse"));
+
+        // Values that only look like named arguments are positional
+        let assigned = assign_prompt_arguments(&schema, &["a=b".to_string()]);
+        assert_eq!(assigned.get("file").map(String::as_str), Some("a=b"));
+        assert_eq!(assigned.len(), 1);
+    }
+
+    #[test]
+    fn test_apply_tool_override() {
+        let mut spec = serde_json::from_value::<ToolSpec>(serde_json::json!({
+            "name": "create_issue",
+            "description": "Creates an issue",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "owner": {}, "repo": {}, "title": {} },
+                "required": ["owner", "title"]
+            }
+        }))
+        .unwrap();
+        let tool_override = serde_json::from_value::<ToolOverride>(serde_json::json!({
+            "appendDescription": "Issues are filed against acme/widgets.",
+            "fixedArgs": { "owner": "acme", "repo": "widgets" }
+        }))
+        .unwrap();
+        apply_tool_override(&mut spec, &tool_override);
+        assert_eq!(
+            spec.description,
+            "Creates an issue\nIssues are filed against acme/widgets."
+        );
+        assert_eq!(
+            spec.input_schema.0,
+            serde_json::json!({
+                "type": "object",
+                "properties": { "title": {} },
+                "required": ["title"]
+            })
+        );
+
+        let tool_override = ToolOverride {
+            description: Some("Files a bug".to_string()),
+            ..Default::default()
+        };
+        apply_tool_override(&mut spec, &tool_override);
+        assert_eq!(spec.description, "Files a bug");
+    }
+
+    #[test]
+    fn test_truncate_description() {
+        let mut description = "short".to_string();
+        truncate_description(&mut description, 5);
+        assert_eq!(description, "short");
+
+        let mut description = "a description that is too long".to_string();
+        truncate_description(&mut description, 6);
+        assert_eq!(description, "a des…");
+    }
 }
@@ -0,0 +1,687 @@
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+use std::time::Duration;
+
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::Deserialize;
+use tokio::io::{
+    AsyncBufReadExt,
+    AsyncRead,
+    AsyncReadExt,
+    AsyncWriteExt as _,
+    BufReader,
+};
+use tokio::process::{
+    Child,
+    ChildStdin,
+    Command,
+};
+use tokio::sync::{
+    Mutex,
+    Notify,
+    broadcast,
+    oneshot,
+};
+use tracing::{
+    debug,
+    trace,
+    warn,
+};
+
+use super::LanguageServerConfig;
+use crate::mcp_client::{
+    JsonRpcMessage,
+    JsonRpcNotification,
+    JsonRpcRequest,
+    JsonRpcResponse,
+    JsonRpcVersion,
+    Listener,
+    LogListener,
+    StdioListener,
+    StdioLogListener,
+    Transport,
+    TransportError,
+};
+
+/// How long to keep waiting for more diagnostics once the server has published some.
+const DIAGNOSTICS_QUIET_PERIOD: Duration = Duration::from_millis(500);
+
+/// JSON-RPC over stdio using the `Content-Length` framing of the Language Server Protocol, as
+/// opposed to the newline delimited framing used by MCP.
+#[derive(Debug)]
+pub struct LspStdioTransport {
+    stdin: Arc<Mutex<ChildStdin>>,
+    receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
+    log_receiver: broadcast::Receiver<String>,
+}
+
+impl LspStdioTransport {
+    pub fn client(child_process: &mut Child) -> Result<Self, TransportError> {
+        let (tx, receiver) = broadcast::channel::<Result<JsonRpcMessage, TransportError>>(100);
+        let Some(stdout) = child_process.stdout.take() else {
+            return Err(TransportError::Custom("No stdout found on child process".to_owned()));
+        };
+        let Some(stdin) = child_process.stdin.take() else {
+            return Err(TransportError::Custom("No stdin found on child process".to_owned()));
+        };
+        let Some(stderr) = child_process.stderr.take() else {
+            return Err(TransportError::Custom("No stderr found on child process".to_owned()));
+        };
+        let (log_tx, log_receiver) = broadcast::channel::<String>(100);
+        tokio::task::spawn(async move {
+            let stderr = BufReader::new(stderr);
+            let mut lines = stderr.lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let _ = log_tx.send(line);
+            }
+        });
+        Self::spawn_reader(stdout, tx);
+        Ok(Self {
+            stdin: Arc::new(Mutex::new(stdin)),
+            receiver,
+            log_receiver,
+        })
+    }
+
+    fn spawn_reader<R: AsyncRead + Unpin + Send + 'static>(
+        reader: R,
+        tx: broadcast::Sender<Result<JsonRpcMessage, TransportError>>,
+    ) {
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            loop {
+                match read_message(&mut reader).await {
+                    Ok(Some(body)) => {
+                        let _ = tx.send(serde_json::from_slice::<JsonRpcMessage>(&body).map_err(Into::into));
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        break;
+                    },
+                }
+            }
+        });
+    }
+}
+
+/// Reads the body of a single `Content-Length` framed message. Returns `None` on EOF.
+async fn read_message<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Result<Option<Vec<u8>>, TransportError> {
+    let mut content_length = None::<usize>;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            // Tolerate stray blank lines between messages
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_err| TransportError::Custom(format!("Invalid Content-Length header: {header}")))?,
+                );
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+fn frame_message(msg: &JsonRpcMessage) -> Result<Vec<u8>, TransportError> {
+    let body = serde_json::to_vec(msg)?;
+    let mut framed = format!("Content-Length: {}\r\n\r\n", body.len()).into_bytes();
+    framed.extend(body);
+    Ok(framed)
+}
+
+#[async_trait::async_trait]
+impl Transport for LspStdioTransport {
+    async fn send(&self, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+        let framed = frame_message(msg)?;
+        let mut stdin = self.stdin.lock().await;
+        stdin
+            .write_all(&framed)
+            .await
+            .map_err(|e| TransportError::Custom(format!("Error writing to language server: {:?}", e)))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| TransportError::Custom(format!("Error writing to language server: {:?}", e)))?;
+        Ok(())
+    }
+
+    fn get_listener(&self) -> impl Listener {
+        StdioListener {
+            receiver: self.receiver.resubscribe(),
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        Ok(self.stdin.lock().await.shutdown().await?)
+    }
+
+    fn get_log_listener(&self) -> impl LogListener {
+        StdioLogListener {
+            receiver: self.log_receiver.resubscribe(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LocationLink {
+    target_uri: String,
+    target_selection_range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Option<u8>,
+    pub message: String,
+    pub source: Option<String>,
+    pub code: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Default)]
+struct PublishedDiagnostics {
+    /// Incremented every time the server publishes diagnostics for the document
+    generation: u64,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Default)]
+struct ClientState {
+    pending: HashMap<u64, oneshot::Sender<JsonRpcResponse>>,
+    diagnostics: HashMap<String, PublishedDiagnostics>,
+    /// Version of each document that has been opened with the server
+    open_documents: HashMap<String, i32>,
+}
+
+/// A language server launched over stdio that lives for the rest of the chat session.
+#[derive(Debug)]
+pub struct LspClient {
+    pub name: String,
+    language_id: String,
+    timeout: Duration,
+    transport: Arc<LspStdioTransport>,
+    state: Arc<std::sync::Mutex<ClientState>>,
+    diagnostics_updated: Arc<Notify>,
+    next_id: AtomicU64,
+    // Held so that the server is killed once the client is dropped
+    _child: Child,
+}
+
+impl LspClient {
+    /// Launches the server and performs the `initialize` handshake.
+    pub async fn start(name: &str, config: &LanguageServerConfig, root: &Path) -> Result<Self> {
+        let mut command = Command::new(&config.command);
+        command
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command
+            .spawn()
+            .map_err(|err| eyre!("Failed to launch language server `{}`: {err}", config.command))?;
+        let transport = Arc::new(LspStdioTransport::client(&mut child)?);
+
+        let client = Self {
+            name: name.to_string(),
+            language_id: config.language_id.clone().unwrap_or_else(|| name.to_string()),
+            timeout: Duration::from_millis(config.timeout),
+            transport,
+            state: Arc::default(),
+            diagnostics_updated: Arc::new(Notify::new()),
+            next_id: AtomicU64::new(0),
+            _child: child,
+        };
+        client.spawn_listener();
+        client.spawn_log_listener();
+
+        let root_uri = path_to_uri(root)?;
+        let init_params = serde_json::json!({
+            "processId": std::process::id(),
+            "clientInfo": { "name": "Q CLI Chat", "version": env!("CARGO_PKG_VERSION") },
+            "rootUri": root_uri,
+            "workspaceFolders": [{
+                "uri": root_uri,
+                "name": root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            }],
+            "initializationOptions": config.initialization_options,
+            "capabilities": {
+                "workspace": { "configuration": true, "workspaceFolders": true },
+                "textDocument": {
+                    "synchronization": { "didSave": true },
+                    "publishDiagnostics": { "versionSupport": true },
+                    "hover": { "contentFormat": ["plaintext", "markdown"] },
+                    "definition": { "linkSupport": true },
+                    "references": {},
+                },
+            },
+        });
+        client.request("initialize", Some(init_params)).await?;
+        client.notify("initialized", Some(serde_json::json!({}))).await?;
+        debug!(server = name, "language server initialized");
+
+        Ok(client)
+    }
+
+    fn spawn_listener(&self) {
+        let mut listener = self.transport.get_listener();
+        let transport = Arc::downgrade(&self.transport);
+        let state = Arc::downgrade(&self.state);
+        let diagnostics_updated = Arc::downgrade(&self.diagnostics_updated);
+        let name = self.name.clone();
+        tokio::spawn(async move {
+            loop {
+                let msg = match listener.recv().await {
+                    Ok(msg) => msg,
+                    Err(TransportError::RecvError(_)) => break,
+                    Err(err) => {
+                        warn!(server = name, ?err, "error reading from language server");
+                        continue;
+                    },
+                };
+                let (Some(state), Some(transport)) = (state.upgrade(), transport.upgrade()) else {
+                    break;
+                };
+                match msg {
+                    JsonRpcMessage::Response(resp) => {
+                        let sender = state.lock().ok().and_then(|mut s| s.pending.remove(&resp.id));
+                        if let Some(sender) = sender {
+                            let _ = sender.send(resp);
+                        }
+                    },
+                    JsonRpcMessage::Notification(notif) if notif.method == "textDocument/publishDiagnostics" => {
+                        #[derive(Deserialize)]
+                        struct Params {
+                            uri: String,
+                            diagnostics: Vec<Diagnostic>,
+                        }
+                        let Some(Ok(params)) = notif.params.map(serde_json::from_value::<Params>) else {
+                            continue;
+                        };
+                        if let Ok(mut state) = state.lock() {
+                            let published = state.diagnostics.entry(params.uri).or_default();
+                            published.generation += 1;
+                            published.diagnostics = params.diagnostics;
+                        }
+                        if let Some(notify) = diagnostics_updated.upgrade() {
+                            notify.notify_waiters();
+                        }
+                    },
+                    JsonRpcMessage::Notification(notif) => {
+                        trace!(server = name, method = notif.method, "ignoring notification");
+                    },
+                    // Servers expect every request they make to be answered. None of the ones we
+                    // receive require anything more than an empty result.
+                    JsonRpcMessage::Request(req) => {
+                        let result = match req.method.as_str() {
+                            "workspace/configuration" => {
+                                let items = req
+                                    .params
+                                    .as_ref()
+                                    .and_then(|p| p.get("items"))
+                                    .and_then(|items| items.as_array())
+                                    .map_or(0, Vec::len);
+                                serde_json::Value::Array(vec![serde_json::Value::Null; items])
+                            },
+                            _ => serde_json::Value::Null,
+                        };
+                        let resp = JsonRpcMessage::Response(JsonRpcResponse {
+                            jsonrpc: JsonRpcVersion::default(),
+                            id: req.id,
+                            result: Some(result),
+                            error: None,
+                        });
+                        if let Err(err) = transport.send(&resp).await {
+                            warn!(server = name, ?err, "failed to reply to language server request");
+                        }
+                    },
+                }
+            }
+        });
+    }
+
+    fn spawn_log_listener(&self) {
+        let mut log_listener = self.transport.get_log_listener();
+        let name = self.name.clone();
+        tokio::spawn(async move {
+            while let Ok(line) = log_listener.recv().await {
+                trace!(target: "lsp", "{name} stderr: {line}");
+            }
+        });
+    }
+
+    pub async fn request(&self, method: &str, params: Option<serde_json::Value>) -> Result<serde_json::Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.state
+            .lock()
+            .map_err(|e| eyre!("language server state poisoned: {e}"))?
+            .pending
+            .insert(id, tx);
+
+        let msg = JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            method: method.to_string(),
+            params,
+        });
+        self.transport.send(&msg).await?;
+
+        let resp = match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(resp)) => resp,
+            Ok(Err(_)) => bail!("Language server {} stopped before responding to {method}", self.name),
+            Err(_) => {
+                if let Ok(mut state) = self.state.lock() {
+                    state.pending.remove(&id);
+                }
+                bail!("Language server {} timed out responding to {method}", self.name)
+            },
+        };
+        if let Some(err) = resp.error {
+            bail!(
+                "Language server {} returned an error for {method}: {}",
+                self.name,
+                err.message
+            );
+        }
+        Ok(resp.result.unwrap_or_default())
+    }
+
+    pub async fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
+        let msg = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JsonRpcVersion::default(),
+            method: method.to_string(),
+            params,
+        });
+        Ok(self.transport.send(&msg).await?)
+    }
+
+    /// Sends the current content of `path` to the server, opening the document if this is the
+    /// first time it is seen. Returns the document uri.
+    pub async fn sync_document(&self, path: &Path, text: &str) -> Result<String> {
+        let uri = path_to_uri(path)?;
+        let version = {
+            let mut state = self
+                .state
+                .lock()
+                .map_err(|e| eyre!("language server state poisoned: {e}"))?;
+            let version = state.open_documents.entry(uri.clone()).or_insert(0);
+            *version += 1;
+            *version
+        };
+
+        if version == 1 {
+            self.notify(
+                "textDocument/didOpen",
+                Some(serde_json::json!({
+                    "textDocument": {
+                        "uri": uri,
+                        "languageId": self.language_id,
+                        "version": version,
+                        "text": text,
+                    }
+                })),
+            )
+            .await?;
+        } else {
+            self.notify(
+                "textDocument/didChange",
+                Some(serde_json::json!({
+                    "textDocument": { "uri": uri, "version": version },
+                    "contentChanges": [{ "text": text }],
+                })),
+            )
+            .await?;
+        }
+        // Some servers (e.g. rust-analyzer) only run their full checks on save.
+        self.notify(
+            "textDocument/didSave",
+            Some(serde_json::json!({ "textDocument": { "uri": uri } })),
+        )
+        .await?;
+
+        Ok(uri)
+    }
+
+    fn diagnostics_generation(&self, uri: &str) -> u64 {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|s| s.diagnostics.get(uri).map(|d| d.generation))
+            .unwrap_or_default()
+    }
+
+    /// Syncs `path` and waits for the server to publish diagnostics for it.
+    pub async fn diagnostics(&self, path: &Path, text: &str) -> Result<Vec<Diagnostic>> {
+        let uri = path_to_uri(path)?;
+        let initial_generation = self.diagnostics_generation(&uri);
+        self.sync_document(path, text).await?;
+
+        // Wait for the first publish after our change, then for the server to go quiet since
+        // servers commonly publish syntax errors before semantic ones.
+        let first_publish = async {
+            loop {
+                let notified = self.diagnostics_updated.notified();
+                if self.diagnostics_generation(&uri) > initial_generation {
+                    break;
+                }
+                notified.await;
+            }
+        };
+        if tokio::time::timeout(self.timeout, first_publish).await.is_err() {
+            bail!(
+                "Language server {} did not publish diagnostics for {} within {}ms. They are not available yet, try again later",
+                self.name,
+                path.display(),
+                self.timeout.as_millis()
+            );
+        }
+        while tokio::time::timeout(DIAGNOSTICS_QUIET_PERIOD, self.diagnostics_updated.notified())
+            .await
+            .is_ok()
+        {}
+
+        Ok(self
+            .state
+            .lock()
+            .map_err(|e| eyre!("language server state poisoned: {e}"))?
+            .diagnostics
+            .get(&uri)
+            .map(|d| d.diagnostics.clone())
+            .unwrap_or_default())
+    }
+
+    pub async fn definition(&self, path: &Path, text: &str, position: Position) -> Result<Vec<Location>> {
+        let uri = self.sync_document(path, text).await?;
+        let result = self
+            .request("textDocument/definition", Some(position_params(&uri, position)))
+            .await?;
+        Ok(parse_locations(result))
+    }
+
+    pub async fn references(&self, path: &Path, text: &str, position: Position) -> Result<Vec<Location>> {
+        let uri = self.sync_document(path, text).await?;
+        let mut params = position_params(&uri, position);
+        params["context"] = serde_json::json!({ "includeDeclaration": true });
+        let result = self.request("textDocument/references", Some(params)).await?;
+        Ok(parse_locations(result))
+    }
+
+    pub async fn hover(&self, path: &Path, text: &str, position: Position) -> Result<Option<String>> {
+        let uri = self.sync_document(path, text).await?;
+        let result = self
+            .request("textDocument/hover", Some(position_params(&uri, position)))
+            .await?;
+        Ok(result.get("contents").map(hover_contents_to_string))
+    }
+
+    pub async fn shutdown(&self) {
+        if let Err(err) = self.request("shutdown", None).await {
+            debug!(server = self.name, ?err, "language server did not shut down cleanly");
+        }
+        let _ = self.notify("exit", None).await;
+        let _ = self.transport.shutdown().await;
+    }
+}
+
+fn position_params(uri: &str, position: Position) -> serde_json::Value {
+    serde_json::json!({
+        "textDocument": { "uri": uri },
+        "position": { "line": position.line, "character": position.character },
+    })
+}
+
+pub fn path_to_uri(path: &Path) -> Result<String> {
+    url::Url::from_file_path(path)
+        .map(String::from)
+        .map_err(|_err| eyre!("'{}' is not an absolute path", path.display()))
+}
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+/// Definition and reference results may be a single location, a list of locations, or a list of
+/// location links.
+fn parse_locations(value: serde_json::Value) -> Vec<Location> {
+    let values = match value {
+        serde_json::Value::Array(values) => values,
+        serde_json::Value::Null => vec![],
+        value => vec![value],
+    };
+    values
+        .into_iter()
+        .filter_map(|value| {
+            if let Ok(link) = serde_json::from_value::<LocationLink>(value.clone()) {
+                return Some(Location {
+                    uri: link.target_uri,
+                    range: link.target_selection_range,
+                });
+            }
+            serde_json::from_value::<Location>(value).ok()
+        })
+        .collect()
+}
+
+fn hover_contents_to_string(contents: &serde_json::Value) -> String {
+    match contents {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .map(hover_contents_to_string)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        // MarkupContent `{ kind, value }` or MarkedString `{ language, value }`
+        serde_json::Value::Object(obj) => obj
+            .get("value")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_message() {
+        let msg: JsonRpcMessage = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "initialized",
+            "params": {}
+        }))
+        .unwrap();
+        let mut framed = frame_message(&msg).unwrap();
+        framed.extend(b"Content-Type: application/vscode-jsonrpc; charset=utf-8\r\nContent-Length: 2\r\n\r\n{}");
+
+        let mut reader = BufReader::new(framed.as_slice());
+        let body = read_message(&mut reader).await.unwrap().unwrap();
+        assert_eq!(serde_json::from_slice::<JsonRpcMessage>(&body).unwrap(), msg);
+        assert_eq!(read_message(&mut reader).await.unwrap().unwrap(), b"{}");
+        assert!(read_message(&mut reader).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_locations() {
+        let range = serde_json::json!({
+            "start": { "line": 1, "character": 2 },
+            "end": { "line": 1, "character": 5 }
+        });
+        assert!(parse_locations(serde_json::Value::Null).is_empty());
+
+        let single = parse_locations(serde_json::json!({ "uri": "file:///a.rs", "range": range }));
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].range.start, Position { line: 1, character: 2 });
+
+        let links = parse_locations(serde_json::json!([{
+            "targetUri": "file:///b.rs",
+            "targetRange": range,
+            "targetSelectionRange": range
+        }]));
+        assert_eq!(links[0].uri, "file:///b.rs");
+    }
+
+    #[test]
+    fn test_hover_contents_to_string() {
+        assert_eq!(
+            hover_contents_to_string(&serde_json::json!({ "kind": "markdown", "value": "```rust\nfn a()\n```" })),
+            "```rust\nfn a()\n```"
+        );
+        assert_eq!(
+            hover_contents_to_string(&serde_json::json!(["a", { "language": "rust", "value": "b" }])),
+            "a\n\nb"
+        );
+    }
+}
@@ -0,0 +1,686 @@
+pub mod client;
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::Arc;
+
+use client::{
+    Diagnostic,
+    Location,
+    LspClient,
+    Position,
+    uri_to_path,
+};
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use globset::{
+    Glob,
+    GlobSetBuilder,
+};
+use serde::Deserialize;
+use tokio::sync::{
+    Mutex,
+    OnceCell,
+};
+use tracing::{
+    error,
+    warn,
+};
+
+use super::{
+    InvokeOutput,
+    MAX_TOOL_RESPONSE_SIZE,
+    OutputKind,
+    format_path,
+    sanitize_path_tool_arg,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::os::Os;
+
+/// Configuration of a single language server, as found in the `code_intel` tool settings of an
+/// agent.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LanguageServerConfig {
+    /// The command used to launch the server. The server must speak LSP over stdio.
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// File extensions (without the leading dot) handled by this server
+    pub file_extensions: Vec<String>,
+    /// The LSP language identifier sent when opening documents. Defaults to the server name.
+    #[serde(default)]
+    pub language_id: Option<String>,
+    #[serde(default)]
+    pub initialization_options: Option<serde_json::Value>,
+    /// Timeout for each request in ms
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_timeout() -> u64 {
+    30 * 1000
+}
+
+/// The `code_intel` entry of an agent's `toolsSettings`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeIntelSettings {
+    /// Servers to use in addition to, or in place of, the default ones
+    #[serde(default)]
+    pub servers: HashMap<String, LanguageServerConfig>,
+    /// Whether `fs_write` should report the diagnostics of the files it changes
+    #[serde(default)]
+    pub diagnostics_on_write: bool,
+}
+
+impl CodeIntelSettings {
+    pub fn from_agent(agent: &Agent) -> Self {
+        match agent.tools_settings.get("code_intel") {
+            Some(settings) => serde_json::from_value::<Self>(settings.clone()).unwrap_or_else(|e| {
+                error!("Failed to deserialize tool settings for code_intel: {:?}", e);
+                Self::default()
+            }),
+            None => Self::default(),
+        }
+    }
+
+    /// Finds the server responsible for `path`, preferring the ones configured by the agent over
+    /// the defaults.
+    fn server_for(&self, path: &Path) -> Option<(String, LanguageServerConfig)> {
+        let ext = path.extension()?.to_str()?;
+        let handles = |config: &LanguageServerConfig| config.file_extensions.iter().any(|e| e == ext);
+        self.servers
+            .iter()
+            .find(|(_, config)| handles(config))
+            .map(|(name, config)| (name.clone(), config.clone()))
+            .or_else(|| {
+                default_servers()
+                    .into_iter()
+                    .find(|(name, config)| !self.servers.contains_key(name) && handles(config))
+            })
+    }
+}
+
+fn default_servers() -> Vec<(String, LanguageServerConfig)> {
+    let server =
+        |command: &str, args: &[&str], file_extensions: &[&str], language_id: Option<&str>| LanguageServerConfig {
+            command: command.to_string(),
+            args: args.iter().map(|s| (*s).to_string()).collect(),
+            file_extensions: file_extensions.iter().map(|s| (*s).to_string()).collect(),
+            language_id: language_id.map(str::to_string),
+            initialization_options: None,
+            timeout: default_timeout(),
+        };
+    vec![
+        ("rust".to_string(), server("rust-analyzer", &[], &["rs"], None)),
+        (
+            "python".to_string(),
+            server("pyright-langserver", &["--stdio"], &["py", "pyi"], None),
+        ),
+        (
+            "typescript".to_string(),
+            server(
+                "typescript-language-server",
+                &["--stdio"],
+                &["ts", "tsx", "mts", "cts"],
+                None,
+            ),
+        ),
+        (
+            "javascript".to_string(),
+            server(
+                "typescript-language-server",
+                &["--stdio"],
+                &["js", "jsx", "mjs", "cjs"],
+                Some("javascript"),
+            ),
+        ),
+    ]
+}
+
+/// A language server that is started on first use.
+type LazyServer = Arc<OnceCell<Arc<LspClient>>>;
+
+/// The language servers of an agent. Servers are launched lazily on first use and kept alive
+/// until [Self::shutdown_all] is called or the last clone is dropped.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageServers {
+    settings: Arc<CodeIntelSettings>,
+    /// Keyed by server name. Each server gets its own cell so that starting one does not hold up
+    /// the others.
+    servers: Arc<Mutex<HashMap<String, LazyServer>>>,
+}
+
+impl LanguageServers {
+    pub fn new(agent: &Agent) -> Self {
+        Self {
+            settings: Arc::new(CodeIntelSettings::from_agent(agent)),
+            servers: Default::default(),
+        }
+    }
+
+    /// Returns the running server for `path`, starting it if necessary.
+    async fn server_for(&self, os: &Os, path: &Path) -> Result<Arc<LspClient>> {
+        let Some((name, config)) = self.settings.server_for(path) else {
+            bail!(
+                "No language server is configured for '{}'. Configure one under the code_intel tool settings of the agent",
+                path.display()
+            );
+        };
+
+        let cell = self.servers.lock().await.entry(name.clone()).or_default().clone();
+        let server = cell
+            .get_or_try_init(|| async {
+                let root = os.env.current_dir()?;
+                Ok::<_, eyre::Report>(Arc::new(LspClient::start(&name, &config, &root).await?))
+            })
+            .await?;
+        Ok(server.clone())
+    }
+
+    /// Gracefully stops every language server started so far.
+    pub async fn shutdown_all(&self) {
+        let servers = std::mem::take(&mut *self.servers.lock().await);
+        for server in servers.values().filter_map(|cell| cell.get()) {
+            server.shutdown().await;
+        }
+    }
+
+    /// Diagnostics to attach to the result of `fs_write`, if the agent asked for them and a
+    /// language server is available for `path`.
+    pub async fn diagnostics_after_write(&self, os: &Os, path: &Path) -> Option<String> {
+        if !self.settings.diagnostics_on_write {
+            return None;
+        }
+        self.settings.server_for(path)?;
+
+        let result = async {
+            let text = os.fs.read_to_string(path).await?;
+            let server = self.server_for(os, path).await?;
+            server.diagnostics(path, &text).await
+        }
+        .await;
+        match result {
+            Ok(diagnostics) if diagnostics.is_empty() => None,
+            Ok(diagnostics) => Some(format!(
+                "The file was written. The language server reported the following diagnostics:\n{}",
+                format_diagnostics(os, path, &diagnostics)
+            )),
+            Err(err) => {
+                warn!(?err, "Failed to collect diagnostics after write");
+                None
+            },
+        }
+    }
+}
+
+/// Code intelligence backed by language servers.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "operation", rename_all = "lowercase")]
+pub enum CodeIntel {
+    Diagnostics(CodeIntelFile),
+    Definition(CodeIntelPosition),
+    References(CodeIntelPosition),
+    Hover(CodeIntelPosition),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CodeIntelFile {
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CodeIntelPosition {
+    pub path: String,
+    /// 1-based line number
+    pub line: u32,
+    /// 1-based column. Either this or `symbol` must be provided.
+    pub column: Option<u32>,
+    /// Text of the symbol on `line` to target
+    pub symbol: Option<String>,
+}
+
+impl CodeIntelPosition {
+    /// Converts the position into the 0-based, UTF-16 encoded position used by LSP.
+    fn to_lsp_position(&self, text: &str) -> Result<Position> {
+        let Some(line) = text.lines().nth(self.line.saturating_sub(1) as usize) else {
+            bail!("Line {} is outside of {}", self.line, self.path);
+        };
+        let byte_offset = match (&self.symbol, self.column) {
+            (Some(symbol), _) => match line.find(symbol.as_str()) {
+                Some(offset) => offset,
+                None => bail!("'{}' was not found on line {} of {}", symbol, self.line, self.path),
+            },
+            (None, Some(column)) => line
+                .char_indices()
+                .nth(column.saturating_sub(1) as usize)
+                .map_or(line.len(), |(i, _)| i),
+            (None, None) => bail!("Either column or symbol must be provided"),
+        };
+        Ok(Position {
+            line: self.line.saturating_sub(1),
+            character: line[..byte_offset].encode_utf16().count() as u32,
+        })
+    }
+}
+
+impl CodeIntel {
+    fn path(&self) -> &str {
+        match self {
+            CodeIntel::Diagnostics(CodeIntelFile { path })
+            | CodeIntel::Definition(CodeIntelPosition { path, .. })
+            | CodeIntel::References(CodeIntelPosition { path, .. })
+            | CodeIntel::Hover(CodeIntelPosition { path, .. }) => path,
+        }
+    }
+
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        let path = sanitize_path_tool_arg(os, self.path());
+        if !os.fs.exists(&path) {
+            bail!("'{}' does not exist", self.path());
+        }
+        if !os.fs.symlink_metadata(&path).await?.is_file() {
+            bail!("'{}' is not a file", self.path());
+        }
+        if let CodeIntel::Definition(position) | CodeIntel::References(position) | CodeIntel::Hover(position) = self {
+            if position.line == 0 {
+                bail!("Line numbers start at 1");
+            }
+            if position.column.is_none() && position.symbol.as_deref().is_none_or(str::is_empty) {
+                bail!("Either column or symbol must be provided");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        let (action, position) = match self {
+            CodeIntel::Diagnostics(_) => ("Checking diagnostics for ", None),
+            CodeIntel::Definition(position) => ("Finding the definition of ", Some(position)),
+            CodeIntel::References(position) => ("Finding references to ", Some(position)),
+            CodeIntel::Hover(position) => ("Getting type information for ", Some(position)),
+        };
+        queue!(output, style::Print(action))?;
+        if let Some(position) = position {
+            let target = match (&position.symbol, position.column) {
+                (Some(symbol), _) => format!("{symbol} at line {}", position.line),
+                (None, column) => format!("line {}, column {}", position.line, column.unwrap_or_default()),
+            };
+            queue!(
+                output,
+                style::SetForegroundColor(Color::Green),
+                style::Print(target),
+                style::ResetColor,
+                style::Print(" in "),
+            )?;
+        }
+        queue!(
+            output,
+            style::SetForegroundColor(Color::Green),
+            style::Print(self.path()),
+            style::ResetColor,
+            style::Print("\n"),
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(
+        &self,
+        os: &Os,
+        updates: &mut impl Write,
+        language_servers: &LanguageServers,
+    ) -> Result<InvokeOutput> {
+        let path = sanitize_path_tool_arg(os, self.path());
+        let text = os.fs.read_to_string(&path).await?;
+        let server = language_servers.server_for(os, &path).await?;
+
+        let (result, summary) = match self {
+            CodeIntel::Diagnostics(_) => {
+                let diagnostics = server.diagnostics(&path, &text).await?;
+                let summary = format!("Found {} diagnostics", diagnostics.len());
+                let result = if diagnostics.is_empty() {
+                    format!("No diagnostics were reported for {}", self.path())
+                } else {
+                    format_diagnostics(os, &path, &diagnostics)
+                };
+                (result, summary)
+            },
+            CodeIntel::Definition(position) => {
+                let locations = server
+                    .definition(&path, &text, position.to_lsp_position(&text)?)
+                    .await?;
+                let summary = format!("Found {} definitions", locations.len());
+                (
+                    format_locations(os, &locations, "No definition was found").await,
+                    summary,
+                )
+            },
+            CodeIntel::References(position) => {
+                let locations = server
+                    .references(&path, &text, position.to_lsp_position(&text)?)
+                    .await?;
+                let summary = format!("Found {} references", locations.len());
+                (
+                    format_locations(os, &locations, "No references were found").await,
+                    summary,
+                )
+            },
+            CodeIntel::Hover(position) => {
+                let hover = server.hover(&path, &text, position.to_lsp_position(&text)?).await?;
+                let summary = "Retrieved type information".to_string();
+                (
+                    hover
+                        .filter(|h| !h.is_empty())
+                        .unwrap_or("No information is available for this position".to_string()),
+                    summary,
+                )
+            },
+        };
+
+        let byte_count = result.len();
+        if byte_count > MAX_TOOL_RESPONSE_SIZE {
+            bail!(
+                "This tool only supports returning {MAX_TOOL_RESPONSE_SIZE} bytes at a time. The result is {byte_count} bytes."
+            );
+        }
+
+        super::queue_function_result(&summary, updates, false, false)?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(result),
+        })
+    }
+
+    pub fn eval_perm(&self, agent: &Agent) -> PermissionEvalResult {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Settings {
+            #[serde(default)]
+            allowed_paths: Vec<String>,
+            #[serde(default)]
+            denied_paths: Vec<String>,
+            #[serde(default = "default_allow_read_only")]
+            allow_read_only: bool,
+        }
+
+        fn default_allow_read_only() -> bool {
+            true
+        }
+
+        let is_in_allowlist = agent.allowed_tools.contains("code_intel");
+        match agent.tools_settings.get("code_intel") {
+            Some(settings) if is_in_allowlist => {
+                let Settings {
+                    allowed_paths,
+                    denied_paths,
+                    allow_read_only,
+                } = match serde_json::from_value::<Settings>(settings.clone()) {
+                    Ok(settings) => settings,
+                    Err(e) => {
+                        error!("Failed to deserialize tool settings for code_intel: {:?}", e);
+                        return PermissionEvalResult::Ask;
+                    },
+                };
+                let allow_set = {
+                    let mut builder = GlobSetBuilder::new();
+                    for path in &allowed_paths {
+                        if let Ok(glob) = Glob::new(path) {
+                            builder.add(glob);
+                        } else {
+                            warn!("Failed to create glob from path given: {path}. Ignoring.");
+                        }
+                    }
+                    builder.build()
+                };
+
+                let deny_set = {
+                    let mut builder = GlobSetBuilder::new();
+                    for path in &denied_paths {
+                        if let Ok(glob) = Glob::new(path) {
+                            builder.add(glob);
+                        } else {
+                            warn!("Failed to create glob from path given: {path}. Ignoring.");
+                        }
+                    }
+                    builder.build()
+                };
+
+                match (allow_set, deny_set) {
+                    (Ok(allow_set), Ok(deny_set)) => {
+                        let path = self.path();
+                        if deny_set.is_match(path) {
+                            PermissionEvalResult::Deny
+                        } else if allow_set.is_match(path) || allow_read_only {
+                            PermissionEvalResult::Allow
+                        } else {
+                            PermissionEvalResult::Ask
+                        }
+                    },
+                    (allow_res, deny_res) => {
+                        if let Err(e) = allow_res {
+                            warn!("code_intel failed to build allow set: {:?}", e);
+                        }
+                        if let Err(e) = deny_res {
+                            warn!("code_intel failed to build deny set: {:?}", e);
+                        }
+                        warn!("One or more detailed args failed to parse, falling back to ask");
+                        PermissionEvalResult::Ask
+                    },
+                }
+            },
+            None if is_in_allowlist => PermissionEvalResult::Allow,
+            _ => PermissionEvalResult::Ask,
+        }
+    }
+}
+
+fn display_path(os: &Os, path: &Path) -> String {
+    match os.env.current_dir() {
+        Ok(cwd) => format_path(cwd, path),
+        Err(_) => path.to_string_lossy().to_string(),
+    }
+}
+
+fn format_diagnostics(os: &Os, path: &Path, diagnostics: &[Diagnostic]) -> String {
+    let path = display_path(os, path);
+    diagnostics
+        .iter()
+        .map(|d| {
+            let severity = match d.severity {
+                Some(1) => "error",
+                Some(2) => "warning",
+                Some(3) => "info",
+                _ => "hint",
+            };
+            let source = match (&d.source, &d.code) {
+                (Some(source), Some(code)) => {
+                    format!(" [{source} {}]", code.as_str().map_or(code.to_string(), str::to_string))
+                },
+                (Some(source), None) => format!(" [{source}]"),
+                _ => String::new(),
+            };
+            format!(
+                "{}:{}:{}: {severity}{source}: {}",
+                path,
+                d.range.start.line + 1,
+                d.range.start.character + 1,
+                d.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn format_locations(os: &Os, locations: &[Location], empty_message: &str) -> String {
+    if locations.is_empty() {
+        return empty_message.to_string();
+    }
+
+    let mut files = HashMap::<PathBuf, Option<String>>::new();
+    let mut lines = Vec::new();
+    for location in locations {
+        let Some(path) = uri_to_path(&location.uri) else {
+            lines.push(location.uri.clone());
+            continue;
+        };
+        if !files.contains_key(&path) {
+            files.insert(path.clone(), os.fs.read_to_string(&path).await.ok());
+        }
+        let line_no = location.range.start.line as usize;
+        let snippet = files
+            .get(&path)
+            .and_then(|f| f.as_ref())
+            .and_then(|f| f.lines().nth(line_no))
+            .map(|l| format!(": {}", l.trim()))
+            .unwrap_or_default();
+        lines.push(format!(
+            "{}:{}:{}{}",
+            display_path(os, &path),
+            line_no + 1,
+            location.range.start.character + 1,
+            snippet
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_intel_deser() {
+        serde_json::from_value::<CodeIntel>(serde_json::json!({ "operation": "diagnostics", "path": "/a.rs" }))
+            .unwrap();
+        serde_json::from_value::<CodeIntel>(
+            serde_json::json!({ "operation": "definition", "path": "/a.rs", "line": 3, "symbol": "foo" }),
+        )
+        .unwrap();
+        serde_json::from_value::<CodeIntel>(
+            serde_json::json!({ "operation": "hover", "path": "/a.rs", "line": 3, "column": 7 }),
+        )
+        .unwrap();
+        assert!(
+            serde_json::from_value::<CodeIntel>(serde_json::json!({ "operation": "rename", "path": "/a.rs" })).is_err()
+        );
+    }
+
+    #[test]
+    fn test_eval_perm() {
+        let tool = |path: &str| {
+            serde_json::from_value::<CodeIntel>(serde_json::json!({ "operation": "diagnostics", "path": path }))
+                .unwrap()
+        };
+        assert_eq!(tool("/a.rs").eval_perm(&Agent::default()), PermissionEvalResult::Ask);
+
+        let agent = serde_json::from_value::<Agent>(serde_json::json!({
+            "name": "test",
+            "allowedTools": ["code_intel"],
+            "toolsSettings": {
+                "code_intel": { "allowedPaths": ["/work/**"], "deniedPaths": ["/work/secrets/**"], "allowReadOnly": false }
+            }
+        }))
+        .unwrap();
+        assert_eq!(tool("/work/src/a.rs").eval_perm(&agent), PermissionEvalResult::Allow);
+        assert_eq!(tool("/work/secrets/a.rs").eval_perm(&agent), PermissionEvalResult::Deny);
+        assert_eq!(tool("/other/a.rs").eval_perm(&agent), PermissionEvalResult::Ask);
+    }
+
+    #[test]
+    fn test_to_lsp_position() {
+        let text = "fn main() {\n    let ünïcode = \"é\"; foo();\n}\n";
+        let position = |column: Option<u32>, symbol: Option<&str>| CodeIntelPosition {
+            path: "/a.rs".to_string(),
+            line: 2,
+            column,
+            symbol: symbol.map(str::to_string),
+        };
+
+        assert_eq!(position(None, Some("foo")).to_lsp_position(text).unwrap(), Position {
+            line: 1,
+            character: 23
+        });
+        assert_eq!(position(Some(9), None).to_lsp_position(text).unwrap(), Position {
+            line: 1,
+            character: 8
+        });
+        assert!(position(None, Some("bar")).to_lsp_position(text).is_err());
+    }
+
+    #[test]
+    fn test_settings_server_for() {
+        let agent = serde_json::from_value::<Agent>(serde_json::json!({
+            "name": "test",
+            "toolsSettings": {
+                "code_intel": {
+                    "diagnosticsOnWrite": true,
+                    "servers": {
+                        "rust": { "command": "ra-multiplex", "fileExtensions": ["rs"] },
+                        "go": { "command": "gopls", "fileExtensions": ["go"] }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let settings = CodeIntelSettings::from_agent(&agent);
+        assert!(settings.diagnostics_on_write);
+
+        let (name, config) = settings.server_for(Path::new("/src/main.rs")).unwrap();
+        assert_eq!((name.as_str(), config.command.as_str()), ("rust", "ra-multiplex"));
+        assert_eq!(settings.server_for(Path::new("/main.go")).unwrap().0, "go");
+        assert_eq!(
+            settings.server_for(Path::new("/app.tsx")).unwrap().1.command,
+            "typescript-language-server"
+        );
+        assert!(settings.server_for(Path::new("/README.md")).is_none());
+
+        assert_eq!(
+            CodeIntelSettings::from_agent(&Agent::default()),
+            CodeIntelSettings::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_language_servers_use_agent_settings() {
+        let os = Os::new().await.unwrap();
+        let agent = serde_json::from_value::<Agent>(serde_json::json!({
+            "name": "test",
+            "toolsSettings": {
+                "code_intel": {
+                    "servers": {
+                        "go": { "command": "/nonexistent/gopls", "fileExtensions": ["go"] }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let servers = LanguageServers::new(&agent);
+        let err = servers.server_for(&os, Path::new("/main.go")).await.unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/gopls"), "{err}");
+        // Failing to start is not remembered, the next use tries again
+        assert!(servers.server_for(&os, Path::new("/main.go")).await.is_err());
+        assert!(servers.servers.lock().await["go"].get().is_none());
+
+        // Settings belong to the agent they were created for
+        let err = LanguageServers::default()
+            .server_for(&os, Path::new("/main.go"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No language server is configured"), "{err}");
+    }
+}
@@ -1,47 +1,78 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::io::Write;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
 
 use crossterm::{
+    cursor,
     queue,
     style,
+    terminal,
+};
+use eyre::{
+    Result,
+    bail,
 };
-use eyre::Result;
 use schemars::JsonSchema;
 use serde::{
     Deserialize,
     Serialize,
 };
 use tokio::sync::RwLock;
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::warn;
 
 use super::InvokeOutput;
+use crate::auth::mcp_oauth::McpTokenProvider;
 use crate::cli::agent::{
     Agent,
     PermissionEvalResult,
 };
 use crate::cli::chat::CONTINUATION_LINE;
 use crate::cli::chat::token_counter::TokenCounter;
+use crate::cli::chat::util::json_schema;
+use crate::database::Database;
 use crate::mcp_client::{
     Client as McpClient,
     ClientConfig as McpClientConfig,
+    CompleteParams,
+    CompleteResult,
+    HttpTransport,
     JsonRpcResponse,
     JsonRpcStdioTransport,
     MessageContent,
     Messenger,
+    ProgressParams,
     PromptGet,
+    ResourceInfo,
+    ResourceTemplateInfo,
     ServerCapabilities,
+    SseTransport,
     StdioTransport,
+    ToolAnnotations,
     ToolCallResult,
+    Tracer,
+    WebSocketTransport,
 };
 use crate::os::Os;
 
-// TODO: support http transport type
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq, JsonSchema)]
 pub struct CustomToolConfig {
     /// The command string used to initialize the mcp server
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
+    /// The url of a remote mcp server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Headers to send with every request to a remote mcp server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+    /// How to communicate with the mcp server. Defaults to `http` for servers with a url and to
+    /// `stdio` otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transport: Option<TransportType>,
     /// A list of arguments to be used to run the command with
     #[serde(default)]
     pub args: Vec<String>,
@@ -54,6 +85,120 @@ pub struct CustomToolConfig {
     /// A boolean flag to denote whether or not to load this mcp server
     #[serde(default)]
     pub disabled: bool,
+    /// How requests of the mcp server to sample from the model are handled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingConfig>,
+    /// How the server is restarted when its process exits. Only applies to stdio servers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartConfig>,
+    /// When the server is started
+    #[serde(default, skip_serializing_if = "StartupMode::is_eager")]
+    pub startup: StartupMode,
+    /// Prepended to the names of the tools of the server as the model sees them
+    #[serde(rename = "toolPrefix", skip_serializing_if = "Option::is_none")]
+    pub tool_prefix: Option<String>,
+    /// Changes to how individual tools are presented to the model, keyed by tool name as the
+    /// server reports it
+    #[serde(rename = "toolOverrides", default, skip_serializing_if = "HashMap::is_empty")]
+    pub tool_overrides: HashMap<String, ToolOverride>,
+    /// Tool descriptions longer than this many characters are truncated
+    #[serde(rename = "maxDescriptionLength", skip_serializing_if = "Option::is_none")]
+    pub max_description_length: Option<usize>,
+}
+
+impl CustomToolConfig {
+    pub fn transport_type(&self) -> TransportType {
+        self.transport.unwrap_or(match self.url {
+            Some(_) => TransportType::Http,
+            None => TransportType::Stdio,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportType {
+    /// Launch the server and talk to it on its stdin and stdout
+    Stdio,
+    /// Streamable HTTP
+    Http,
+    /// HTTP with SSE, used by servers implementing protocol versions before 2025-03-26
+    Sse,
+    /// One message per WebSocket message
+    #[serde(alias = "ws")]
+    Websocket,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupMode {
+    /// Start the server with the chat session
+    #[default]
+    Eager,
+    /// Advertise the tools the server had the last time it was started, and only start it when
+    /// one of them is used. The server is started with the chat session if its tools are not
+    /// known yet.
+    Lazy,
+}
+
+impl StartupMode {
+    pub fn is_eager(&self) -> bool {
+        *self == StartupMode::Eager
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolOverride {
+    /// Replaces the description the server gives the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Appended to the description of the tool, on a new line
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub append_description: Option<String>,
+    /// Arguments that are always passed with these values. They are hidden from the model and
+    /// added to every call of the tool
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub fixed_args: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingConfig {
+    /// Answer the sampling requests of the server without asking first
+    #[serde(default)]
+    pub auto_approve: bool,
+    /// The most tokens a response may contain, whatever the server asks for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartConfig {
+    /// How many times in a row a restart is attempted before giving up. 0 disables restarts
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// The delay before the first restart attempt in ms, which doubles with every failed attempt
+    #[serde(default = "default_backoff")]
+    pub backoff: u64,
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            backoff: default_backoff(),
+        }
+    }
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_backoff() -> u64 {
+    1000
 }
 
 pub fn default_timeout() -> u64 {
@@ -63,106 +208,254 @@ pub fn default_timeout() -> u64 {
 #[derive(Debug)]
 pub enum CustomToolClient {
     Stdio {
-        /// This is the server name as recognized by the model (post sanitized)
-        server_name: String,
         client: McpClient<StdioTransport>,
         server_capabilities: RwLock<Option<ServerCapabilities>>,
     },
+    Http {
+        client: McpClient<HttpTransport>,
+        server_capabilities: RwLock<Option<ServerCapabilities>>,
+    },
+    Sse {
+        client: McpClient<SseTransport>,
+        server_capabilities: RwLock<Option<ServerCapabilities>>,
+    },
+    WebSocket {
+        client: McpClient<WebSocketTransport>,
+        server_capabilities: RwLock<Option<ServerCapabilities>>,
+    },
+}
+
+/// Evaluates `$body` with `$client` bound to the mcp client of any kind of [CustomToolClient]
+macro_rules! with_client {
+    ($self:expr, $client:ident => $body:expr) => {
+        match $self {
+            CustomToolClient::Stdio { client: $client, .. } => $body,
+            CustomToolClient::Http { client: $client, .. } => $body,
+            CustomToolClient::Sse { client: $client, .. } => $body,
+            CustomToolClient::WebSocket { client: $client, .. } => $body,
+        }
+    };
 }
 
 impl CustomToolClient {
-    // TODO: add support for http transport
-    pub fn from_config(server_name: String, config: CustomToolConfig) -> Result<Self> {
+    /// Tokens of servers that require authorization are read from `database`.
+    pub fn from_config(server_name: String, config: CustomToolConfig, database: &Database) -> Result<Self> {
+        let transport_type = config.transport_type();
         let CustomToolConfig {
             command,
+            url,
+            headers,
+            transport: _,
             args,
             env,
             timeout,
             disabled: _,
+            sampling: _,
+            restart: _,
+            startup: _,
+            tool_prefix: _,
+            tool_overrides: _,
+            max_description_length: _,
         } = config;
-        let mcp_client_config = McpClientConfig {
-            server_name: server_name.clone(),
-            bin_path: command.clone(),
-            args,
-            timeout,
-            client_info: serde_json::json!({
-               "name": "Q CLI Chat",
-               "version": "1.0.0"
-            }),
-            env,
+        let client_info = serde_json::json!({
+           "name": "Q CLI Chat",
+           "version": "1.0.0"
+        });
+        let server_capabilities = RwLock::new(None);
+        let headers = headers.unwrap_or_default();
+        let url = match (transport_type, url) {
+            (TransportType::Stdio, _) => None,
+            (_, Some(url)) => Some(url),
+            (_, None) => bail!("A url is required for servers using the {transport_type:?} transport"),
         };
-        let client = McpClient::<JsonRpcStdioTransport>::from_config(mcp_client_config)?;
-        Ok(CustomToolClient::Stdio {
-            server_name,
-            client,
-            server_capabilities: RwLock::new(None),
+        Ok(match (transport_type, url) {
+            (TransportType::Http, Some(url)) => CustomToolClient::Http {
+                client: McpClient::from_transport(
+                    server_name.clone(),
+                    HttpTransport::client(
+                        &url,
+                        &headers,
+                        Some(Arc::new(McpTokenProvider::new(
+                            server_name.clone(),
+                            url.clone(),
+                            database.clone(),
+                            crate::request::new_client()?,
+                        ))),
+                    )?,
+                    timeout,
+                    client_info,
+                ),
+                server_capabilities,
+            },
+            (TransportType::Sse, Some(url)) => CustomToolClient::Sse {
+                client: McpClient::from_transport(
+                    server_name.clone(),
+                    SseTransport::client(&url, &headers)?,
+                    timeout,
+                    client_info,
+                ),
+                server_capabilities,
+            },
+            (TransportType::Websocket, Some(url)) => CustomToolClient::WebSocket {
+                client: McpClient::from_transport(
+                    server_name.clone(),
+                    WebSocketTransport::client(&url, &headers)?,
+                    timeout,
+                    client_info,
+                ),
+                server_capabilities,
+            },
+            _ => {
+                if command.is_empty() {
+                    bail!("A command is required for servers using the stdio transport");
+                }
+                let mcp_client_config = McpClientConfig {
+                    server_name: server_name.clone(),
+                    bin_path: command.clone(),
+                    args,
+                    timeout,
+                    client_info,
+                    env,
+                };
+                let client = McpClient::<JsonRpcStdioTransport>::from_config(mcp_client_config)?;
+                CustomToolClient::Stdio {
+                    client,
+                    server_capabilities,
+                }
+            },
         })
     }
 
     pub async fn init(&self) -> Result<()> {
-        match self {
-            CustomToolClient::Stdio {
-                client,
-                server_capabilities,
-                ..
-            } => {
-                if let Some(messenger) = &client.messenger {
-                    let _ = messenger.send_init_msg().await;
-                }
-                // We'll need to first initialize. This is the handshake every client and server
-                // needs to do before proceeding to anything else
-                let cap = client.init().await?;
-                // We'll be scrapping this for background server load: https://github.com/aws/amazon-q-developer-cli/issues/1466
-                // So don't worry about the tidiness for now
-                server_capabilities.write().await.replace(cap);
-                Ok(())
-            },
-        }
+        let server_capabilities = self.server_capabilities();
+        with_client!(self, client => {
+            if let Some(messenger) = &client.messenger {
+                let _ = messenger.send_init_msg().await;
+            }
+            // We'll need to first initialize. This is the handshake every client and server
+            // needs to do before proceeding to anything else
+            let cap = client.init().await?;
+            // We'll be scrapping this for background server load: https://github.com/aws/amazon-q-developer-cli/issues/1466
+            // So don't worry about the tidiness for now
+            server_capabilities.write().await.replace(cap);
+            Ok(())
+        })
+    }
+
+    /// Resolves, with a description of why, once the connection to the server is lost
+    pub fn closed(&self) -> impl Future<Output = String> + Send + 'static {
+        // The futures of the clients of each transport are of different types
+        let closed: Pin<Box<dyn Future<Output = String> + Send>> =
+            with_client!(self, client => Box::pin(client.closed()));
+        closed
+    }
+
+    /// Stops the server process, if there is one
+    pub fn terminate(&self) {
+        with_client!(self, client => client.terminate());
     }
 
     pub fn assign_messenger(&mut self, messenger: Box<dyn Messenger>) {
-        match self {
-            CustomToolClient::Stdio { client, .. } => {
-                client.messenger = Some(messenger);
-            },
-        }
+        with_client!(self, client => client.messenger = Some(messenger));
+    }
+
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        with_client!(self, client => client.set_tracer(tracer));
+    }
+
+    /// The capabilities the server reported during initialization
+    pub async fn capabilities(&self) -> Option<ServerCapabilities> {
+        self.server_capabilities().read().await.clone()
     }
 
-    pub fn get_server_name(&self) -> &str {
+    fn server_capabilities(&self) -> &RwLock<Option<ServerCapabilities>> {
         match self {
-            CustomToolClient::Stdio { server_name, .. } => server_name.as_str(),
+            CustomToolClient::Stdio {
+                server_capabilities, ..
+            }
+            | CustomToolClient::Http {
+                server_capabilities, ..
+            }
+            | CustomToolClient::Sse {
+                server_capabilities, ..
+            }
+            | CustomToolClient::WebSocket {
+                server_capabilities, ..
+            } => server_capabilities,
         }
     }
 
     pub async fn request(&self, method: &str, params: Option<serde_json::Value>) -> Result<JsonRpcResponse> {
-        match self {
-            CustomToolClient::Stdio { client, .. } => Ok(client.request(method, params).await?),
-        }
+        with_client!(self, client => Ok(client.request(method, params).await?))
+    }
+
+    pub async fn request_with_progress(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        progress: UnboundedSender<ProgressParams>,
+    ) -> Result<JsonRpcResponse> {
+        with_client!(self, client => Ok(client.request_with_progress(method, params, Some(progress)).await?))
     }
 
     pub fn list_prompt_gets(&self) -> Arc<std::sync::RwLock<HashMap<String, PromptGet>>> {
-        match self {
-            CustomToolClient::Stdio { client, .. } => client.prompt_gets.clone(),
-        }
+        with_client!(self, client => client.prompt_gets.clone())
     }
 
-    #[allow(dead_code)]
     pub async fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
-        match self {
-            CustomToolClient::Stdio { client, .. } => Ok(client.notify(method, params).await?),
-        }
+        with_client!(self, client => Ok(client.notify(method, params).await?))
     }
 
     pub fn is_prompts_out_of_date(&self) -> bool {
-        match self {
-            CustomToolClient::Stdio { client, .. } => client.is_prompts_out_of_date.load(Ordering::Relaxed),
-        }
+        with_client!(self, client => client.is_prompts_out_of_date.load(Ordering::Relaxed))
     }
 
     pub fn prompts_updated(&self) {
-        match self {
-            CustomToolClient::Stdio { client, .. } => client.is_prompts_out_of_date.store(false, Ordering::Relaxed),
+        with_client!(self, client => client.is_prompts_out_of_date.store(false, Ordering::Relaxed));
+    }
+
+    pub fn list_resources(&self) -> Vec<ResourceInfo> {
+        with_client!(self, client => client.resources.read().map(|r| r.clone()).unwrap_or_default())
+    }
+
+    pub fn list_resource_templates(&self) -> Vec<ResourceTemplateInfo> {
+        with_client!(self, client => client.resource_templates.read().map(|r| r.clone()).unwrap_or_default())
+    }
+
+    /// Whether the server sends `notifications/resources/updated` for subscribed resources
+    pub async fn supports_resource_subscriptions(&self) -> bool {
+        self.server_capabilities()
+            .read()
+            .await
+            .as_ref()
+            .and_then(|cap| cap.resources.as_ref())
+            .and_then(|resources| resources.get("subscribe"))
+            .and_then(|subscribe| subscribe.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Asks the server for values of an argument of a prompt or a resource template. Servers that
+    /// do not support completion yield no values.
+    pub async fn complete(&self, params: CompleteParams) -> Result<Vec<String>> {
+        let supports_completions = self
+            .server_capabilities()
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|cap| cap.completions.is_some());
+        if !supports_completions {
+            return Ok(Vec::new());
         }
+        let resp = self
+            .request("completion/complete", Some(serde_json::to_value(params)?))
+            .await?;
+        if let Some(error) = resp.error {
+            bail!("completion/complete failed: {}", error.message);
+        }
+        let Some(result) = resp.result else {
+            return Ok(Vec::new());
+        };
+        Ok(serde_json::from_value::<CompleteResult>(result)?.completion.values)
     }
 }
 
@@ -172,20 +465,49 @@ pub struct CustomTool {
     /// Actual tool name as recognized by its MCP server. This differs from the tool names as they
     /// are seen by the model since they are not prefixed by its MCP server name.
     pub name: String,
-    /// Reference to the client that manages communication with the tool's server process.
-    pub client: Arc<CustomToolClient>,
+    /// Name of the MCP server providing the tool
+    pub server_name: String,
+    /// Reference to the client that manages communication with the tool's server process. [None]
+    /// for lazy servers that have not been started yet, which happens once the tool use has been
+    /// approved.
+    pub client: Option<Arc<CustomToolClient>>,
     /// The method name to call on the tool's server, following the JSON-RPC convention.
     /// This corresponds to a specific functionality provided by the tool.
     pub method: String,
     /// Optional parameters to pass to the tool when invoking the method.
     /// Structured as a JSON value to accommodate various parameter types and structures.
     pub params: Option<serde_json::Value>,
+    /// The schema that the structured output of the tool conforms to, if it has one
+    pub output_schema: Option<serde_json::Value>,
+    pub annotations: Option<ToolAnnotations>,
 }
 
 impl CustomTool {
-    pub async fn invoke(&self, _os: &Os, _updates: impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, _os: &Os, mut updates: impl Write) -> Result<InvokeOutput> {
         // Assuming a response shape as per https://spec.modelcontextprotocol.io/specification/2024-11-05/server/tools/#calling-tools
-        let resp = self.client.request(self.method.as_str(), self.params.clone()).await?;
+        let Some(client) = &self.client else {
+            bail!("The mcp server {} is not running", self.server_name);
+        };
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let request = client.request_with_progress(self.method.as_str(), self.params.clone(), progress_tx);
+        tokio::pin!(request);
+        let resp = loop {
+            tokio::select! {
+                resp = &mut request => break resp?,
+                Some(progress) = progress_rx.recv() => {
+                    // Each update replaces the previous one on the line below the tool description
+                    let _ = queue!(
+                        updates,
+                        terminal::Clear(terminal::ClearType::CurrentLine),
+                        cursor::MoveToColumn(0),
+                        style::SetForegroundColor(style::Color::DarkGrey),
+                        style::Print(format_progress(&progress)),
+                        style::ResetColor,
+                    );
+                    let _ = updates.flush();
+                },
+            }
+        };
         let result = match resp.result {
             Some(result) => result,
             None => {
@@ -198,6 +520,24 @@ impl CustomTool {
 
         match serde_json::from_value::<ToolCallResult>(result.clone()) {
             Ok(mut de_result) => {
+                if !de_result.is_error.unwrap_or(false) {
+                    if let Some(structured) = de_result.structured_content.take() {
+                        if let Some(schema) = &self.output_schema {
+                            json_schema::validate(schema, &structured).map_err(|e| {
+                                eyre::eyre!("The output of {} does not match its output schema: {e}", self.name)
+                            })?;
+                        }
+                        return Ok(InvokeOutput {
+                            output: super::OutputKind::Json(structured),
+                        });
+                    }
+                    if self.output_schema.is_some() {
+                        warn!(
+                            "Tool {} has an output schema but returned no structured content",
+                            self.name
+                        );
+                    }
+                }
                 for content in &mut de_result.content {
                     if let MessageContent::Image { data, .. } = content {
                         *data = format!("Redacted base64 encoded string of an image of size {}", data.len());
@@ -224,6 +564,17 @@ impl CustomTool {
             style::Print(&self.name),
             style::ResetColor,
         )?;
+        let hint = match &self.annotations {
+            Some(annotations) if annotations.is_read_only() => " (read-only)",
+            Some(annotations) if annotations.is_destructive() => " (may be destructive)",
+            _ => "",
+        };
+        queue!(
+            output,
+            style::SetForegroundColor(style::Color::DarkGrey),
+            style::Print(hint),
+            style::ResetColor,
+        )?;
         if let Some(params) = &self.params {
             let params = match serde_json::to_string_pretty(params) {
                 Ok(params) => params
@@ -259,19 +610,127 @@ impl CustomTool {
         use crate::util::MCP_SERVER_TOOL_DELIMITER;
         let Self {
             name: tool_name,
-            client,
+            server_name,
             ..
         } = self;
-        let server_name = client.get_server_name();
+
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Settings {
+            /// Trust the tools of the server that are annotated as read-only
+            #[serde(default)]
+            allow_read_only: bool,
+        }
 
         if agent.allowed_tools.contains(&format!("@{server_name}"))
             || agent
                 .allowed_tools
                 .contains(&format!("@{server_name}{MCP_SERVER_TOOL_DELIMITER}{tool_name}"))
         {
+            return PermissionEvalResult::Allow;
+        }
+
+        let settings = match agent.tools_settings.get(format!("@{server_name}").as_str()) {
+            Some(settings) => serde_json::from_value::<Settings>(settings.clone()).unwrap_or_else(|e| {
+                warn!("Failed to deserialize tool settings for @{server_name}: {:?}", e);
+                Settings::default()
+            }),
+            None => Settings::default(),
+        };
+        if settings.allow_read_only && self.annotations.as_ref().is_some_and(ToolAnnotations::is_read_only) {
             PermissionEvalResult::Allow
         } else {
             PermissionEvalResult::Ask
         }
     }
 }
+
+/// Renders a progress notification as a bar when the total is known, and as a count otherwise
+fn format_progress(progress: &ProgressParams) -> String {
+    const BAR_WIDTH: usize = 20;
+    let mut line = match progress.total.filter(|total| *total > 0.0) {
+        Some(total) => {
+            let ratio = (progress.progress / total).clamp(0.0, 1.0);
+            let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+            format!(
+                " ↳ [{}{}] {:>3}%",
+                "█".repeat(filled),
+                "░".repeat(BAR_WIDTH - filled),
+                (ratio * 100.0).round()
+            )
+        },
+        None => format!(" ↳ {}", progress.progress),
+    };
+    if let Some(message) = &progress.message {
+        line.push_str(&format!(" {message}"));
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn custom_tool(annotations: Option<ToolAnnotations>) -> CustomTool {
+        let config =
+            serde_json::from_value::<CustomToolConfig>(serde_json::json!({ "url": "http://localhost:1/mcp" })).unwrap();
+        let database = Database::new().await.unwrap();
+        let client = CustomToolClient::from_config("git".to_string(), config, &database).unwrap();
+        CustomTool {
+            name: "status".to_string(),
+            server_name: "git".to_string(),
+            client: Some(Arc::new(client)),
+            method: "tools/call".to_string(),
+            params: None,
+            output_schema: None,
+            annotations,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_eval_perm_annotations() {
+        let read_only = ToolAnnotations {
+            read_only_hint: Some(true),
+            ..Default::default()
+        };
+        let agent = Agent::default();
+        assert_eq!(
+            custom_tool(Some(read_only.clone())).await.eval_perm(&agent),
+            PermissionEvalResult::Ask
+        );
+
+        let agent = serde_json::from_value::<Agent>(serde_json::json!({
+            "name": "test",
+            "toolsSettings": { "@git": { "allowReadOnly": true } }
+        }))
+        .unwrap();
+        assert_eq!(
+            custom_tool(Some(read_only)).await.eval_perm(&agent),
+            PermissionEvalResult::Allow
+        );
+        assert_eq!(custom_tool(None).await.eval_perm(&agent), PermissionEvalResult::Ask);
+        assert_eq!(
+            custom_tool(Some(ToolAnnotations::default())).await.eval_perm(&agent),
+            PermissionEvalResult::Ask
+        );
+    }
+
+    #[test]
+    fn test_format_progress() {
+        let progress = |progress: f64, total: Option<f64>, message: Option<&str>| ProgressParams {
+            progress_token: 1.into(),
+            progress,
+            total,
+            message: message.map(str::to_string),
+        };
+        assert_eq!(
+            format_progress(&progress(5.0, Some(10.0), Some("Indexing"))),
+            format!(" ↳ [{}{}]  50% Indexing", "█".repeat(10), "░".repeat(10))
+        );
+        assert_eq!(
+            format_progress(&progress(12.0, Some(10.0), None)),
+            format!(" ↳ [{}] 100%", "█".repeat(20))
+        );
+        assert_eq!(format_progress(&progress(3.0, None, Some("files"))), " ↳ 3 files");
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 use std::fs::Metadata;
 use std::io::Write;
+use std::path::PathBuf;
 
 use crossterm::queue;
 use crossterm::style::{
@@ -44,6 +45,10 @@ use crate::cli::chat::util::images::{
     is_supported_image_type,
     pre_process,
 };
+use crate::cli::chat::util::outline::{
+    self,
+    Language,
+};
 use crate::os::Os;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -60,6 +65,8 @@ pub enum FsReadOperation {
     Directory(FsDirectory),
     Search(FsSearch),
     Image(FsImage),
+    Outline(FsOutline),
+    Symbol(FsSymbol),
 }
 
 impl FsRead {
@@ -162,7 +169,9 @@ impl FsRead {
                                 match op {
                                     FsReadOperation::Line(FsLine { path, .. })
                                     | FsReadOperation::Directory(FsDirectory { path, .. })
-                                    | FsReadOperation::Search(FsSearch { path, .. }) => {
+                                    | FsReadOperation::Search(FsSearch { path, .. })
+                                    | FsReadOperation::Outline(FsOutline { path })
+                                    | FsReadOperation::Symbol(FsSymbol { path, .. }) => {
                                         if deny_set.is_match(path) {
                                             return PermissionEvalResult::Deny;
                                         }
@@ -312,6 +321,8 @@ impl FsReadOperation {
             FsReadOperation::Directory(fs_directory) => fs_directory.validate(os).await,
             FsReadOperation::Search(fs_search) => fs_search.validate(os).await,
             FsReadOperation::Image(fs_image) => fs_image.validate(os).await,
+            FsReadOperation::Outline(fs_outline) => fs_outline.validate(os).await,
+            FsReadOperation::Symbol(fs_symbol) => fs_symbol.validate(os).await,
         }
     }
 
@@ -321,6 +332,8 @@ impl FsReadOperation {
             FsReadOperation::Directory(fs_directory) => fs_directory.queue_description(updates),
             FsReadOperation::Search(fs_search) => fs_search.queue_description(updates),
             FsReadOperation::Image(fs_image) => fs_image.queue_description(updates),
+            FsReadOperation::Outline(fs_outline) => fs_outline.queue_description(updates),
+            FsReadOperation::Symbol(fs_symbol) => fs_symbol.queue_description(updates),
         }
     }
 
@@ -330,6 +343,8 @@ impl FsReadOperation {
             FsReadOperation::Directory(fs_directory) => fs_directory.invoke(os, updates).await,
             FsReadOperation::Search(fs_search) => fs_search.invoke(os, updates).await,
             FsReadOperation::Image(fs_image) => fs_image.invoke(updates).await,
+            FsReadOperation::Outline(fs_outline) => fs_outline.invoke(os, updates).await,
+            FsReadOperation::Symbol(fs_symbol) => fs_symbol.invoke(os, updates).await,
         }
     }
 }
@@ -754,6 +769,164 @@ impl FsDirectory {
     }
 }
 
+/// Outline the symbols declared in a source file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FsOutline {
+    pub path: String,
+}
+
+impl FsOutline {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        validate_source_file(os, &self.path).await
+    }
+
+    pub fn queue_description(&self, updates: &mut impl Write) -> Result<()> {
+        queue!(
+            updates,
+            style::Print("Outlining symbols in: "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&self.path),
+            style::ResetColor,
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let (path, symbols, _) = parse_source_file(os, &self.path).await?;
+        let result = outline::format_outline(&symbols);
+
+        let byte_count = result.len();
+        if byte_count > MAX_TOOL_RESPONSE_SIZE {
+            bail!(
+                "This tool only supports reading up to {MAX_TOOL_RESPONSE_SIZE} bytes at a time. The outline is {byte_count} bytes. Try reading the file with Line mode in smaller ranges."
+            );
+        }
+
+        super::queue_function_result(
+            &format!("Found {} top level symbols in {}", symbols.len(), &path.display()),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(result),
+        })
+    }
+}
+
+/// Read the full body of a named symbol in a source file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FsSymbol {
+    pub path: String,
+    /// Name of the symbol, optionally qualified by its containers, e.g. `Config::new`.
+    pub symbol: String,
+}
+
+impl FsSymbol {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        if self.symbol.trim().is_empty() {
+            bail!("Symbol name cannot be empty");
+        }
+        validate_source_file(os, &self.path).await
+    }
+
+    pub fn queue_description(&self, updates: &mut impl Write) -> Result<()> {
+        queue!(
+            updates,
+            style::Print("Reading symbol: "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&self.symbol),
+            style::ResetColor,
+            style::Print(" in "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&self.path),
+            style::ResetColor,
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
+        let (path, symbols, file_content) = parse_source_file(os, &self.path).await?;
+        let found = outline::find_symbols(&symbols, &self.symbol);
+        if found.is_empty() {
+            bail!(
+                "No symbol named '{}' was found in {}. Use Outline mode to list the available symbols.",
+                self.symbol,
+                self.path
+            );
+        }
+
+        let lines = file_content.lines().collect::<Vec<_>>();
+        let result = found
+            .iter()
+            .map(|symbol| {
+                let body = lines
+                    .get(symbol.start_line - 1..symbol.end_line.min(lines.len()))
+                    .unwrap_or_default()
+                    .join("\n");
+                format!(
+                    "{} {} (lines {}-{}):\n{}",
+                    symbol.kind, symbol.name, symbol.start_line, symbol.end_line, body
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let byte_count = result.len();
+        if byte_count > MAX_TOOL_RESPONSE_SIZE {
+            bail!(
+                "This tool only supports reading {MAX_TOOL_RESPONSE_SIZE} bytes at a time. The symbol is {byte_count} bytes. Try reading it with Line mode in smaller ranges."
+            );
+        }
+
+        super::queue_function_result(
+            &format!(
+                "Successfully read {} matching {} from {}",
+                found.len(),
+                if found.len() == 1 { "symbol" } else { "symbols" },
+                &path.display()
+            ),
+            updates,
+            false,
+            false,
+        )?;
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(result),
+        })
+    }
+}
+
+async fn validate_source_file(os: &Os, path: &str) -> Result<()> {
+    let sanitized = sanitize_path_tool_arg(os, path);
+    if !sanitized.exists() {
+        bail!("'{}' does not exist", path);
+    }
+    if !os.fs.symlink_metadata(&sanitized).await?.is_file() {
+        bail!("'{}' is not a file", path);
+    }
+    if Language::from_path(&sanitized).is_none() {
+        bail!(
+            "'{}' is not a supported source file. Outline and Symbol modes support Rust, Python, TypeScript, JavaScript, Go and Java",
+            path
+        );
+    }
+    Ok(())
+}
+
+async fn parse_source_file(os: &Os, path: &str) -> Result<(PathBuf, Vec<outline::Symbol>, String)> {
+    let path = sanitize_path_tool_arg(os, path);
+    let Some(language) = Language::from_path(&path) else {
+        bail!("'{}' is not a supported source file", path.display());
+    };
+    debug!(?path, ?language, "Outlining");
+    let file_bytes = os.fs.read(&path).await?;
+    let file_content = String::from_utf8_lossy(&file_bytes).to_string();
+    let symbols = outline::outline(language, &file_content)?;
+    Ok((path, symbols, file_content))
+}
+
 /// Converts negative 1-based indices to positive 0-based indices.
 fn convert_negative_index(line_count: usize, i: i32) -> usize {
     if i <= 0 {
@@ -854,6 +1027,14 @@ mod tests {
             "operations": [{ "image_paths": ["/img1.png", "/img2.jpg"], "mode": "Image" }]
         }))
         .unwrap();
+        serde_json::from_value::<FsRead>(
+            serde_json::json!({ "operations": [{ "path": "/lib.rs", "mode": "Outline" }] }),
+        )
+        .unwrap();
+        serde_json::from_value::<FsRead>(
+            serde_json::json!({ "operations": [{ "path": "/lib.rs", "mode": "Symbol", "symbol": "Config::new" }] }),
+        )
+        .unwrap();
 
         // Test mixed batch operations
         serde_json::from_value::<FsRead>(serde_json::json!({
@@ -1028,6 +1209,65 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_fs_read_outline_and_symbol_invoke() {
+        let os = Os::new().await.unwrap();
+        let mut stdout = std::io::stdout();
+        os.fs
+            .write(
+                "/lib.rs",
+                "struct Config;\n\nimpl Config {\n    fn new() -> Self {\n        Config\n    }\n}\n",
+            )
+            .await
+            .unwrap();
+
+        let mut fs_read = serde_json::from_value::<FsRead>(serde_json::json!({
+            "operations": [{ "path": "/lib.rs", "mode": "Outline" }]
+        }))
+        .unwrap();
+        fs_read.validate(&os).await.unwrap();
+        let output = fs_read.invoke(&os, &mut stdout).await.unwrap();
+        if let OutputKind::Text(text) = output.output {
+            assert_eq!(
+                text,
+                "1-1 struct struct Config;\n3-7 impl impl Config\n  4-6 method fn new() -> Self\n"
+            );
+        } else {
+            panic!("expected text output");
+        }
+
+        let output = serde_json::from_value::<FsRead>(serde_json::json!({
+            "operations": [{ "path": "/lib.rs", "mode": "Symbol", "symbol": "Config::new" }]
+        }))
+        .unwrap()
+        .invoke(&os, &mut stdout)
+        .await
+        .unwrap();
+        if let OutputKind::Text(text) = output.output {
+            assert_eq!(
+                text,
+                "method new (lines 4-6):\n    fn new() -> Self {\n        Config\n    }"
+            );
+        } else {
+            panic!("expected text output");
+        }
+
+        let missing = serde_json::from_value::<FsRead>(serde_json::json!({
+            "operations": [{ "path": "/lib.rs", "mode": "Symbol", "symbol": "missing" }]
+        }))
+        .unwrap()
+        .invoke(&os, &mut stdout)
+        .await;
+        assert!(missing.is_err());
+
+        os.fs.write("/notes.txt", "hello").await.unwrap();
+        let mut unsupported = serde_json::from_value::<FsRead>(serde_json::json!({
+            "operations": [{ "path": "/notes.txt", "mode": "Outline" }]
+        }))
+        .unwrap();
+        assert!(unsupported.validate(&os).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_fs_read_non_utf8_binary_file() {
         let os = Os::new().await.unwrap();
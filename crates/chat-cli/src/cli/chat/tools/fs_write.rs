@@ -31,8 +31,10 @@ use tracing::{
     warn,
 };
 
+use super::code_intel::LanguageServers;
 use super::{
     InvokeOutput,
+    OutputKind,
     format_path,
     sanitize_path_tool_arg,
     supports_truecolor,
@@ -81,7 +83,25 @@ pub enum FsWrite {
 }
 
 impl FsWrite {
-    pub async fn invoke(&self, os: &Os, output: &mut impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(
+        &self,
+        os: &Os,
+        output: &mut impl Write,
+        language_servers: &LanguageServers,
+    ) -> Result<InvokeOutput> {
+        self.write(os, output).await?;
+
+        // Let the model know about any errors it introduced, if the agent asked for it.
+        let path = sanitize_path_tool_arg(os, self.path());
+        match language_servers.diagnostics_after_write(os, &path).await {
+            Some(diagnostics) => Ok(InvokeOutput {
+                output: OutputKind::Text(diagnostics),
+            }),
+            None => Ok(Default::default()),
+        }
+    }
+
+    async fn write(&self, os: &Os, output: &mut impl Write) -> Result<()> {
         let cwd = os.env.current_dir()?;
         match self {
             FsWrite::Create { path, .. } => {
@@ -106,7 +126,7 @@ impl FsWrite {
                 )?;
 
                 write_to_file(os, path, file_text).await?;
-                Ok(Default::default())
+                Ok(())
             },
             FsWrite::StrReplace {
                 path, old_str, new_str, ..
@@ -127,7 +147,7 @@ impl FsWrite {
                     1 => {
                         let file = file.replacen(old_str, new_str, 1);
                         os.fs.write(path, file).await?;
-                        Ok(Default::default())
+                        Ok(())
                     },
                     x => Err(eyre!("{x} occurrences of old_str were found when only 1 is expected")),
                 }
@@ -159,7 +179,7 @@ impl FsWrite {
                 }
                 file.insert_str(i, new_str);
                 write_to_file(os, &path, file).await?;
-                Ok(Default::default())
+                Ok(())
             },
             FsWrite::Append { path, new_str, .. } => {
                 let path = sanitize_path_tool_arg(os, path);
@@ -179,7 +199,7 @@ impl FsWrite {
                 }
                 file.push_str(new_str);
                 write_to_file(os, path, file).await?;
-                Ok(Default::default())
+                Ok(())
             },
         }
     }
@@ -295,16 +315,19 @@ impl FsWrite {
         Ok(())
     }
 
-    fn print_relative_path(&self, os: &Os, output: &mut impl Write) -> Result<()> {
-        let cwd = os.env.current_dir()?;
-        let path = match self {
+    pub fn path(&self) -> &str {
+        match self {
             FsWrite::Create { path, .. } => path,
             FsWrite::StrReplace { path, .. } => path,
             FsWrite::Insert { path, .. } => path,
             FsWrite::Append { path, .. } => path,
-        };
+        }
+    }
+
+    fn print_relative_path(&self, os: &Os, output: &mut impl Write) -> Result<()> {
+        let cwd = os.env.current_dir()?;
         // Sanitize the path to handle tilde expansion
-        let path = sanitize_path_tool_arg(os, path);
+        let path = sanitize_path_tool_arg(os, self.path());
         let relative_path = format_path(cwd, &path);
         queue!(
             output,
@@ -824,7 +847,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
 
@@ -841,7 +864,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
 
@@ -859,7 +882,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
 
@@ -884,7 +907,7 @@ mod tests {
         assert!(
             serde_json::from_value::<FsWrite>(v)
                 .unwrap()
-                .invoke(&os, &mut stdout)
+                .invoke(&os, &mut stdout, &LanguageServers::default())
                 .await
                 .is_err()
         );
@@ -899,7 +922,7 @@ mod tests {
         assert!(
             serde_json::from_value::<FsWrite>(v)
                 .unwrap()
-                .invoke(&os, &mut stdout)
+                .invoke(&os, &mut stdout, &LanguageServers::default())
                 .await
                 .is_err()
         );
@@ -913,7 +936,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
         assert_eq!(
@@ -943,7 +966,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
         let actual = os.fs.read_to_string(TEST_FILE_PATH).await.unwrap();
@@ -975,7 +998,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
         let actual = os.fs.read_to_string(TEST_FILE_PATH).await.unwrap();
@@ -1012,7 +1035,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
         let actual = os.fs.read_to_string(test_file_path).await.unwrap();
@@ -1027,7 +1050,7 @@ mod tests {
         });
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
         let actual = os.fs.read_to_string(test_file_path).await.unwrap();
@@ -1049,7 +1072,7 @@ mod tests {
 
         serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await
             .unwrap();
 
@@ -1071,7 +1094,7 @@ mod tests {
 
         let result = serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await;
 
         assert!(result.is_err(), "Appending to non-existent file should fail");
@@ -1124,7 +1147,7 @@ mod tests {
 
         let result = serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await;
 
         match &result {
@@ -1159,7 +1182,7 @@ mod tests {
 
         let result = serde_json::from_value::<FsWrite>(v)
             .unwrap()
-            .invoke(&os, &mut stdout)
+            .invoke(&os, &mut stdout, &LanguageServers::default())
             .await;
 
         assert!(result.is_ok(), "Writing to ~/nested/path/file.txt should succeed");
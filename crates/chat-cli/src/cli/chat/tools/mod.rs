@@ -1,9 +1,11 @@
+pub mod code_intel;
 pub mod custom_tool;
 pub mod execute;
 pub mod fs_read;
 pub mod fs_write;
 pub mod gh_issue;
 pub mod knowledge;
+pub mod script_tool;
 pub mod thinking;
 pub mod use_aws;
 
@@ -14,6 +16,10 @@ use std::path::{
     PathBuf,
 };
 
+use code_intel::{
+    CodeIntel,
+    LanguageServers,
+};
 use crossterm::queue;
 use crossterm::style::{
     self,
@@ -26,6 +32,7 @@ use fs_read::FsRead;
 use fs_write::FsWrite;
 use gh_issue::GhIssue;
 use knowledge::Knowledge;
+use script_tool::ScriptTool;
 use serde::{
     Deserialize,
     Serialize,
@@ -39,10 +46,11 @@ use crate::cli::agent::{
     Agent,
     PermissionEvalResult,
 };
+use crate::mcp_client::ToolAnnotations;
 use crate::os::Os;
 
 pub const DEFAULT_APPROVE: [&str; 1] = ["fs_read"];
-pub const NATIVE_TOOLS: [&str; 7] = [
+pub const NATIVE_TOOLS: [&str; 8] = [
     "fs_read",
     "fs_write",
     #[cfg(windows)]
@@ -53,6 +61,7 @@ pub const NATIVE_TOOLS: [&str; 7] = [
     "gh_issue",
     "knowledge",
     "thinking",
+    "code_intel",
 ];
 
 /// Represents an executable tool use.
@@ -67,9 +76,42 @@ pub enum Tool {
     GhIssue(GhIssue),
     Knowledge(Knowledge),
     Thinking(Thinking),
+    CodeIntel(CodeIntel),
+    Script(ScriptTool),
 }
 
 impl Tool {
+    /// Parses the arguments of the native tool named `name`. Returns [None] for any other tool.
+    pub fn native(name: &str, args: &serde_json::Value) -> Option<Result<Self, serde_json::Error>> {
+        Some(match name {
+            "fs_read" => FsRead::deserialize(args).map(Self::FsRead),
+            "fs_write" => FsWrite::deserialize(args).map(Self::FsWrite),
+            #[cfg(windows)]
+            "execute_cmd" => ExecuteCommand::deserialize(args).map(Self::ExecuteCommand),
+            #[cfg(not(windows))]
+            "execute_bash" => ExecuteCommand::deserialize(args).map(Self::ExecuteCommand),
+            "use_aws" => UseAws::deserialize(args).map(Self::UseAws),
+            "report_issue" => GhIssue::deserialize(args).map(Self::GhIssue),
+            "thinking" => Thinking::deserialize(args).map(Self::Thinking),
+            "knowledge" => Knowledge::deserialize(args).map(Self::Knowledge),
+            "code_intel" => CodeIntel::deserialize(args).map(Self::CodeIntel),
+            _ => return None,
+        })
+    }
+
+    /// Whether the tool use only reads, and changes nothing on the system or elsewhere
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Tool::FsRead(_) | Tool::Thinking(_) | Tool::CodeIntel(_) => true,
+            Tool::ExecuteCommand(execute_command) => !execute_command.requires_acceptance(None, true),
+            Tool::UseAws(use_aws) => !use_aws.requires_acceptance(),
+            Tool::Knowledge(knowledge) => {
+                matches!(knowledge, Knowledge::Search(_) | Knowledge::Show | Knowledge::Status)
+            },
+            Tool::FsWrite(_) | Tool::GhIssue(_) | Tool::Custom(_) | Tool::Script(_) => false,
+        }
+    }
+
     /// The display name of a tool
     pub fn display_name(&self) -> String {
         match self {
@@ -84,6 +126,8 @@ impl Tool {
             Tool::GhIssue(_) => "gh_issue",
             Tool::Knowledge(_) => "knowledge",
             Tool::Thinking(_) => "thinking (prerelease)",
+            Tool::CodeIntel(_) => "code_intel",
+            Tool::Script(script_tool) => &script_tool.config.name,
         }
         .to_owned()
     }
@@ -99,20 +143,29 @@ impl Tool {
             Tool::GhIssue(_) => PermissionEvalResult::Allow,
             Tool::Thinking(_) => PermissionEvalResult::Allow,
             Tool::Knowledge(_) => PermissionEvalResult::Ask,
+            Tool::CodeIntel(code_intel) => code_intel.eval_perm(agent),
+            Tool::Script(script_tool) => script_tool.eval_perm(agent),
         }
     }
 
     /// Invokes the tool asynchronously
-    pub async fn invoke(&self, os: &Os, stdout: &mut impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(
+        &self,
+        os: &Os,
+        stdout: &mut impl Write,
+        language_servers: &LanguageServers,
+    ) -> Result<InvokeOutput> {
         match self {
             Tool::FsRead(fs_read) => fs_read.invoke(os, stdout).await,
-            Tool::FsWrite(fs_write) => fs_write.invoke(os, stdout).await,
+            Tool::FsWrite(fs_write) => fs_write.invoke(os, stdout, language_servers).await,
             Tool::ExecuteCommand(execute_command) => execute_command.invoke(stdout).await,
             Tool::UseAws(use_aws) => use_aws.invoke(os, stdout).await,
             Tool::Custom(custom_tool) => custom_tool.invoke(os, stdout).await,
             Tool::GhIssue(gh_issue) => gh_issue.invoke(os, stdout).await,
             Tool::Knowledge(knowledge) => knowledge.invoke(os, stdout).await,
             Tool::Thinking(think) => think.invoke(stdout).await,
+            Tool::CodeIntel(code_intel) => code_intel.invoke(os, stdout, language_servers).await,
+            Tool::Script(script_tool) => script_tool.invoke(os, stdout).await,
         }
     }
 
@@ -127,6 +180,8 @@ impl Tool {
             Tool::GhIssue(gh_issue) => gh_issue.queue_description(output),
             Tool::Knowledge(knowledge) => knowledge.queue_description(os, output).await,
             Tool::Thinking(thinking) => thinking.queue_description(output),
+            Tool::CodeIntel(code_intel) => code_intel.queue_description(output),
+            Tool::Script(script_tool) => script_tool.queue_description(output),
         }
     }
 
@@ -141,6 +196,8 @@ impl Tool {
             Tool::GhIssue(gh_issue) => gh_issue.validate(os).await,
             Tool::Knowledge(knowledge) => knowledge.validate(os).await,
             Tool::Thinking(think) => think.validate(os).await,
+            Tool::CodeIntel(code_intel) => code_intel.validate(os).await,
+            Tool::Script(script_tool) => script_tool.validate(os).await,
         }
     }
 }
@@ -153,6 +210,11 @@ pub struct ToolSpec {
     pub description: String,
     #[serde(alias = "inputSchema")]
     pub input_schema: InputSchema,
+    /// The schema of the structured output of mcp tools
+    #[serde(alias = "outputSchema", default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
     #[serde(skip_serializing, default = "tool_origin")]
     pub tool_origin: ToolOrigin,
 }
@@ -160,6 +222,8 @@ pub struct ToolSpec {
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ToolOrigin {
     Native,
+    /// Tools declared in the `scriptTools` section of an agent
+    Script,
     McpServer(String),
 }
 
@@ -167,6 +231,7 @@ impl std::hash::Hash for ToolOrigin {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
             Self::Native => "native".hash(state),
+            Self::Script => "script".hash(state),
             Self::McpServer(name) => name.hash(state),
         }
     }
@@ -177,6 +242,7 @@ impl Borrow<str> for ToolOrigin {
         match self {
             Self::McpServer(name) => name.as_str(),
             Self::Native => "native",
+            Self::Script => "script",
         }
     }
 }
@@ -189,6 +255,8 @@ impl<'de> Deserialize<'de> for ToolOrigin {
         let s = String::deserialize(deserializer)?;
         if s == "native___" {
             Ok(ToolOrigin::Native)
+        } else if s == "script___" {
+            Ok(ToolOrigin::Script)
         } else {
             Ok(ToolOrigin::McpServer(s))
         }
@@ -202,6 +270,7 @@ impl Serialize for ToolOrigin {
     {
         match self {
             ToolOrigin::Native => serializer.serialize_str("native___"),
+            ToolOrigin::Script => serializer.serialize_str("script___"),
             ToolOrigin::McpServer(server) => serializer.serialize_str(server),
         }
     }
@@ -211,6 +280,7 @@ impl std::fmt::Display for ToolOrigin {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ToolOrigin::Native => write!(f, "Built-in"),
+            ToolOrigin::Script => write!(f, "Script"),
             ToolOrigin::McpServer(server) => write!(f, "{} (MCP)", server),
         }
     }
@@ -408,6 +478,33 @@ mod tests {
     use super::*;
     use crate::os::ACTIVE_USER_HOME;
 
+    #[test]
+    fn test_tool_spec_from_mcp() {
+        let spec = serde_json::from_value::<ToolSpec>(serde_json::json!({
+            "name": "get_weather",
+            "description": "Get the weather",
+            "inputSchema": { "type": "object" },
+            "outputSchema": { "type": "object", "properties": { "temperature": { "type": "number" } } },
+            "annotations": { "readOnlyHint": true, "openWorldHint": true }
+        }))
+        .unwrap();
+        assert!(spec.output_schema.is_some());
+        let annotations = spec.annotations.unwrap();
+        assert!(annotations.is_read_only());
+        assert!(!annotations.is_destructive());
+        assert_eq!(annotations.open_world_hint, Some(true));
+
+        let spec = serde_json::from_value::<ToolSpec>(serde_json::json!({
+            "name": "rm",
+            "description": "Remove",
+            "inputSchema": { "type": "object" },
+            "annotations": {}
+        }))
+        .unwrap();
+        assert!(spec.output_schema.is_none());
+        assert!(spec.annotations.unwrap().is_destructive());
+    }
+
     #[tokio::test]
     async fn test_tilde_path_expansion() {
         let os = Os::new().await.unwrap();
@@ -0,0 +1,449 @@
+use std::borrow::Cow;
+use std::io::Write;
+use std::process::Stdio;
+use std::sync::Arc;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Context as EyreContext,
+    Result,
+    bail,
+};
+use schemars::JsonSchema;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::execute::format_output;
+use super::{
+    InputSchema,
+    InvokeOutput,
+    MAX_TOOL_RESPONSE_SIZE,
+    NATIVE_TOOLS,
+    OutputKind,
+    ToolOrigin,
+    ToolSpec,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::os::Os;
+
+/// A tool declared in the `scriptTools` section of an agent, which runs a command built from a
+/// template instead of talking to an MCP server.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptToolConfig {
+    /// The name of the tool as seen by the model
+    pub name: String,
+    /// Description of the tool as seen by the model
+    pub description: String,
+    /// JSON schema of the tool input
+    #[serde(default = "default_input_schema")]
+    pub input_schema: serde_json::Value,
+    /// The bash command to run. Occurrences of `{{argument}}` are replaced by the value of that
+    /// argument, passed as a positional parameter, so placeholders must not be quoted in the
+    /// template
+    pub command: String,
+    /// Timeout for the command in ms
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+fn default_input_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+fn default_timeout() -> u64 {
+    120 * 1000
+}
+
+impl ScriptToolConfig {
+    /// Checks that the tool can be exposed to the model under its name.
+    pub fn validate(&self) -> Result<()> {
+        let valid_name = self.name.len() <= 64
+            && self.name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            bail!(
+                "Invalid script tool name '{}'. Names must start with a letter and only contain letters, numbers, underscores and hyphens",
+                self.name
+            );
+        }
+        if NATIVE_TOOLS.contains(&self.name.as_str()) {
+            bail!("Script tool '{}' conflicts with a native tool", self.name);
+        }
+        if self.description.is_empty() {
+            bail!("Script tool '{}' is missing a description", self.name);
+        }
+        if cfg!(windows) {
+            bail!("Script tool '{}' is not supported on Windows", self.name);
+        }
+        if let Some(placeholder) = quoted_placeholder(&self.command) {
+            bail!(
+                "Script tool '{}' quotes the placeholder '{placeholder}'. Arguments are passed as separate words, so placeholders must not be quoted",
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    pub fn tool_spec(&self) -> ToolSpec {
+        ToolSpec {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            input_schema: InputSchema(self.input_schema.clone()),
+            output_schema: None,
+            annotations: None,
+            tool_origin: ToolOrigin::Script,
+        }
+    }
+}
+
+/// An invocation of a [ScriptToolConfig].
+#[derive(Debug, Clone)]
+pub struct ScriptTool {
+    pub config: Arc<ScriptToolConfig>,
+    pub args: serde_json::Value,
+}
+
+impl ScriptTool {
+    /// The command with every placeholder replaced by its shell-quoted argument, for display only
+    fn command(&self) -> Result<String> {
+        display_command(&self.config.command, &self.args)
+    }
+
+    pub async fn invoke(&self, os: &Os, _updates: impl Write) -> Result<InvokeOutput> {
+        if cfg!(windows) {
+            bail!("Script tools are not supported on Windows");
+        }
+
+        let command = self.command()?;
+        let (script, params) = render_command(&self.config.command, &self.args)?;
+        // The arguments never become part of the script itself, so bash can't interpret them.
+        let child = tokio::process::Command::new("bash")
+            .arg("-c")
+            .arg(&script)
+            .arg(&self.config.name)
+            .args(&params)
+            .current_dir(os.env.current_dir()?)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .wrap_err_with(|| format!("Unable to spawn command '{}'", command))?;
+
+        let timeout = std::time::Duration::from_millis(self.config.timeout);
+        let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(output) => output.wrap_err_with(|| format!("No exit status for '{}'", command))?,
+            Err(_) => bail!("'{}' timed out after {}ms", self.config.name, self.config.timeout),
+        };
+
+        let max_size = MAX_TOOL_RESPONSE_SIZE / 3;
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::json!({
+                "exit_status": output.status.code().unwrap_or(0).to_string(),
+                "stdout": format_output(&String::from_utf8_lossy(&output.stdout), max_size),
+                "stderr": format_output(&String::from_utf8_lossy(&output.stderr), max_size),
+            })),
+        })
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Running "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&self.config.name),
+            style::ResetColor,
+            style::Print(": "),
+        )?;
+        match self.command() {
+            Ok(command) => queue!(
+                output,
+                style::SetForegroundColor(Color::Green),
+                style::Print(command),
+                style::ResetColor,
+                style::Print("\n"),
+            )?,
+            Err(e) => queue!(output, style::Print(format!("{e}\n")))?,
+        }
+        Ok(())
+    }
+
+    pub async fn validate(&mut self, _os: &Os) -> Result<()> {
+        let Some(args) = self.args.as_object() else {
+            bail!("The input of '{}' must be an object", self.config.name);
+        };
+        let required = self
+            .config
+            .input_schema
+            .get("required")
+            .and_then(|r| r.as_array())
+            .map(|r| r.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let missing = required
+            .into_iter()
+            .filter(|name| args.get(*name).is_none_or(|v| v.is_null()))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            bail!("Missing required arguments: {}", missing.join(", "));
+        }
+        self.command()?;
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, agent: &Agent) -> PermissionEvalResult {
+        if agent.allowed_tools.contains(&self.config.name) {
+            PermissionEvalResult::Allow
+        } else {
+            PermissionEvalResult::Ask
+        }
+    }
+}
+
+/// Replaces every `{{name}}` in `template` with `f(name, words)`, where `words` is the value of
+/// `args[name]`. Missing and null arguments have no words, arrays have one word per element and
+/// objects are passed as JSON.
+fn substitute(
+    template: &str,
+    args: &serde_json::Value,
+    mut f: impl FnMut(&str, Vec<String>) -> Result<String>,
+) -> Result<String> {
+    let mut command = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        command.push_str(&rest[..start]);
+        let name = rest[start + 2..start + len].trim();
+        let words = match args.get(name) {
+            None | Some(serde_json::Value::Null) => vec![],
+            Some(serde_json::Value::Array(values)) => values.iter().map(to_word).collect(),
+            Some(value) => vec![to_word(value)],
+        };
+        command.push_str(&f(name, words)?);
+        rest = &rest[start + len + 2..];
+    }
+    command.push_str(rest);
+    Ok(command)
+}
+
+/// Turns `template` into a bash script referring to the arguments as positional parameters, and
+/// the list of those parameters.
+fn render_command(template: &str, args: &serde_json::Value) -> Result<(String, Vec<String>)> {
+    let mut params = Vec::new();
+    let script = substitute(template, args, |_, words| {
+        let refs = words
+            .into_iter()
+            .map(|word| {
+                params.push(word);
+                format!("\"${{{}}}\"", params.len())
+            })
+            .collect::<Vec<_>>();
+        Ok(refs.join(" "))
+    })?;
+    Ok((script, params))
+}
+
+/// Renders `template` with every argument shell-quoted in place, to show the user what will run.
+fn display_command(template: &str, args: &serde_json::Value) -> Result<String> {
+    substitute(template, args, |name, words| {
+        let quoted = words
+            .iter()
+            .map(|word| shlex::try_quote(word).map(Cow::into_owned))
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err_with(|| format!("The argument '{name}' cannot be passed to a shell"))?;
+        Ok(quoted.join(" "))
+    })
+}
+
+/// Returns the first placeholder of `template` that sits inside single or double quotes.
+fn quoted_placeholder(template: &str) -> Option<&str> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (idx, c) in template.char_indices() {
+        match (quote, c) {
+            _ if escaped => escaped = false,
+            (Some(_), '{') if template[idx..].starts_with("{{") => {
+                let end = template[idx..].find("}}").map_or(template.len(), |len| idx + len + 2);
+                return Some(&template[idx..end]);
+            },
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {},
+            (_, '\\') => escaped = true,
+            (None, '\'' | '"') => quote = Some(c),
+            (Some('"'), '"') => quote = None,
+            _ => {},
+        }
+    }
+    None
+}
+
+fn to_word(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(value: serde_json::Value) -> ScriptToolConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_render_command() {
+        let args = serde_json::json!({
+            "target": "lint",
+            "path": "src/it's here; rm -rf ~",
+            "jobs": 4,
+            "features": ["a b", "c"],
+            "filter": { "name": "x" },
+        });
+        assert_eq!(
+            render_command("make {{target}} -j {{ jobs }} DIR={{path}}", &args).unwrap(),
+            (r#"make "${1}" -j "${2}" DIR="${3}""#.to_string(), vec![
+                "lint".to_string(),
+                "4".to_string(),
+                "src/it's here; rm -rf ~".to_string()
+            ])
+        );
+        assert_eq!(
+            render_command("cargo test --features {{features}} {{missing}}", &args).unwrap(),
+            (r#"cargo test --features "${1}" "${2}" "#.to_string(), vec![
+                "a b".to_string(),
+                "c".to_string()
+            ])
+        );
+        assert_eq!(
+            render_command("echo {{unterminated", &args).unwrap(),
+            ("echo {{unterminated".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_display_command() {
+        let args = serde_json::json!({
+            "path": "src/it's here; rm -rf ~",
+            "features": ["a b", "c"],
+            "filter": { "name": "x" },
+        });
+        assert_eq!(
+            display_command("make DIR={{path}}", &args).unwrap(),
+            r#"make DIR="src/it's here; rm -rf ~""#
+        );
+        assert_eq!(
+            display_command("cargo test --features {{features}}", &args).unwrap(),
+            "cargo test --features 'a b' c"
+        );
+        assert_eq!(
+            display_command("./query {{filter}}", &args).unwrap(),
+            r#"./query '{"name":"x"}'"#
+        );
+    }
+
+    #[test]
+    fn test_quoted_placeholder() {
+        assert_eq!(quoted_placeholder("echo {{a}} '{{b}}'"), Some("{{b}}"));
+        assert_eq!(quoted_placeholder(r#"echo "x {{a}}""#), Some("{{a}}"));
+        assert_eq!(quoted_placeholder(r#"echo "it's" {{a}}"#), None);
+        assert_eq!(quoted_placeholder(r#"echo \" {{a}} \""#), None);
+        assert_eq!(quoted_placeholder("echo {{a}} {{b}}"), None);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn test_config_validate() {
+        let valid = config(serde_json::json!({
+            "name": "make_lint",
+            "description": "Run the linters",
+            "command": "make lint"
+        }));
+        assert!(valid.validate().is_ok());
+        assert_eq!(valid.timeout, default_timeout());
+        assert_eq!(valid.tool_spec().input_schema.0, default_input_schema());
+
+        for name in ["fs_read", "1lint", "make lint", ""] {
+            let mut invalid = valid.clone();
+            invalid.name = name.to_string();
+            assert!(invalid.validate().is_err(), "{name} should be invalid");
+        }
+
+        let mut quoted = valid.clone();
+        quoted.command = "make \"{{target}}\"".to_string();
+        assert!(quoted.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_required_args() {
+        let os = Os::new().await.unwrap();
+        let config = Arc::new(config(serde_json::json!({
+            "name": "greet",
+            "description": "Greets someone",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "name": { "type": "string" } },
+                "required": ["name"]
+            },
+            "command": "echo hello {{name}}"
+        })));
+
+        let mut tool = ScriptTool {
+            config: config.clone(),
+            args: serde_json::json!({}),
+        };
+        assert!(tool.validate(&os).await.is_err());
+
+        let mut tool = ScriptTool {
+            config,
+            args: serde_json::json!({ "name": "world" }),
+        };
+        assert!(tool.validate(&os).await.is_ok());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_invoke() {
+        let os = Os::new().await.unwrap();
+        let tool = ScriptTool {
+            config: Arc::new(config(serde_json::json!({
+                "name": "greet",
+                "description": "Greets someone",
+                "command": "echo hello {{name}}; echo oops >&2; exit 3"
+            }))),
+            args: serde_json::json!({ "name": "$(whoami)" }),
+        };
+        let OutputKind::Json(json) = tool.invoke(&os, std::io::sink()).await.unwrap().output else {
+            panic!("Expected JSON output");
+        };
+        assert_eq!(json["stdout"], "hello $(whoami)\n");
+        assert_eq!(json["stderr"], "oops\n");
+        assert_eq!(json["exit_status"], "3");
+
+        let tool = ScriptTool {
+            config: Arc::new(config(serde_json::json!({
+                "name": "sleep",
+                "description": "Sleeps",
+                "command": "sleep 5",
+                "timeout": 100
+            }))),
+            args: serde_json::json!({}),
+        };
+        assert!(tool.invoke(&os, std::io::sink()).await.is_err());
+    }
+}
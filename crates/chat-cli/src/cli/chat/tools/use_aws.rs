@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::io::Write;
+use std::path::PathBuf;
 use std::process::Stdio;
 
 use bstr::ByteSlice;
@@ -14,6 +16,11 @@ use crossterm::{
 use eyre::{
     Result,
     WrapErr,
+    bail,
+};
+use globset::{
+    Glob,
+    GlobMatcher,
 };
 use serde::Deserialize;
 use tracing::error;
@@ -31,14 +38,96 @@ use crate::os::Os;
 
 const READONLY_OPS: [&str; 6] = ["get", "describe", "list", "ls", "search", "batch_get"];
 
+/// Services whose operations accept `--dry-run`, which checks permissions and parameters without
+/// making the request.
+const DRY_RUN_SERVICES: [&str; 1] = ["ec2"];
+
+/// Options accepted by every operation, which never appear in an input skeleton.
+const GLOBAL_PARAMS: [&str; 11] = [
+    "--query",
+    "--output",
+    "--no-paginate",
+    "--page-size",
+    "--max-items",
+    "--starting-token",
+    "--endpoint-url",
+    "--no-verify-ssl",
+    "--no-cli-pager",
+    "--cli-read-timeout",
+    "--cli-connect-timeout",
+];
+
 /// The environment variable name where we set additional metadata for the AWS CLI user agent.
 const USER_AGENT_ENV_VAR: &str = "AWS_EXECUTION_ENV";
 const USER_AGENT_APP_NAME: &str = "AmazonQ-For-CLI";
 const USER_AGENT_VERSION_KEY: &str = "Version";
 const USER_AGENT_VERSION_VALUE: &str = env!("CARGO_PKG_VERSION");
 
-// TODO: we should perhaps composite this struct with an interface that we can use to mock the
-// actual cli with. That will allow us to more thoroughly test it.
+/// The output of a finished AWS CLI process.
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    pub exit_status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs the AWS CLI. Abstracted so that the tool can be tested without a real `aws` binary.
+pub trait CommandRunner {
+    fn run(&self, args: &[String], envs: &HashMap<String, String>) -> impl Future<Output = Result<CommandOutput>>;
+}
+
+/// Runs the AWS CLI as a child process.
+#[derive(Debug, Clone)]
+pub struct AwsCli {
+    program: PathBuf,
+}
+
+impl AwsCli {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+}
+
+impl Default for AwsCli {
+    fn default() -> Self {
+        Self::new("aws")
+    }
+}
+
+impl CommandRunner for AwsCli {
+    async fn run(&self, args: &[String], envs: &HashMap<String, String>) -> Result<CommandOutput> {
+        let output = tokio::process::Command::new(&self.program)
+            .args(args)
+            .envs(envs)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .wrap_err_with(|| {
+                format!(
+                    "Unable to spawn command '{} {}'",
+                    self.program.display(),
+                    args.join(" ")
+                )
+            })?
+            .wait_with_output()
+            .await
+            .wrap_err_with(|| {
+                format!(
+                    "Unable to spawn command '{} {}'",
+                    self.program.display(),
+                    args.join(" ")
+                )
+            })?;
+        Ok(CommandOutput {
+            exit_status: output.status.code(),
+            stdout: output.stdout.to_str_lossy().to_string(),
+            stderr: output.stderr.to_str_lossy().to_string(),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct UseAws {
     pub service_name: String,
@@ -47,6 +136,9 @@ pub struct UseAws {
     pub region: String,
     pub profile_name: Option<String>,
     pub label: Option<String>,
+    /// Validate the call instead of making it
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 impl UseAws {
@@ -55,76 +147,18 @@ impl UseAws {
     }
 
     pub async fn invoke(&self, _os: &Os, _updates: impl Write) -> Result<InvokeOutput> {
-        let mut command = tokio::process::Command::new("aws");
-        command.envs(std::env::vars());
-
-        // Set up environment variables
-        let mut env_vars: std::collections::HashMap<String, String> = std::env::vars().collect();
-
-        // Set up additional metadata for the AWS CLI user agent
-        let user_agent_metadata_value = format!(
-            "{} {}/{}",
-            USER_AGENT_APP_NAME, USER_AGENT_VERSION_KEY, USER_AGENT_VERSION_VALUE
-        );
+        self.invoke_with(&AwsCli::default()).await
+    }
 
-        // If the user agent metadata env var already exists, append to it, otherwise set it
-        if let Some(existing_value) = env_vars.get(USER_AGENT_ENV_VAR) {
-            if !existing_value.is_empty() {
-                env_vars.insert(
-                    USER_AGENT_ENV_VAR.to_string(),
-                    format!("{} {}", existing_value, user_agent_metadata_value),
-                );
-            } else {
-                env_vars.insert(USER_AGENT_ENV_VAR.to_string(), user_agent_metadata_value);
-            }
-        } else {
-            env_vars.insert(USER_AGENT_ENV_VAR.to_string(), user_agent_metadata_value);
+    async fn invoke_with(&self, runner: &impl CommandRunner) -> Result<InvokeOutput> {
+        if self.dry_run {
+            return self.dry_run_with(runner).await;
         }
 
-        command.envs(env_vars).arg("--region").arg(&self.region);
-        if let Some(profile_name) = self.profile_name.as_deref() {
-            command.arg("--profile").arg(profile_name);
-        }
-        command.arg(&self.service_name).arg(&self.operation_name);
-        if let Some(parameters) = self.cli_parameters() {
-            for (name, val) in parameters {
-                command.arg(name);
-                if !val.is_empty() {
-                    command.arg(val);
-                }
-            }
-        }
-        let output = command
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .wrap_err_with(|| format!("Unable to spawn command '{:?}'", self))?
-            .wait_with_output()
-            .await
-            .wrap_err_with(|| format!("Unable to spawn command '{:?}'", self))?;
-        let status = output.status.code().unwrap_or(0).to_string();
-        let stdout = output.stdout.to_str_lossy();
-        let stderr = output.stderr.to_str_lossy();
-
-        let stdout = format!(
-            "{}{}",
-            &stdout[0..stdout.len().min(MAX_TOOL_RESPONSE_SIZE / 3)],
-            if stdout.len() > MAX_TOOL_RESPONSE_SIZE / 3 {
-                " ... truncated"
-            } else {
-                ""
-            }
-        );
-
-        let stderr = format!(
-            "{}{}",
-            &stderr[0..stderr.len().min(MAX_TOOL_RESPONSE_SIZE / 3)],
-            if stderr.len() > MAX_TOOL_RESPONSE_SIZE / 3 {
-                " ... truncated"
-            } else {
-                ""
-            }
-        );
+        let output = runner.run(&self.cli_args(), &cli_env()).await?;
+        let status = output.exit_status.unwrap_or(0).to_string();
+        let stdout = truncate_output(&output.stdout);
+        let stderr = truncate_output(&output.stderr);
 
         if status.eq("0") {
             Ok(InvokeOutput {
@@ -139,6 +173,76 @@ impl UseAws {
         }
     }
 
+    /// Checks the call without making it. Services that support `--dry-run` are asked to verify
+    /// the request; for everything else the parameters are checked against the input skeleton of
+    /// the operation.
+    async fn dry_run_with(&self, runner: &impl CommandRunner) -> Result<InvokeOutput> {
+        if DRY_RUN_SERVICES.contains(&self.service_name.as_str()) {
+            let mut args = self.cli_args();
+            args.push("--dry-run".to_string());
+            let output = runner.run(&args, &cli_env()).await?;
+            // A successful dry run is reported as a DryRunOperation error
+            if output.stderr.contains("DryRunOperation") {
+                return Ok(InvokeOutput {
+                    output: OutputKind::Json(serde_json::json!({
+                        "dry_run": true,
+                        "result": "The request would have succeeded.",
+                    })),
+                });
+            }
+            bail!("Dry run failed: {}", truncate_output(&output.stderr));
+        }
+
+        let mut args = self.scope_args();
+        args.extend([
+            self.service_name.clone(),
+            self.operation_name.clone(),
+            "--generate-cli-skeleton".to_string(),
+            "input".to_string(),
+        ]);
+        let output = runner.run(&args, &cli_env()).await?;
+        if output.exit_status != Some(0) {
+            bail!(
+                "Unable to validate {} {}: {}",
+                self.service_name,
+                self.operation_name,
+                truncate_output(&output.stderr)
+            );
+        }
+        let skeleton = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&output.stdout)
+            .wrap_err("The AWS CLI returned an invalid input skeleton")?;
+        let known = skeleton
+            .keys()
+            .map(|k| format!("--{}", k.to_case(Case::Kebab)))
+            .collect::<Vec<_>>();
+        let unknown = self
+            .cli_parameters()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| {
+                let name = name.strip_prefix("--no-").map_or(name.clone(), |n| format!("--{n}"));
+                !known.contains(&name) && !GLOBAL_PARAMS.contains(&name.as_str())
+            })
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            bail!(
+                "Unknown parameters for {} {}: {}. Valid parameters are: {}",
+                self.service_name,
+                self.operation_name,
+                unknown.join(", "),
+                known.join(", ")
+            );
+        }
+
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::json!({
+                "dry_run": true,
+                "result": "The parameters are valid. The request was not sent, so permissions and parameter values were not checked.",
+            })),
+        })
+    }
+
     pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
         queue!(
             output,
@@ -171,6 +275,13 @@ impl UseAws {
         if let Some(ref label) = self.label {
             queue!(output, style::Print(format!("\nLabel: {}", label)))?;
         }
+
+        if self.dry_run {
+            queue!(
+                output,
+                style::Print("\nDry run: the request will be validated but not sent")
+            )?;
+        }
         Ok(())
     }
 
@@ -178,6 +289,28 @@ impl UseAws {
         Ok(())
     }
 
+    /// The `--region` and `--profile` arguments of the call
+    fn scope_args(&self) -> Vec<String> {
+        let mut args = vec!["--region".to_string(), self.region.clone()];
+        if let Some(profile_name) = self.profile_name.as_deref() {
+            args.extend(["--profile".to_string(), profile_name.to_string()]);
+        }
+        args
+    }
+
+    /// The full list of arguments passed to the AWS CLI
+    fn cli_args(&self) -> Vec<String> {
+        let mut args = self.scope_args();
+        args.extend([self.service_name.clone(), self.operation_name.clone()]);
+        for (name, val) in self.cli_parameters().unwrap_or_default() {
+            args.push(name);
+            if !val.is_empty() {
+                args.push(val);
+            }
+        }
+        args
+    }
+
     /// Returns the CLI arguments properly formatted as kebab case if parameters is
     /// [Option::Some], otherwise None
     fn cli_parameters(&self) -> Option<Vec<(String, String)>> {
@@ -195,28 +328,44 @@ impl UseAws {
     }
 
     pub fn eval_perm(&self, agent: &Agent) -> PermissionEvalResult {
-        #[derive(Debug, Deserialize)]
-        #[serde(rename_all = "camelCase")]
-        struct Settings {
-            allowed_services: Vec<String>,
-            denied_services: Vec<String>,
-        }
-
         let Self { service_name, .. } = self;
         let is_in_allowlist = agent.allowed_tools.contains("use_aws");
-        match agent.tools_settings.get("use_aws") {
+        let settings = match agent.tools_settings.get("use_aws") {
+            Some(settings) => match serde_json::from_value::<Settings>(settings.clone()) {
+                Ok(settings) => Some(settings),
+                Err(e) => {
+                    error!("Failed to deserialize tool settings for use_aws: {:?}", e);
+                    return PermissionEvalResult::Ask;
+                },
+            },
+            None => None,
+        };
+
+        // Denials always apply, regardless of whether the tool is trusted
+        if let Some(settings) = &settings {
+            if settings.denied_services.contains(service_name)
+                || settings
+                    .rules
+                    .iter()
+                    .any(|rule| rule.effect == PolicyEffect::Deny && rule.matches(self))
+            {
+                return PermissionEvalResult::Deny;
+            }
+        }
+
+        // Dry runs never change anything, but only skip the prompt when use_aws is trusted
+        if self.dry_run && is_in_allowlist {
+            return PermissionEvalResult::Allow;
+        }
+
+        match settings {
             Some(settings) if is_in_allowlist => {
-                let settings = match serde_json::from_value::<Settings>(settings.clone()) {
-                    Ok(settings) => settings,
-                    Err(e) => {
-                        error!("Failed to deserialize tool settings for use_aws: {:?}", e);
-                        return PermissionEvalResult::Ask;
-                    },
-                };
-                if settings.denied_services.contains(service_name) {
-                    return PermissionEvalResult::Deny;
-                }
-                if settings.allowed_services.contains(service_name) {
+                if settings.allowed_services.contains(service_name)
+                    || settings
+                        .rules
+                        .iter()
+                        .any(|rule| rule.effect == PolicyEffect::Allow && rule.matches(self))
+                {
                     return PermissionEvalResult::Allow;
                 }
                 PermissionEvalResult::Ask
@@ -233,6 +382,140 @@ impl UseAws {
     }
 }
 
+/// Checks the `use_aws` entry of an agent's `toolsSettings`, including its glob patterns.
+pub fn validate_settings(settings: &serde_json::Value) -> Result<()> {
+    serde_json::from_value::<Settings>(settings.clone())
+        .map(|_| ())
+        .wrap_err("Invalid settings for use_aws")
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Settings {
+    #[serde(default)]
+    allowed_services: Vec<String>,
+    #[serde(default)]
+    denied_services: Vec<String>,
+    #[serde(default)]
+    rules: Vec<PolicyRule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PolicyEffect {
+    Allow,
+    Deny,
+}
+
+/// A rule of the `use_aws` policy. Each field is a list of glob patterns, and a rule matches a
+/// call when every non-empty list has a pattern matching the call.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PolicyRule {
+    effect: PolicyEffect,
+    #[serde(default)]
+    services: Patterns,
+    #[serde(default, deserialize_with = "deserialize_operations")]
+    operations: Patterns,
+    #[serde(default)]
+    regions: Patterns,
+    /// Calls without a profile use the `default` profile
+    #[serde(default)]
+    profiles: Patterns,
+}
+
+/// Glob patterns, compiled when the settings are loaded so that invalid ones are rejected.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "Vec<String>")]
+struct Patterns(Vec<GlobMatcher>);
+
+impl TryFrom<Vec<String>> for Patterns {
+    type Error = String;
+
+    fn try_from(patterns: Vec<String>) -> Result<Self, Self::Error> {
+        patterns
+            .iter()
+            .map(|p| {
+                Glob::new(p)
+                    .map(|glob| glob.compile_matcher())
+                    .map_err(|e| format!("invalid glob pattern {p}: {e}"))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl Patterns {
+    fn matches(&self, value: &str) -> bool {
+        self.0.is_empty() || self.0.iter().any(|matcher| matcher.is_match(value))
+    }
+}
+
+fn deserialize_operations<'de, D>(deserializer: D) -> Result<Patterns, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let operations = Vec::<String>::deserialize(deserializer)?;
+    Patterns::try_from(operations.iter().map(|o| normalize_operation(o)).collect::<Vec<_>>())
+        .map_err(serde::de::Error::custom)
+}
+
+impl PolicyRule {
+    fn matches(&self, use_aws: &UseAws) -> bool {
+        let operation = normalize_operation(&use_aws.operation_name);
+        let profile = use_aws.profile_name.as_deref().unwrap_or("default");
+        self.services.matches(&use_aws.service_name)
+            && self.operations.matches(&operation)
+            && self.regions.matches(&use_aws.region)
+            && self.profiles.matches(profile)
+    }
+}
+
+/// Operations may be given as `list-buckets`, `list_buckets` or `ListBuckets`.
+fn normalize_operation(operation: &str) -> String {
+    if operation.contains(['-', '_', '*', '?']) {
+        operation.replace('_', "-").to_lowercase()
+    } else {
+        operation.to_case(Case::Kebab)
+    }
+}
+
+/// The environment of the AWS CLI, with our metadata added to the user agent.
+fn cli_env() -> HashMap<String, String> {
+    let mut env_vars: HashMap<String, String> = std::env::vars().collect();
+
+    // Set up additional metadata for the AWS CLI user agent
+    let user_agent_metadata_value = format!(
+        "{} {}/{}",
+        USER_AGENT_APP_NAME, USER_AGENT_VERSION_KEY, USER_AGENT_VERSION_VALUE
+    );
+
+    // If the user agent metadata env var already exists, append to it, otherwise set it
+    match env_vars.get(USER_AGENT_ENV_VAR) {
+        Some(existing_value) if !existing_value.is_empty() => {
+            let value = format!("{} {}", existing_value, user_agent_metadata_value);
+            env_vars.insert(USER_AGENT_ENV_VAR.to_string(), value);
+        },
+        _ => {
+            env_vars.insert(USER_AGENT_ENV_VAR.to_string(), user_agent_metadata_value);
+        },
+    }
+    env_vars
+}
+
+fn truncate_output(output: &str) -> String {
+    let max = MAX_TOOL_RESPONSE_SIZE / 3;
+    if output.len() > max {
+        let mut end = max;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{} ... truncated", &output[..end])
+    } else {
+        output.to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,6 +582,247 @@ mod tests {
         );
     }
 
+    /// Records the arguments it is called with and replies with canned outputs.
+    #[derive(Default)]
+    struct FakeRunner {
+        outputs: std::sync::Mutex<Vec<CommandOutput>>,
+        calls: std::sync::Mutex<Vec<Vec<String>>>,
+    }
+
+    impl FakeRunner {
+        fn new(outputs: Vec<CommandOutput>) -> Self {
+            Self {
+                outputs: std::sync::Mutex::new(outputs),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl CommandRunner for FakeRunner {
+        async fn run(&self, args: &[String], _envs: &HashMap<String, String>) -> Result<CommandOutput> {
+            self.calls.lock().unwrap().push(args.to_vec());
+            Ok(self.outputs.lock().unwrap().remove(0))
+        }
+    }
+
+    fn agent_from_json(value: serde_json::Value) -> Agent {
+        let mut value = value;
+        value["name"] = "test".into();
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_eval_perm_policy() {
+        let agent = agent_from_json(serde_json::json!({
+            "allowedTools": ["use_aws"],
+            "toolsSettings": {
+                "use_aws": {
+                    "rules": [
+                        { "effect": "allow", "services": ["s3", "s3api"], "operations": ["list-*", "get-*"] },
+                        { "effect": "deny", "services": ["iam"] },
+                        { "effect": "deny", "profiles": ["prod"] },
+                        { "effect": "deny", "operations": ["delete-*"], "regions": ["eu-*"] }
+                    ]
+                }
+            }
+        }));
+        let eval = |service: &str, operation: &str, region: &str, profile: Option<&str>| {
+            use_aws! {{
+                "service_name": service,
+                "operation_name": operation,
+                "region": region,
+                "profile_name": profile,
+            }}
+            .eval_perm(&agent)
+        };
+
+        assert_eq!(
+            eval("s3api", "list-buckets", "us-east-1", None),
+            PermissionEvalResult::Allow
+        );
+        assert_eq!(
+            eval("s3api", "ListObjectsV2", "us-east-1", None),
+            PermissionEvalResult::Allow
+        );
+        assert_eq!(
+            eval("s3api", "put-object", "us-east-1", None),
+            PermissionEvalResult::Ask
+        );
+        assert_eq!(eval("iam", "list-users", "us-east-1", None), PermissionEvalResult::Deny);
+        assert_eq!(eval("s3", "ls", "us-east-1", Some("prod")), PermissionEvalResult::Deny);
+        assert_eq!(
+            eval("s3api", "delete_bucket", "eu-west-1", None),
+            PermissionEvalResult::Deny
+        );
+        assert_eq!(
+            eval("s3api", "delete-bucket", "us-east-1", None),
+            PermissionEvalResult::Ask
+        );
+
+        // Denials apply even when the tool is not trusted
+        let untrusted = agent_from_json(serde_json::json!({
+            "toolsSettings": { "use_aws": { "deniedServices": ["iam"] } }
+        }));
+        let cmd = use_aws! {{
+            "service_name": "iam",
+            "operation_name": "list-users",
+            "region": "us-east-1",
+        }};
+        assert_eq!(cmd.eval_perm(&untrusted), PermissionEvalResult::Deny);
+
+        // Dry runs are allowed without a prompt only when the tool is trusted
+        let cmd = use_aws! {{
+            "service_name": "s3api",
+            "operation_name": "put-object",
+            "region": "us-east-1",
+            "dry_run": true,
+        }};
+        assert_eq!(cmd.eval_perm(&agent), PermissionEvalResult::Allow);
+        assert_eq!(cmd.eval_perm(&untrusted), PermissionEvalResult::Ask);
+        let cmd = use_aws! {{
+            "service_name": "iam",
+            "operation_name": "create-user",
+            "region": "us-east-1",
+            "dry_run": true,
+        }};
+        assert_eq!(cmd.eval_perm(&agent), PermissionEvalResult::Deny);
+    }
+
+    #[test]
+    fn test_validate_settings() {
+        assert!(
+            validate_settings(&serde_json::json!({
+                "rules": [{ "effect": "allow", "services": ["s3*"], "operations": ["ListBuckets"] }]
+            }))
+            .is_ok()
+        );
+        assert!(
+            validate_settings(&serde_json::json!({
+                "rules": [{ "effect": "deny", "services": ["s3[api"] }]
+            }))
+            .is_err()
+        );
+        assert!(
+            validate_settings(&serde_json::json!({
+                "rules": [{ "effect": "deny", "operations": ["{delete-*"] }]
+            }))
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invoke_with_runner() {
+        let cmd = use_aws! {{
+            "service_name": "s3api",
+            "operation_name": "list-objects-v2",
+            "parameters": { "bucket": "my-bucket" },
+            "region": "us-west-2",
+            "profile_name": "dev",
+        }};
+        let runner = FakeRunner::new(vec![CommandOutput {
+            exit_status: Some(0),
+            stdout: "{}".to_string(),
+            stderr: String::new(),
+        }]);
+        let out = cmd.invoke_with(&runner).await.unwrap();
+        assert_eq!(runner.calls.lock().unwrap()[0], [
+            "--region",
+            "us-west-2",
+            "--profile",
+            "dev",
+            "s3api",
+            "list-objects-v2",
+            "--bucket",
+            "my-bucket"
+        ]);
+        let OutputKind::Json(json) = out.output else {
+            panic!("Expected JSON output");
+        };
+        assert_eq!(json["stdout"], "{}");
+
+        let runner = FakeRunner::new(vec![CommandOutput {
+            exit_status: Some(255),
+            stdout: String::new(),
+            stderr: "AccessDenied".to_string(),
+        }]);
+        assert!(cmd.invoke_with(&runner).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let skeleton = CommandOutput {
+            exit_status: Some(0),
+            stdout: r#"{ "Bucket": "", "Prefix": "", "MaxKeys": 0 }"#.to_string(),
+            stderr: String::new(),
+        };
+
+        let cmd = use_aws! {{
+            "service_name": "s3api",
+            "operation_name": "list-objects-v2",
+            "parameters": { "bucket": "my-bucket", "query": "Contents[].Key" },
+            "region": "us-west-2",
+            "dry_run": true,
+        }};
+        let runner = FakeRunner::new(vec![skeleton.clone()]);
+        cmd.invoke_with(&runner).await.unwrap();
+        assert_eq!(runner.calls.lock().unwrap()[0], [
+            "--region",
+            "us-west-2",
+            "s3api",
+            "list-objects-v2",
+            "--generate-cli-skeleton",
+            "input"
+        ]);
+
+        let cmd = use_aws! {{
+            "service_name": "s3api",
+            "operation_name": "list-objects-v2",
+            "parameters": { "bucket-name": "my-bucket" },
+            "region": "us-west-2",
+            "dry_run": true,
+        }};
+        let err = cmd.invoke_with(&FakeRunner::new(vec![skeleton])).await.unwrap_err();
+        assert!(err.to_string().contains("--bucket-name"), "{err}");
+
+        let cmd = use_aws! {{
+            "service_name": "ec2",
+            "operation_name": "terminate-instances",
+            "parameters": { "instance-ids": "i-123" },
+            "region": "us-west-2",
+            "dry_run": true,
+        }};
+        let runner = FakeRunner::new(vec![CommandOutput {
+            exit_status: Some(254),
+            stdout: String::new(),
+            stderr: "An error occurred (DryRunOperation) when calling the TerminateInstances operation".to_string(),
+        }]);
+        cmd.invoke_with(&runner).await.unwrap();
+        assert_eq!(runner.calls.lock().unwrap()[0].last().unwrap(), "--dry-run");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_fake_aws_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("aws");
+        std::fs::write(&program, "#!/bin/sh\necho \"$@\"\necho \"$AWS_EXECUTION_ENV\" >&2\n").unwrap();
+        std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let cmd = use_aws! {{
+            "service_name": "sts",
+            "operation_name": "get-caller-identity",
+            "region": "us-east-1",
+        }};
+        let out = cmd.invoke_with(&AwsCli::new(&program)).await.unwrap();
+        let OutputKind::Json(json) = out.output else {
+            panic!("Expected JSON output");
+        };
+        assert_eq!(json["stdout"], "--region us-east-1 sts get-caller-identity\n");
+        assert!(json["stderr"].as_str().unwrap().contains(USER_AGENT_APP_NAME));
+    }
+
     #[tokio::test]
     #[ignore = "not in ci"]
     async fn test_aws_read_only() {
@@ -0,0 +1,180 @@
+//! A validator for the subset of JSON Schema that tool schemas commonly use: `type`, `enum`,
+//! `const`, `properties`, `required`, `additionalProperties`, `items`, `anyOf` and `oneOf`, and the
+//! length and range keywords. Other keywords are ignored, so values are only ever rejected for a
+//! reason the schema states.
+
+use serde_json::Value;
+
+/// Checks that `value` conforms to `schema`, and describes the first violation found otherwise
+pub fn validate(schema: &Value, value: &Value) -> Result<(), String> {
+    validate_at(schema, value, "$")
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    let Some(schema) = schema.as_object() else {
+        // `true` and `{}` accept anything, `false` accepts nothing
+        return match schema {
+            Value::Bool(false) => Err(format!("{path} is not allowed")),
+            _ => Ok(()),
+        };
+    };
+
+    if let Some(ty) = schema.get("type") {
+        let types = match ty {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|ty| has_type(value, ty)) {
+            return Err(format!(
+                "{path} should be of type {}, found {}",
+                types.join(" or "),
+                type_of(value)
+            ));
+        }
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !values.contains(value) {
+            return Err(format!("{path} should be one of {}", Value::Array(values.clone())));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return Err(format!("{path} should be {expected}"));
+        }
+    }
+    if let Some(schemas) = schema.get("anyOf").and_then(Value::as_array) {
+        if !schemas.iter().any(|s| validate_at(s, value, path).is_ok()) {
+            return Err(format!("{path} matches none of the allowed schemas"));
+        }
+    }
+    if let Some(schemas) = schema.get("oneOf").and_then(Value::as_array) {
+        if schemas.iter().filter(|s| validate_at(s, value, path).is_ok()).count() != 1 {
+            return Err(format!("{path} should match exactly one of the allowed schemas"));
+        }
+    }
+
+    let number = |key: &str| schema.get(key).and_then(Value::as_f64);
+    let count = |key: &str| schema.get(key).and_then(Value::as_u64).map(|n| n as usize);
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                if let Some(missing) = required
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find(|key| !object.contains_key(*key))
+                {
+                    return Err(format!("{path} is missing the required property {missing}"));
+                }
+            }
+            for (key, value) in object {
+                let path = format!("{path}.{key}");
+                match (properties.and_then(|p| p.get(key)), schema.get("additionalProperties")) {
+                    (Some(property), _) => validate_at(property, value, &path)?,
+                    (None, Some(additional)) => validate_at(additional, value, &path)?,
+                    (None, None) => {},
+                }
+            }
+        },
+        Value::Array(items) => {
+            if count("minItems").is_some_and(|min| items.len() < min) {
+                return Err(format!("{path} has too few items"));
+            }
+            if count("maxItems").is_some_and(|max| items.len() > max) {
+                return Err(format!("{path} has too many items"));
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{path}[{i}]"))?;
+                }
+            }
+        },
+        Value::String(s) => {
+            let len = s.chars().count();
+            if count("minLength").is_some_and(|min| len < min) || count("maxLength").is_some_and(|max| len > max) {
+                return Err(format!("{path} has a length out of bounds"));
+            }
+        },
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if number("minimum").is_some_and(|min| n < min)
+                || number("maximum").is_some_and(|max| n > max)
+                || number("exclusiveMinimum").is_some_and(|min| n <= min)
+                || number("exclusiveMaximum").is_some_and(|max| n >= max)
+            {
+                return Err(format!("{path} is out of range"));
+            }
+        },
+        Value::Bool(_) | Value::Null => {},
+    }
+    Ok(())
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        ty => type_of(value) == ty,
+    }
+}
+
+fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "temperature": { "type": "number", "minimum": -273.15 },
+                "unit": { "enum": ["C", "F"] },
+                "readings": { "type": "array", "items": { "type": "integer" } },
+                "note": { "type": ["string", "null"] }
+            },
+            "required": ["temperature"],
+            "additionalProperties": false
+        });
+        assert!(
+            validate(
+                &schema,
+                &json!({ "temperature": 21.5, "unit": "C", "readings": [1, 2], "note": null })
+            )
+            .is_ok()
+        );
+        assert_eq!(
+            validate(&schema, &json!({ "unit": "C" })).unwrap_err(),
+            "$ is missing the required property temperature"
+        );
+        assert_eq!(
+            validate(&schema, &json!({ "temperature": "warm" })).unwrap_err(),
+            "$.temperature should be of type number, found string"
+        );
+        assert!(validate(&schema, &json!({ "temperature": -300 })).is_err());
+        assert!(validate(&schema, &json!({ "temperature": 1, "unit": "K" })).is_err());
+        assert_eq!(
+            validate(&schema, &json!({ "temperature": 1, "readings": [1, 2.5] })).unwrap_err(),
+            "$.readings[1] should be of type integer, found number"
+        );
+        assert_eq!(
+            validate(&schema, &json!({ "temperature": 1, "extra": true })).unwrap_err(),
+            "$.extra is not allowed"
+        );
+        // Unknown keywords are ignored
+        assert!(validate(&json!({ "format": "uuid" }), &json!("not a uuid")).is_ok());
+    }
+}
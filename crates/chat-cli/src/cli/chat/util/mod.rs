@@ -1,5 +1,7 @@
 pub mod images;
 pub mod issue;
+pub mod json_schema;
+pub mod outline;
 #[cfg(test)]
 pub mod test;
 pub mod ui;
@@ -0,0 +1,445 @@
+//! Extracts a symbol outline from source files using tree-sitter.
+//!
+//! The outline is intentionally shallow: containers such as impls, classes, traits and modules are
+//! descended into, while function bodies are not.
+
+use std::fmt;
+use std::path::Path;
+
+use eyre::{
+    Result,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tree_sitter::{
+    Node,
+    Parser,
+};
+
+/// Source languages that can be outlined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    TypeScript,
+    Tsx,
+    JavaScript,
+    Go,
+    Java,
+}
+
+impl Language {
+    /// Infers the language from the file extension of `path`.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let ext = path.as_ref().extension()?.to_str()?.to_lowercase();
+        Some(match ext.as_str() {
+            "rs" => Self::Rust,
+            "py" | "pyi" => Self::Python,
+            "ts" | "mts" | "cts" => Self::TypeScript,
+            "tsx" => Self::Tsx,
+            "js" | "jsx" | "mjs" | "cjs" => Self::JavaScript,
+            "go" => Self::Go,
+            "java" => Self::Java,
+            _ => return None,
+        })
+    }
+
+    fn grammar(&self) -> tree_sitter::Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+            Self::Java => tree_sitter_java::LANGUAGE.into(),
+        }
+    }
+
+    /// Maps a tree-sitter node kind to the kind of symbol it declares, if any.
+    fn symbol_kind(&self, node: &Node<'_>) -> Option<SymbolKind> {
+        let kind = node.kind();
+        match self {
+            Self::Rust => match kind {
+                "function_item" | "function_signature_item" => Some(SymbolKind::Function),
+                "struct_item" | "union_item" => Some(SymbolKind::Struct),
+                "enum_item" => Some(SymbolKind::Enum),
+                "trait_item" => Some(SymbolKind::Trait),
+                "impl_item" => Some(SymbolKind::Impl),
+                "mod_item" => Some(SymbolKind::Module),
+                "type_item" => Some(SymbolKind::TypeAlias),
+                "const_item" | "static_item" => Some(SymbolKind::Constant),
+                "macro_definition" => Some(SymbolKind::Macro),
+                _ => None,
+            },
+            Self::Python => match kind {
+                "function_definition" => Some(SymbolKind::Function),
+                "class_definition" => Some(SymbolKind::Class),
+                _ => None,
+            },
+            Self::TypeScript | Self::Tsx | Self::JavaScript => match kind {
+                "function_declaration" | "generator_function_declaration" => Some(SymbolKind::Function),
+                "class_declaration" | "abstract_class_declaration" => Some(SymbolKind::Class),
+                "method_definition" => Some(SymbolKind::Method),
+                "interface_declaration" => Some(SymbolKind::Interface),
+                "type_alias_declaration" => Some(SymbolKind::TypeAlias),
+                "enum_declaration" => Some(SymbolKind::Enum),
+                "internal_module" | "module" => Some(SymbolKind::Module),
+                // `const foo = () => {}` and friends
+                "variable_declarator" => node
+                    .child_by_field_name("value")
+                    .filter(|value| {
+                        matches!(
+                            value.kind(),
+                            "arrow_function" | "function_expression" | "function" | "generator_function"
+                        )
+                    })
+                    .map(|_| SymbolKind::Function),
+                _ => None,
+            },
+            Self::Go => match kind {
+                "function_declaration" => Some(SymbolKind::Function),
+                "method_declaration" => Some(SymbolKind::Method),
+                "type_spec" | "type_alias" => Some(match node.child_by_field_name("type").map(|t| t.kind()) {
+                    Some("struct_type") => SymbolKind::Struct,
+                    Some("interface_type") => SymbolKind::Interface,
+                    _ => SymbolKind::TypeAlias,
+                }),
+                _ => None,
+            },
+            Self::Java => match kind {
+                "class_declaration" | "record_declaration" => Some(SymbolKind::Class),
+                "interface_declaration" | "annotation_type_declaration" => Some(SymbolKind::Interface),
+                "enum_declaration" => Some(SymbolKind::Enum),
+                "method_declaration" | "constructor_declaration" => Some(SymbolKind::Method),
+                _ => None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Class,
+    Interface,
+    Module,
+    TypeAlias,
+    Constant,
+    Macro,
+}
+
+impl SymbolKind {
+    /// Whether the symbols declared inside of this one should be part of the outline.
+    fn is_container(&self) -> bool {
+        matches!(
+            self,
+            Self::Impl | Self::Trait | Self::Class | Self::Interface | Self::Module | Self::Enum
+        )
+    }
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Function => "fn",
+            Self::Method => "method",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Trait => "trait",
+            Self::Impl => "impl",
+            Self::Class => "class",
+            Self::Interface => "interface",
+            Self::Module => "mod",
+            Self::TypeAlias => "type",
+            Self::Constant => "const",
+            Self::Macro => "macro",
+        })
+    }
+}
+
+/// A declaration found in a source file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// The declaration with its body stripped, collapsed onto a single line.
+    pub signature: String,
+    /// 1-based, inclusive.
+    pub start_line: usize,
+    /// 1-based, inclusive.
+    pub end_line: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Symbol>,
+}
+
+/// Parses `source` and returns its top level symbols.
+pub fn outline(language: Language, source: &str) -> Result<Vec<Symbol>> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language.grammar())
+        .map_err(|err| eyre!("failed to load grammar for {language:?}: {err}"))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| eyre!("failed to parse source as {language:?}"))?;
+
+    let mut symbols = Vec::new();
+    collect_symbols(language, source, tree.root_node(), None, &mut symbols);
+    Ok(symbols)
+}
+
+fn collect_symbols(
+    language: Language,
+    source: &str,
+    node: Node<'_>,
+    parent_kind: Option<SymbolKind>,
+    out: &mut Vec<Symbol>,
+) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let Some(mut kind) = language.symbol_kind(&child) else {
+            collect_symbols(language, source, child, parent_kind, out);
+            continue;
+        };
+
+        if kind == SymbolKind::Function && parent_kind.is_some_and(|k| k.is_container()) {
+            kind = SymbolKind::Method;
+        }
+
+        // Decorators and attributes are part of the symbol as far as reading its body goes. Python
+        // wraps decorated definitions, while Rust attributes and decorators on TS/JS class members
+        // precede the symbol as siblings.
+        let mut start_node = match child.parent() {
+            Some(parent) if parent.kind() == "decorated_definition" => parent,
+            _ => child,
+        };
+        while let Some(prev) = start_node
+            .prev_named_sibling()
+            .filter(|prev| matches!(prev.kind(), "attribute_item" | "decorator"))
+        {
+            start_node = prev;
+        }
+
+        let mut symbol = Symbol {
+            kind,
+            name: symbol_name(source, &child),
+            signature: signature(source, &child),
+            start_line: start_node.start_position().row + 1,
+            end_line: child.end_position().row + 1,
+            children: Vec::new(),
+        };
+        if kind.is_container() {
+            collect_symbols(language, source, child, Some(kind), &mut symbol.children);
+        }
+        out.push(symbol);
+    }
+}
+
+fn symbol_name(source: &str, node: &Node<'_>) -> String {
+    let text = |n: Node<'_>| n.utf8_text(source.as_bytes()).unwrap_or_default().to_string();
+
+    if node.kind() == "impl_item" {
+        let ty = node.child_by_field_name("type").map(text).unwrap_or_default();
+        return match node.child_by_field_name("trait").map(text) {
+            Some(tr) => format!("{tr} for {ty}"),
+            None => ty,
+        };
+    }
+
+    node.child_by_field_name("name").map(text).unwrap_or_default()
+}
+
+fn signature(source: &str, node: &Node<'_>) -> String {
+    let end = match node.child_by_field_name("body") {
+        Some(body) => body.start_byte(),
+        None => source[node.start_byte()..node.end_byte()]
+            .find('\n')
+            .map_or(node.end_byte(), |i| node.start_byte() + i),
+    };
+    let signature = source[node.start_byte()..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    signature.trim_end_matches(['{', ':', '=']).trim_end().to_string()
+}
+
+/// Finds every symbol named `path`. A path may be qualified with its containers using either `::`
+/// or `.`, e.g. `Config::new` or `Parser.parse`.
+pub fn find_symbols<'a>(symbols: &'a [Symbol], path: &str) -> Vec<&'a Symbol> {
+    let segments = path
+        .split("::")
+        .flat_map(|s| s.split('.'))
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let mut found = Vec::new();
+    if !segments.is_empty() {
+        find_in(symbols, &segments, &mut found);
+    }
+    found
+}
+
+fn find_in<'a>(symbols: &'a [Symbol], segments: &[&str], found: &mut Vec<&'a Symbol>) {
+    for symbol in symbols {
+        if matches_segment(symbol, segments[0]) {
+            if segments.len() == 1 {
+                found.push(symbol);
+            } else {
+                find_in(&symbol.children, &segments[1..], found);
+            }
+        }
+        // Qualified paths need not start at the top level.
+        find_in(&symbol.children, segments, found);
+    }
+}
+
+fn matches_segment(symbol: &Symbol, segment: &str) -> bool {
+    if symbol.name == segment {
+        return true;
+    }
+    // Allow `Foo::bar` to find methods in `impl Foo` and `impl<T> Display for Foo<T>`
+    symbol.kind == SymbolKind::Impl
+        && symbol
+            .name
+            .rsplit(" for ")
+            .next()
+            .is_some_and(|ty| ty.split('<').next() == Some(segment))
+}
+
+/// Renders an outline as an indented list, one symbol per line.
+pub fn format_outline(symbols: &[Symbol]) -> String {
+    fn format_into(symbols: &[Symbol], depth: usize, out: &mut String) {
+        for symbol in symbols {
+            out.push_str(&format!(
+                "{}{}-{} {} {}\n",
+                "  ".repeat(depth),
+                symbol.start_line,
+                symbol.end_line,
+                symbol.kind,
+                symbol.signature
+            ));
+            format_into(&symbol.children, depth + 1, out);
+        }
+    }
+
+    let mut out = String::new();
+    format_into(symbols, 0, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_SOURCE: &str = r#"use std::fmt;
+
+pub struct Config {
+    name: String,
+}
+
+impl Config {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string() }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+fn main() {}
+"#;
+
+    #[test]
+    fn test_language_from_path() {
+        assert_eq!(Language::from_path("/a/b.rs"), Some(Language::Rust));
+        assert_eq!(Language::from_path("b.TSX"), Some(Language::Tsx));
+        assert_eq!(Language::from_path("b.md"), None);
+        assert_eq!(Language::from_path("Makefile"), None);
+    }
+
+    #[test]
+    fn test_rust_outline() {
+        let symbols = outline(Language::Rust, RUST_SOURCE).unwrap();
+        let names = symbols.iter().map(|s| (s.kind, s.name.as_str())).collect::<Vec<_>>();
+        assert_eq!(names, vec![
+            (SymbolKind::Struct, "Config"),
+            (SymbolKind::Impl, "Config"),
+            (SymbolKind::Impl, "fmt::Display for Config"),
+            (SymbolKind::Function, "main"),
+        ]);
+
+        let new = &symbols[1].children[0];
+        assert_eq!(new.kind, SymbolKind::Method);
+        assert_eq!(new.signature, "pub fn new(name: &str) -> Self");
+        assert_eq!((new.start_line, new.end_line), (8, 10));
+
+        let symbols = outline(Language::Rust, "#[derive(Debug)]\n#[serde(default)]\nstruct A;\n").unwrap();
+        assert_eq!((symbols[0].start_line, symbols[0].end_line), (1, 3));
+    }
+
+    #[test]
+    fn test_find_symbols() {
+        let symbols = outline(Language::Rust, RUST_SOURCE).unwrap();
+        assert_eq!(find_symbols(&symbols, "new").len(), 1);
+        assert_eq!(find_symbols(&symbols, "Config::fmt").len(), 1);
+        assert_eq!(find_symbols(&symbols, "Config").len(), 3);
+        assert!(find_symbols(&symbols, "Other::new").is_empty());
+    }
+
+    #[test]
+    fn test_python_outline() {
+        let source = "class Greeter(Base):\n    @staticmethod\n    def hello(name: str) -> str:\n        return name\n\ndef main():\n    pass\n";
+        let symbols = outline(Language::Python, source).unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].signature, "class Greeter(Base)");
+        let hello = &symbols[0].children[0];
+        assert_eq!(hello.kind, SymbolKind::Method);
+        assert_eq!(hello.signature, "def hello(name: str) -> str");
+        assert_eq!((hello.start_line, hello.end_line), (2, 4));
+        assert_eq!(find_symbols(&symbols, "Greeter.hello").len(), 1);
+    }
+
+    #[test]
+    fn test_other_languages() {
+        let ts = "export class A {\n  run(): void {}\n}\nexport const helper = (x: number) => x;\ninterface I {}\n";
+        let symbols = outline(Language::TypeScript, ts).unwrap();
+        let names = symbols.iter().map(|s| s.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["A", "helper", "I"]);
+        assert_eq!(symbols[0].children[0].kind, SymbolKind::Method);
+
+        let symbols = outline(Language::TypeScript, "class B {\n  @log\n  run() {}\n}\n").unwrap();
+        assert_eq!(
+            (symbols[0].children[0].start_line, symbols[0].children[0].end_line),
+            (2, 3)
+        );
+
+        let go = "package main\n\ntype Server struct {\n}\n\nfunc (s *Server) Run() error {\n\treturn nil\n}\n";
+        let symbols = outline(Language::Go, go).unwrap();
+        assert_eq!(symbols[0].kind, SymbolKind::Struct);
+        assert_eq!(symbols[1].kind, SymbolKind::Method);
+        assert_eq!(symbols[1].signature, "func (s *Server) Run() error");
+
+        let java = "class Main {\n  public static void main(String[] args) {}\n}\n";
+        let symbols = outline(Language::Java, java).unwrap();
+        assert_eq!(symbols[0].children[0].name, "main");
+    }
+
+    #[test]
+    fn test_format_outline() {
+        let symbols = outline(Language::Rust, RUST_SOURCE).unwrap();
+        let formatted = format_outline(&symbols);
+        assert!(formatted.starts_with("3-5 struct pub struct Config\n"));
+        assert!(formatted.contains("\n  8-10 method pub fn new(name: &str) -> Self\n"));
+    }
+}
@@ -0,0 +1,167 @@
+use std::process::ExitCode;
+
+use anstream::{
+    eprintln,
+    println,
+};
+use clap::Subcommand;
+use crossterm::style::Stylize;
+use eyre::Result;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use super::OutputFormat;
+use super::audit::parse_time;
+use crate::database::ConversationMetadata;
+use crate::os::Os;
+
+/// Search and manage the conversations saved by `q chat`.
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum HistorySubcommand {
+    /// List saved conversations, most recently updated first
+    List {
+        /// Only show the most recent N conversations
+        #[arg(long, short = 'n')]
+        limit: Option<usize>,
+        /// Format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Full-text search over the transcripts of saved conversations
+    Search {
+        /// Terms to search for. Every term must match
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+        /// Only show the best N matches
+        #[arg(long, short = 'n')]
+        limit: Option<usize>,
+        /// Format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Delete saved conversations. A prefix of the id is enough
+    #[command(alias = "remove")]
+    Rm {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Delete saved conversations that have not been updated recently
+    Prune {
+        /// Delete conversations last updated before this time. Accepts an RFC 3339 timestamp or a
+        /// relative duration such as 12h, 30d or 8w
+        #[arg(long, value_parser = parse_time)]
+        older_than: OffsetDateTime,
+    },
+}
+
+impl HistorySubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        match self {
+            Self::List { limit, format } => {
+                let conversations = os.database.list_conversations(limit)?;
+                match format {
+                    OutputFormat::Plain => {
+                        if conversations.is_empty() {
+                            println!("No saved conversations found.");
+                        }
+                        for conversation in &conversations {
+                            println!("{}", format_conversation(conversation));
+                        }
+                    },
+                    OutputFormat::Json => println!("{}", serde_json::to_string(&conversations)?),
+                    OutputFormat::JsonPretty => println!("{}", serde_json::to_string_pretty(&conversations)?),
+                }
+            },
+            Self::Search { query, limit, format } => {
+                let results = os.database.search_conversations(&query.join(" "), limit)?;
+                match format {
+                    OutputFormat::Plain => {
+                        if results.is_empty() {
+                            println!("No conversations match the query.");
+                        }
+                        for result in &results {
+                            println!(
+                                "{}\n    {}",
+                                format_conversation(&result.conversation),
+                                result.snippet.replace('\n', " ")
+                            );
+                        }
+                    },
+                    OutputFormat::Json => println!("{}", serde_json::to_string(&results)?),
+                    OutputFormat::JsonPretty => println!("{}", serde_json::to_string_pretty(&results)?),
+                }
+            },
+            Self::Rm { ids } => {
+                let mut exit_code = ExitCode::SUCCESS;
+                for id in ids {
+                    match os.database.delete_conversation(&id)? {
+                        Some(id) => println!("Deleted conversation {}", id.green()),
+                        None => {
+                            eprintln!("No saved conversation matches {}", id.red());
+                            exit_code = ExitCode::FAILURE;
+                        },
+                    }
+                }
+
+                return Ok(exit_code);
+            },
+            Self::Prune { older_than } => {
+                let deleted = os.database.prune_conversations(older_than)?;
+                println!(
+                    "Deleted {deleted} conversation{} last updated before {}",
+                    if deleted == 1 { "" } else { "s" },
+                    older_than.format(&Rfc3339).unwrap_or_default()
+                );
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn format_conversation(conversation: &ConversationMetadata) -> String {
+    let updated_at = conversation.updated_at.format(&Rfc3339).unwrap_or_default();
+    let agent = conversation
+        .agent
+        .as_deref()
+        .map(|agent| format!(" [{agent}]"))
+        .unwrap_or_default();
+
+    format!(
+        "{} {} {}{}\n    directory: {}",
+        updated_at.dark_grey(),
+        conversation.id.as_str().green(),
+        conversation.title.as_deref().unwrap_or("(untitled)"),
+        agent,
+        conversation.cwd,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::RootSubcommand;
+    use crate::util::test::assert_parse;
+
+    #[test]
+    fn test_history_search_args() {
+        assert_parse!(
+            ["history", "search", "cargo", "build", "-n", "3"],
+            RootSubcommand::History(HistorySubcommand::Search {
+                query: vec!["cargo".to_string(), "build".to_string()],
+                limit: Some(3),
+                format: OutputFormat::Plain,
+            })
+        );
+    }
+
+    #[test]
+    fn test_history_rm_args() {
+        assert_parse!(
+            ["history", "rm", "0b6e", "7f3a"],
+            RootSubcommand::History(HistorySubcommand::Rm {
+                ids: vec!["0b6e".to_string(), "7f3a".to_string()],
+            })
+        );
+    }
+}
@@ -1,5 +1,9 @@
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{
+    IsTerminal,
+    Read,
+    Write,
+};
 use std::path::PathBuf;
 use std::process::ExitCode;
 
@@ -17,21 +21,54 @@ use eyre::{
     bail,
 };
 
+use super::OutputFormat;
 use super::agent::{
     Agent,
     Agents,
     McpServerConfig,
 };
+use crate::auth::mcp_oauth::{
+    McpAuthorization,
+    McpToken,
+};
+use crate::auth::pkce::DEFAULT_AUTHORIZATION_TIMEOUT;
+use crate::cli::chat::server_config_vars::{
+    ResolvedConfig,
+    is_valid_secret_name,
+    mask,
+    resolve,
+    secret_key,
+    secret_names,
+};
 use crate::cli::chat::tool_manager::{
     global_mcp_config_path,
     workspace_mcp_config_path,
 };
 use crate::cli::chat::tools::custom_tool::{
+    CustomToolClient,
     CustomToolConfig,
+    TransportType,
     default_timeout,
 };
+use crate::cli::mcp_import::{
+    ImportSource,
+    ask_input,
+    translate,
+};
+use crate::cli::mcp_serve::NativeToolServer;
+use crate::mcp_client::{
+    JsonRpcMessage,
+    Server,
+    ServerCapabilities,
+    TraceDirection,
+    Tracer,
+};
 use crate::os::Os;
 use crate::util::directories;
+use crate::util::spinner::{
+    Spinner,
+    SpinnerComponent,
+};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
 pub enum Scope {
@@ -57,10 +94,23 @@ pub enum McpSubcommand {
     Remove(RemoveArgs),
     /// List configured servers
     List(ListArgs),
-    /// Import a server configuration from another file
+    /// Import server configurations from another file or mcp client
     Import(ImportArgs),
     /// Get the status of a configured server
     Status(StatusArgs),
+    /// Start a server and show what it offers
+    Inspect(InspectArgs),
+    /// Start a server and call one of its tools
+    Call(CallArgs),
+    /// Authorize access to a remote server in the browser
+    Login(LoginArgs),
+    /// Forget the tokens of a remote server
+    Logout(LogoutArgs),
+    /// Serve the native tools over stdio to other MCP clients
+    Serve(ServeArgs),
+    /// Manage the secrets that server configs reference as ${secret:name}
+    #[command(subcommand)]
+    Secret(SecretSubcommand),
 }
 
 impl McpSubcommand {
@@ -71,6 +121,12 @@ impl McpSubcommand {
             Self::List(args) => args.execute(os, output).await?,
             Self::Import(args) => args.execute(os, output).await?,
             Self::Status(args) => args.execute(os, output).await?,
+            Self::Inspect(args) => args.execute(os).await?,
+            Self::Call(args) => args.execute(os).await?,
+            Self::Login(args) => args.execute(os, output).await?,
+            Self::Logout(args) => args.execute(os, output).await?,
+            Self::Serve(args) => args.execute(os).await?,
+            Self::Secret(subcommand) => subcommand.execute(os, output).await?,
         }
 
         output.flush()?;
@@ -84,8 +140,17 @@ pub struct AddArgs {
     #[arg(long)]
     pub name: String,
     /// The command used to launch the server
+    #[arg(long, required_unless_present = "url", conflicts_with = "url")]
+    pub command: Option<String>,
+    /// The url of a remote server
     #[arg(long)]
-    pub command: String,
+    pub url: Option<String>,
+    /// The transport used to reach the remote server. Defaults to Streamable HTTP
+    #[arg(long, value_enum, requires = "url")]
+    pub transport: Option<TransportType>,
+    /// Headers to send with every request to a remote server, as 'name=value'
+    #[arg(long = "header", value_parser = parse_header, requires = "url")]
+    pub headers: Vec<(String, String)>,
     /// Arguments to pass to the command
     #[arg(long, action = ArgAction::Append, allow_hyphen_values = true, value_delimiter = ',')]
     pub args: Vec<String>,
@@ -122,8 +187,12 @@ impl AddArgs {
         }
 
         let merged_env = self.env.into_iter().flatten().collect::<HashMap<_, _>>();
+        let headers = (!self.headers.is_empty()).then(|| self.headers.into_iter().collect::<HashMap<_, _>>());
         let tool: CustomToolConfig = serde_json::from_value(serde_json::json!({
-            "command": self.command,
+            "command": self.command.unwrap_or_default(),
+            "url": self.url,
+            "headers": headers,
+            "transport": self.transport,
             "args": self.args,
             "env": merged_env,
             "timeout": self.timeout.unwrap_or(default_timeout()),
@@ -207,7 +276,8 @@ impl ListArgs {
                 Some(cfg) if !cfg.mcp_servers.is_empty() => {
                     for (name, tool_cfg) in &cfg.mcp_servers {
                         let status = if tool_cfg.disabled { " (disabled)" } else { "" };
-                        writeln!(output, "    • {name:<12} {}{}", tool_cfg.command, status)?;
+                        let target = tool_cfg.url.as_ref().unwrap_or(&tool_cfg.command);
+                        writeln!(output, "    • {name:<12} {}{}", target, status)?;
                     }
                 },
                 _ => {
@@ -223,8 +293,12 @@ impl ListArgs {
 
 #[derive(Debug, Clone, PartialEq, Eq, Args)]
 pub struct ImportArgs {
-    #[arg(long)]
-    pub file: String,
+    /// The file to import. Defaults to where the client given with --from keeps its config
+    #[arg(long, required_unless_present = "from")]
+    pub file: Option<String>,
+    /// The mcp client whose config format the file is in
+    #[arg(long, value_enum)]
+    pub from: Option<ImportSource>,
     #[arg(value_enum)]
     pub scope: Option<Scope>,
     /// Overwrite an existing server with the same name
@@ -238,8 +312,29 @@ impl ImportArgs {
         let config_path = resolve_scope_profile(os, self.scope)?;
         let mut dst_cfg = ensure_config_file(os, &config_path, output).await?;
 
-        let src_path = expand_path(os, &self.file)?;
-        let src_cfg: McpServerConfig = McpServerConfig::load_from_file(os, &src_path).await?;
+        let src_path = match (&self.file, self.from) {
+            (Some(file), _) => expand_path(os, file)?,
+            (None, Some(source)) => source.default_path(os)?,
+            (None, None) => bail!("Either --file or --from is required"),
+        };
+        let mut skipped = Vec::new();
+        let mut secrets = HashMap::new();
+        let src_cfg = match self.from {
+            Some(source) => {
+                let content = os
+                    .fs
+                    .read_to_string(&src_path)
+                    .await
+                    .map_err(|e| eyre::eyre!("Failed to read {}: {e}", src_path.display()))?;
+                let imported = translate(source, &content, &os.env.current_dir()?, &mut ask_input)?;
+                skipped = imported.skipped;
+                secrets = imported.secrets;
+                McpServerConfig {
+                    mcp_servers: imported.servers,
+                }
+            },
+            None => McpServerConfig::load_from_file(os, &src_path).await?,
+        };
 
         let mut added = 0;
         for (name, cfg) in src_cfg.mcp_servers {
@@ -254,6 +349,16 @@ impl ImportArgs {
             dst_cfg.mcp_servers.insert(name.clone(), cfg);
             added += 1;
         }
+        let mut secret_names = secrets.keys().cloned().collect::<Vec<_>>();
+        secret_names.sort();
+        for name in &secret_names {
+            if os.database.get_secret(&secret_key(name)).await?.is_some() && !self.force {
+                bail!("\nSecret '{name}' already exists. Use --force to overwrite.\n");
+            }
+        }
+        for name in &secret_names {
+            os.database.set_secret(&secret_key(name), &secrets[name]).await?;
+        }
 
         writeln!(
             output,
@@ -266,6 +371,15 @@ impl ImportArgs {
             "✓ Imported {added} MCP server(s) into {}\n",
             scope_display(&scope)
         )?;
+        for name in secret_names {
+            writeln!(
+                output,
+                "✓ Stored a password answer as secret '{name}', referenced as ${{secret:{name}}}"
+            )?;
+        }
+        for (name, reason) in skipped {
+            writeln!(output, "⚠ Skipped {name}: {reason}")?;
+        }
         Ok(())
     }
 }
@@ -289,7 +403,10 @@ impl StatusArgs {
                     style::Print("\n─────────────\n"),
                     style::Print(format!("Scope   : {}\n", scope_display(&sc))),
                     style::Print(format!("File    : {}\n", path.display())),
-                    style::Print(format!("Command : {}\n", cfg.command)),
+                    style::Print(match &cfg.url {
+                        Some(url) => format!("Url     : {} ({:?})\n", url, cfg.transport_type()),
+                        None => format!("Command : {}\n", cfg.command),
+                    }),
                     style::Print(format!("Timeout : {} ms\n", cfg.timeout)),
                     style::Print(format!("Disabled: {}\n", cfg.disabled)),
                     style::Print(format!(
@@ -311,6 +428,411 @@ impl StatusArgs {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct InspectArgs {
+    /// Name of the server
+    pub name: String,
+    /// Format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    /// Print every JSON-RPC message exchanged with the server to stderr
+    #[arg(long)]
+    pub trace: bool,
+}
+
+impl InspectArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<()> {
+        let client = connect(os, &self.name, self.trace).await?;
+        let capabilities = client.capabilities().await;
+        let list = |method: &'static str, key: &'static str, supported: bool| {
+            let client = &client;
+            async move {
+                if !supported {
+                    return Ok::<_, eyre::Report>(Vec::new());
+                }
+                // Pages are requested until the server stops giving a cursor
+                let mut items = Vec::new();
+                let mut params = None;
+                loop {
+                    let resp = client.request(method, params).await?;
+                    if let Some(error) = resp.error {
+                        bail!("{method} failed: {}", error.message);
+                    }
+                    let result = resp.result.unwrap_or_default();
+                    items.extend(
+                        result
+                            .get(key)
+                            .and_then(serde_json::Value::as_array)
+                            .into_iter()
+                            .flatten()
+                            .cloned(),
+                    );
+                    match result.get("nextCursor") {
+                        Some(cursor) if !cursor.is_null() => {
+                            params = Some(serde_json::json!({ "cursor": cursor }));
+                        },
+                        _ => break Ok(items),
+                    }
+                }
+            }
+        };
+        let supports = |capability: fn(&ServerCapabilities) -> bool| capabilities.as_ref().is_some_and(capability);
+        let inspection = Inspection {
+            tools: list("tools/list", "tools", supports(|c| c.tools.is_some())).await?,
+            prompts: list("prompts/list", "prompts", supports(|c| c.prompts.is_some())).await?,
+            resources: list("resources/list", "resources", supports(|c| c.resources.is_some())).await?,
+            capabilities,
+        };
+        client.terminate();
+
+        self.format.print(|| format_inspection(&inspection), || &inspection);
+        Ok(())
+    }
+}
+
+/// What a server reported about itself
+#[derive(Debug, serde::Serialize)]
+struct Inspection {
+    capabilities: Option<ServerCapabilities>,
+    tools: Vec<serde_json::Value>,
+    prompts: Vec<serde_json::Value>,
+    resources: Vec<serde_json::Value>,
+}
+
+fn format_inspection(inspection: &Inspection) -> String {
+    let str_field =
+        |value: &serde_json::Value, key: &str| value.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let mut out = String::new();
+    let capabilities = inspection.capabilities.as_ref().map(|c| {
+        [
+            ("tools", c.tools.is_some()),
+            ("prompts", c.prompts.is_some()),
+            ("resources", c.resources.is_some()),
+            ("logging", c.logging.is_some()),
+            ("completions", c.completions.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, supported)| supported.then_some(name))
+        .collect::<Vec<_>>()
+    });
+    out.push_str(&format!(
+        "Capabilities: {}\n",
+        capabilities.map_or("(unknown)".to_string(), |c| if c.is_empty() {
+            "(none)".to_string()
+        } else {
+            c.join(", ")
+        })
+    ));
+
+    out.push_str(&format!("\nTools ({}):\n", inspection.tools.len()));
+    for tool in &inspection.tools {
+        let description = str_field(tool, "description");
+        let summary = description.lines().next().unwrap_or_default();
+        out.push_str(&format!("  {}: {summary}\n", str_field(tool, "name")));
+        if let Some(schema) = tool.get("inputSchema") {
+            let schema = serde_json::to_string_pretty(schema).unwrap_or_default();
+            for line in schema.lines() {
+                out.push_str(&format!("      {line}\n"));
+            }
+        }
+    }
+
+    out.push_str(&format!("\nPrompts ({}):\n", inspection.prompts.len()));
+    for prompt in &inspection.prompts {
+        let arguments = prompt
+            .get("arguments")
+            .and_then(|a| a.as_array())
+            .map(|arguments| {
+                arguments
+                    .iter()
+                    .map(|arg| {
+                        let required = arg.get("required").and_then(|r| r.as_bool()).unwrap_or(false);
+                        format!("{}{}", str_field(arg, "name"), if required { "" } else { "?" })
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "  {}({arguments}): {}\n",
+            str_field(prompt, "name"),
+            str_field(prompt, "description")
+        ));
+    }
+
+    out.push_str(&format!("\nResources ({}):\n", inspection.resources.len()));
+    for resource in &inspection.resources {
+        out.push_str(&format!(
+            "  {} ({})\n",
+            str_field(resource, "uri"),
+            str_field(resource, "name")
+        ));
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct CallArgs {
+    /// Name of the server
+    pub name: String,
+    /// Name of the tool, as the server knows it
+    pub tool: String,
+    /// Arguments of the tool as a JSON object
+    #[arg(long)]
+    pub args: Option<String>,
+    /// Format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+    /// Print every JSON-RPC message exchanged with the server to stderr
+    #[arg(long)]
+    pub trace: bool,
+}
+
+impl CallArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<()> {
+        let arguments = match &self.args {
+            Some(args) => serde_json::from_str::<serde_json::Value>(args)?,
+            None => serde_json::json!({}),
+        };
+        if !arguments.is_object() {
+            bail!("--args must be a JSON object");
+        }
+
+        let client = connect(os, &self.name, self.trace).await?;
+        let resp = client
+            .request(
+                "tools/call",
+                Some(serde_json::json!({ "name": self.tool, "arguments": arguments })),
+            )
+            .await;
+        client.terminate();
+        let resp = resp?;
+        if let Some(error) = resp.error {
+            bail!("The call failed: {} ({})", error.message, error.code);
+        }
+        let result = resp.result.unwrap_or_default();
+
+        self.format
+            .print(|| serde_json::to_string_pretty(&result).unwrap_or_default(), || &result);
+        if result.get("isError").and_then(|e| e.as_bool()).unwrap_or(false) {
+            bail!("The tool reported an error");
+        }
+        Ok(())
+    }
+}
+
+/// Launches the configured server and initializes it
+async fn connect(os: &mut Os, name: &str, trace: bool) -> Result<CustomToolClient> {
+    let Some(ResolvedConfig { config, secrets }) = find_server_config(os, name).await? else {
+        bail!("No MCP server named '{name}' found in any scope/profile");
+    };
+    let mut client = CustomToolClient::from_config(name.to_string(), config, &os.database)?;
+    if trace {
+        client.set_tracer(Tracer::new(move |direction, msg| {
+            eprintln!("{}", trace_line(direction, msg, &secrets));
+        }));
+    }
+    client.init().await?;
+    Ok(client)
+}
+
+/// Renders a message exchanged with a server for `--trace`, with the secrets of its config masked
+fn trace_line(direction: TraceDirection, msg: &JsonRpcMessage, secrets: &[String]) -> String {
+    let arrow = match direction {
+        TraceDirection::Sent => "-->",
+        TraceDirection::Received => "<--",
+    };
+    // Secrets appear JSON escaped in the serialized message
+    let secrets = secrets
+        .iter()
+        .flat_map(|secret| {
+            let escaped = serde_json::to_string(secret).unwrap_or_default();
+            [secret.clone(), escaped.trim_matches('"').to_string()]
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "{arrow} {}",
+        mask(&serde_json::to_string(msg).unwrap_or_default(), &secrets)
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct LoginArgs {
+    /// Name of the server
+    pub name: String,
+}
+
+impl LoginArgs {
+    pub async fn execute(self, os: &mut Os, output: &mut impl Write) -> Result<()> {
+        let url = find_server_config(os, &self.name)
+            .await?
+            .and_then(|ResolvedConfig { config: cfg, .. }| match cfg.transport_type() {
+                TransportType::Http => cfg.url,
+                _ => None,
+            })
+            .ok_or_else(|| eyre::eyre!("No remote MCP server named '{}' using the http transport", self.name))?;
+
+        let client = crate::request::new_client()?;
+        let authorization = McpAuthorization::start(client, &url).await?;
+        if crate::util::open::open_url_async(&authorization.url).await.is_err() {
+            writeln!(output, "Open this URL: {}", authorization.url)?;
+        }
+
+        let mut spinner = Spinner::new(vec![
+            SpinnerComponent::Spinner,
+            SpinnerComponent::Text(format!(" Waiting for authorization of {}...", self.name)),
+        ]);
+        let token = tokio::select! {
+            res = authorization.finish(DEFAULT_AUTHORIZATION_TIMEOUT) => res,
+            Ok(_) = tokio::signal::ctrl_c() => {
+                spinner.stop_with_message("Cancelled".into());
+                return Ok(());
+            },
+        };
+        let token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                spinner.stop_with_message("Failed to log in".into());
+                return Err(e.into());
+            },
+        };
+        token.save(&os.database, &self.name).await?;
+        spinner.stop_with_message(format!("Logged in to {}", self.name));
+        writeln!(output)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct LogoutArgs {
+    /// Name of the server
+    pub name: String,
+}
+
+impl LogoutArgs {
+    pub async fn execute(self, os: &mut Os, output: &mut impl Write) -> Result<()> {
+        if !McpToken::delete(&os.database, &self.name).await? {
+            writeln!(output, "\nNot logged in to MCP server '{}'\n", self.name)?;
+            return Ok(());
+        }
+        writeln!(output, "\n✓ Logged out of MCP server '{}'\n", self.name)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ServeArgs {
+    /// Name of the agent whose tools and permissions apply. Defaults to the default agent.
+    #[arg(long)]
+    pub agent: Option<String>,
+    /// Refuse every tool use that could make changes
+    #[arg(long)]
+    pub read_only: bool,
+    /// Run the tool uses that the agent would ask about instead of refusing them
+    #[arg(long)]
+    pub trust_all_tools: bool,
+}
+
+impl ServeArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<()> {
+        let mut stderr = std::io::stderr();
+        let agents = Agents::load(os, self.agent.as_deref(), true, &mut stderr).await;
+        let agent = match &self.agent {
+            Some(name) => agents
+                .agents
+                .get(name)
+                .cloned()
+                .ok_or(eyre::eyre!("No agent named {name}"))?,
+            None => agents.get_active().cloned().unwrap_or_default(),
+        };
+
+        let handler = NativeToolServer::new(os.clone(), agent, self.read_only, self.trust_all_tools)?;
+        let server = Server::new(handler, tokio::io::stdin(), tokio::io::stdout())?;
+        // Returns once the client closes stdin
+        server.init()?.await??;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, clap::Subcommand)]
+pub enum SecretSubcommand {
+    /// Store a secret. Its value is asked for in the terminal, or read from stdin.
+    Set {
+        /// Name of the secret
+        name: String,
+    },
+    /// Delete a secret
+    #[command(alias = "rm")]
+    Remove {
+        /// Name of the secret
+        name: String,
+    },
+    /// List the names of the stored secrets
+    List,
+}
+
+impl SecretSubcommand {
+    pub async fn execute(self, os: &Os, output: &mut impl Write) -> Result<()> {
+        match self {
+            Self::Set { name } => {
+                if !is_valid_secret_name(&name) {
+                    bail!("Invalid secret name '{name}'. Names may only contain letters, digits, '-', '_' and '.'");
+                }
+                let value = read_secret_value(&name)?;
+                if value.is_empty() {
+                    bail!("The value of a secret cannot be empty");
+                }
+                os.database.set_secret(&secret_key(&name), &value).await?;
+                writeln!(
+                    output,
+                    "\n✓ Stored secret '{name}', reference it in server configs as ${{secret:{name}}}\n"
+                )?;
+            },
+            Self::Remove { name } => {
+                if os.database.get_secret(&secret_key(&name)).await?.is_none() {
+                    writeln!(output, "\nNo secret named '{name}' found\n")?;
+                    return Ok(());
+                }
+                os.database.delete_secret(&secret_key(&name)).await?;
+                writeln!(output, "\n✓ Removed secret '{name}'\n")?;
+            },
+            Self::List => {
+                let names = secret_names(os).await?;
+                if names.is_empty() {
+                    writeln!(output, "No secrets stored.\n")?;
+                }
+                for name in names {
+                    writeln!(output, "  • {name}")?;
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+fn read_secret_value(name: &str) -> Result<String> {
+    if std::io::stdin().is_terminal() {
+        return Ok(dialoguer::Password::with_theme(&crate::util::dialoguer_theme())
+            .with_prompt(format!("Value of {name}"))
+            .interact()?);
+    }
+    let mut value = String::new();
+    std::io::stdin().read_to_string(&mut value)?;
+    Ok(value.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Finds the config of a server in any agent, with its `${...}` references resolved
+async fn find_server_config(os: &mut Os, name: &str) -> Result<Option<ResolvedConfig>> {
+    let config = get_mcp_server_configs(os, None)
+        .await?
+        .into_iter()
+        .find_map(|(_, _, cfg)| cfg?.mcp_servers.get(name).cloned());
+    match config {
+        Some(config) => Ok(Some(resolve(os, &config).await?)),
+        None => Ok(None),
+    }
+}
+
 async fn get_mcp_server_configs(
     os: &mut Os,
     scope: Option<Scope>,
@@ -326,20 +848,16 @@ async fn get_mcp_server_configs(
     let agents = Agents::load(os, None, true, &mut stderr).await;
     let global_path = directories::chat_global_agent_path(os)?;
     for (_, agent) in agents.agents {
-        let scope = if agent
-            .path
-            .as_ref()
-            .is_some_and(|p| p.parent().is_some_and(|p| p == global_path))
-        {
+        // The built-in default agent has no file and thus no servers configured
+        let Some(path) = agent.path else {
+            continue;
+        };
+        let scope = if path.parent().is_some_and(|p| p == global_path) {
             Scope::Global
         } else {
             Scope::Workspace
         };
-        results.push((
-            scope,
-            agent.path.ok_or(eyre::eyre!("Agent missing path info"))?,
-            Some(agent.mcp_servers),
-        ));
+        results.push((scope, path, Some(agent.mcp_servers)));
     }
     Ok(results)
 }
@@ -399,6 +917,13 @@ fn parse_env_vars(arg: &str) -> Result<HashMap<String, String>> {
     Ok(vars)
 }
 
+fn parse_header(arg: &str) -> Result<(String, String)> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => bail!("Failed to parse header '{}'. Expected 'name=value'", arg),
+    }
+}
+
 async fn load_cfg(os: &Os, p: &PathBuf) -> Result<McpServerConfig> {
     Ok(if os.fs.exists(p) {
         McpServerConfig::load_from_file(os, p).await?
@@ -455,7 +980,10 @@ mod tests {
         // 1. add
         AddArgs {
             name: "local".into(),
-            command: "echo hi".into(),
+            command: Some("echo hi".into()),
+            url: None,
+            transport: None,
+            headers: vec![],
             args: vec![
                 "awslabs.eks-mcp-server".to_string(),
                 "--allow-write".to_string(),
@@ -506,7 +1034,10 @@ mod tests {
             ],
             RootSubcommand::Mcp(McpSubcommand::Add(AddArgs {
                 name: "test_server".to_string(),
-                command: "test_command".to_string(),
+                command: Some("test_command".to_string()),
+                url: None,
+                transport: None,
+                headers: vec![],
                 args: vec![
                     "awslabs.eks-mcp-server".to_string(),
                     "--allow-write".to_string(),
@@ -528,6 +1059,108 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mcp_subcomman_add_url() {
+        assert_parse!(
+            [
+                "mcp",
+                "add",
+                "--name",
+                "remote",
+                "--url",
+                "https://example.com/mcp",
+                "--header",
+                "Authorization=Bearer abc=",
+                "--transport",
+                "sse"
+            ],
+            RootSubcommand::Mcp(McpSubcommand::Add(AddArgs {
+                name: "remote".to_string(),
+                command: None,
+                url: Some("https://example.com/mcp".to_string()),
+                transport: Some(TransportType::Sse),
+                headers: vec![("Authorization".to_string(), "Bearer abc=".to_string())],
+                args: vec![],
+                agent: None,
+                env: vec![],
+                timeout: None,
+                disabled: false,
+                force: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_mcp_subcommand_login_logout() {
+        assert_parse!(
+            ["mcp", "login", "tickets"],
+            RootSubcommand::Mcp(McpSubcommand::Login(LoginArgs { name: "tickets".into() }))
+        );
+        assert_parse!(
+            ["mcp", "logout", "tickets"],
+            RootSubcommand::Mcp(McpSubcommand::Logout(LogoutArgs { name: "tickets".into() }))
+        );
+    }
+
+    #[test]
+    fn test_mcp_subcommand_inspect_call() {
+        assert_parse!(
+            ["mcp", "inspect", "git", "--format", "json", "--trace"],
+            RootSubcommand::Mcp(McpSubcommand::Inspect(InspectArgs {
+                name: "git".into(),
+                format: OutputFormat::Json,
+                trace: true,
+            }))
+        );
+        assert_parse!(
+            ["mcp", "call", "git", "git_status", "--args", r#"{"repo":"."}"#],
+            RootSubcommand::Mcp(McpSubcommand::Call(CallArgs {
+                name: "git".into(),
+                tool: "git_status".into(),
+                args: Some(r#"{"repo":"."}"#.into()),
+                format: OutputFormat::Plain,
+                trace: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_format_inspection() {
+        let inspection = Inspection {
+            capabilities: serde_json::from_value(serde_json::json!({ "tools": {}, "prompts": {} })).ok(),
+            tools: vec![serde_json::json!({
+                "name": "git_status",
+                "description": "Show the working tree status\nMore details",
+                "inputSchema": { "type": "object" }
+            })],
+            prompts: vec![serde_json::json!({
+                "name": "review",
+                "description": "Review a change",
+                "arguments": [{ "name": "ref", "required": true }, { "name": "focus" }]
+            })],
+            resources: vec![],
+        };
+        let text = format_inspection(&inspection);
+        assert!(text.starts_with("Capabilities: tools, prompts\n"));
+        assert!(text.contains("  git_status: Show the working tree status\n"));
+        assert!(!text.contains("More details"));
+        assert!(text.contains("\"type\": \"object\""));
+        assert!(text.contains("  review(ref, focus?): Review a change\n"));
+        assert!(text.contains("Resources (0):\n"));
+    }
+
+    #[test]
+    fn test_mcp_subcommand_serve() {
+        assert_parse!(
+            ["mcp", "serve", "--agent", "reviewer", "--read-only"],
+            RootSubcommand::Mcp(McpSubcommand::Serve(ServeArgs {
+                agent: Some("reviewer".into()),
+                read_only: true,
+                trust_all_tools: false,
+            }))
+        );
+    }
+
     #[test]
     fn test_mcp_subcomman_remove_workspace() {
         assert_parse!(
@@ -544,13 +1177,31 @@ mod tests {
         assert_parse!(
             ["mcp", "import", "--file", "servers.json", "--force"],
             RootSubcommand::Mcp(McpSubcommand::Import(ImportArgs {
-                file: "servers.json".into(),
+                file: Some("servers.json".into()),
+                from: None,
                 scope: None,
                 force: true,
             }))
         );
     }
 
+    #[test]
+    fn test_mcp_subcommand_import_from() {
+        assert_parse!(
+            ["mcp", "import", "--from", "vscode", "global"],
+            RootSubcommand::Mcp(McpSubcommand::Import(ImportArgs {
+                file: None,
+                from: Some(ImportSource::Vscode),
+                scope: Some(Scope::Global),
+                force: false,
+            }))
+        );
+        assert!(
+            <crate::cli::Cli as clap::Parser>::try_parse_from([crate::util::CHAT_BINARY_NAME, "mcp", "import"])
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_mcp_subcommand_status_simple() {
         assert_parse!(
@@ -559,6 +1210,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mcp_subcommand_secret() {
+        assert_parse!(
+            ["mcp", "secret", "set", "gh-token"],
+            RootSubcommand::Mcp(McpSubcommand::Secret(SecretSubcommand::Set {
+                name: "gh-token".into()
+            }))
+        );
+        assert_parse!(
+            ["mcp", "secret", "rm", "gh-token"],
+            RootSubcommand::Mcp(McpSubcommand::Secret(SecretSubcommand::Remove {
+                name: "gh-token".into()
+            }))
+        );
+        assert_parse!(
+            ["mcp", "secret", "list"],
+            RootSubcommand::Mcp(McpSubcommand::Secret(SecretSubcommand::List))
+        );
+    }
+
     #[test]
     fn test_mcp_subcommand_list() {
         assert_parse!(
@@ -569,4 +1240,24 @@ mod tests {
             }))
         );
     }
+
+    #[test]
+    fn test_trace_line() {
+        let msg = serde_json::from_value::<JsonRpcMessage>(serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/message",
+            "params": { "token": "abc123", "quoted": "p\"w" }
+        }))
+        .unwrap();
+        let line = trace_line(TraceDirection::Received, &msg, &[
+            "abc123".to_string(),
+            "p\"w".to_string(),
+        ]);
+        assert!(line.starts_with("<-- "));
+        assert!(!line.contains("abc123") && !line.contains("p\\\"w"), "{line}");
+        assert!(
+            line.contains(r#""token":"****""#) && line.contains(r#""quoted":"****""#),
+            "{line}"
+        );
+    }
 }
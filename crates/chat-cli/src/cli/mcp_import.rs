@@ -0,0 +1,440 @@
+//! Translates the mcp server configs of other mcp clients into ours, for `q mcp import --from`.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use clap::ValueEnum;
+use eyre::{
+    Result,
+    bail,
+};
+use serde::Deserialize;
+use serde_json::{
+    Value,
+    json,
+};
+
+use crate::cli::chat::server_config_vars::interpolate;
+use crate::cli::chat::tools::custom_tool::{
+    CustomToolConfig,
+    TransportType,
+    default_timeout,
+};
+use crate::os::Os;
+use crate::util::MCP_SERVER_TOOL_DELIMITER;
+use crate::util::directories::home_dir;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ImportSource {
+    /// The claude_desktop_config.json of Claude Desktop
+    ClaudeDesktop,
+    /// The .vscode/mcp.json of the current workspace, with its `servers` and `inputs`
+    Vscode,
+    /// The .cursor/mcp.json of the current workspace, or else ~/.cursor/mcp.json
+    Cursor,
+    /// The .mcp.json at the root of the current project
+    McpJson,
+}
+
+impl ImportSource {
+    /// Where the client keeps its config, which is read unless another file is given
+    pub fn default_path(&self, os: &Os) -> Result<PathBuf> {
+        let cwd = os.env.current_dir()?;
+        Ok(match self {
+            ImportSource::ClaudeDesktop => {
+                let config_dir = if cfg!(target_os = "macos") {
+                    home_dir(os)?.join("Library").join("Application Support")
+                } else if cfg!(windows) {
+                    home_dir(os)?.join("AppData").join("Roaming")
+                } else {
+                    home_dir(os)?.join(".config")
+                };
+                config_dir.join("Claude").join("claude_desktop_config.json")
+            },
+            ImportSource::Vscode => cwd.join(".vscode").join("mcp.json"),
+            ImportSource::Cursor => {
+                let workspace = cwd.join(".cursor").join("mcp.json");
+                if os.fs.exists(&workspace) {
+                    workspace
+                } else {
+                    home_dir(os)?.join(".cursor").join("mcp.json")
+                }
+            },
+            ImportSource::McpJson => cwd.join(".mcp.json"),
+        })
+    }
+
+    /// The field that holds the servers
+    fn servers_key(&self) -> &'static str {
+        match self {
+            ImportSource::Vscode => "servers",
+            _ => "mcpServers",
+        }
+    }
+}
+
+/// A value that VS Code asks the user for when a server refers to it as `${input:<id>}`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Input {
+    pub id: String,
+    /// `promptString` or `pickString`
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub password: bool,
+    pub default: Option<String>,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// A server entry in any of the supported formats
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ForeignServer {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    command: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    env: Option<HashMap<String, String>>,
+    env_file: Option<String>,
+    url: Option<String>,
+    headers: Option<HashMap<String, String>>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub servers: HashMap<String, CustomToolConfig>,
+    /// The names of the servers that could not be imported, and why
+    pub skipped: Vec<(String, String)>,
+    /// The answers to password inputs by secret name, to be stored with the other secrets. The
+    /// servers reference them as `${secret:<name>}` instead of containing them.
+    pub secrets: HashMap<String, String>,
+}
+
+/// Translates the servers of a config in the format of `source`. `${input:<id>}` placeholders are
+/// replaced with what `ask` answers for the input, or with a reference to a secret holding the
+/// answer for password inputs, and `${workspaceFolder}` with `workspace`.
+pub fn translate(
+    source: ImportSource,
+    content: &str,
+    workspace: &Path,
+    ask: &mut dyn FnMut(&Input) -> Result<String>,
+) -> Result<Imported> {
+    let config = serde_json::from_str::<Value>(content)?;
+    let Some(servers) = config.get(source.servers_key()).and_then(Value::as_object) else {
+        bail!("No {} found in config", source.servers_key());
+    };
+    let inputs = config
+        .get("inputs")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|input| serde_json::from_value::<Input>(input.clone()).ok())
+        .map(|input| (input.id.clone(), input))
+        .collect::<HashMap<_, _>>();
+
+    let mut answers = HashMap::<String, String>::new();
+    let mut imported = Imported::default();
+    for (name, server) in servers {
+        let result = serde_json::from_value::<ForeignServer>(server.clone())
+            .map_err(|e| eyre::eyre!("not a valid server config: {e}"))
+            .and_then(|server| {
+                let mut resolve = |value: &str| {
+                    resolve_placeholders(value, workspace, &inputs, &mut answers, &mut imported.secrets, ask)
+                };
+                translate_server(name, server, &mut resolve)
+            });
+        match result {
+            Ok(config) => {
+                imported.servers.insert(name.clone(), config);
+            },
+            Err(e) => imported.skipped.push((name.clone(), e.to_string())),
+        }
+    }
+    imported.skipped.sort();
+    Ok(imported)
+}
+
+fn translate_server(
+    name: &str,
+    server: ForeignServer,
+    resolve: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<CustomToolConfig> {
+    if name.contains(MCP_SERVER_TOOL_DELIMITER) {
+        bail!("server names cannot contain {MCP_SERVER_TOOL_DELIMITER}");
+    }
+    if server.env_file.is_some() {
+        bail!("envFile is not supported, set the variables in env instead");
+    }
+    let transport = match (server.kind.as_deref(), &server.url) {
+        (Some("stdio"), _) | (None, None) => TransportType::Stdio,
+        (Some("http" | "streamable-http" | "streamableHttp"), _) => TransportType::Http,
+        (Some("sse"), _) => TransportType::Sse,
+        (Some("ws" | "websocket"), _) => TransportType::Websocket,
+        // Clients that do not say guess from the url
+        (None, Some(url)) if url.trim_end_matches('/').ends_with("/sse") => TransportType::Sse,
+        (None, Some(_)) => TransportType::Http,
+        (Some(kind), _) => bail!("the {kind} type is not supported"),
+    };
+    // Checked before any input is asked for
+    match (transport, &server.command, &server.url) {
+        (TransportType::Stdio, Some(command), _) if !command.is_empty() => {},
+        (TransportType::Stdio, _, _) => bail!("a command is required for servers using the stdio transport"),
+        (_, _, None) => bail!("a url is required for servers using the {transport:?} transport"),
+        _ => {},
+    }
+    let mut resolve_map = |map: Option<HashMap<String, String>>| -> Result<Option<HashMap<String, String>>> {
+        map.map(|map| {
+            map.into_iter()
+                .map(|(key, value)| Ok((key, resolve(&value)?)))
+                .collect::<Result<HashMap<_, _>>>()
+        })
+        .transpose()
+    };
+    let env = resolve_map(server.env)?;
+    let headers = resolve_map(server.headers)?;
+
+    let config = match transport {
+        TransportType::Stdio => json!({
+            "command": resolve(&server.command.unwrap_or_default())?,
+            "args": server.args.iter().map(|arg| resolve(arg)).collect::<Result<Vec<_>>>()?,
+            "env": env,
+            "timeout": default_timeout(),
+            "disabled": server.disabled,
+        }),
+        transport => json!({
+            "url": resolve(&server.url.unwrap_or_default())?,
+            "headers": headers,
+            "transport": transport,
+            "timeout": default_timeout(),
+            "disabled": server.disabled,
+        }),
+    };
+    Ok(serde_json::from_value(config)?)
+}
+
+/// Replaces `${input:<id>}` and `${workspaceFolder}`, leaving other placeholders as they are.
+/// Answers to password inputs are added to `secrets` and replaced with a reference to the secret.
+fn resolve_placeholders(
+    value: &str,
+    workspace: &Path,
+    inputs: &HashMap<String, Input>,
+    answers: &mut HashMap<String, String>,
+    secrets: &mut HashMap<String, String>,
+    ask: &mut dyn FnMut(&Input) -> Result<String>,
+) -> Result<String> {
+    interpolate(value, |placeholder| {
+        if placeholder == "workspaceFolder" {
+            return Ok(Some(workspace.to_string_lossy().to_string()));
+        }
+        let Some(id) = placeholder.strip_prefix("input:") else {
+            return Ok(None);
+        };
+        if !answers.contains_key(id) {
+            let Some(input) = inputs.get(id) else {
+                bail!("the input {id} is not defined");
+            };
+            let mut answer = ask(input)?;
+            if input.password && !answer.is_empty() {
+                let name = secret_name(id);
+                let reference = format!("${{secret:{name}}}");
+                secrets.insert(name, std::mem::replace(&mut answer, reference));
+            }
+            answers.insert(id.to_string(), answer);
+        }
+        Ok(answers.get(id).cloned())
+    })
+}
+
+/// The name of the secret that holds the answer to the password input `id`
+fn secret_name(id: &str) -> String {
+    id.chars()
+        .map(|c| match c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            true => c,
+            false => '-',
+        })
+        .collect()
+}
+
+/// Asks the user for the value of an input in the terminal
+pub fn ask_input(input: &Input) -> Result<String> {
+    use std::io::IsTerminal;
+
+    if !std::io::stdin().is_terminal() {
+        bail!("the input {} can only be entered in a terminal", input.id);
+    }
+    let prompt = input.description.clone().unwrap_or_else(|| input.id.clone());
+    let theme = crate::util::dialoguer_theme();
+    if input.kind == "pickString" && !input.options.is_empty() {
+        let default = input
+            .default
+            .as_ref()
+            .and_then(|default| input.options.iter().position(|option| option == default))
+            .unwrap_or(0);
+        let choice = dialoguer::Select::with_theme(&theme)
+            .with_prompt(prompt)
+            .items(&input.options)
+            .default(default)
+            .interact()?;
+        return Ok(input.options[choice].clone());
+    }
+    if input.password {
+        return Ok(dialoguer::Password::with_theme(&theme)
+            .with_prompt(prompt)
+            .allow_empty_password(true)
+            .interact()?);
+    }
+    let mut text = dialoguer::Input::<String>::with_theme(&theme)
+        .with_prompt(prompt)
+        .allow_empty(true);
+    if let Some(default) = &input.default {
+        text = text.default(default.clone());
+    }
+    Ok(text.interact_text()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate_with(source: ImportSource, config: Value, answers: &[(&str, &str)]) -> Imported {
+        let mut ask = |input: &Input| {
+            answers
+                .iter()
+                .find(|(id, _)| *id == input.id)
+                .map(|(_, answer)| (*answer).to_string())
+                .ok_or_else(|| eyre::eyre!("no answer for {}", input.id))
+        };
+        translate(source, &config.to_string(), Path::new("/work"), &mut ask).unwrap()
+    }
+
+    #[test]
+    fn test_translate_claude_desktop() {
+        let imported = translate_with(
+            ImportSource::ClaudeDesktop,
+            json!({
+                "mcpServers": {
+                    "fs": {
+                        "command": "npx",
+                        "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"],
+                        "env": { "DEBUG": "1" }
+                    },
+                    "broken": { "args": ["x"] },
+                    "a/b": { "command": "ab" }
+                }
+            }),
+            &[],
+        );
+        let fs = &imported.servers["fs"];
+        assert_eq!(fs.command, "npx");
+        assert_eq!(fs.args.len(), 3);
+        assert_eq!(fs.env.as_ref().unwrap()["DEBUG"], "1");
+        assert_eq!(fs.transport_type(), TransportType::Stdio);
+        assert_eq!(
+            imported
+                .skipped
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            ["a/b", "broken"]
+        );
+        assert!(imported.skipped[1].1.contains("command is required"));
+    }
+
+    #[test]
+    fn test_translate_vscode() {
+        let imported = translate_with(
+            ImportSource::Vscode,
+            json!({
+                "inputs": [
+                    { "type": "promptString", "id": "api-key", "description": "API key", "password": true },
+                    { "type": "promptString", "id": "region", "description": "Region" }
+                ],
+                "servers": {
+                    "github": {
+                        "type": "http",
+                        "url": "https://api.example.com/mcp",
+                        "headers": { "Authorization": "Bearer ${input:api-key}" }
+                    },
+                    "local": {
+                        "type": "stdio",
+                        "command": "${workspaceFolder}/bin/server",
+                        "args": ["--token", "${input:api-key}", "${env:HOME}", "x${input:api-key"]
+                    },
+                    "events": {
+                        "type": "sse",
+                        "url": "https://events.example.com/sse",
+                        "headers": { "X-Region": "${input:region}" }
+                    },
+                    "missing": { "command": "x", "env": { "TOKEN": "${input:other}" } },
+                    "dotenv": { "command": "x", "envFile": "${workspaceFolder}/.env" },
+                    "custom": { "type": "carrier-pigeon", "command": "x" }
+                }
+            }),
+            &[("api-key", "secret"), ("region", "eu-west-1")],
+        );
+        // Password answers are stored as secrets rather than written into the config
+        assert_eq!(
+            imported.secrets,
+            HashMap::from([("api-key".to_string(), "secret".to_string())])
+        );
+        let github = &imported.servers["github"];
+        assert_eq!(github.transport_type(), TransportType::Http);
+        assert_eq!(
+            github.headers.as_ref().unwrap()["Authorization"],
+            "Bearer ${secret:api-key}"
+        );
+        let local = &imported.servers["local"];
+        assert_eq!(local.command, "/work/bin/server");
+        assert_eq!(local.args, [
+            "--token",
+            "${secret:api-key}",
+            "${env:HOME}",
+            "x${input:api-key"
+        ]);
+        let events = &imported.servers["events"];
+        assert_eq!(events.transport_type(), TransportType::Sse);
+        assert_eq!(events.headers.as_ref().unwrap()["X-Region"], "eu-west-1");
+
+        let skipped = imported.skipped.iter().cloned().collect::<HashMap<_, _>>();
+        assert_eq!(skipped.len(), 3);
+        assert!(skipped["missing"].contains("input other is not defined"));
+        assert!(skipped["dotenv"].contains("envFile"));
+        assert!(skipped["custom"].contains("carrier-pigeon"));
+    }
+
+    #[test]
+    fn test_translate_cursor() {
+        let imported = translate_with(
+            ImportSource::Cursor,
+            json!({
+                "mcpServers": {
+                    "remote": { "url": "https://mcp.example.com/sse" },
+                    "streamable": { "url": "https://mcp.example.com/mcp", "headers": { "X-Key": "k" } }
+                }
+            }),
+            &[],
+        );
+        assert_eq!(imported.servers["remote"].transport_type(), TransportType::Sse);
+        assert_eq!(imported.servers["streamable"].transport_type(), TransportType::Http);
+        assert!(imported.skipped.is_empty());
+
+        let mut ask = |_: &Input| Ok(String::new());
+        assert!(
+            translate(
+                ImportSource::Vscode,
+                r#"{ "mcpServers": {} }"#,
+                Path::new("/"),
+                &mut ask
+            )
+            .is_err()
+        );
+    }
+}
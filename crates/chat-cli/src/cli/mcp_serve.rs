@@ -0,0 +1,321 @@
+//! Serves the native tools over stdio as an mcp server, with the permissions of an agent.
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde_json::{
+    Value,
+    json,
+};
+
+use super::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::api_client::model::{
+    ImageFormat,
+    ImageSource,
+};
+use crate::cli::chat::tools::code_intel::LanguageServers;
+use crate::cli::chat::tools::knowledge::Knowledge;
+use crate::cli::chat::tools::thinking::Thinking;
+use crate::cli::chat::tools::{
+    OutputKind,
+    Tool,
+    ToolSpec,
+};
+use crate::cli::chat::util::images::RichImageBlocks;
+use crate::mcp_client::{
+    JsonRpcRequest,
+    JsonRpcResponse,
+    PreServerRequestHandler,
+    Response,
+    ServerError,
+    ServerRequestHandler,
+};
+use crate::os::Os;
+use crate::util::CLI_BINARY_NAME;
+
+const PROTOCOL_VERSION: &str = "2025-03-26";
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2024-11-05", "2025-03-26", "2025-06-18"];
+
+/// Native tools that are never served, because they only make sense in a chat session
+const UNSERVED_TOOLS: [&str; 2] = ["dummy", "gh_issue"];
+
+pub struct NativeToolServer {
+    os: Os,
+    agent: Agent,
+    /// Refuse every tool use that could change something
+    read_only: bool,
+    /// Run the tools the agent would ask about, instead of refusing them
+    trust_all_tools: bool,
+    tools: HashMap<String, ToolSpec>,
+    language_servers: LanguageServers,
+}
+
+impl NativeToolServer {
+    pub fn new(os: Os, agent: Agent, read_only: bool, trust_all_tools: bool) -> eyre::Result<Self> {
+        let is_included = |name: &str| {
+            agent.tools.len() == 1 && agent.tools.first().is_some_and(|n| n == "*")
+                || agent.tools.iter().any(|n| n == name)
+        };
+        let mut tools = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("chat/tools/tool_index.json"))?
+            .into_iter()
+            .filter(|(key, _)| !UNSERVED_TOOLS.contains(&key.as_str()) && is_included(key))
+            .map(|(_, spec)| (spec.name.clone(), spec))
+            .collect::<HashMap<_, _>>();
+        if !Thinking::is_enabled(&os) {
+            tools.remove("thinking");
+        }
+        if !Knowledge::is_enabled(&os) {
+            tools.remove("knowledge");
+        }
+        if read_only {
+            tools.remove("fs_write");
+        }
+
+        Ok(Self {
+            os,
+            language_servers: LanguageServers::new(&agent),
+            agent,
+            read_only,
+            trust_all_tools,
+            tools,
+        })
+    }
+
+    fn list_tools(&self) -> Value {
+        let mut tools = self.tools.values().collect::<Vec<_>>();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        let tools = tools
+            .into_iter()
+            .map(|spec| {
+                json!({
+                    "name": spec.name,
+                    "description": spec.description,
+                    "inputSchema": spec.input_schema.0,
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "tools": tools })
+    }
+
+    /// Runs a tool use if the agent allows it. Refusals and failures are reported to the client as
+    /// tool errors rather than protocol errors, so that the model gets to see them.
+    async fn call_tool(&self, params: Option<Value>) -> Value {
+        let name = params.as_ref().and_then(|p| p.get("name")).and_then(Value::as_str);
+        let args = params
+            .as_ref()
+            .and_then(|p| p.get("arguments"))
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        let result = match name {
+            Some(name) => self.run(name, &args).await,
+            None => Err("Missing tool name".to_string()),
+        };
+        match result {
+            Ok(content) => json!({ "content": content, "isError": false }),
+            Err(e) => json!({ "content": [{ "type": "text", "text": e }], "isError": true }),
+        }
+    }
+
+    async fn run(&self, name: &str, args: &Value) -> Result<Vec<Value>, String> {
+        if !self.tools.contains_key(name) {
+            return Err(format!("No tool named {name}"));
+        }
+        let mut tool = match Tool::native(name, args) {
+            Some(Ok(tool)) => tool,
+            Some(Err(e)) => return Err(format!("Invalid arguments for {name}: {e}")),
+            None => return Err(format!("No tool named {name}")),
+        };
+        if self.read_only && !tool.is_read_only() {
+            return Err(format!(
+                "This use of {name} could make changes, which the server does not allow in read-only mode"
+            ));
+        }
+        match tool.requires_acceptance(&self.agent) {
+            PermissionEvalResult::Allow => {},
+            PermissionEvalResult::Ask if self.trust_all_tools => {},
+            PermissionEvalResult::Ask => {
+                return Err(format!(
+                    "The agent {} requires approval for this use of {name}. Add it to the allowedTools of the agent to trust it",
+                    self.agent.name
+                ));
+            },
+            PermissionEvalResult::Deny => {
+                return Err(format!(
+                    "This use of {name} is denied by the settings of the agent {}",
+                    self.agent.name
+                ));
+            },
+        }
+        tool.validate(&self.os).await.map_err(|e| e.to_string())?;
+
+        // Whatever the tool prints for the chat must not end up in the protocol stream
+        let mut sink = Vec::new();
+        let output = tool
+            .invoke(&self.os, &mut sink, &self.language_servers)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(content(output.output))
+    }
+}
+
+/// Converts the output of a tool into mcp content blocks
+fn content(output: OutputKind) -> Vec<Value> {
+    let text = |text: String| json!({ "type": "text", "text": text });
+    let images = |images: RichImageBlocks| {
+        images
+            .into_iter()
+            .filter_map(|(block, _)| match block.source {
+                ImageSource::Bytes(bytes) => Some(json!({
+                    "type": "image",
+                    "data": STANDARD.encode(bytes),
+                    "mimeType": mime_type(&block.format),
+                })),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    match output {
+        OutputKind::Text(t) => vec![text(t)],
+        OutputKind::Json(json) => vec![text(json.to_string())],
+        OutputKind::Images(blocks) => images(blocks),
+        OutputKind::Mixed {
+            text: t,
+            images: blocks,
+        } => {
+            let mut content = vec![text(t)];
+            content.extend(images(blocks));
+            content
+        },
+    }
+}
+
+fn mime_type(format: &ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Png => "image/png",
+        ImageFormat::Webp => "image/webp",
+    }
+}
+
+impl PreServerRequestHandler for NativeToolServer {
+    // The server never makes requests of its own
+    fn register_pending_request_callback(
+        &mut self,
+        _cb: impl Fn(u64) -> Option<JsonRpcRequest> + Send + Sync + 'static,
+    ) {
+    }
+
+    fn register_send_request_callback(
+        &mut self,
+        _cb: impl Fn(&str, Option<Value>) -> Result<(), ServerError> + Send + Sync + 'static,
+    ) {
+    }
+}
+
+#[async_trait::async_trait]
+impl ServerRequestHandler for NativeToolServer {
+    async fn handle_initialize(&self, params: Option<Value>) -> Result<Response, ServerError> {
+        let protocol_version = params
+            .as_ref()
+            .and_then(|p| p.get("protocolVersion"))
+            .and_then(Value::as_str)
+            .filter(|version| SUPPORTED_PROTOCOL_VERSIONS.contains(version))
+            .unwrap_or(PROTOCOL_VERSION);
+        Ok(Some(json!({
+            "protocolVersion": protocol_version,
+            "capabilities": { "tools": {} },
+            "serverInfo": {
+                "name": format!("{}-{}", CLI_BINARY_NAME, self.agent.name),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        })))
+    }
+
+    async fn handle_incoming(&self, method: &str, params: Option<Value>) -> Result<Response, ServerError> {
+        match method {
+            "ping" => Ok(Some(json!({}))),
+            "tools/list" => Ok(Some(self.list_tools())),
+            "tools/call" => Ok(Some(self.call_tool(params).await)),
+            method if method.starts_with("notifications/") => Ok(None),
+            method => Err(ServerError::MethodNotFound(method.to_string())),
+        }
+    }
+
+    async fn handle_response(&self, _resp: JsonRpcResponse) -> Result<(), ServerError> {
+        Ok(())
+    }
+
+    async fn handle_shutdown(&self) -> Result<(), ServerError> {
+        self.language_servers.shutdown_all().await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn server(read_only: bool) -> NativeToolServer {
+        let os = Os::new().await.unwrap();
+        os.fs.write("/notes.txt", "hello\nworld").await.unwrap();
+        NativeToolServer::new(os, Agent::default(), read_only, false).unwrap()
+    }
+
+    fn tool_names(list: &Value) -> Vec<&str> {
+        list["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_list_tools() {
+        let names = tool_names(&server(false).await.list_tools()).join(",");
+        assert!(names.contains("fs_read"));
+        assert!(names.contains("fs_write"));
+        assert!(!names.contains("dummy"));
+        assert!(!names.contains("report_issue"));
+
+        let list = server(true).await.list_tools();
+        assert!(!tool_names(&list).contains(&"fs_write"));
+        assert!(list["tools"][0]["inputSchema"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_call_tool() {
+        let server = server(true).await;
+        let call =
+            |name: &str, arguments: Value| server.call_tool(Some(json!({ "name": name, "arguments": arguments })));
+
+        let result = call(
+            "fs_read",
+            json!({ "operations": [{ "path": "/notes.txt", "mode": "Line" }] }),
+        )
+        .await;
+        assert_eq!(result["isError"], false);
+        assert_eq!(result["content"][0]["text"], "hello\nworld");
+
+        // Hidden in read-only mode
+        let result = call(
+            "fs_write",
+            json!({ "command": "create", "path": "/new.txt", "file_text": "hi" }),
+        )
+        .await;
+        assert_eq!(result["isError"], true);
+        assert!(!server.os.fs.exists("/new.txt"));
+
+        let result = call("execute_bash", json!({ "command": "rm /notes.txt" })).await;
+        assert_eq!(result["isError"], true);
+        assert!(result["content"][0]["text"].as_str().unwrap().contains("read-only"));
+
+        let result = call("fs_read", json!({ "nope": true })).await;
+        assert_eq!(result["isError"], true);
+    }
+}
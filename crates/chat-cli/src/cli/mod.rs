@@ -1,10 +1,14 @@
 mod agent;
+mod audit;
 mod chat;
 mod debug;
 mod diagnostics;
 mod feed;
+mod history;
 mod issue;
 mod mcp;
+mod mcp_import;
+mod mcp_serve;
 mod settings;
 mod user;
 
@@ -17,7 +21,10 @@ use std::process::ExitCode;
 
 use agent::AgentArgs;
 use anstream::println;
-pub use chat::ConversationState;
+pub use chat::{
+    ConversationState,
+    FileCheckpoint,
+};
 use clap::{
     ArgAction,
     CommandFactory,
@@ -116,6 +123,11 @@ pub enum RootSubcommand {
     /// Model Context Protocol (MCP)
     #[command(subcommand)]
     Mcp(McpSubcommand),
+    /// Query the local audit log of tool invocations
+    Audit(audit::AuditArgs),
+    /// Search and manage saved chat conversations
+    #[command(subcommand)]
+    History(history::HistorySubcommand),
 }
 
 impl RootSubcommand {
@@ -127,7 +139,7 @@ impl RootSubcommand {
     }
 
     pub fn requires_auth(&self) -> bool {
-        matches!(self, Self::Chat(_) | Self::Profile)
+        matches!(self, Self::Chat(ChatArgs { subcommand: None, .. }) | Self::Profile)
     }
 
     pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
@@ -156,6 +168,8 @@ impl RootSubcommand {
             Self::Version { changelog } => Cli::print_version(changelog),
             Self::Chat(args) => args.execute(os).await,
             Self::Mcp(args) => args.execute(os, &mut std::io::stderr()).await,
+            Self::Audit(args) => args.execute(os).await,
+            Self::History(subcommand) => subcommand.execute(os).await,
         }
     }
 }
@@ -180,6 +194,8 @@ impl Display for RootSubcommand {
             Self::Issue(_) => "issue",
             Self::Version { .. } => "version",
             Self::Mcp(_) => "mcp",
+            Self::Audit(_) => "audit",
+            Self::History(_) => "history",
         };
 
         write!(f, "{name}")
@@ -356,6 +372,7 @@ mod test {
         assert_eq!(Cli::parse_from([CHAT_BINARY_NAME, "chat", "-vv"]), Cli {
             subcommand: Some(RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_picker: false,
                 input: None,
                 agent: None,
                 model: None,
@@ -363,6 +380,7 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 migrate: false,
+                subcommand: None,
             })),
             verbose: 2,
             help_all: false,
@@ -396,6 +414,7 @@ mod test {
             ["chat", "--profile", "my-profile"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_picker: false,
                 input: None,
                 agent: Some("my-profile".to_string()),
                 model: None,
@@ -403,6 +422,7 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 migrate: false,
+                subcommand: None,
             })
         );
     }
@@ -413,6 +433,7 @@ mod test {
             ["chat", "--profile", "my-profile", "Hello"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_picker: false,
                 input: Some("Hello".to_string()),
                 agent: Some("my-profile".to_string()),
                 model: None,
@@ -420,6 +441,7 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 migrate: false,
+                subcommand: None,
             })
         );
     }
@@ -430,6 +452,7 @@ mod test {
             ["chat", "--profile", "my-profile", "--trust-all-tools"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_picker: false,
                 input: None,
                 agent: Some("my-profile".to_string()),
                 model: None,
@@ -437,6 +460,7 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 migrate: false,
+                subcommand: None,
             })
         );
     }
@@ -447,6 +471,7 @@ mod test {
             ["chat", "--no-interactive", "--resume"],
             RootSubcommand::Chat(ChatArgs {
                 resume: true,
+                resume_picker: false,
                 input: None,
                 agent: None,
                 model: None,
@@ -454,12 +479,14 @@ mod test {
                 trust_tools: None,
                 no_interactive: true,
                 migrate: false,
+                subcommand: None,
             })
         );
         assert_parse!(
             ["chat", "--non-interactive", "-r"],
             RootSubcommand::Chat(ChatArgs {
                 resume: true,
+                resume_picker: false,
                 input: None,
                 agent: None,
                 model: None,
@@ -467,7 +494,60 @@ mod test {
                 trust_tools: None,
                 no_interactive: true,
                 migrate: false,
+                subcommand: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_with_resume_picker() {
+        assert_parse!(
+            ["chat", "--resume-picker"],
+            RootSubcommand::Chat(ChatArgs {
+                resume: false,
+                resume_picker: true,
+                input: None,
+                agent: None,
+                model: None,
+                trust_all_tools: false,
+                trust_tools: None,
+                no_interactive: false,
+                migrate: false,
+                subcommand: None,
+            })
+        );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--resume-picker", "--resume"]).is_err());
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "--resume-picker", "--no-interactive"]).is_err());
+    }
+
+    #[test]
+    fn test_chat_export() {
+        use crate::cli::chat::{
+            ChatSubcommand,
+            ExportConversationArgs,
+        };
+
+        assert_parse!(
+            ["chat", "export", "0b6e", "-o", "conversation.html"],
+            RootSubcommand::Chat(ChatArgs {
+                subcommand: Some(ChatSubcommand::Export(ExportConversationArgs {
+                    id: "0b6e".to_string(),
+                    output: Some("conversation.html".into()),
+                    format: None,
+                })),
+                ..Default::default()
+            })
+        );
+        assert!(
+            !RootSubcommand::Chat(ChatArgs {
+                subcommand: Some(ChatSubcommand::Export(ExportConversationArgs {
+                    id: "0b6e".to_string(),
+                    output: None,
+                    format: None,
+                })),
+                ..Default::default()
             })
+            .requires_auth()
         );
     }
 
@@ -477,6 +557,7 @@ mod test {
             ["chat", "--trust-all-tools"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_picker: false,
                 input: None,
                 agent: None,
                 model: None,
@@ -484,6 +565,7 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 migrate: false,
+                subcommand: None,
             })
         );
     }
@@ -494,6 +576,7 @@ mod test {
             ["chat", "--trust-tools="],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_picker: false,
                 input: None,
                 agent: None,
                 model: None,
@@ -501,6 +584,7 @@ mod test {
                 trust_tools: Some(vec!["".to_string()]),
                 no_interactive: false,
                 migrate: false,
+                subcommand: None,
             })
         );
     }
@@ -511,6 +595,7 @@ mod test {
             ["chat", "--trust-tools=fs_read,fs_write"],
             RootSubcommand::Chat(ChatArgs {
                 resume: false,
+                resume_picker: false,
                 input: None,
                 agent: None,
                 model: None,
@@ -518,6 +603,7 @@ mod test {
                 trust_tools: Some(vec!["fs_read".to_string(), "fs_write".to_string()]),
                 no_interactive: false,
                 migrate: false,
+                subcommand: None,
             })
         );
     }
@@ -27,6 +27,7 @@ use serde_json::{
 };
 use settings::Settings;
 use thiserror::Error;
+use time::OffsetDateTime;
 use tracing::{
     error,
     info,
@@ -34,7 +35,10 @@ use tracing::{
 };
 use uuid::Uuid;
 
-use crate::cli::ConversationState;
+use crate::cli::{
+    ConversationState,
+    FileCheckpoint,
+};
 use crate::util::directories::{
     DirectoryError,
     database_path,
@@ -69,7 +73,9 @@ const MIGRATIONS: &[Migration] = migrations![
     "004_state_table",
     "005_auth_table",
     "006_make_state_blob",
-    "007_conversations_table"
+    "007_conversations_table",
+    "008_conversation_history",
+    "009_file_checkpoints"
 ];
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -114,6 +120,49 @@ where
     }
 }
 
+/// Metadata about a conversation stored in the conversation history.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConversationMetadata {
+    pub id: String,
+    /// The directory the conversation was started in.
+    pub cwd: String,
+    pub agent: Option<String>,
+    pub model: Option<String>,
+    /// Derived from the first prompt of the conversation.
+    pub title: Option<String>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl ConversationMetadata {
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        let timestamp = |idx: usize| -> rusqlite::Result<OffsetDateTime> {
+            Ok(OffsetDateTime::from_unix_timestamp(row.get::<_, i64>(idx)?).unwrap_or(OffsetDateTime::UNIX_EPOCH))
+        };
+
+        Ok(Self {
+            id: row.get(0)?,
+            cwd: row.get(1)?,
+            agent: row.get(2)?,
+            model: row.get(3)?,
+            title: row.get(4)?,
+            created_at: timestamp(5)?,
+            updated_at: timestamp(6)?,
+        })
+    }
+}
+
+/// A conversation matching a full-text search, along with an excerpt of the matching transcript.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConversationSearchResult {
+    #[serde(flatten)]
+    pub conversation: ConversationMetadata,
+    /// Excerpt of the transcript with the matched terms wrapped in `**`.
+    pub snippet: String,
+}
+
 // A cloneable error
 #[derive(Debug, Clone, thiserror::Error)]
 #[error("Failed to open database: {}", .0)]
@@ -143,6 +192,8 @@ pub enum DatabaseError {
     StrFromUtf8(#[from] std::str::Utf8Error),
     #[error("`{}` is not a valid setting", .0)]
     InvalidSetting(String),
+    #[error("`{}` matches more than one conversation", .0)]
+    AmbiguousConversationId(String),
 }
 
 impl<T> From<PoisonError<T>> for DatabaseError {
@@ -159,6 +210,9 @@ pub enum Table {
     Conversations,
     /// The auth table contains SSO and Builder ID credentials.
     Auth,
+    /// The file checkpoints table contains the file snapshots of each conversation, keyed by
+    /// conversation id.
+    FileCheckpoints,
 }
 
 impl std::fmt::Display for Table {
@@ -167,6 +221,7 @@ impl std::fmt::Display for Table {
             Table::State => write!(f, "state"),
             Table::Conversations => write!(f, "conversations"),
             Table::Auth => write!(f, "auth_kv"),
+            Table::FileCheckpoints => write!(f, "file_checkpoints"),
         }
     }
 }
@@ -344,6 +399,208 @@ impl Database {
         self.set_json_entry(Table::Conversations, path, state)
     }
 
+    /// Save a chat conversation to the conversation history, keyed by its conversation id.
+    ///
+    /// Unlike [Self::set_conversation_by_path], this keeps every conversation rather than only
+    /// the latest one for each directory.
+    pub fn set_conversation(&mut self, path: impl AsRef<Path>, state: &ConversationState) -> Result<(), DatabaseError> {
+        // We would need to encode this to support non utf8 paths.
+        let path = match path.as_ref().to_str() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let id = state.conversation_id();
+        let value = serde_json::to_string(state)?;
+        let transcript = state
+            .transcript
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let mut conn = self.pool.get()?;
+        let transaction = conn.transaction()?;
+        transaction.execute(
+            "INSERT INTO conversation_history (id, cwd, agent, model, title, created_at, updated_at, value)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?7)
+             ON CONFLICT (id) DO UPDATE SET
+                 cwd = excluded.cwd,
+                 agent = excluded.agent,
+                 model = excluded.model,
+                 title = COALESCE(conversation_history.title, excluded.title),
+                 updated_at = excluded.updated_at,
+                 value = excluded.value",
+            params![
+                id,
+                path,
+                state.current_profile(),
+                state.model,
+                state.title(),
+                now,
+                value
+            ],
+        )?;
+        transaction.execute("DELETE FROM conversation_history_fts WHERE id = ?1", [id])?;
+        transaction.execute(
+            "INSERT INTO conversation_history_fts (id, title, transcript)
+             SELECT id, title, ?2 FROM conversation_history WHERE id = ?1",
+            params![id, transcript],
+        )?;
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    /// Get a chat conversation from the conversation history given its id or a unique prefix of
+    /// it.
+    pub fn get_conversation(&self, id: &str) -> Result<Option<ConversationState>, DatabaseError> {
+        let Some(id) = self.resolve_conversation_id(id)? else {
+            return Ok(None);
+        };
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT value FROM conversation_history WHERE id = ?1")?;
+        match stmt.query_row([id], |row| row.get::<_, String>(0)) {
+            Ok(value) => Ok(Some(serde_json::from_str(&value)?)),
+            Err(Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Get the metadata of a conversation in the conversation history given its id or a unique
+    /// prefix of it.
+    pub fn get_conversation_metadata(&self, id: &str) -> Result<Option<ConversationMetadata>, DatabaseError> {
+        let Some(id) = self.resolve_conversation_id(id)? else {
+            return Ok(None);
+        };
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, cwd, agent, model, title, created_at, updated_at FROM conversation_history WHERE id = ?1",
+        )?;
+        match stmt.query_row([id], ConversationMetadata::from_row) {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// List the conversations in the conversation history, most recently updated first.
+    pub fn list_conversations(&self, limit: Option<usize>) -> Result<Vec<ConversationMetadata>, DatabaseError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, cwd, agent, model, title, created_at, updated_at FROM conversation_history
+             ORDER BY updated_at DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit.map_or(-1, |limit| limit as i64)], ConversationMetadata::from_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Full-text search over the titles and transcripts of the conversation history, best matches
+    /// first. Every whitespace separated term in `query` must match.
+    pub fn search_conversations(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<ConversationSearchResult>, DatabaseError> {
+        let query = fts5_query(query);
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT h.id, h.cwd, h.agent, h.model, h.title, h.created_at, h.updated_at,
+                    snippet(conversation_history_fts, 2, '**', '**', '…', 16)
+             FROM conversation_history_fts
+             JOIN conversation_history h ON h.id = conversation_history_fts.id
+             WHERE conversation_history_fts MATCH ?1
+             ORDER BY rank LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![query, limit.map_or(-1, |limit| limit as i64)], |row| {
+            Ok(ConversationSearchResult {
+                conversation: ConversationMetadata::from_row(row)?,
+                snippet: row.get(7)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Get the file checkpoints of a conversation given its full id.
+    pub fn get_file_checkpoints(&self, conversation_id: &str) -> Result<Vec<FileCheckpoint>, DatabaseError> {
+        Ok(self
+            .get_json_entry(Table::FileCheckpoints, conversation_id)?
+            .unwrap_or_default())
+    }
+
+    /// Set the file checkpoints of a conversation given its full id.
+    ///
+    /// These are kept apart from the conversation since they hold whole files, which would
+    /// otherwise be written out with every turn and copied by `/save`.
+    pub fn set_file_checkpoints(
+        &mut self,
+        conversation_id: &str,
+        checkpoints: &[FileCheckpoint],
+    ) -> Result<(), DatabaseError> {
+        if checkpoints.is_empty() {
+            self.delete_entry(Table::FileCheckpoints, conversation_id)
+        } else {
+            self.set_json_entry(Table::FileCheckpoints, conversation_id, checkpoints)
+                .map(|_| ())
+        }
+    }
+
+    /// Delete a conversation given its id or a unique prefix of it, returning the full id of the
+    /// deleted conversation.
+    pub fn delete_conversation(&mut self, id: &str) -> Result<Option<String>, DatabaseError> {
+        let Some(id) = self.resolve_conversation_id(id)? else {
+            return Ok(None);
+        };
+
+        let mut conn = self.pool.get()?;
+        let transaction = conn.transaction()?;
+        transaction.execute("DELETE FROM conversation_history_fts WHERE id = ?1", [&id])?;
+        transaction.execute("DELETE FROM file_checkpoints WHERE key = ?1", [&id])?;
+        transaction.execute(
+            "DELETE FROM conversations WHERE json_extract(value, '$.conversation_id') = ?1",
+            [&id],
+        )?;
+        transaction.execute("DELETE FROM conversation_history WHERE id = ?1", [&id])?;
+        transaction.commit()?;
+
+        Ok(Some(id))
+    }
+
+    /// Delete every conversation that was last updated before `older_than`, returning the number
+    /// of deleted conversations.
+    pub fn prune_conversations(&mut self, older_than: OffsetDateTime) -> Result<usize, DatabaseError> {
+        let older_than = older_than.unix_timestamp();
+
+        let mut conn = self.pool.get()?;
+        let transaction = conn.transaction()?;
+        transaction.execute(
+            "DELETE FROM conversation_history_fts
+             WHERE id IN (SELECT id FROM conversation_history WHERE updated_at < ?1)",
+            [older_than],
+        )?;
+        transaction.execute(
+            "DELETE FROM file_checkpoints
+             WHERE key IN (SELECT id FROM conversation_history WHERE updated_at < ?1)",
+            [older_than],
+        )?;
+        transaction.execute(
+            "DELETE FROM conversations WHERE json_extract(value, '$.conversation_id') IN
+             (SELECT id FROM conversation_history WHERE updated_at < ?1)",
+            [older_than],
+        )?;
+        let deleted = transaction.execute("DELETE FROM conversation_history WHERE updated_at < ?1", [older_than])?;
+        transaction.commit()?;
+
+        Ok(deleted)
+    }
+
     pub async fn get_secret(&self, key: &str) -> Result<Option<Secret>, DatabaseError> {
         trace!(key, "getting secret");
         Ok(self.get_entry::<String>(Table::Auth, key)?.map(Into::into))
@@ -360,6 +617,17 @@ impl Database {
         self.delete_entry(Table::Auth, key)
     }
 
+    /// The keys of the secrets that start with `prefix`
+    pub async fn get_secret_keys(&self, prefix: &str) -> Result<Vec<String>, DatabaseError> {
+        trace!(prefix, "getting secret keys");
+        Ok(self
+            .all_entries(Table::Auth)?
+            .into_iter()
+            .map(|(key, _)| key)
+            .filter(|key| key.starts_with(prefix))
+            .collect())
+    }
+
     // Private functions. Do not expose.
 
     fn migrate(self) -> Result<Self, DatabaseError> {
@@ -391,6 +659,22 @@ impl Database {
         Ok(self)
     }
 
+    /// Expands a conversation id prefix to the full id of the only conversation it matches.
+    fn resolve_conversation_id(&self, id: &str) -> Result<Option<String>, DatabaseError> {
+        let conn = self.pool.get()?;
+        let mut stmt =
+            conn.prepare("SELECT id FROM conversation_history WHERE substr(id, 1, length(?1)) = ?1 LIMIT 2")?;
+        let ids = stmt
+            .query_map([id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match ids.as_slice() {
+            [] => Ok(None),
+            [full_id] => Ok(Some(full_id.clone())),
+            _ => Err(DatabaseError::AmbiguousConversationId(id.to_string())),
+        }
+    }
+
     fn get_entry<T: FromSql>(&self, table: Table, key: impl AsRef<str>) -> Result<Option<T>, DatabaseError> {
         let conn = self.pool.get()?;
         let mut stmt = conn.prepare(&format!("SELECT value FROM {table} WHERE key = ?1"))?;
@@ -454,6 +738,16 @@ impl Database {
     }
 }
 
+/// Quotes each term of a user provided query so that FTS5 syntax characters are matched
+/// literally.
+fn fts5_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn max_migration_version<C: Deref<Target = Connection>>(conn: &C) -> Option<i64> {
     let mut stmt = conn.prepare("SELECT MAX(version) FROM migrations").ok()?;
     stmt.query_row([], |row| row.get(0)).ok()
@@ -551,6 +845,56 @@ mod tests {
         assert_eq!(MIGRATIONS.len(), migration_count);
     }
 
+    #[test]
+    fn test_conversation_history_migration() {
+        let conn = Connection::open_in_memory().unwrap();
+        let migration = |name: &str| MIGRATIONS.iter().find(|m| m.name == name).unwrap().sql;
+        conn.execute_batch(migration("007_conversations_table")).unwrap();
+        conn.execute("INSERT INTO conversations (key, value) VALUES ('/work', ?1)", [
+            serde_json::json!({
+                "conversation_id": "legacy",
+                "transcript": ["> How do I build?> \nwith tests?", "Run cargo build"]
+            })
+            .to_string(),
+        ])
+        .unwrap();
+        conn.execute("INSERT INTO conversations (key, value) VALUES ('/long', ?1)", [
+            serde_json::json!({
+                "conversation_id": "long",
+                "transcript": [format!("> {}", "é".repeat(100))]
+            })
+            .to_string(),
+        ])
+        .unwrap();
+        conn.execute_batch(migration("008_conversation_history")).unwrap();
+
+        // The transcript is indexed the same way as by set_conversation
+        let (title, transcript) = conn
+            .query_row(
+                "SELECT title, transcript FROM conversation_history_fts WHERE id = 'legacy'",
+                [],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .unwrap();
+        assert_eq!(title, "How do I build?");
+        assert_eq!(transcript, "> How do I build?> \nwith tests?\nRun cargo build");
+
+        // Titles are cut the same way as by ConversationState::title
+        let title: String = conn
+            .query_row("SELECT title FROM conversation_history WHERE id = 'long'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(title, format!("{}…", "é".repeat(80)));
+    }
+
+    #[test]
+    fn test_fts5_query() {
+        assert_eq!(fts5_query("  cargo   build "), r#""cargo" "build""#);
+        assert_eq!(fts5_query(r#"say "hi" OR*"#), r#""say" """hi""" "OR*""#);
+        assert_eq!(fts5_query(""), "");
+    }
+
     #[tokio::test]
     async fn state_table_tests() {
         let db = Database::new().await.unwrap();
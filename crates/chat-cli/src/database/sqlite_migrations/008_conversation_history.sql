@@ -0,0 +1,55 @@
+CREATE TABLE conversation_history (
+    id TEXT PRIMARY KEY,
+    cwd TEXT NOT NULL,
+    agent TEXT,
+    model TEXT,
+    title TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    value TEXT NOT NULL
+);
+
+CREATE INDEX conversation_history_updated_at ON conversation_history (updated_at);
+
+CREATE VIRTUAL TABLE conversation_history_fts USING fts5 (
+    id UNINDEXED,
+    title,
+    transcript
+);
+
+INSERT OR IGNORE INTO conversation_history (id, cwd, agent, model, title, created_at, updated_at, value)
+SELECT
+    json_extract(value, '$.conversation_id'),
+    key,
+    json_extract(value, '$.context_manager.current_profile'),
+    json_extract(value, '$.model'),
+    CASE WHEN length(title) > 80 THEN substr(title, 1, 80) || '…' ELSE nullif(title, '') END,
+    strftime('%s', 'now'),
+    strftime('%s', 'now'),
+    value
+FROM (
+    -- The first line of the first prompt, which the transcript stores as "> line> \nline"
+    SELECT
+        key,
+        value,
+        trim(CASE instr(prompt, char(10))
+            WHEN 0 THEN substr(prompt, 3)
+            ELSE substr(prompt, 3, instr(prompt, char(10)) - 5)
+        END) AS title
+    FROM (
+        SELECT key, value, json_extract(value, '$.transcript[0]') AS prompt
+        FROM conversations
+        WHERE json_valid(value) AND json_extract(value, '$.conversation_id') IS NOT NULL
+    )
+);
+
+INSERT INTO conversation_history_fts (id, title, transcript)
+SELECT
+    id,
+    title,
+    (
+        SELECT group_concat(line, char(10)) FROM (
+            SELECT value AS line FROM json_each(conversation_history.value, '$.transcript') ORDER BY key
+        )
+    )
+FROM conversation_history;
@@ -0,0 +1,4 @@
+CREATE TABLE file_checkpoints (
+    key TEXT PRIMARY KEY,
+    value TEXT
+);
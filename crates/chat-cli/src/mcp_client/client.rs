@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::process::Stdio;
 use std::sync::atomic::{
     AtomicBool,
@@ -16,6 +17,8 @@ use serde::{
     Serialize,
 };
 use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::time;
 use tokio::time::error::Elapsed;
 
@@ -25,23 +28,36 @@ use super::transport::base_protocol::{
     JsonRpcRequest,
     JsonRpcVersion,
 };
+use super::transport::http::JsonRpcHttpTransport;
+use super::transport::sse::JsonRpcSseTransport;
 use super::transport::stdio::JsonRpcStdioTransport;
+use super::transport::websocket::JsonRpcWebSocketTransport;
 use super::transport::{
     self,
     Transport,
     TransportError,
 };
 use super::{
+    CreateMessageParams,
+    ElicitParams,
+    JsonRpcError,
     JsonRpcResponse,
     Listener as _,
     LogListener,
+    LoggingLevel,
+    LoggingMessageParams,
     Messenger,
     PaginationSupportedOps,
+    ProgressParams,
     PromptGet,
     PromptsListResult,
+    ResourceInfo,
+    ResourceTemplateInfo,
     ResourceTemplatesListResult,
     ResourcesListResult,
+    RootsListResult,
     ServerCapabilities,
+    ServerLogMessage,
     ToolsListResult,
 };
 use crate::util::process::{
@@ -51,6 +67,9 @@ use crate::util::process::{
 
 pub type ClientInfo = serde_json::Value;
 pub type StdioTransport = JsonRpcStdioTransport;
+pub type HttpTransport = JsonRpcHttpTransport;
+pub type SseTransport = JsonRpcSseTransport;
+pub type WebSocketTransport = JsonRpcWebSocketTransport;
 
 /// Represents the capabilities of a client in the Model Context Protocol.
 /// This structure is sent to the server during initialization to communicate
@@ -69,6 +88,11 @@ impl From<ClientInfo> for ClientCapabilities {
     fn from(client_info: ClientInfo) -> Self {
         ClientCapabilities {
             client_info,
+            capabilities: HashMap::from([
+                ("sampling".to_string(), serde_json::json!({})),
+                ("elicitation".to_string(), serde_json::json!({})),
+                ("roots".to_string(), serde_json::json!({ "listChanged": true })),
+            ]),
             ..Default::default()
         }
     }
@@ -131,8 +155,44 @@ pub struct Client<T: Transport> {
     // TODO: move this to tool manager that way all the assets are treated equally
     pub prompt_gets: Arc<SyncRwLock<HashMap<String, PromptGet>>>,
     pub is_prompts_out_of_date: Arc<AtomicBool>,
+    pub resources: Arc<SyncRwLock<Vec<ResourceInfo>>>,
+    pub resource_templates: Arc<SyncRwLock<Vec<ResourceTemplateInfo>>>,
+    /// Where to forward the progress notifications of in flight requests, keyed by request id
+    progress_senders: ProgressSenders,
+    tracer: Option<Tracer>,
 }
 
+/// Which way a traced message went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceDirection {
+    Sent,
+    Received,
+}
+
+type TraceFn = dyn Fn(TraceDirection, &JsonRpcMessage) + Send + Sync;
+
+/// Sees every message exchanged with the server, for debugging
+#[derive(Clone)]
+pub struct Tracer(Arc<TraceFn>);
+
+impl Tracer {
+    pub fn new(f: impl Fn(TraceDirection, &JsonRpcMessage) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    fn trace(&self, direction: TraceDirection, msg: &JsonRpcMessage) {
+        (self.0)(direction, msg);
+    }
+}
+
+impl std::fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Tracer")
+    }
+}
+
+type ProgressSenders = Arc<SyncRwLock<HashMap<u64, UnboundedSender<ProgressParams>>>>;
+
 impl<T: Transport> Clone for Client<T> {
     fn clone(&self) -> Self {
         Self {
@@ -147,6 +207,10 @@ impl<T: Transport> Clone for Client<T> {
             messenger: None,
             prompt_gets: self.prompt_gets.clone(),
             is_prompts_out_of_date: self.is_prompts_out_of_date.clone(),
+            resources: self.resources.clone(),
+            resource_templates: self.resource_templates.clone(),
+            progress_senders: self.progress_senders.clone(),
+            tracer: self.tracer.clone(),
         }
     }
 }
@@ -209,6 +273,10 @@ impl Client<StdioTransport> {
             messenger: None,
             prompt_gets: Arc::new(SyncRwLock::new(HashMap::new())),
             is_prompts_out_of_date: Arc::new(AtomicBool::new(false)),
+            resources: Arc::new(SyncRwLock::new(Vec::new())),
+            resource_templates: Arc::new(SyncRwLock::new(Vec::new())),
+            progress_senders: Arc::new(SyncRwLock::new(HashMap::new())),
+            tracer: None,
         })
     }
 
@@ -266,6 +334,27 @@ impl Client<StdioTransport> {
     }
 }
 
+impl<T: Transport> Client<T> {
+    /// Creates a client for a remote server, which we do not manage the process of.
+    pub fn from_transport(server_name: String, transport: T, timeout: u64, client_info: ClientInfo) -> Self {
+        Self {
+            server_name,
+            transport: Arc::new(transport),
+            timeout,
+            server_process_id: None,
+            client_info,
+            current_id: Arc::new(AtomicU64::new(0)),
+            messenger: None,
+            prompt_gets: Arc::new(SyncRwLock::new(HashMap::new())),
+            is_prompts_out_of_date: Arc::new(AtomicBool::new(false)),
+            resources: Arc::new(SyncRwLock::new(Vec::new())),
+            resource_templates: Arc::new(SyncRwLock::new(Vec::new())),
+            progress_senders: Arc::new(SyncRwLock::new(HashMap::new())),
+            tracer: None,
+        }
+    }
+}
+
 impl<T> Drop for Client<T>
 where
     T: Transport,
@@ -283,6 +372,29 @@ impl<T> Client<T>
 where
     T: Transport,
 {
+    /// Passes every message exchanged with the server from now on to `tracer`
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        let mut listener = self.transport.get_listener();
+        let received = tracer.clone();
+        tokio::spawn(async move {
+            loop {
+                match listener.recv().await {
+                    Ok(msg) => received.trace(TraceDirection::Received, &msg),
+                    Err(TransportError::RecvError(RecvError::Closed)) => break,
+                    Err(_) => {},
+                }
+            }
+        });
+        self.tracer = Some(tracer);
+    }
+
+    async fn send(&self, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+        if let Some(tracer) = &self.tracer {
+            tracer.trace(TraceDirection::Sent, msg);
+        }
+        self.transport.send(msg).await
+    }
+
     /// Exchange of information specified as per https://spec.modelcontextprotocol.io/specification/2024-11-05/basic/lifecycle/#initialization
     ///
     /// Also done are the following:
@@ -294,12 +406,16 @@ where
         let server_name = self.server_name.clone();
 
         // Spawning a task to listen and log stderr output
+        let messenger_ref = self.messenger.as_ref().map(|m| m.duplicate());
         tokio::spawn(async move {
             let mut log_listener = transport_ref.get_log_listener();
             loop {
                 match log_listener.recv().await {
                     Ok(msg) => {
                         tracing::trace!(target: "mcp", "{server_name} logged {}", msg);
+                        if let Some(messenger) = &messenger_ref {
+                            messenger.log(ServerLogMessage::Stderr(msg));
+                        }
                     },
                     Err(e) => {
                         tracing::error!(
@@ -312,51 +428,8 @@ where
             }
         });
 
-        let init_params = Some({
-            let client_cap = ClientCapabilities::from(self.client_info.clone());
-            serde_json::json!(client_cap)
-        });
-        let init_resp = self.request("initialize", init_params).await?;
-        if let Err(e) = examine_server_capabilities(&init_resp) {
-            return Err(ClientError::NegotiationError(format!(
-                "Client {} has failed to negotiate server capabilities with server: {:?}",
-                self.server_name, e
-            )));
-        }
-        let cap = {
-            let result = init_resp.result.ok_or(ClientError::NegotiationError(format!(
-                "Server {} init resp is missing result",
-                self.server_name
-            )))?;
-            let cap = result
-                .get("capabilities")
-                .ok_or(ClientError::NegotiationError(format!(
-                    "Server {} init resp result is missing capabilities",
-                    self.server_name
-                )))?
-                .clone();
-            serde_json::from_value::<ServerCapabilities>(cap)?
-        };
-        self.notify("initialized", None).await?;
-
-        // TODO: group this into examine_server_capabilities
-        // Prefetch prompts in the background. We should only do this after the server has been
-        // initialized
-        if cap.prompts.is_some() {
-            self.is_prompts_out_of_date.store(true, Ordering::Relaxed);
-            let client_ref = (*self).clone();
-            let messenger_ref = self.messenger.as_ref().map(|m| m.duplicate());
-            tokio::spawn(async move {
-                fetch_prompts_and_notify_with_messenger(&client_ref, messenger_ref.as_ref()).await;
-            });
-        }
-        if cap.tools.is_some() {
-            let client_ref = (*self).clone();
-            let messenger_ref = self.messenger.as_ref().map(|m| m.duplicate());
-            tokio::spawn(async move {
-                fetch_tools_and_notify_with_messenger(&client_ref, messenger_ref.as_ref()).await;
-            });
-        }
+        let cap = self.handshake().await?;
+        self.fetch_server_lists(&cap);
 
         let transport_ref = self.transport.clone();
         let server_name = self.server_name.clone();
@@ -371,38 +444,44 @@ where
                 match listener.recv().await {
                     Ok(msg) => {
                         match msg {
-                            JsonRpcMessage::Request(_req) => {},
+                            JsonRpcMessage::Request(req) => {
+                                // Answering may take a while (e.g. waiting for the user to approve a
+                                // sampling request), so we do not hold up the other messages
+                                let client_ref = client_ref.clone();
+                                let messenger_ref = messenger_ref.as_ref().map(|m| m.duplicate());
+                                tokio::spawn(async move {
+                                    let resp = handle_server_request(req, messenger_ref.as_ref()).await;
+                                    if let Err(e) = client_ref.send(&JsonRpcMessage::Response(resp)).await {
+                                        tracing::error!("Failed to answer request of server: {:?}", e);
+                                    }
+                                });
+                            },
                             JsonRpcMessage::Notification(notif) => {
                                 let JsonRpcNotification { method, params, .. } = notif;
                                 match method.as_str() {
                                     "notifications/message" | "message" => {
-                                        let level = params
-                                            .as_ref()
-                                            .and_then(|p| p.get("level"))
-                                            .and_then(|v| serde_json::to_string(v).ok());
-                                        let data = params
-                                            .as_ref()
-                                            .and_then(|p| p.get("data"))
-                                            .and_then(|v| serde_json::to_string(v).ok());
-                                        if let (Some(level), Some(data)) = (level, data) {
-                                            match level.to_lowercase().as_str() {
-                                                "error" => {
-                                                    tracing::error!(target: "mcp", "{}: {}", server_name, data);
-                                                },
-                                                "warn" => {
-                                                    tracing::warn!(target: "mcp", "{}: {}", server_name, data);
-                                                },
-                                                "info" => {
-                                                    tracing::info!(target: "mcp", "{}: {}", server_name, data);
-                                                },
-                                                "debug" => {
-                                                    tracing::debug!(target: "mcp", "{}: {}", server_name, data);
-                                                },
-                                                "trace" => {
-                                                    tracing::trace!(target: "mcp", "{}: {}", server_name, data);
-                                                },
-                                                _ => {},
-                                            }
+                                        let Some(message) =
+                                            params.and_then(|p| serde_json::from_value::<LoggingMessageParams>(p).ok())
+                                        else {
+                                            continue;
+                                        };
+                                        let data = &message.data;
+                                        match message.level {
+                                            LoggingLevel::Debug => {
+                                                tracing::debug!(target: "mcp", "{}: {}", server_name, data);
+                                            },
+                                            LoggingLevel::Info | LoggingLevel::Notice => {
+                                                tracing::info!(target: "mcp", "{}: {}", server_name, data);
+                                            },
+                                            LoggingLevel::Warning => {
+                                                tracing::warn!(target: "mcp", "{}: {}", server_name, data);
+                                            },
+                                            _ => {
+                                                tracing::error!(target: "mcp", "{}: {}", server_name, data);
+                                            },
+                                        }
+                                        if let Some(messenger) = &messenger_ref {
+                                            messenger.log(ServerLogMessage::Notification(message));
                                         }
                                     },
                                     "notifications/prompts/list_changed" | "prompts/list_changed"
@@ -421,6 +500,35 @@ where
                                         fetch_tools_and_notify_with_messenger(&client_ref, messenger_ref.as_ref())
                                             .await;
                                     },
+                                    "notifications/resources/list_changed" => {
+                                        fetch_resources(&client_ref).await;
+                                    },
+                                    "notifications/progress" => {
+                                        let Some(progress) =
+                                            params.and_then(|p| serde_json::from_value::<ProgressParams>(p).ok())
+                                        else {
+                                            continue;
+                                        };
+                                        let sender = progress
+                                            .progress_token
+                                            .as_u64()
+                                            .and_then(|id| client_ref.progress_senders.read().ok()?.get(&id).cloned());
+                                        if let Some(sender) = sender {
+                                            let _ = sender.send(progress);
+                                        }
+                                    },
+                                    "notifications/resources/updated" => {
+                                        let uri =
+                                            params.as_ref().and_then(|p| p.get("uri")).and_then(|uri| uri.as_str());
+                                        if let (Some(uri), Some(messenger)) = (uri, messenger_ref.as_ref()) {
+                                            if let Err(e) = messenger.send_resource_updated(uri.to_string()).await {
+                                                tracing::error!(
+                                                    "Failed to send resource update through messenger {:?}",
+                                                    e
+                                                );
+                                            }
+                                        }
+                                    },
                                     _ => {},
                                 }
                             },
@@ -432,7 +540,7 @@ where
                         tracing::error!("Background listening thread for client {}: {:?}", server_name, e);
                         // If we don't have anything on the other end, we should just end the task
                         // now
-                        if let TransportError::RecvError(tokio::sync::broadcast::error::RecvError::Closed) = e {
+                        if let TransportError::RecvError(RecvError::Closed) = e {
                             tracing::error!(
                                 "All senders dropped for transport layer for server {}: {:?}. This likely means the mcp server process is no longer running.",
                                 server_name,
@@ -445,19 +553,158 @@ where
             }
         });
 
+        // Every new connection of a reconnecting transport is a new session, which the server only
+        // accepts requests on once it has been initialized again
+        if let Some(mut connections) = self.transport.connections() {
+            connections.borrow_and_update();
+            let client_ref = (*self).clone();
+            tokio::spawn(async move {
+                while connections.changed().await.is_ok() {
+                    match client_ref.handshake().await {
+                        Ok(cap) => client_ref.fetch_server_lists(&cap),
+                        Err(e) => tracing::error!(
+                            "Failed to initialize server {} after reconnecting: {:?}",
+                            client_ref.server_name,
+                            e
+                        ),
+                    }
+                }
+            });
+        }
+
         Ok(cap)
     }
 
+    /// Sends the `initialize` request and the `initialized` notification, returning the
+    /// capabilities of the server.
+    async fn handshake(&self) -> Result<ServerCapabilities, ClientError> {
+        let init_params = Some({
+            let client_cap = ClientCapabilities::from(self.client_info.clone());
+            serde_json::json!(client_cap)
+        });
+        let init_resp = self.request("initialize", init_params).await?;
+        if let Err(e) = examine_server_capabilities(&init_resp) {
+            return Err(ClientError::NegotiationError(format!(
+                "Client {} has failed to negotiate server capabilities with server: {:?}",
+                self.server_name, e
+            )));
+        }
+        let cap = {
+            let result = init_resp.result.ok_or(ClientError::NegotiationError(format!(
+                "Server {} init resp is missing result",
+                self.server_name
+            )))?;
+            let cap = result
+                .get("capabilities")
+                .ok_or(ClientError::NegotiationError(format!(
+                    "Server {} init resp result is missing capabilities",
+                    self.server_name
+                )))?
+                .clone();
+            serde_json::from_value::<ServerCapabilities>(cap)?
+        };
+        self.notify("initialized", None).await?;
+
+        Ok(cap)
+    }
+
+    /// Fetches, in the background, the tools, prompts and resources the server advertised.
+    fn fetch_server_lists(&self, cap: &ServerCapabilities) {
+        // TODO: group this into examine_server_capabilities
+        // Prefetch prompts in the background. We should only do this after the server has been
+        // initialized
+        if cap.prompts.is_some() {
+            self.is_prompts_out_of_date.store(true, Ordering::Relaxed);
+            let client_ref = (*self).clone();
+            let messenger_ref = self.messenger.as_ref().map(|m| m.duplicate());
+            tokio::spawn(async move {
+                fetch_prompts_and_notify_with_messenger(&client_ref, messenger_ref.as_ref()).await;
+            });
+        }
+        if cap.resources.is_some() {
+            let client_ref = (*self).clone();
+            tokio::spawn(async move {
+                fetch_resources(&client_ref).await;
+            });
+        }
+        if cap.tools.is_some() {
+            let client_ref = (*self).clone();
+            let messenger_ref = self.messenger.as_ref().map(|m| m.duplicate());
+            tokio::spawn(async move {
+                fetch_tools_and_notify_with_messenger(&client_ref, messenger_ref.as_ref()).await;
+            });
+        }
+    }
+
+    /// Returns a future that resolves, with a description of why, once the connection to the
+    /// server is lost (e.g. because its process exited). The future does not keep the client alive.
+    pub fn closed(&self) -> impl Future<Output = String> + Send + 'static {
+        let mut listener = self.transport.get_listener();
+        let transport = self.transport.clone();
+        async move {
+            loop {
+                if let Err(TransportError::RecvError(RecvError::Closed)) = listener.recv().await {
+                    break;
+                }
+            }
+            transport
+                .exit_reason()
+                .await
+                .unwrap_or_else(|| connection_closed().to_string())
+        }
+    }
+
+    /// Stops the server process, if the client launched one
+    pub fn terminate(&self) {
+        if let Some(process_id) = self.server_process_id {
+            let _ = terminate_process(process_id);
+        }
+    }
+
     /// Sends a request to the server associated.
     /// This call will yield until a response is received.
     pub async fn request(
         &self,
         method: &str,
         params: Option<serde_json::Value>,
+    ) -> Result<JsonRpcResponse, ClientError> {
+        self.request_with_progress(method, params, None).await
+    }
+
+    /// Sends a request and waits for its response. If a progress sender is given, the request asks
+    /// the server for progress notifications, which are forwarded to it.
+    ///
+    /// The server is told to stop working on the request when it times out, or when the returned
+    /// future is dropped before the response arrives (e.g. when the user interrupts a tool call).
+    pub async fn request_with_progress(
+        &self,
+        method: &str,
+        mut params: Option<serde_json::Value>,
+        progress: Option<UnboundedSender<ProgressParams>>,
     ) -> Result<JsonRpcResponse, ClientError> {
         let send_map_err = |e: Elapsed| (e, method.to_string());
         let recv_map_err = |e: Elapsed| (e, format!("recv for {method}"));
         let mut id = self.get_id();
+        let mut in_flight = InFlightRequest {
+            id,
+            transport: self.transport.clone(),
+            tracer: self.tracer.clone(),
+            progress_senders: self.progress_senders.clone(),
+            // The initialize request must not be cancelled
+            cancel_reason: (method != "initialize").then_some("The request was cancelled by the client"),
+        };
+        if let Some(progress) = progress {
+            let params = params.get_or_insert_with(|| serde_json::json!({}));
+            if let Some(params) = params.as_object_mut() {
+                let meta = params.entry("_meta").or_insert_with(|| serde_json::json!({}));
+                if let Some(meta) = meta.as_object_mut() {
+                    meta.insert("progressToken".to_string(), id.into());
+                }
+            }
+            if let Ok(mut senders) = self.progress_senders.write() {
+                senders.insert(id, progress);
+            }
+        }
         let request = JsonRpcRequest {
             jsonrpc: JsonRpcVersion::default(),
             id,
@@ -466,10 +713,17 @@ where
         };
         tracing::trace!(target: "mcp", "To {}:\n{:#?}", self.server_name, request);
         let msg = JsonRpcMessage::Request(request);
-        time::timeout(Duration::from_millis(self.timeout), self.transport.send(&msg))
-            .await
-            .map_err(send_map_err)??;
+        // The listener is obtained before sending so that responses delivered as part of the send
+        // (as some transports do) are not missed
         let mut listener = self.transport.get_listener();
+        let sent = time::timeout(Duration::from_millis(self.timeout), self.send(&msg))
+            .await
+            .map_err(send_map_err);
+        if !matches!(sent, Ok(Ok(()))) {
+            // There is nothing to cancel if the server never got the request
+            in_flight.cancel_reason = None;
+        }
+        sent??;
         let mut resp = time::timeout(Duration::from_millis(self.timeout), async {
             // we want to ignore all other messages sent by the server at this point and let the
             // background loop handle them
@@ -477,15 +731,24 @@ where
             // not deserialize into a valid JsonRpcMessage (they are not supposed to do this but
             // too many people complained about this so we are adding this safeguard in)
             loop {
-                if let Ok(JsonRpcMessage::Response(resp)) = listener.recv().await {
-                    if resp.id == id {
+                match listener.recv().await {
+                    Ok(JsonRpcMessage::Response(resp)) if resp.id == id => {
                         break Ok::<JsonRpcResponse, TransportError>(resp);
-                    }
+                    },
+                    Err(TransportError::RecvError(RecvError::Closed)) => break Err(connection_closed()),
+                    _ => {},
                 }
             }
         })
         .await
-        .map_err(recv_map_err)??;
+        .map_err(|e| {
+            if in_flight.cancel_reason.is_some() {
+                in_flight.cancel_reason = Some("The request timed out");
+            }
+            recv_map_err(e)
+        })??;
+        in_flight.cancel_reason = None;
+        drop(in_flight);
         // Pagination support: https://spec.modelcontextprotocol.io/specification/2024-11-05/server/utilities/pagination/#pagination-model
         let mut next_cursor = resp.result.as_ref().and_then(|v| v.get("nextCursor"));
         if next_cursor.is_some() {
@@ -539,15 +802,17 @@ where
                         })),
                     };
                     let msg = JsonRpcMessage::Request(next_request);
-                    time::timeout(Duration::from_millis(self.timeout), self.transport.send(&msg))
+                    time::timeout(Duration::from_millis(self.timeout), self.send(&msg))
                         .await
                         .map_err(send_map_err)??;
                     let resp = time::timeout(Duration::from_millis(self.timeout), async {
                         loop {
-                            if let Ok(JsonRpcMessage::Response(resp)) = listener.recv().await {
-                                if resp.id == id {
+                            match listener.recv().await {
+                                Ok(JsonRpcMessage::Response(resp)) if resp.id == id => {
                                     break Ok::<JsonRpcResponse, TransportError>(resp);
-                                }
+                                },
+                                Err(TransportError::RecvError(RecvError::Closed)) => break Err(connection_closed()),
+                                _ => {},
                             }
                         }
                     })
@@ -577,11 +842,9 @@ where
             params,
         };
         let msg = JsonRpcMessage::Notification(notification);
-        Ok(
-            time::timeout(Duration::from_millis(self.timeout), self.transport.send(&msg))
-                .await
-                .map_err(send_map_err)??,
-        )
+        Ok(time::timeout(Duration::from_millis(self.timeout), self.send(&msg))
+            .await
+            .map_err(send_map_err)??)
     }
 
     fn get_id(&self) -> u64 {
@@ -645,6 +908,125 @@ where
     }
 }
 
+/// Answers a request the server made to the client
+#[allow(clippy::borrowed_box)]
+async fn handle_server_request(req: JsonRpcRequest, messenger: Option<&Box<dyn Messenger>>) -> JsonRpcResponse {
+    fn parse<P: serde::de::DeserializeOwned>(params: Option<serde_json::Value>) -> Result<P, JsonRpcError> {
+        let params = params.ok_or_else(|| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, "Missing params"))?;
+        serde_json::from_value(params).map_err(|e| JsonRpcError::new(JsonRpcError::INVALID_PARAMS, e.to_string()))
+    }
+    fn to_value(result: impl Serialize) -> Result<serde_json::Value, JsonRpcError> {
+        serde_json::to_value(result).map_err(|e| JsonRpcError::new(JsonRpcError::INTERNAL_ERROR, e.to_string()))
+    }
+
+    let JsonRpcRequest { id, method, params, .. } = req;
+    let result = match (method.as_str(), messenger) {
+        ("ping", _) => Ok(serde_json::json!({})),
+        ("sampling/createMessage", Some(messenger)) => match parse::<CreateMessageParams>(params) {
+            Ok(params) => messenger.send_sampling_request(params).await.and_then(to_value),
+            Err(e) => Err(e),
+        },
+        ("elicitation/create", Some(messenger)) => match parse::<ElicitParams>(params) {
+            Ok(params) => messenger.send_elicitation_request(params).await.and_then(to_value),
+            Err(e) => Err(e),
+        },
+        ("roots/list", Some(messenger)) => messenger
+            .list_roots()
+            .await
+            .and_then(|roots| to_value(RootsListResult { roots })),
+        _ => Err(JsonRpcError::new(
+            JsonRpcError::METHOD_NOT_FOUND,
+            format!("Method not found: {method}"),
+        )),
+    };
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    JsonRpcResponse {
+        jsonrpc: JsonRpcVersion::default(),
+        id,
+        result,
+        error,
+    }
+}
+
+fn connection_closed() -> TransportError {
+    TransportError::Custom("The connection to the server was closed".to_string())
+}
+
+/// A request waiting for its response. Dropping it stops the forwarding of progress notifications
+/// and, unless the response has arrived, tells the server that the request is cancelled.
+struct InFlightRequest<T: Transport> {
+    id: u64,
+    transport: Arc<T>,
+    tracer: Option<Tracer>,
+    progress_senders: ProgressSenders,
+    cancel_reason: Option<&'static str>,
+}
+
+impl<T: Transport> Drop for InFlightRequest<T> {
+    fn drop(&mut self) {
+        if let Ok(mut senders) = self.progress_senders.write() {
+            senders.remove(&self.id);
+        }
+        let Some(reason) = self.cancel_reason else {
+            return;
+        };
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            jsonrpc: JsonRpcVersion::default(),
+            method: "notifications/cancelled".to_string(),
+            params: Some(serde_json::json!({ "requestId": self.id, "reason": reason })),
+        });
+        if let Some(tracer) = &self.tracer {
+            tracer.trace(TraceDirection::Sent, &notification);
+        }
+        let transport = self.transport.clone();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if let Err(e) = transport.send(&notification).await {
+                    tracing::warn!("Failed to cancel request: {:?}", e);
+                }
+            });
+        }
+    }
+}
+
+/// Refreshes the resources and resource templates cached by the client
+async fn fetch_resources<T>(client: &Client<T>)
+where
+    T: Transport,
+{
+    async fn list<T: Transport, I: serde::de::DeserializeOwned>(client: &Client<T>, method: &str, key: &str) -> Vec<I> {
+        let result = match client.request(method, None).await {
+            Ok(resp) => resp.result,
+            Err(e) => {
+                tracing::warn!("Failed to query {method} for {}: {:?}", client.server_name, e);
+                return vec![];
+            },
+        };
+        result
+            .as_ref()
+            .and_then(|r| r.get(key))
+            .and_then(|list| list.as_array())
+            .map(|list| {
+                list.iter()
+                    .filter_map(|item| serde_json::from_value(item.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    let resources = list::<T, ResourceInfo>(client, "resources/list", "resources").await;
+    let templates = list::<T, ResourceTemplateInfo>(client, "resources/templates/list", "resourceTemplates").await;
+    if let Ok(mut lock) = client.resources.write() {
+        *lock = resources;
+    }
+    if let Ok(mut lock) = client.resource_templates.write() {
+        *lock = templates;
+    }
+}
+
 #[allow(clippy::borrowed_box)]
 async fn fetch_tools_and_notify_with_messenger<T>(client: &Client<T>, messenger: Option<&Box<dyn Messenger>>)
 where
@@ -1144,4 +1526,85 @@ mod tests {
             assert_eq!(result, "python -m mcp_server --config C:\\configs\\server.json");
         }
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_request_cancelled_on_timeout() {
+        // cat echoes the request back instead of answering it
+        let client = Client::<StdioTransport>::from_config(ClientConfig {
+            server_name: "echo".to_string(),
+            bin_path: "cat".to_string(),
+            args: vec![],
+            timeout: 200,
+            client_info: serde_json::json!({}),
+            env: None,
+        })
+        .unwrap();
+        let mut listener = client.transport.get_listener();
+        let (progress_tx, _progress_rx) = tokio::sync::mpsc::unbounded_channel();
+        let result = client
+            .request_with_progress(
+                "tools/call",
+                Some(serde_json::json!({ "name": "slow" })),
+                Some(progress_tx),
+            )
+            .await;
+        assert!(matches!(result, Err(ClientError::RuntimeError { .. })));
+        assert!(client.progress_senders.read().unwrap().is_empty());
+
+        let Ok(JsonRpcMessage::Request(request)) = listener.recv().await else {
+            panic!("Expected the echoed request");
+        };
+        assert_eq!(request.params.unwrap()["_meta"]["progressToken"], request.id);
+        let cancelled = time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(JsonRpcMessage::Notification(notification)) = listener.recv().await {
+                    break notification;
+                }
+            }
+        })
+        .await
+        .expect("Expected a cancellation");
+        assert_eq!(cancelled.method, "notifications/cancelled");
+        assert_eq!(cancelled.params.unwrap()["requestId"], request.id);
+    }
+
+    #[tokio::test]
+    async fn test_handle_server_request() {
+        let request = |method: &str, params: Option<Value>| JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id: 7,
+            method: method.to_string(),
+            params,
+        };
+        let messenger: Box<dyn Messenger> = Box::new(crate::mcp_client::NullMessenger);
+
+        let resp = handle_server_request(request("ping", None), None).await;
+        assert_eq!(resp.id, 7);
+        assert_eq!(resp.result, Some(serde_json::json!({})));
+
+        let resp = handle_server_request(request("roots/unknown", None), Some(&messenger)).await;
+        assert_eq!(resp.error.unwrap().code, JsonRpcError::METHOD_NOT_FOUND);
+
+        let resp = handle_server_request(
+            request("sampling/createMessage", Some(serde_json::json!({ "messages": [] }))),
+            Some(&messenger),
+        )
+        .await;
+        assert_eq!(resp.error.unwrap().code, JsonRpcError::INVALID_PARAMS);
+
+        let params = serde_json::json!({
+            "messages": [{ "role": "user", "content": { "type": "text", "text": "hello" } }],
+            "maxTokens": 100
+        });
+        let resp = handle_server_request(request("sampling/createMessage", Some(params)), Some(&messenger)).await;
+        assert!(resp.result.is_none());
+        assert_eq!(resp.error.unwrap().code, JsonRpcError::METHOD_NOT_FOUND);
+
+        let resp = handle_server_request(request("roots/list", None), Some(&messenger)).await;
+        assert_eq!(resp.result, Some(serde_json::json!({ "roots": [] })));
+
+        let resp = handle_server_request(request("elicitation/create", None), Some(&messenger)).await;
+        assert_eq!(resp.error.unwrap().code, JsonRpcError::INVALID_PARAMS);
+    }
 }
@@ -1,9 +1,16 @@
 use thiserror::Error;
 
 use super::{
+    CreateMessageParams,
+    CreateMessageResult,
+    ElicitParams,
+    ElicitResult,
+    JsonRpcError,
     PromptsListResult,
     ResourceTemplatesListResult,
     ResourcesListResult,
+    Root,
+    ServerLogMessage,
     ToolsListResult,
 };
 
@@ -34,9 +41,26 @@ pub trait Messenger: std::fmt::Debug + Send + Sync + 'static {
         result: eyre::Result<ResourceTemplatesListResult>,
     ) -> Result<(), MessengerError>;
 
+    /// Signals to the consumer that a resource it subscribed to has been updated
+    async fn send_resource_updated(&self, uri: String) -> Result<(), MessengerError>;
+
+    /// Forwards a `sampling/createMessage` request of the server to the consumer, which decides
+    /// whether and how it is answered. The error is sent back to the server as is.
+    async fn send_sampling_request(&self, params: CreateMessageParams) -> Result<CreateMessageResult, JsonRpcError>;
+
+    /// Forwards an `elicitation/create` request of the server to the consumer, which asks the user
+    /// for the requested input
+    async fn send_elicitation_request(&self, params: ElicitParams) -> Result<ElicitResult, JsonRpcError>;
+
+    /// Asks the consumer for the roots the server is allowed to operate on
+    async fn list_roots(&self) -> Result<Vec<Root>, JsonRpcError>;
+
     /// Signals to the orchestrator that a server has started initializing
     async fn send_init_msg(&self) -> Result<(), MessengerError>;
 
+    /// Hands a line of stderr output or a log message of the server to the consumer
+    fn log(&self, message: ServerLogMessage);
+
     /// Creates a duplicate of the messenger object
     /// This function is used to create a new instance of the messenger with the same configuration
     fn duplicate(&self) -> Box<dyn Messenger>;
@@ -75,10 +99,34 @@ impl Messenger for NullMessenger {
         Ok(())
     }
 
+    async fn send_resource_updated(&self, _uri: String) -> Result<(), MessengerError> {
+        Ok(())
+    }
+
+    async fn send_sampling_request(&self, _params: CreateMessageParams) -> Result<CreateMessageResult, JsonRpcError> {
+        Err(JsonRpcError::new(
+            JsonRpcError::METHOD_NOT_FOUND,
+            "Sampling is not supported",
+        ))
+    }
+
+    async fn send_elicitation_request(&self, _params: ElicitParams) -> Result<ElicitResult, JsonRpcError> {
+        Err(JsonRpcError::new(
+            JsonRpcError::METHOD_NOT_FOUND,
+            "Elicitation is not supported",
+        ))
+    }
+
+    async fn list_roots(&self) -> Result<Vec<Root>, JsonRpcError> {
+        Ok(Vec::new())
+    }
+
     async fn send_init_msg(&self) -> Result<(), MessengerError> {
         Ok(())
     }
 
+    fn log(&self, _message: ServerLogMessage) {}
+
     fn duplicate(&self) -> Box<dyn Messenger> {
         Box::new(NullMessenger)
     }
@@ -14,6 +14,7 @@ use tokio::io::{
     Stdin,
     Stdout,
 };
+use tokio::sync::broadcast::error::RecvError;
 use tokio::task::JoinHandle;
 
 use super::Listener as _;
@@ -80,6 +81,8 @@ pub enum ServerError {
     MutexError,
     #[error("Failed to obtain request method")]
     MissingMethod,
+    #[error("Method not found: {0}")]
+    MethodNotFound(String),
     #[error("Failed to obtain request id")]
     MissingId,
     #[error("Failed to initialize server. Missing transport")]
@@ -164,15 +167,24 @@ where
         let has_initialized = Arc::new(AtomicBool::new(false));
         let listener = tokio::spawn(async move {
             let mut listener = transport.get_listener();
+            let mut requests = tokio::task::JoinSet::new();
             loop {
                 let request = listener.recv().await;
+                // The client has closed its end, once the requests in flight are answered there is
+                // nothing left to do
+                if let Err(TransportError::RecvError(RecvError::Closed)) = request {
+                    break;
+                }
                 let transport_clone = transport.clone();
                 let has_init_clone = has_initialized.clone();
                 let handler_clone = handler.clone();
-                tokio::task::spawn(async move {
+                requests.spawn(async move {
                     process_request(has_init_clone, transport_clone, handler_clone, request).await;
                 });
+                while requests.try_join_next().is_some() {}
             }
+            while requests.join_next().await.is_some() {}
+            Ok(())
         });
         Ok(listener)
     }
@@ -226,8 +238,8 @@ async fn process_request<T, H>(
                         result,
                         ..Default::default()
                     });
-                    let _ = transport.send(&resp).await;
                     has_initialized.store(true, Ordering::SeqCst);
+                    let _ = transport.send(&resp).await;
                 },
                 Err(_e) => {
                     let resp = JsonRpcMessage::Response(JsonRpcResponse {
@@ -257,8 +269,12 @@ async fn process_request<T, H>(
                 } = req;
                 let resp = handler.handle_incoming(method, params).await.map_or_else(
                     |error| {
+                        let code = match error {
+                            ServerError::MethodNotFound(_) => ErrorCode::MethodNotFound,
+                            _ => ErrorCode::InternalError,
+                        };
                         let err = JsonRpcError {
-                            code: ErrorCode::InternalError.into(),
+                            code: code.into(),
                             message: error.to_string(),
                             data: None,
                         };
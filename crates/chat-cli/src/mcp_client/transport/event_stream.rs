@@ -0,0 +1,126 @@
+//! Incremental decoding of `text/event-stream` bodies, as specified by
+//! https://html.spec.whatwg.org/multipage/server-sent-events.html#parsing-an-event-stream
+
+/// A single server-sent event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The event type. Defaults to `message` when the server does not specify one.
+    pub event: String,
+    pub data: String,
+    pub id: Option<String>,
+    /// The reconnection time requested by the server, in ms
+    pub retry: Option<u64>,
+}
+
+/// Turns chunks of an event stream into [SseEvent]s. Chunks do not need to be aligned with
+/// events or lines.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseDecoder {
+    /// Feeds a chunk of the body to the decoder and returns the events completed by it.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n' || *b == b'\r') {
+            // A CRLF may be split across chunks, in which case we wait for the next one
+            if self.buffer[end] == b'\r' && end + 1 == self.buffer.len() {
+                break;
+            }
+            let skip = if self.buffer[end] == b'\r' && self.buffer.get(end + 1) == Some(&b'\n') {
+                2
+            } else {
+                1
+            };
+            let line = String::from_utf8_lossy(&self.buffer[..end]).to_string();
+            self.buffer.drain(..end + skip);
+            if let Some(event) = self.process_line(&line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => match &mut self.data {
+                Some(data) => {
+                    data.push('\n');
+                    data.push_str(value);
+                },
+                None => self.data = Some(value.to_string()),
+            },
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" => self.retry = value.parse().ok(),
+            _ => {},
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        let retry = self.retry.take();
+        // The id is kept by the stream even if the event carries no data
+        let id = self.id.clone();
+        let data = self.data.take()?;
+        Some(SseEvent {
+            event: event.filter(|e| !e.is_empty()).unwrap_or("message".to_string()),
+            data,
+            id,
+            retry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_events() {
+        let mut decoder = SseDecoder::default();
+        let mut events = decoder.push(b": keep-alive\n\nid: 1\ndata: {\"a\":");
+        assert!(events.is_empty());
+        events.extend(decoder.push(b"1}\r"));
+        events.extend(decoder.push(b"\n\r\nevent: endpoint\ndata: /messages\ndata: ?x=1\nretry: 500\n\n"));
+        events.extend(decoder.push(b"id: 2\n\ndata: last\n\n"));
+
+        assert_eq!(events, vec![
+            SseEvent {
+                event: "message".to_string(),
+                data: "{\"a\":1}".to_string(),
+                id: Some("1".to_string()),
+                retry: None,
+            },
+            SseEvent {
+                event: "endpoint".to_string(),
+                data: "/messages\n?x=1".to_string(),
+                id: Some("1".to_string()),
+                retry: Some(500),
+            },
+            SseEvent {
+                event: "message".to_string(),
+                data: "last".to_string(),
+                id: Some("2".to_string()),
+                retry: None,
+            },
+        ]);
+    }
+}
@@ -0,0 +1,694 @@
+//! Streamable HTTP transport, as specified by
+//! https://modelcontextprotocol.io/specification/2025-03-26/basic/transports#streamable-http
+use std::collections::HashMap;
+use std::sync::{
+    Arc,
+    Mutex as SyncMutex,
+    RwLock as SyncRwLock,
+};
+use std::time::Duration;
+
+use reqwest::header::{
+    ACCEPT,
+    AUTHORIZATION,
+    CONTENT_TYPE,
+    HeaderMap,
+    HeaderName,
+    HeaderValue,
+};
+use reqwest::{
+    Response,
+    StatusCode,
+    Url,
+};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use super::base_protocol::{
+    JsonRpcMessage,
+    RequestId,
+};
+use super::event_stream::SseDecoder;
+use super::{
+    Listener,
+    LogListener,
+    StdioListener,
+    StdioLogListener,
+    Transport,
+    TransportError,
+};
+use crate::auth::mcp_oauth::McpTokenProvider;
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// Time to wait before reconnecting to an event stream, unless the server asks for another one
+const DEFAULT_RETRY_MS: u64 = 1000;
+/// Number of consecutive failed attempts after which we stop reconnecting to an event stream
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+#[derive(Debug)]
+pub struct JsonRpcHttpTransport {
+    inner: Arc<HttpTransportInner>,
+    receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
+    log_receiver: broadcast::Receiver<String>,
+    /// The stream on which the server sends messages unrelated to any request
+    server_stream: SyncMutex<Option<JoinHandle<()>>>,
+}
+
+/// State shared between the transport and the tasks reading its event streams.
+#[derive(Debug)]
+struct HttpTransportInner {
+    client: reqwest::Client,
+    url: Url,
+    headers: HeaderMap,
+    /// Supplies the access token of servers that require authorization, unless an Authorization
+    /// header is configured
+    auth: Option<Arc<McpTokenProvider>>,
+    session_id: SyncRwLock<Option<String>>,
+    protocol_version: SyncRwLock<Option<String>>,
+    /// Id of the initialize request, used to find the protocol version negotiated
+    initialize_id: SyncRwLock<Option<RequestId>>,
+    tx: broadcast::Sender<Result<JsonRpcMessage, TransportError>>,
+    log_tx: broadcast::Sender<String>,
+}
+
+impl JsonRpcHttpTransport {
+    pub fn client(
+        url: &str,
+        headers: &HashMap<String, String>,
+        auth: Option<Arc<McpTokenProvider>>,
+    ) -> Result<Self, TransportError> {
+        let url = Url::parse(url).map_err(|e| TransportError::Custom(format!("Invalid url {url}: {e}")))?;
+        let header_map = header_map(headers)?;
+        let client = crate::request::new_client().map_err(|e| TransportError::Http(e.to_string()))?;
+        let (tx, receiver) = broadcast::channel::<Result<JsonRpcMessage, TransportError>>(100);
+        let (log_tx, log_receiver) = broadcast::channel::<String>(100);
+
+        Ok(Self {
+            inner: Arc::new(HttpTransportInner {
+                client,
+                url,
+                auth: auth.filter(|_| !header_map.contains_key(AUTHORIZATION)),
+                headers: header_map,
+                session_id: SyncRwLock::new(None),
+                protocol_version: SyncRwLock::new(None),
+                initialize_id: SyncRwLock::new(None),
+                tx,
+                log_tx,
+            }),
+            receiver,
+            log_receiver,
+            server_stream: SyncMutex::new(None),
+        })
+    }
+
+    /// Opens the stream on which the server can send requests and notifications of its own, once
+    /// the session is initialized.
+    fn open_server_stream(&self) {
+        let Ok(mut server_stream) = self.server_stream.lock() else {
+            return;
+        };
+        if server_stream.is_some() {
+            return;
+        }
+        let inner = self.inner.clone();
+        server_stream.replace(tokio::spawn(async move {
+            match inner.get_event_stream(None).await {
+                Ok(Some(response)) => inner.read_event_stream(response, None).await,
+                Ok(None) => {},
+                Err(e) => inner.log(format!("Failed to open event stream: {e}")),
+            }
+        }));
+    }
+}
+
+/// Converts the headers of a server config, failing on names or values that cannot be sent.
+pub(super) fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap, TransportError> {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::try_from(name.as_str())
+            .map_err(|e| TransportError::Custom(format!("Invalid header name {name}: {e}")))?;
+        let value = HeaderValue::try_from(value.as_str())
+            .map_err(|e| TransportError::Custom(format!("Invalid value for header {name}: {e}")))?;
+        header_map.insert(name, value);
+    }
+    Ok(header_map)
+}
+
+impl HttpTransportInner {
+    fn log(&self, msg: String) {
+        let _ = self.log_tx.send(msg);
+    }
+
+    /// Adds the headers every request of the session needs.
+    async fn request(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let mut builder = self
+            .client
+            .request(method, self.url.clone())
+            .headers(self.headers.clone());
+        if let Some(access_token) = self.access_token().await {
+            builder = builder.bearer_auth(access_token);
+        }
+        if let Some(session_id) = self.session_id.read().ok().and_then(|s| s.clone()) {
+            builder = builder.header(SESSION_ID_HEADER, session_id);
+        }
+        if let Some(version) = self.protocol_version.read().ok().and_then(|v| v.clone()) {
+            builder = builder.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        builder
+    }
+
+    async fn access_token(&self) -> Option<String> {
+        self.auth.as_ref()?.access_token().await
+    }
+
+    /// Sends a message, retrying once with a new access token if the server refused the current
+    /// one.
+    async fn post(&self, msg: &JsonRpcMessage) -> Result<Response, TransportError> {
+        let mut retried = false;
+        loop {
+            let response = self
+                .request(reqwest::Method::POST)
+                .await
+                .header(ACCEPT, "application/json, text/event-stream")
+                .json(msg)
+                .send()
+                .await
+                .map_err(|e| TransportError::Http(e.to_string()))?;
+            if response.status() != StatusCode::UNAUTHORIZED || retried {
+                return Ok(response);
+            }
+            match &self.auth {
+                Some(auth) if auth.refresh().await => retried = true,
+                _ => return Ok(response),
+            }
+        }
+    }
+
+    /// Checks the status of a response, forgetting the session if the server no longer knows it.
+    async fn check_status(&self, response: Response) -> Result<Response, TransportError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if status == StatusCode::NOT_FOUND {
+            if let Ok(mut session_id) = self.session_id.write() {
+                if session_id.take().is_some() {
+                    return Err(TransportError::Http(
+                        "The session has expired. The server needs to be initialized again".to_string(),
+                    ));
+                }
+            }
+        }
+        if let (StatusCode::UNAUTHORIZED, Some(auth)) = (status, &self.auth) {
+            return Err(TransportError::Http(format!(
+                "{status}: The server requires authorization. Run `q mcp login {}` to log in",
+                auth.server_name()
+            )));
+        }
+        let body = response.text().await.unwrap_or_default();
+        Err(TransportError::Http(format!("{status}: {body}")))
+    }
+
+    fn dispatch(&self, msg: JsonRpcMessage) {
+        if let JsonRpcMessage::Response(resp) = &msg {
+            let is_initialize = self.initialize_id.read().is_ok_and(|id| *id == Some(resp.id));
+            if let (true, Some(version)) = (
+                is_initialize,
+                resp.result
+                    .as_ref()
+                    .and_then(|r| r.get("protocolVersion"))
+                    .and_then(|v| v.as_str()),
+            ) {
+                if let Ok(mut protocol_version) = self.protocol_version.write() {
+                    protocol_version.replace(version.to_string());
+                }
+            }
+        }
+        let _ = self.tx.send(Ok(msg));
+    }
+
+    /// Dispatches the messages of a JSON body, which may be a single message or a batch.
+    fn dispatch_json(&self, body: &[u8]) {
+        match serde_json::from_slice::<JsonRpcMessage>(body) {
+            Ok(msg) => self.dispatch(msg),
+            Err(e) => match serde_json::from_slice::<Vec<JsonRpcMessage>>(body) {
+                Ok(batch) => batch.into_iter().for_each(|msg| self.dispatch(msg)),
+                Err(_) => {
+                    let _ = self.tx.send(Err(e.into()));
+                },
+            },
+        }
+    }
+
+    /// Opens an event stream with a GET request, resuming after `last_event_id` if given. Returns
+    /// [None] if the server does not offer one.
+    async fn get_event_stream(&self, last_event_id: Option<&str>) -> Result<Option<Response>, TransportError> {
+        let mut builder = self
+            .request(reqwest::Method::GET)
+            .await
+            .header(ACCEPT, "text/event-stream");
+        if let Some(last_event_id) = last_event_id {
+            builder = builder.header(LAST_EVENT_ID_HEADER, last_event_id);
+        }
+        let response = builder.send().await.map_err(|e| TransportError::Http(e.to_string()))?;
+        if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(None);
+        }
+        Ok(Some(self.check_status(response).await?))
+    }
+
+    /// Reads the messages of an event stream until it is done.
+    ///
+    /// A stream opened for a request is done once the response to `request_id` has been received.
+    /// The server stream (`request_id` is [None]) is only done when the server closes it for good.
+    /// Interrupted streams are resumed from the last event received.
+    async fn read_event_stream(&self, mut response: Response, request_id: Option<RequestId>) {
+        let mut last_event_id = None::<String>;
+        let mut retry = DEFAULT_RETRY_MS;
+        let mut attempts = 0;
+        loop {
+            let mut decoder = SseDecoder::default();
+            let mut responded = false;
+            let result = loop {
+                match response.chunk().await {
+                    Ok(Some(chunk)) => {
+                        attempts = 0;
+                        for event in decoder.push(&chunk) {
+                            if let Some(id) = event.id {
+                                last_event_id = Some(id);
+                            }
+                            if let Some(ms) = event.retry {
+                                retry = ms;
+                            }
+                            if event.event != "message" || event.data.is_empty() {
+                                continue;
+                            }
+                            match serde_json::from_str::<JsonRpcMessage>(&event.data) {
+                                Ok(msg) => {
+                                    if let (JsonRpcMessage::Response(resp), Some(id)) = (&msg, request_id) {
+                                        responded |= resp.id == id;
+                                    }
+                                    self.dispatch(msg);
+                                },
+                                Err(e) => {
+                                    let _ = self.tx.send(Err(e.into()));
+                                },
+                            }
+                        }
+                        if responded {
+                            return;
+                        }
+                    },
+                    Ok(None) => break Ok(()),
+                    Err(e) => break Err(e),
+                }
+            };
+
+            if let Err(e) = result {
+                self.log(format!("Event stream interrupted: {e}"));
+            }
+            // Streams of requests can only be resumed if the server gave its events ids
+            if request_id.is_some() && last_event_id.is_none() {
+                self.log("Event stream closed before the response was received".to_string());
+                return;
+            }
+
+            loop {
+                attempts += 1;
+                if attempts > MAX_RECONNECT_ATTEMPTS {
+                    self.log(format!(
+                        "Giving up on event stream after {MAX_RECONNECT_ATTEMPTS} attempts"
+                    ));
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(retry)).await;
+                match self.get_event_stream(last_event_id.as_deref()).await {
+                    Ok(Some(resumed)) => {
+                        response = resumed;
+                        break;
+                    },
+                    Ok(None) => return,
+                    Err(e) => self.log(format!("Failed to reconnect to event stream: {e}")),
+                }
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for JsonRpcHttpTransport {
+    async fn send(&self, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+        if msg.is_initialize() {
+            if let Ok(mut initialize_id) = self.inner.initialize_id.write() {
+                *initialize_id = msg.id();
+            }
+        }
+        let response = self.inner.post(msg).await?;
+
+        if msg.is_initialize() {
+            let session_id = response
+                .headers()
+                .get(SESSION_ID_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            if let Ok(mut current) = self.inner.session_id.write() {
+                *current = session_id;
+            }
+        }
+        let response = self.inner.check_status(response).await?;
+
+        if let JsonRpcMessage::Notification(notif) = msg {
+            if notif.method == "notifications/initialized" {
+                self.open_server_stream();
+            }
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if response.status() == StatusCode::ACCEPTED || !matches!(msg, JsonRpcMessage::Request(_)) {
+            return Ok(());
+        }
+
+        if content_type.starts_with("text/event-stream") {
+            let inner = self.inner.clone();
+            let request_id = msg.id();
+            tokio::spawn(async move {
+                inner.read_event_stream(response, request_id).await;
+            });
+        } else {
+            let body = response
+                .bytes()
+                .await
+                .map_err(|e| TransportError::Http(e.to_string()))?;
+            self.inner.dispatch_json(&body);
+        }
+        Ok(())
+    }
+
+    fn get_listener(&self) -> impl Listener {
+        StdioListener {
+            receiver: self.receiver.resubscribe(),
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        if let Some(server_stream) = self.server_stream.lock().ok().and_then(|mut s| s.take()) {
+            server_stream.abort();
+        }
+        // Terminate the session if the server gave us one. Servers may not allow this.
+        let has_session = self.inner.session_id.read().is_ok_and(|s| s.is_some());
+        if has_session {
+            let _ = self.inner.request(reqwest::Method::DELETE).await.send().await;
+            if let Ok(mut session_id) = self.inner.session_id.write() {
+                session_id.take();
+            }
+        }
+        Ok(())
+    }
+
+    fn get_log_listener(&self) -> impl LogListener {
+        StdioLogListener {
+            receiver: self.log_receiver.resubscribe(),
+        }
+    }
+}
+
+impl Drop for JsonRpcHttpTransport {
+    fn drop(&mut self) {
+        if let Some(server_stream) = self.server_stream.lock().ok().and_then(|mut s| s.take()) {
+            server_stream.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use bytes::Bytes;
+    use http_body_util::{
+        BodyExt,
+        Full,
+    };
+    use hyper::body::Incoming;
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper_util::rt::TokioIo;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::mcp_client::transport::base_protocol::{
+        JsonRpcNotification,
+        JsonRpcRequest,
+        JsonRpcVersion,
+    };
+
+    const SESSION_ID: &str = "test-session";
+    const PROTOCOL_VERSION: &str = "2025-03-26";
+
+    /// A request received by the test server: its method, session id, last event id and body
+    type Received = (String, Option<String>, Option<String>, serde_json::Value);
+
+    #[derive(Default)]
+    struct ServerState {
+        received: Vec<Received>,
+        server_stream_opened: bool,
+    }
+
+    fn sse(body: String) -> hyper::Response<Full<Bytes>> {
+        hyper::Response::builder()
+            .header(CONTENT_TYPE, "text/event-stream")
+            .body(Full::new(Bytes::from(body)))
+            .unwrap()
+    }
+
+    fn status(status: u16) -> hyper::Response<Full<Bytes>> {
+        hyper::Response::builder()
+            .status(status)
+            .body(Full::new(Bytes::new()))
+            .unwrap()
+    }
+
+    async fn handle(
+        state: Arc<SyncMutex<ServerState>>,
+        req: hyper::Request<Incoming>,
+    ) -> Result<hyper::Response<Full<Bytes>>, Infallible> {
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let method = req.method().to_string();
+        let session_id = header(SESSION_ID_HEADER);
+        let last_event_id = header(LAST_EVENT_ID_HEADER);
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap_or_default();
+        let mut state = state.lock().unwrap();
+        state
+            .received
+            .push((method.clone(), session_id.clone(), last_event_id.clone(), body.clone()));
+
+        if body["method"] == "protected/call" {
+            return Ok(status(401));
+        }
+        let is_initialize = body["method"] == "initialize";
+        if !is_initialize && session_id.as_deref() != Some(SESSION_ID) {
+            return Ok(status(404));
+        }
+        let response = match (method.as_str(), body["method"].as_str()) {
+            ("POST", Some("initialize")) => hyper::Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .header(SESSION_ID_HEADER, SESSION_ID)
+                .body(Full::new(Bytes::from(
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": body["id"],
+                        "result": { "protocolVersion": PROTOCOL_VERSION, "capabilities": {} }
+                    })
+                    .to_string(),
+                )))
+                .unwrap(),
+            // Answered on a stream that is interrupted before the response, and then resumed
+            ("POST", Some("slow/call")) => sse(format!(
+                "retry: 10\nid: 1\ndata: {}\n\n",
+                json!({ "jsonrpc": "2.0", "method": "notifications/progress", "params": {} })
+            )),
+            ("POST", Some(_)) if body.get("id").is_some() => sse(format!(
+                ": ping\n\nid: 1\ndata: {}\n\nid: 2\ndata: {}\n\n",
+                json!({ "jsonrpc": "2.0", "method": "notifications/message", "params": {} }),
+                json!({ "jsonrpc": "2.0", "id": body["id"], "result": { "tools": [] } })
+            )),
+            ("POST", _) => status(202),
+            ("GET", _) if last_event_id.as_deref() == Some("1") => sse(format!(
+                "id: 2\ndata: {}\n\n",
+                json!({ "jsonrpc": "2.0", "id": 2, "result": { "done": true } })
+            )),
+            ("GET", _) if !state.server_stream_opened => {
+                state.server_stream_opened = true;
+                sse(format!(
+                    "data: {}\n\n",
+                    json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })
+                ))
+            },
+            ("GET", _) => status(405),
+            ("DELETE", _) => status(200),
+            _ => status(400),
+        };
+        Ok(response)
+    }
+
+    async fn serve(state: Arc<SyncMutex<ServerState>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(state.clone(), req));
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        format!("http://{addr}/mcp")
+    }
+
+    fn request(id: u64, method: &str) -> JsonRpcMessage {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            method: method.to_string(),
+            params: None,
+        })
+    }
+
+    async fn recv_response(listener: &mut impl Listener, id: u64) -> serde_json::Value {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(JsonRpcMessage::Response(resp)) = listener.recv().await {
+                    if resp.id == id {
+                        break resp.result.unwrap();
+                    }
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for response")
+    }
+
+    async fn recv_notification(listener: &mut impl Listener, method: &str) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(JsonRpcMessage::Notification(notif)) = listener.recv().await {
+                    if notif.method == method {
+                        break;
+                    }
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for notification");
+    }
+
+    #[tokio::test]
+    async fn test_streamable_http_session() {
+        let state = Arc::new(SyncMutex::new(ServerState::default()));
+        let url = serve(state.clone()).await;
+        let headers = HashMap::from([("x-api-key".to_string(), "secret".to_string())]);
+        let transport = JsonRpcHttpTransport::client(&url, &headers, None).unwrap();
+        let mut listener = transport.get_listener();
+
+        // JSON response, which starts the session
+        transport.send(&request(0, "initialize")).await.unwrap();
+        assert_eq!(
+            recv_response(&mut listener, 0).await["protocolVersion"],
+            PROTOCOL_VERSION
+        );
+
+        // Server messages are received on the GET stream once initialized
+        transport
+            .send(&JsonRpcMessage::Notification(JsonRpcNotification {
+                jsonrpc: JsonRpcVersion::default(),
+                method: "notifications/initialized".to_string(),
+                params: None,
+            }))
+            .await
+            .unwrap();
+        recv_notification(&mut listener, "notifications/tools/list_changed").await;
+
+        // SSE response, with a notification preceding the response
+        transport.send(&request(1, "tools/list")).await.unwrap();
+        recv_notification(&mut listener, "notifications/message").await;
+        assert_eq!(recv_response(&mut listener, 1).await, json!({ "tools": [] }));
+
+        // SSE response interrupted before the response, then resumed
+        transport.send(&request(2, "slow/call")).await.unwrap();
+        assert_eq!(recv_response(&mut listener, 2).await, json!({ "done": true }));
+
+        transport.shutdown().await.unwrap();
+
+        let received = state.lock().unwrap().received.clone();
+        let (method, session_id, ..) = received.last().unwrap();
+        assert_eq!((method.as_str(), session_id.as_deref()), ("DELETE", Some(SESSION_ID)));
+        assert!(
+            received
+                .iter()
+                .skip(1)
+                .all(|(_, session_id, ..)| session_id.as_deref() == Some(SESSION_ID))
+        );
+        assert!(
+            received
+                .iter()
+                .any(|(method, _, last_event_id, _)| method == "GET" && last_event_id.as_deref() == Some("1"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_session() {
+        let state = Arc::new(SyncMutex::new(ServerState::default()));
+        let url = serve(state.clone()).await;
+        let transport = JsonRpcHttpTransport::client(&url, &HashMap::new(), None).unwrap();
+        transport
+            .inner
+            .session_id
+            .write()
+            .unwrap()
+            .replace("expired".to_string());
+
+        let err = transport.send(&request(1, "tools/list")).await.unwrap_err();
+        assert!(err.to_string().contains("session has expired"));
+        assert!(transport.inner.session_id.read().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized() {
+        let state = Arc::new(SyncMutex::new(ServerState::default()));
+        let url = serve(state.clone()).await;
+        let database = crate::database::Database::new().await.unwrap();
+        let auth = Arc::new(McpTokenProvider::new(
+            "tickets".to_string(),
+            url.clone(),
+            database,
+            reqwest::Client::new(),
+        ));
+        let transport = JsonRpcHttpTransport::client(&url, &HashMap::new(), Some(auth.clone())).unwrap();
+
+        let err = transport.send(&request(1, "protected/call")).await.unwrap_err();
+        assert!(err.to_string().contains("q mcp login tickets"), "{err}");
+
+        // Configured Authorization headers take precedence over logging in
+        let headers = HashMap::from([("Authorization".to_string(), "Bearer abc".to_string())]);
+        let transport = JsonRpcHttpTransport::client(&url, &headers, Some(auth)).unwrap();
+        assert!(transport.inner.auth.is_none());
+    }
+}
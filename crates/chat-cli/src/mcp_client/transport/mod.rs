@@ -1,11 +1,17 @@
 pub mod base_protocol;
+pub mod event_stream;
+pub mod http;
+pub mod reconnect;
+pub mod sse;
 pub mod stdio;
+pub mod websocket;
 
 use std::fmt::Debug;
 
 pub use base_protocol::*;
 pub use stdio::*;
 use thiserror::Error;
+use tokio::sync::watch;
 
 #[derive(Clone, Debug, Error)]
 pub enum TransportError {
@@ -13,6 +19,8 @@ pub enum TransportError {
     Serialization(String),
     #[error("IO error: {0}")]
     Stdio(String),
+    #[error("HTTP error: {0}")]
+    Http(String),
     #[error("{0}")]
     Custom(String),
     #[error(transparent)]
@@ -44,6 +52,15 @@ pub trait Transport: Send + Sync + Debug + 'static {
     async fn shutdown(&self) -> Result<(), TransportError>;
     /// Listener that listens for logging messages.
     fn get_log_listener(&self) -> impl LogListener;
+    /// Describes why the connection ended, once the listeners have been closed
+    async fn exit_reason(&self) -> Option<String> {
+        None
+    }
+    /// Counts the connections made to the server, for transports that reconnect after losing
+    /// their connection. Every connection after the first starts a new session with the server.
+    fn connections(&self) -> Option<watch::Receiver<u64>> {
+        None
+    }
 }
 
 #[async_trait::async_trait]
@@ -0,0 +1,115 @@
+//! Building blocks for transports holding a long lived connection to a remote server.
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use super::TransportError;
+
+/// State of the connection of a transport to its server, as published by the task maintaining it.
+#[derive(Debug, Clone)]
+pub enum ConnectionState<T> {
+    /// Not connected yet, or trying to connect again after losing the connection
+    Connecting,
+    /// Connected, along with what is needed to send messages on the connection
+    Connected(T),
+    /// The transport gave up on reconnecting or was shut down
+    Closed,
+}
+
+/// Publishes the state of the connection of a transport, counting every time it is established.
+#[derive(Debug)]
+pub struct ConnectionStateSender<T> {
+    state: watch::Sender<ConnectionState<T>>,
+    connections: watch::Sender<u64>,
+}
+
+impl<T> ConnectionStateSender<T> {
+    /// Creates the sender along with receivers of the state and of the number of connections.
+    pub fn new() -> (Self, watch::Receiver<ConnectionState<T>>, watch::Receiver<u64>) {
+        let (state, state_rx) = watch::channel(ConnectionState::Connecting);
+        let (connections, connections_rx) = watch::channel(0);
+        (Self { state, connections }, state_rx, connections_rx)
+    }
+
+    pub fn send(&self, state: ConnectionState<T>) {
+        let connected = matches!(state, ConnectionState::Connected(_));
+        let _ = self.state.send(state);
+        if connected {
+            self.connections.send_modify(|n| *n += 1);
+        }
+    }
+}
+
+/// Waits for the transport to be connected and returns what is needed to send messages, or fails
+/// if the transport is closed.
+pub async fn connected<T: Clone>(state: &watch::Receiver<ConnectionState<T>>) -> Result<T, TransportError> {
+    let closed = || TransportError::Custom("The connection to the server is closed".to_string());
+    let mut state = state.clone();
+    let state = state
+        .wait_for(|state| !matches!(state, ConnectionState::Connecting))
+        .await
+        .map_err(|_err| closed())?;
+    match &*state {
+        ConnectionState::Connected(connection) => Ok(connection.clone()),
+        _ => Err(closed()),
+    }
+}
+
+/// Exponential backoff between attempts to reconnect.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    max_attempts: u32,
+    attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration, max_attempts: u32) -> Self {
+        Self {
+            initial,
+            max,
+            max_attempts,
+            attempts: 0,
+        }
+    }
+
+    /// Returns how long to wait before the next attempt, or [None] once out of attempts.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if self.attempts >= self.max_attempts {
+            return None;
+        }
+        let delay = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempts))
+            .min(self.max);
+        self.attempts += 1;
+        Some(delay)
+    }
+
+    /// Starts over after a successful attempt.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(30), 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(350), 4);
+        let delays = std::iter::from_fn(|| backoff.next_delay()).collect::<Vec<_>>();
+        assert_eq!(delays, [100, 200, 350, 350].map(Duration::from_millis));
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(Duration::from_millis(100)));
+    }
+}
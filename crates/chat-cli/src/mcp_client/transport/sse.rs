@@ -0,0 +1,430 @@
+//! The HTTP with SSE transport of earlier protocol revisions, as specified by
+//! https://modelcontextprotocol.io/specification/2024-11-05/basic/transports#http-with-sse
+use std::collections::HashMap;
+
+use reqwest::header::{
+    ACCEPT,
+    HeaderMap,
+};
+use reqwest::{
+    Response,
+    Url,
+};
+use tokio::sync::{
+    broadcast,
+    watch,
+};
+use tokio::task::JoinHandle;
+
+use super::base_protocol::JsonRpcMessage;
+use super::event_stream::SseDecoder;
+use super::http::header_map;
+use super::reconnect::{
+    Backoff,
+    ConnectionState,
+    ConnectionStateSender,
+    connected,
+};
+use super::{
+    Listener,
+    LogListener,
+    StdioListener,
+    StdioLogListener,
+    Transport,
+    TransportError,
+};
+
+#[derive(Debug)]
+pub struct JsonRpcSseTransport {
+    client: reqwest::Client,
+    headers: HeaderMap,
+    /// The endpoint messages are posted to, as announced by the server on the event stream
+    endpoint: watch::Receiver<ConnectionState<Url>>,
+    /// Number of times the event stream has been connected
+    connections: watch::Receiver<u64>,
+    receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
+    log_receiver: broadcast::Receiver<String>,
+    event_stream: JoinHandle<()>,
+}
+
+impl JsonRpcSseTransport {
+    pub fn client(url: &str, headers: &HashMap<String, String>) -> Result<Self, TransportError> {
+        Self::with_backoff(url, headers, Backoff::default())
+    }
+
+    pub fn with_backoff(
+        url: &str,
+        headers: &HashMap<String, String>,
+        backoff: Backoff,
+    ) -> Result<Self, TransportError> {
+        let url = Url::parse(url).map_err(|e| TransportError::Custom(format!("Invalid url {url}: {e}")))?;
+        let headers = header_map(headers)?;
+        let client = crate::request::new_client().map_err(|e| TransportError::Http(e.to_string()))?;
+        let (tx, receiver) = broadcast::channel::<Result<JsonRpcMessage, TransportError>>(100);
+        let (log_tx, log_receiver) = broadcast::channel::<String>(100);
+        let (endpoint_tx, endpoint, connections) = ConnectionStateSender::new();
+
+        let event_stream = tokio::spawn(maintain_event_stream(
+            client.clone(),
+            url,
+            headers.clone(),
+            backoff,
+            tx,
+            log_tx,
+            endpoint_tx,
+        ));
+
+        Ok(Self {
+            client,
+            headers,
+            endpoint,
+            connections,
+            receiver,
+            log_receiver,
+            event_stream,
+        })
+    }
+}
+
+/// Keeps the event stream of the server open, reconnecting whenever it is lost. The senders are
+/// owned by this task so that listeners see the transport as closed once it gives up.
+async fn maintain_event_stream(
+    client: reqwest::Client,
+    url: Url,
+    headers: HeaderMap,
+    mut backoff: Backoff,
+    tx: broadcast::Sender<Result<JsonRpcMessage, TransportError>>,
+    log_tx: broadcast::Sender<String>,
+    endpoint_tx: ConnectionStateSender<Url>,
+) {
+    let log = |msg: String| {
+        let _ = log_tx.send(msg);
+    };
+    loop {
+        let response = client
+            .get(url.clone())
+            .headers(headers.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .and_then(Response::error_for_status);
+        match response {
+            Ok(mut response) => {
+                let mut decoder = SseDecoder::default();
+                let result = loop {
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => {
+                            for event in decoder.push(&chunk) {
+                                match event.event.as_str() {
+                                    "endpoint" => match url.join(&event.data) {
+                                        Ok(endpoint) if endpoint.origin() == url.origin() => {
+                                            log(format!("Connected to {url}"));
+                                            backoff.reset();
+                                            endpoint_tx.send(ConnectionState::Connected(endpoint));
+                                        },
+                                        _ => log(format!("Ignoring invalid endpoint {}", event.data)),
+                                    },
+                                    "message" => {
+                                        let msg = serde_json::from_str::<JsonRpcMessage>(&event.data)
+                                            .map_err(TransportError::from);
+                                        let _ = tx.send(msg);
+                                    },
+                                    _ => {},
+                                }
+                            }
+                        },
+                        Ok(None) => break Ok(()),
+                        Err(e) => break Err(e),
+                    }
+                };
+                match result {
+                    Ok(()) => log(format!("Event stream of {url} closed by the server")),
+                    Err(e) => log(format!("Event stream of {url} interrupted: {e}")),
+                }
+            },
+            Err(e) => log(format!("Failed to connect to {url}: {e}")),
+        }
+
+        endpoint_tx.send(ConnectionState::Connecting);
+        let Some(delay) = backoff.next_delay() else {
+            log(format!("Giving up on reconnecting to {url}"));
+            endpoint_tx.send(ConnectionState::Closed);
+            return;
+        };
+        log(format!("Reconnecting to {url} in {}ms", delay.as_millis()));
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for JsonRpcSseTransport {
+    async fn send(&self, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+        // Messages can only be sent once the server told us where to, which it does again every time
+        // we reconnect
+        let endpoint = connected(&self.endpoint).await?;
+        self.client
+            .post(endpoint)
+            .headers(self.headers.clone())
+            .json(msg)
+            .send()
+            .await
+            .and_then(Response::error_for_status)
+            .map_err(|e| TransportError::Http(e.to_string()))?;
+        Ok(())
+    }
+
+    fn get_listener(&self) -> impl Listener {
+        StdioListener {
+            receiver: self.receiver.resubscribe(),
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        self.event_stream.abort();
+        Ok(())
+    }
+
+    fn get_log_listener(&self) -> impl LogListener {
+        StdioLogListener {
+            receiver: self.log_receiver.resubscribe(),
+        }
+    }
+
+    fn connections(&self) -> Option<watch::Receiver<u64>> {
+        Some(self.connections.clone())
+    }
+}
+
+impl Drop for JsonRpcSseTransport {
+    fn drop(&mut self) {
+        self.event_stream.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::{
+        Arc,
+        Mutex,
+    };
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use http_body_util::combinators::BoxBody;
+    use http_body_util::{
+        BodyExt,
+        Full,
+        StreamBody,
+    };
+    use hyper::body::{
+        Frame,
+        Incoming,
+    };
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper_util::rt::TokioIo;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::mcp_client::client::Client;
+    use crate::mcp_client::transport::base_protocol::{
+        JsonRpcRequest,
+        JsonRpcVersion,
+    };
+
+    #[derive(Default)]
+    struct ServerState {
+        sessions: HashMap<u32, mpsc::UnboundedSender<Bytes>>,
+        connections: u32,
+        /// Methods of the requests and notifications received, along with their session
+        received: Vec<(u32, String)>,
+    }
+
+    fn body(bytes: impl Into<Bytes>) -> BoxBody<Bytes, Infallible> {
+        Full::new(bytes.into()).boxed()
+    }
+
+    /// A server holding one event stream per session. Requests are answered with the session they
+    /// were received on, except for `initialize` which is answered with empty capabilities. The
+    /// `close` method closes the event stream after answering.
+    async fn handle(
+        state: Arc<Mutex<ServerState>>,
+        req: hyper::Request<Incoming>,
+    ) -> Result<hyper::Response<BoxBody<Bytes, Infallible>>, Infallible> {
+        let status = |status: u16| hyper::Response::builder().status(status).body(body("")).unwrap();
+        let method = req.method().to_string();
+        let session = req
+            .uri()
+            .query()
+            .and_then(|q| q.strip_prefix("session="))
+            .and_then(|s| s.parse::<u32>().ok());
+
+        if method == "GET" && req.uri().path() == "/sse" {
+            let (events_tx, events_rx) = mpsc::unbounded_channel::<Bytes>();
+            let mut state = state.lock().unwrap();
+            state.connections += 1;
+            let session = state.connections;
+            let _ = events_tx.send(Bytes::from(format!(
+                ": connected\n\nevent: endpoint\ndata: /messages?session={session}\n\n"
+            )));
+            state.sessions.insert(session, events_tx);
+            let stream = futures::stream::unfold(events_rx, |mut rx| async move {
+                rx.recv().await.map(|bytes| (Ok(Frame::data(bytes)), rx))
+            });
+            return Ok(hyper::Response::builder()
+                .header("content-type", "text/event-stream")
+                .body(StreamBody::new(stream).boxed())
+                .unwrap());
+        }
+
+        let Some(session) = session.filter(|_| method == "POST" && req.uri().path() == "/messages") else {
+            return Ok(status(404));
+        };
+        let msg = req.into_body().collect().await.unwrap().to_bytes();
+        let msg = serde_json::from_slice::<serde_json::Value>(&msg).unwrap();
+        let mut state = state.lock().unwrap();
+        if let Some(method) = msg["method"].as_str() {
+            state.received.push((session, method.to_string()));
+        }
+        let Some(events_tx) = state.sessions.get(&session) else {
+            return Ok(status(404));
+        };
+        if msg.get("id").is_some() {
+            let result = match msg["method"].as_str() {
+                Some("initialize") => json!({ "capabilities": {} }),
+                _ => json!({ "session": session }),
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": msg["id"], "result": result });
+            let _ = events_tx.send(Bytes::from(format!("event: message\ndata: {response}\n\n")));
+        }
+        if msg["method"] == "close" {
+            state.sessions.remove(&session);
+        }
+        Ok(status(202))
+    }
+
+    async fn serve() -> (String, Arc<Mutex<ServerState>>) {
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        let server_state = state.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(state.clone(), req));
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+        (format!("http://{addr}/sse"), server_state)
+    }
+
+    async fn call(transport: &JsonRpcSseTransport, id: u64, method: &str) -> serde_json::Value {
+        let mut listener = transport.get_listener();
+        let request = JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            method: method.to_string(),
+            params: None,
+        });
+        tokio::time::timeout(Duration::from_secs(5), async {
+            transport.send(&request).await.unwrap();
+            loop {
+                if let Ok(JsonRpcMessage::Response(resp)) = listener.recv().await {
+                    if resp.id == id {
+                        break resp.result.unwrap();
+                    }
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for response")
+    }
+
+    #[tokio::test]
+    async fn test_sse_reconnect() {
+        let (url, _) = serve().await;
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10), 3);
+        let transport = JsonRpcSseTransport::with_backoff(&url, &HashMap::new(), backoff).unwrap();
+        let mut log_listener = transport.get_log_listener();
+
+        assert_eq!(call(&transport, 1, "ping").await, json!({ "session": 1 }));
+        assert_eq!(call(&transport, 2, "close").await, json!({ "session": 1 }));
+        let mut logs = Vec::new();
+        while !logs
+            .last()
+            .is_some_and(|log: &String| log.contains("closed by the server"))
+        {
+            logs.push(log_listener.recv().await.unwrap());
+        }
+
+        // Sent once the transport has reconnected and received the endpoint of the new session
+        assert_eq!(call(&transport, 3, "ping").await, json!({ "session": 2 }));
+        while let Ok(Ok(log)) = tokio::time::timeout(Duration::from_millis(100), log_listener.recv()).await {
+            logs.push(log);
+        }
+        assert_eq!(logs.iter().filter(|log| log.starts_with("Connected")).count(), 2);
+        assert_eq!(*transport.connections().unwrap().borrow(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_initializes_after_reconnect() {
+        let (url, state) = serve().await;
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10), 3);
+        let transport = JsonRpcSseTransport::with_backoff(&url, &HashMap::new(), backoff).unwrap();
+        let client = Client::from_transport("test".to_string(), transport, 5000, json!({}));
+        client.init().await.unwrap();
+        client.request("close", None).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !state
+                .lock()
+                .unwrap()
+                .received
+                .contains(&(2, "notifications/initialized".to_string()))
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the new session to be initialized");
+        let received = state.lock().unwrap().received.clone();
+        let initialize = |session: u32| received.iter().position(|r| *r == (session, "initialize".to_string()));
+        let initialized = |session: u32| {
+            received
+                .iter()
+                .position(|r| *r == (session, "notifications/initialized".to_string()))
+        };
+        assert!(initialize(1) < initialized(1));
+        assert!(initialize(2).is_some());
+        assert!(initialize(2) < initialized(2));
+    }
+
+    #[tokio::test]
+    async fn test_sse_gives_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/sse", listener.local_addr().unwrap());
+        drop(listener);
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10), 2);
+        let transport = JsonRpcSseTransport::with_backoff(&url, &HashMap::new(), backoff).unwrap();
+        let mut listener = transport.get_listener();
+
+        let request = JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id: 1,
+            method: "ping".to_string(),
+            params: None,
+        });
+        assert!(transport.send(&request).await.is_err());
+        assert!(matches!(
+            listener.recv().await,
+            Err(TransportError::RecvError(broadcast::error::RecvError::Closed))
+        ));
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use tokio::io::{
     AsyncBufReadExt,
@@ -15,6 +16,7 @@ use tokio::process::{
 use tokio::sync::{
     Mutex,
     broadcast,
+    watch,
 };
 
 use super::base_protocol::JsonRpcMessage;
@@ -25,24 +27,27 @@ use super::{
     TransportError,
 };
 
+type MessageSender = broadcast::Sender<Result<JsonRpcMessage, TransportError>>;
+
 #[derive(Debug)]
 pub enum JsonRpcStdioTransport {
     Client {
         stdin: Arc<Mutex<ChildStdin>>,
         receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
         log_receiver: broadcast::Receiver<String>,
+        /// How the server process exited, once it has
+        exit_status: watch::Receiver<Option<String>>,
     },
     Server {
         stdout: Arc<Mutex<Stdout>>,
         receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
+        /// Stdin is only read once there is a listener, since messages sent before that are lost
+        reader: std::sync::Mutex<Option<(Stdin, MessageSender)>>,
     },
 }
 
 impl JsonRpcStdioTransport {
-    fn spawn_reader<R: AsyncRead + Unpin + Send + 'static>(
-        reader: R,
-        tx: broadcast::Sender<Result<JsonRpcMessage, TransportError>>,
-    ) {
+    fn spawn_reader<R: AsyncRead + Unpin + Send + 'static>(reader: R, tx: MessageSender) {
         tokio::spawn(async move {
             let mut buffer = Vec::<u8>::new();
             let mut buf_reader = BufReader::new(reader);
@@ -68,17 +73,25 @@ impl JsonRpcStdioTransport {
         });
     }
 
-    pub fn client(child_process: Child) -> Result<Self, TransportError> {
+    pub fn client(mut child_process: Child) -> Result<Self, TransportError> {
         let (tx, receiver) = broadcast::channel::<Result<JsonRpcMessage, TransportError>>(100);
-        let Some(stdout) = child_process.stdout else {
+        let Some(stdout) = child_process.stdout.take() else {
             return Err(TransportError::Custom("No stdout found on child process".to_owned()));
         };
-        let Some(stdin) = child_process.stdin else {
+        let Some(stdin) = child_process.stdin.take() else {
             return Err(TransportError::Custom("No stdin found on child process".to_owned()));
         };
-        let Some(stderr) = child_process.stderr else {
+        let Some(stderr) = child_process.stderr.take() else {
             return Err(TransportError::Custom("No stderr found on child process".to_owned()));
         };
+        let (exit_tx, exit_status) = watch::channel(None);
+        tokio::task::spawn(async move {
+            let status = match child_process.wait().await {
+                Ok(status) => status.to_string(),
+                Err(e) => e.to_string(),
+            };
+            let _ = exit_tx.send(Some(status));
+        });
         let (log_tx, log_receiver) = broadcast::channel::<String>(100);
         tokio::task::spawn(async move {
             let stderr = tokio::io::BufReader::new(stderr);
@@ -93,14 +106,18 @@ impl JsonRpcStdioTransport {
             stdin,
             receiver,
             log_receiver,
+            exit_status,
         })
     }
 
     pub fn server(stdin: Stdin, stdout: Stdout) -> Result<Self, TransportError> {
         let (tx, receiver) = broadcast::channel::<Result<JsonRpcMessage, TransportError>>(100);
-        Self::spawn_reader(stdin, tx);
         let stdout = Arc::new(Mutex::new(stdout));
-        Ok(JsonRpcStdioTransport::Server { stdout, receiver })
+        Ok(JsonRpcStdioTransport::Server {
+            stdout,
+            receiver,
+            reader: std::sync::Mutex::new(Some((stdin, tx))),
+        })
     }
 }
 
@@ -141,10 +158,17 @@ impl Transport for JsonRpcStdioTransport {
 
     fn get_listener(&self) -> impl Listener {
         match self {
-            JsonRpcStdioTransport::Client { receiver, .. } | JsonRpcStdioTransport::Server { receiver, .. } => {
-                StdioListener {
+            JsonRpcStdioTransport::Client { receiver, .. } => StdioListener {
+                receiver: receiver.resubscribe(),
+            },
+            JsonRpcStdioTransport::Server { receiver, reader, .. } => {
+                let listener = StdioListener {
                     receiver: receiver.resubscribe(),
+                };
+                if let Some((stdin, tx)) = reader.lock().ok().and_then(|mut reader| reader.take()) {
+                    Self::spawn_reader(stdin, tx);
                 }
+                listener
             },
         }
     }
@@ -162,6 +186,22 @@ impl Transport for JsonRpcStdioTransport {
         }
     }
 
+    async fn exit_reason(&self) -> Option<String> {
+        match self {
+            JsonRpcStdioTransport::Client { exit_status, .. } => {
+                // The server closes its stdout right before it exits
+                let mut exit_status = exit_status.clone();
+                let status = tokio::time::timeout(Duration::from_secs(1), exit_status.wait_for(Option::is_some))
+                    .await
+                    .ok()?
+                    .ok()?
+                    .clone()?;
+                Some(format!("The server process exited ({status})"))
+            },
+            JsonRpcStdioTransport::Server { .. } => None,
+        }
+    }
+
     fn get_log_listener(&self) -> impl LogListener {
         match self {
             JsonRpcStdioTransport::Client { log_receiver, .. } => StdioLogListener {
@@ -0,0 +1,313 @@
+//! Transport exchanging one JSON-RPC message per WebSocket text message.
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::stream::SplitSink;
+use futures::{
+    SinkExt,
+    StreamExt,
+};
+use tokio::net::TcpStream;
+use tokio::sync::{
+    Mutex,
+    broadcast,
+    watch,
+};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::{
+    Connector,
+    MaybeTlsStream,
+    WebSocketStream,
+};
+
+use super::base_protocol::JsonRpcMessage;
+use super::http::header_map;
+use super::reconnect::{
+    Backoff,
+    ConnectionState,
+    ConnectionStateSender,
+    connected,
+};
+use super::{
+    Listener,
+    LogListener,
+    StdioListener,
+    StdioLogListener,
+    Transport,
+    TransportError,
+};
+
+type WebSocketSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+
+#[derive(Debug)]
+pub struct JsonRpcWebSocketTransport {
+    sink: watch::Receiver<ConnectionState<Arc<Mutex<WebSocketSink>>>>,
+    /// Number of times the connection has been established
+    connections: watch::Receiver<u64>,
+    receiver: broadcast::Receiver<Result<JsonRpcMessage, TransportError>>,
+    log_receiver: broadcast::Receiver<String>,
+    connection: JoinHandle<()>,
+}
+
+impl JsonRpcWebSocketTransport {
+    pub fn client(url: &str, headers: &HashMap<String, String>) -> Result<Self, TransportError> {
+        Self::with_backoff(url, headers, Backoff::default())
+    }
+
+    pub fn with_backoff(
+        url: &str,
+        headers: &HashMap<String, String>,
+        backoff: Backoff,
+    ) -> Result<Self, TransportError> {
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| TransportError::Custom(format!("Invalid url {url}: {e}")))?;
+        request.headers_mut().extend(header_map(headers)?);
+        let (tx, receiver) = broadcast::channel::<Result<JsonRpcMessage, TransportError>>(100);
+        let (log_tx, log_receiver) = broadcast::channel::<String>(100);
+        let (sink_tx, sink, connections) = ConnectionStateSender::new();
+
+        let connection = tokio::spawn(maintain_connection(request, backoff, tx, log_tx, sink_tx));
+
+        Ok(Self {
+            sink,
+            connections,
+            receiver,
+            log_receiver,
+            connection,
+        })
+    }
+}
+
+/// Keeps the connection to the server open, reconnecting whenever it is lost. The senders are owned
+/// by this task so that listeners see the transport as closed once it gives up.
+async fn maintain_connection(
+    request: Request,
+    mut backoff: Backoff,
+    tx: broadcast::Sender<Result<JsonRpcMessage, TransportError>>,
+    log_tx: broadcast::Sender<String>,
+    sink_tx: ConnectionStateSender<Arc<Mutex<WebSocketSink>>>,
+) {
+    let log = |msg: String| {
+        let _ = log_tx.send(msg);
+    };
+    let url = request.uri().to_string();
+    loop {
+        let connector = Connector::Rustls(Arc::new(crate::request::client_config()));
+        match tokio_tungstenite::connect_async_tls_with_config(request.clone(), None, false, Some(connector)).await {
+            Ok((stream, _)) => {
+                log(format!("Connected to {url}"));
+                backoff.reset();
+                let (sink, mut stream) = stream.split();
+                sink_tx.send(ConnectionState::Connected(Arc::new(Mutex::new(sink))));
+                while let Some(msg) = stream.next().await {
+                    let msg = match msg {
+                        Ok(Message::Text(text)) => serde_json::from_str::<JsonRpcMessage>(&text),
+                        Ok(Message::Binary(bytes)) => serde_json::from_slice::<JsonRpcMessage>(&bytes),
+                        Ok(Message::Close(frame)) => {
+                            let reason = frame.map(|f| f.reason.to_string()).unwrap_or_default();
+                            log(format!("Connection to {url} closed by the server {reason}"));
+                            break;
+                        },
+                        Ok(_) => continue,
+                        Err(e) => {
+                            log(format!("Connection to {url} interrupted: {e}"));
+                            break;
+                        },
+                    };
+                    let _ = tx.send(msg.map_err(TransportError::from));
+                }
+            },
+            Err(e) => log(format!("Failed to connect to {url}: {e}")),
+        }
+
+        sink_tx.send(ConnectionState::Connecting);
+        let Some(delay) = backoff.next_delay() else {
+            log(format!("Giving up on reconnecting to {url}"));
+            sink_tx.send(ConnectionState::Closed);
+            return;
+        };
+        log(format!("Reconnecting to {url} in {}ms", delay.as_millis()));
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for JsonRpcWebSocketTransport {
+    async fn send(&self, msg: &JsonRpcMessage) -> Result<(), TransportError> {
+        let sink = connected(&self.sink).await?;
+        let serialized = serde_json::to_string(msg)?;
+        let mut sink = sink.lock().await;
+        sink.send(Message::text(serialized))
+            .await
+            .map_err(|e| TransportError::Custom(format!("Error writing to server: {:?}", e)))
+    }
+
+    fn get_listener(&self) -> impl Listener {
+        StdioListener {
+            receiver: self.receiver.resubscribe(),
+        }
+    }
+
+    async fn shutdown(&self) -> Result<(), TransportError> {
+        self.connection.abort();
+        if let ConnectionState::Connected(sink) = &*self.sink.borrow() {
+            let sink = sink.clone();
+            // Best effort, the server may already be gone
+            tokio::spawn(async move {
+                let _ = sink.lock().await.close().await;
+            });
+        }
+        Ok(())
+    }
+
+    fn get_log_listener(&self) -> impl LogListener {
+        StdioLogListener {
+            receiver: self.log_receiver.resubscribe(),
+        }
+    }
+
+    fn connections(&self) -> Option<watch::Receiver<u64>> {
+        Some(self.connections.clone())
+    }
+}
+
+impl Drop for JsonRpcWebSocketTransport {
+    fn drop(&mut self) {
+        self.connection.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{
+        AtomicU32,
+        Ordering,
+    };
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{
+        Request as ServerRequest,
+        Response as ServerResponse,
+    };
+
+    use super::*;
+    use crate::mcp_client::transport::base_protocol::{
+        JsonRpcRequest,
+        JsonRpcVersion,
+    };
+
+    /// A server answering requests with the number of the connection they were received on, and
+    /// which closes the connection after answering the `close` method. Connections are only
+    /// accepted with the expected api key.
+    async fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicU32::new(0));
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let connections = connections.clone();
+                tokio::spawn(async move {
+                    let check_key = |req: &ServerRequest, resp: ServerResponse| {
+                        if req.headers().get("x-api-key").is_some_and(|v| v == "secret") {
+                            Ok(resp)
+                        } else {
+                            Err(tokio_tungstenite::tungstenite::http::Response::builder()
+                                .status(401)
+                                .body(None)
+                                .unwrap())
+                        }
+                    };
+                    let Ok(mut ws) = tokio_tungstenite::accept_hdr_async(stream, check_key).await else {
+                        return;
+                    };
+                    let connection = connections.fetch_add(1, Ordering::SeqCst) + 1;
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let msg = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+                        let response =
+                            json!({ "jsonrpc": "2.0", "id": msg["id"], "result": { "connection": connection } });
+                        ws.send(Message::text(response.to_string())).await.unwrap();
+                        if msg["method"] == "close" {
+                            let _ = ws.close(None).await;
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        format!("ws://{addr}/mcp")
+    }
+
+    async fn call(transport: &JsonRpcWebSocketTransport, id: u64, method: &str) -> serde_json::Value {
+        let mut listener = transport.get_listener();
+        let request = JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id,
+            method: method.to_string(),
+            params: None,
+        });
+        tokio::time::timeout(Duration::from_secs(5), async {
+            transport.send(&request).await.unwrap();
+            loop {
+                if let Ok(JsonRpcMessage::Response(resp)) = listener.recv().await {
+                    if resp.id == id {
+                        break resp.result.unwrap();
+                    }
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for response")
+    }
+
+    #[tokio::test]
+    async fn test_websocket_reconnect() {
+        let url = serve().await;
+        let headers = HashMap::from([("x-api-key".to_string(), "secret".to_string())]);
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10), 3);
+        let transport = JsonRpcWebSocketTransport::with_backoff(&url, &headers, backoff).unwrap();
+        let mut log_listener = transport.get_log_listener();
+
+        assert_eq!(call(&transport, 1, "ping").await, json!({ "connection": 1 }));
+        assert_eq!(call(&transport, 2, "close").await, json!({ "connection": 1 }));
+        let mut logs = Vec::new();
+        while !logs
+            .last()
+            .is_some_and(|log: &String| log.contains("closed by the server"))
+        {
+            logs.push(log_listener.recv().await.unwrap());
+        }
+
+        assert_eq!(call(&transport, 3, "ping").await, json!({ "connection": 2 }));
+        assert_eq!(*transport.connections().unwrap().borrow(), 2);
+        transport.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_websocket_gives_up() {
+        let url = serve().await;
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10), 2);
+        // Rejected by the server for missing the api key
+        let transport = JsonRpcWebSocketTransport::with_backoff(&url, &HashMap::new(), backoff).unwrap();
+        let mut listener = transport.get_listener();
+        let mut log_listener = transport.get_log_listener();
+
+        let request = JsonRpcMessage::Request(JsonRpcRequest {
+            jsonrpc: JsonRpcVersion::default(),
+            id: 1,
+            method: "ping".to_string(),
+            params: None,
+        });
+        assert!(transport.send(&request).await.is_err());
+        assert!(matches!(
+            listener.recv().await,
+            Err(TransportError::RecvError(broadcast::error::RecvError::Closed))
+        ));
+        assert!(log_listener.recv().await.unwrap().contains("401"));
+    }
+}
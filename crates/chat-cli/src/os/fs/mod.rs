@@ -230,6 +230,30 @@ impl Fs {
         }
     }
 
+    /// Opens a file in append mode, creating it if it does not exist, and writes the entire
+    /// contents of `contents` to the end of it.
+    pub async fn append_file(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let path = match self {
+            Self::Real => path.as_ref().to_path_buf(),
+            Self::Chroot(root) => append(root.path(), path),
+            Self::Fake(map) => {
+                let Ok(mut lock) = map.lock() else {
+                    return Err(io::Error::other("poisoned lock"));
+                };
+                lock.entry(path.as_ref().to_owned())
+                    .or_default()
+                    .extend_from_slice(contents.as_ref());
+                return Ok(());
+            },
+        };
+
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path).await?;
+        file.write_all(contents.as_ref()).await?;
+        file.flush().await
+    }
+
     /// Removes a file from the filesystem.
     ///
     /// Note that there is no guarantee that the file is immediately deleted (e.g.
@@ -466,6 +490,8 @@ mod tests {
         fs.write(dir.join("write"), b"write").await.unwrap();
         assert_eq!(fs.read(dir.join("write")).await.unwrap(), b"write");
         assert_eq!(fs.read_to_string(dir.join("write")).await.unwrap(), "write");
+        fs.append_file(dir.join("write"), b"-append").await.unwrap();
+        assert_eq!(fs.read_to_string(dir.join("write")).await.unwrap(), "write-append");
     }
 
     #[tokio::test]
@@ -478,6 +504,12 @@ mod tests {
         fs.write(dir.path().join("write"), b"write").await.unwrap();
         assert_eq!(fs.read(dir.path().join("write")).await.unwrap(), b"write");
         assert_eq!(fs.read_to_string(dir.path().join("write")).await.unwrap(), "write");
+        fs.append_file(dir.path().join("append"), b"one\n").await.unwrap();
+        fs.append_file(dir.path().join("append"), b"two\n").await.unwrap();
+        assert_eq!(
+            fs.read_to_string(dir.path().join("append")).await.unwrap(),
+            "one\ntwo\n"
+        );
     }
 
     macro_rules! test_append_cases {
@@ -49,7 +49,7 @@ pub fn create_default_root_cert_store() -> RootCertStore {
     root_cert_store
 }
 
-fn client_config() -> ClientConfig {
+pub fn client_config() -> ClientConfig {
     let provider = rustls::crypto::CryptoProvider::get_default()
         .cloned()
         .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()));
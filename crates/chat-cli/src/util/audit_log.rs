@@ -0,0 +1,297 @@
+//! A local, append-only record of every tool invocation made in `q chat`.
+//!
+//! Unlike telemetry, entries never leave the machine and include the full tool arguments along
+//! with the permission decision that allowed (or prevented) the tool from running. Each entry is
+//! written as a single line of JSON to [directories::audit_log_path].
+
+use std::path::PathBuf;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use time::OffsetDateTime;
+use tracing::warn;
+
+use super::directories;
+use crate::os::Os;
+
+/// Number of hex characters of the output digest that are kept in the log.
+const OUTPUT_HASH_LEN: usize = 16;
+
+/// The outcome of evaluating whether a tool use was allowed to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionDecision {
+    /// The tool was permitted to run.
+    Allowed,
+    /// The tool was forbidden by the agent configuration.
+    Denied,
+    /// The user declined the tool use when prompted.
+    Rejected,
+}
+
+/// Who made the [PermissionDecision] for a tool use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionSource {
+    /// Evaluated automatically from the agent's permissions.
+    Auto,
+    /// Answered by the user at the confirmation prompt.
+    User,
+    /// Allowed because every tool is trusted for the session.
+    TrustAll,
+}
+
+/// How a tool use ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+    Success,
+    Error,
+    /// The tool was never invoked, e.g. because it was denied or rejected.
+    NotRun,
+    /// The tool use was refused before asking for permission, because the tool is unknown or its
+    /// arguments are invalid.
+    Invalid,
+    /// The user interrupted the tool use, either while it ran or before it was decided on.
+    Interrupted,
+}
+
+/// A single line in the audit log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(with = "time::serde::rfc3339")]
+    pub timestamp: OffsetDateTime,
+    pub conversation_id: String,
+    pub agent: Option<String>,
+    pub tool_use_id: String,
+    /// Tool name as it was exposed to the model.
+    pub tool_name: String,
+    /// Where the tool comes from, either `native` or the name of its MCP server.
+    pub origin: String,
+    pub arguments: serde_json::Value,
+    pub decision: Option<PermissionDecision>,
+    pub decided_by: Option<DecisionSource>,
+    pub exit_status: ExitStatus,
+    pub duration_ms: Option<u64>,
+    /// Truncated SHA-256 digest of the output returned to the model.
+    pub output_hash: Option<String>,
+    /// Size of the output returned to the model, in bytes.
+    pub output_bytes: Option<usize>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        conversation_id: impl Into<String>,
+        agent: Option<String>,
+        tool_use_id: impl Into<String>,
+        tool_name: impl Into<String>,
+        origin: impl Into<String>,
+        arguments: serde_json::Value,
+    ) -> Self {
+        Self {
+            timestamp: OffsetDateTime::now_utc(),
+            conversation_id: conversation_id.into(),
+            agent,
+            tool_use_id: tool_use_id.into(),
+            tool_name: tool_name.into(),
+            origin: origin.into(),
+            arguments,
+            decision: None,
+            decided_by: None,
+            exit_status: ExitStatus::NotRun,
+            duration_ms: None,
+            output_hash: None,
+            output_bytes: None,
+        }
+    }
+
+    pub fn decide(&mut self, decision: PermissionDecision, decided_by: DecisionSource) {
+        self.decision = Some(decision);
+        self.decided_by = Some(decided_by);
+    }
+
+    /// Records the result of invoking the tool.
+    pub fn complete(&mut self, exit_status: ExitStatus, duration: std::time::Duration, output: &str) {
+        self.exit_status = exit_status;
+        self.duration_ms = Some(duration.as_millis() as u64);
+        self.output_hash = Some(hash_output(output));
+        self.output_bytes = Some(output.len());
+    }
+}
+
+/// Filters applied when reading entries back from the audit log.
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub since: Option<OffsetDateTime>,
+    pub until: Option<OffsetDateTime>,
+    pub tool_name: Option<String>,
+    pub conversation_id: Option<String>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp <= until)
+            && self.tool_name.as_ref().is_none_or(|name| &entry.tool_name == name)
+            && self
+                .conversation_id
+                .as_ref()
+                .is_none_or(|id| entry.conversation_id.starts_with(id.as_str()))
+    }
+}
+
+pub struct AuditLog;
+
+impl AuditLog {
+    pub fn path() -> Result<PathBuf, directories::DirectoryError> {
+        directories::audit_log_path()
+    }
+
+    /// Appends an entry to the audit log. Failures are logged rather than returned since
+    /// auditing should never interrupt the chat session.
+    pub async fn append(os: &Os, entry: &AuditEntry) {
+        if let Err(err) = Self::try_append(os, entry).await {
+            warn!(?err, "Failed to write to the audit log");
+        }
+    }
+
+    async fn try_append(os: &Os, entry: &AuditEntry) -> eyre::Result<()> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            os.fs.create_dir_all(parent).await?;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        os.fs.append_file(&path, line).await?;
+        Ok(())
+    }
+
+    /// Reads every entry matching `query`, oldest first. Lines that fail to parse are skipped.
+    pub async fn read(os: &Os, query: &AuditQuery) -> eyre::Result<Vec<AuditEntry>> {
+        let path = Self::path()?;
+        if !os.fs.exists(&path) {
+            return Ok(Vec::new());
+        }
+
+        let content = os.fs.read_to_string(&path).await?;
+        Ok(parse_entries(&content).filter(|entry| query.matches(entry)).collect())
+    }
+}
+
+fn parse_entries(content: &str) -> impl Iterator<Item = AuditEntry> + '_ {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str::<AuditEntry>(line) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                warn!(?err, "Skipping malformed audit log entry");
+                None
+            },
+        })
+}
+
+fn hash_output(output: &str) -> String {
+    let digest = hex::encode(Sha256::digest(output.as_bytes()));
+    digest[..OUTPUT_HASH_LEN].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(conversation_id: &str, tool_name: &str, timestamp: OffsetDateTime) -> AuditEntry {
+        AuditEntry {
+            timestamp,
+            ..AuditEntry::new(
+                conversation_id,
+                Some("default".to_string()),
+                "tooluse_1",
+                tool_name,
+                "native",
+                serde_json::json!({ "command": "ls" }),
+            )
+        }
+    }
+
+    #[test]
+    fn test_complete_hashes_output() {
+        let mut e = entry("conv", "execute_bash", OffsetDateTime::now_utc());
+        e.decide(PermissionDecision::Allowed, DecisionSource::User);
+        e.complete(ExitStatus::Success, std::time::Duration::from_millis(1500), "hello");
+
+        assert_eq!(e.duration_ms, Some(1500));
+        assert_eq!(e.output_bytes, Some(5));
+        assert_eq!(e.output_hash.as_deref(), Some("2cf24dba5fb0a30e"));
+        assert_eq!(e.decided_by, Some(DecisionSource::User));
+    }
+
+    #[test]
+    fn test_query_matches() {
+        let now = OffsetDateTime::now_utc();
+        let e = entry("abcdef", "fs_read", now);
+
+        assert!(AuditQuery::default().matches(&e));
+        assert!(
+            AuditQuery {
+                conversation_id: Some("abc".to_string()),
+                tool_name: Some("fs_read".to_string()),
+                ..Default::default()
+            }
+            .matches(&e)
+        );
+        assert!(
+            !AuditQuery {
+                tool_name: Some("fs_write".to_string()),
+                ..Default::default()
+            }
+            .matches(&e)
+        );
+        assert!(
+            !AuditQuery {
+                since: Some(now + time::Duration::minutes(1)),
+                ..Default::default()
+            }
+            .matches(&e)
+        );
+        assert!(
+            !AuditQuery {
+                until: Some(now - time::Duration::minutes(1)),
+                ..Default::default()
+            }
+            .matches(&e)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_append_and_read() {
+        let os = Os::new().await.unwrap();
+        let now = OffsetDateTime::now_utc();
+
+        AuditLog::append(&os, &entry("one", "fs_read", now)).await;
+        AuditLog::append(&os, &entry("two", "execute_bash", now)).await;
+        os.fs
+            .append_file(AuditLog::path().unwrap(), "not json\n")
+            .await
+            .unwrap();
+
+        let all = AuditLog::read(&os, &AuditQuery::default()).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].conversation_id, "one");
+
+        let filtered = AuditLog::read(&os, &AuditQuery {
+            tool_name: Some("execute_bash".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].conversation_id, "two");
+    }
+}
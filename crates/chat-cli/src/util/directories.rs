@@ -129,6 +129,11 @@ pub fn logs_dir() -> Result<PathBuf> {
     }
 }
 
+/// The append-only audit log of every tool invocation made in `q chat`
+pub fn audit_log_path() -> Result<PathBuf> {
+    Ok(logs_dir()?.join("audit.jsonl"))
+}
+
 /// Example agent config path
 pub fn example_agent_config(os: &Os) -> Result<PathBuf> {
     let global_path = chat_global_agent_path(os)?;
@@ -166,6 +171,15 @@ pub fn chat_global_context_path(os: &Os) -> Result<PathBuf> {
     Ok(home_dir(os)?.join(".aws").join("amazonq").join("global_context.json"))
 }
 
+/// The directory where the tools of lazily started mcp servers are cached
+pub fn mcp_tool_cache_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?
+        .join(".aws")
+        .join("amazonq")
+        .join("cache")
+        .join("mcp-tools"))
+}
+
 /// The directory to the directory containing config for the `/context` feature in `q chat`.
 #[allow(dead_code)]
 pub fn chat_profiles_dir(os: &Os) -> Result<PathBuf> {
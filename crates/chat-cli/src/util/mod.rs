@@ -1,3 +1,4 @@
+pub mod audit_log;
 pub mod consts;
 pub mod directories;
 pub mod knowledge_store;
@@ -169,6 +169,16 @@ impl ServerRequestHandler for Handler {
                 } else {
                     let tool_spec_key_list = self.tool_spec_key_list.lock().await;
                     let tool_spec = self.tool_spec.lock().await;
+                    // Servers that have not been given mock tools have a single one
+                    if tool_spec_key_list.is_empty() {
+                        return Ok(Some(serde_json::json!({
+                            "tools": [{
+                                "name": "ping",
+                                "description": "Replies with pong",
+                                "inputSchema": { "type": "object", "properties": {} }
+                            }]
+                        })));
+                    }
                     let first_key = tool_spec_key_list
                         .first()
                         .expect("First key missing from tool specs")
@@ -301,6 +311,9 @@ impl ServerRequestHandler for Handler {
                     // If there is no parameter, this is the request to retrieve the first page
                     let prompt_key_list = self.prompt_key_list.lock().await;
                     let prompts = self.prompts.lock().await;
+                    if prompt_key_list.is_empty() {
+                        return Ok(Some(serde_json::json!({ "prompts": [] })));
+                    }
                     let first_key = prompt_key_list.first().expect("first key missing");
                     let first_value = prompts.get(first_key).cloned().unwrap().unwrap();
                     let second_key = prompt_key_list.get(1).expect("second key missing");